@@ -1,7 +1,10 @@
+use std::net::SocketAddr;
 use std::path::PathBuf;
 
 use anyhow::{anyhow, Result};
-use apple_apns::{Endpoint, InterruptionLevel, Priority, PushType};
+use apple_apns::{
+    Badge, Endpoint, EnvironmentHint, Event, Expiration, InterruptionLevel, Priority, PushType,
+};
 use clap::{ArgGroup, Parser};
 use humantime::parse_duration;
 use time::{format_description::well_known::Iso8601, OffsetDateTime};
@@ -23,7 +26,15 @@ use uuid::Uuid;
     ArgGroup::new("token")
         .conflicts_with("certificate")
         .args(["key_id", "key_pem_file", "team_id"])
-        .requires_all(["key_id", "key_pem_file", "team_id"])
+        .requires_all(["key_pem_file", "team_id"])
+        .multiple(true)
+), group(
+    ArgGroup::new("device")
+        .args(["device_token", "device_token_file"])
+        .required(true)
+), group(
+    ArgGroup::new("alert_sound")
+        .args(["sound", "sound_critical"])
         .multiple(true)
 ))]
 pub struct Cli {
@@ -33,9 +44,13 @@ pub struct Cli {
     #[arg(long, env)]
     pub client_pem_file: Option<PathBuf>,
 
+    /// The key ID. Defaults to the ID in a key file named
+    /// `AuthKey_<key id>.p8`.
     #[arg(long, env)]
     pub key_id: Option<String>,
 
+    /// The `.p8` signing key file. DER-encoded PKCS#8 keys are detected
+    /// and accepted too.
     #[arg(long, env)]
     pub key_pem_file: Option<String>,
 
@@ -45,12 +60,67 @@ pub struct Cli {
     #[arg(long, env)]
     pub endpoint: Option<Endpoint>,
 
+    /// Fail if --endpoint is not in this environment, `sandbox` or
+    /// `production`. Custom endpoints match either.
+    #[arg(long, env)]
+    pub expect_environment: Option<EnvironmentHint>,
+
+    /// Allow an http:// --endpoint without TLS, e.g. a local mock server.
+    #[arg(long, env, default_value_t = false)]
+    pub allow_insecure_http: bool,
+
+    /// Allow sending without --client-pem-file or --key-pem-file, e.g. to a
+    /// local mock server.
+    #[arg(long, env, default_value_t = false)]
+    pub allow_unauthenticated: bool,
+
+    /// Connect to these addresses instead of resolving the --endpoint host,
+    /// e.g. `17.188.166.29:443`. Repeat or separate with commas.
+    #[arg(long = "pin-address", env, value_delimiter = ',')]
+    pub pin_addresses: Vec<SocketAddr>,
+
+    /// Enable or disable HTTP/2 adaptive flow control, e.g. `false` to avoid
+    /// stalls on high-latency links.
+    #[arg(long, env)]
+    pub http2_adaptive_window: Option<bool>,
+
+    /// Maximum HTTP/2 frame size in bytes, between 16384 and 16777215.
+    #[arg(long, env)]
+    pub http2_max_frame_size: Option<u32>,
+
     #[arg(long, env)]
     pub user_agent: Option<String>,
 
-    /// The hex-encoded device token.
+    /// The hex-encoded device token. Surrounding whitespace is ignored and
+    /// uppercase hex digits are accepted.
+    #[arg(long, env)]
+    pub device_token: Option<String>,
+
+    /// A file with one hex-encoded device token per line. Sends the
+    /// notification to every device token and prints a JSON line for each
+    /// result as it completes, followed by a JSON summary. Exits with status 2
+    /// if some sends failed and 1 if all failed.
     #[arg(long, env)]
-    pub device_token: String,
+    pub device_token_file: Option<PathBuf>,
+
+    /// Print the results of --device-token-file in the order of the file
+    /// instead of as they complete.
+    #[arg(long, env, default_value_t = false)]
+    pub ordered: bool,
+
+    /// The maximum number of notifications in flight at once for
+    /// --device-token-file.
+    #[arg(long, env, default_value_t = 16)]
+    pub concurrency: usize,
+
+    /// Print the request that would be sent as JSON instead of sending it.
+    #[arg(
+        long,
+        env,
+        default_value_t = false,
+        conflicts_with = "device_token_file"
+    )]
+    pub dry_run: bool,
 
     /// The push type of the notification to send.
     #[arg(long, env, default_value_t = PushType::Alert)]
@@ -60,13 +130,15 @@ pub struct Cli {
     #[arg(long, env)]
     pub id: Option<Uuid>,
 
-    /// The date at which the notification is no longer valid.
-    #[arg(long, env, value_parser = parse_timestamp)]
-    pub expiration: Option<OffsetDateTime>,
+    /// The date at which the notification is no longer valid, a duration from
+    /// now, or `0`/`never-store` to attempt delivery only once.
+    #[arg(long, env, value_parser = parse_expiration)]
+    pub expiration: Option<Expiration>,
 
-    /// The priority of the notification.
-    #[arg(long, env, default_value_t = Default::default())]
-    pub priority: Priority,
+    /// The priority of the notification. Defaults to 5 for background, file
+    /// provider and widgets pushes and 10 otherwise.
+    #[arg(long, env)]
+    pub priority: Option<Priority>,
 
     /// The topic for the notification, e.g. bundle ID or app ID.
     #[arg(long, env)]
@@ -94,20 +166,28 @@ pub struct Cli {
     #[arg(long, env)]
     pub launch_image: Option<String>,
 
-    /// The number to display in a badge on your app’s icon. Specify `0` to
-    /// remove the current badge, if any.
+    /// The number to display in a badge on your app’s icon. Specify `clear`
+    /// or `0` to remove the current badge, if any.
     #[arg(long, env)]
-    pub badge: Option<u32>,
+    pub badge: Option<Badge>,
 
     /// The name of a sound file in your app’s main bundle or in the
     /// `Library/Sounds` folder of your app’s container directory.
     #[arg(long, env)]
     pub sound: Option<String>,
 
+    /// The critical alert flag of the sound, with the `default` sound unless
+    /// --sound is set. APNs requires no interruption level or `critical`
+    /// with a critical sound.
+    #[arg(long, env, default_value_t = false)]
+    pub sound_critical: bool,
+
     /// The volume for the critical alert’s sound. Set this to a value
-    /// between `0` (silent) and `1` (full volume).
-    #[arg(long, env)]
-    pub volume: Option<f64>,
+    /// between `0` (silent) and `1` (full volume). Requires --sound or
+    /// --sound-critical. Read from `VOLUME`, the variable of its old name
+    /// `--volume`.
+    #[arg(long, env = "VOLUME", alias = "volume", requires = "alert_sound")]
+    pub sound_volume: Option<f64>,
 
     /// An app-specific identifier for grouping related notifications.
     #[arg(long, env)]
@@ -137,17 +217,53 @@ pub struct Cli {
     #[arg(long, env)]
     pub relevance_score: Option<f64>,
 
+    /// (Live Activity) The Live Activity event, `start`, `update` or `end`.
+    #[arg(long, env)]
+    pub event: Option<Event>,
+
+    /// (Live Activity) The date of the update, or a duration from now.
+    #[arg(long, env, value_parser = parse_date)]
+    pub timestamp: Option<OffsetDateTime>,
+
+    /// (Live Activity) The date at which the system considers the Live
+    /// Activity content outdated, or a duration from now.
+    #[arg(long, env, value_parser = parse_date)]
+    pub stale_date: Option<OffsetDateTime>,
+
+    /// (Live Activity) The date at which the system removes an ended Live
+    /// Activity from the Lock Screen, or a duration from now.
+    #[arg(long, env, value_parser = parse_date)]
+    pub dismissal_date: Option<OffsetDateTime>,
+
+    /// (Live Activity) A predicate that limits a broadcast update to the
+    /// Live Activities that match it.
+    #[arg(long, env)]
+    pub filter_criteria: Option<String>,
+
     /// Additional data to send.
     #[arg(long, env)]
     pub user_info: Option<serde_json::Value>,
 }
 
-fn parse_timestamp(arg: &str) -> Result<OffsetDateTime> {
+fn parse_expiration(arg: &str) -> Result<Expiration> {
+    if matches!(arg, "0" | "never-store") {
+        return Ok(Expiration::Immediate);
+    }
     match OffsetDateTime::parse(arg, &Iso8601::DEFAULT) {
-        Ok(timestamp) => Ok(timestamp),
+        Ok(timestamp) => Ok(Expiration::At(timestamp)),
         Err(duration_err) => match parse_duration(arg) {
-            Ok(duration) => Ok(OffsetDateTime::now_utc() + duration),
+            Ok(duration) => Ok(Expiration::In(duration)),
             Err(timestamp_err) => Err(anyhow!("Invalid expiration; invalid timestamp: {timestamp_err}; invalid duration: {duration_err}")),
         },
     }
 }
+
+fn parse_date(arg: &str) -> Result<OffsetDateTime> {
+    match OffsetDateTime::parse(arg, &Iso8601::DEFAULT) {
+        Ok(timestamp) => Ok(timestamp),
+        Err(timestamp_err) => match parse_duration(arg) {
+            Ok(duration) => Ok(OffsetDateTime::now_utc() + duration),
+            Err(duration_err) => Err(anyhow!("Invalid date; invalid timestamp: {timestamp_err}; invalid duration: {duration_err}")),
+        },
+    }
+}
@@ -1,17 +1,25 @@
+use std::collections::BTreeMap;
 use std::fs;
+use std::io::Write;
+use std::process::ExitCode;
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
+use apple_apns::token::{is_der, key_id_from_path};
 use apple_apns::{
-    Alert, Authentication, CertificateAuthority, ClientBuilder, InterruptionLevel, Request, Sound,
+    Alert, Authentication, BatchOptions, BatchReport, CertificateAuthority, ClientBuilder, Reason,
+    Request, Sound,
 };
 use clap::Parser;
+use futures_util::StreamExt;
+use serde_json::json;
+use zeroize::Zeroizing;
 
 mod cli;
 
 pub use cli::*;
 
 #[allow(unused_assignments)]
-pub async fn main() -> Result<()> {
+pub async fn main() -> Result<ExitCode> {
     dotenvy::dotenv().ok();
 
     let cli = Cli::parse();
@@ -22,6 +30,13 @@ pub async fn main() -> Result<()> {
         builder.endpoint = endpoint;
     }
 
+    builder.environment_hint = cli.expect_environment;
+    builder.allow_insecure_http = cli.allow_insecure_http;
+    builder.allow_unauthenticated = cli.allow_unauthenticated;
+    builder.pin_addresses = cli.pin_addresses.clone();
+    builder.http2_adaptive_window = cli.http2_adaptive_window;
+    builder.http2_max_frame_size = cli.http2_max_frame_size;
+
     if let Some(user_agent) = &cli.user_agent {
         builder.user_agent = user_agent;
     }
@@ -33,55 +48,43 @@ pub async fn main() -> Result<()> {
     }
 
     let mut client_pem = None;
-    let mut key_pem = None;
     if let Some(client_pem_file) = &cli.client_pem_file {
-        client_pem = Some(fs::read(client_pem_file)?);
+        client_pem = Some(Zeroizing::new(fs::read(client_pem_file)?));
         builder.authentication = Some(Authentication::Certificate {
-            client_pem: client_pem.as_ref().unwrap(),
+            client_pem: client_pem.as_deref().unwrap(),
         })
-    } else if let (Some(key_id), Some(key_pem_file), Some(team_id)) =
-        (&cli.key_id, &cli.key_pem_file, &cli.team_id)
-    {
-        key_pem = Some(fs::read(key_pem_file)?);
-        builder.authentication = Some(Authentication::Token {
-            key_id,
-            key_pem: key_pem.as_ref().unwrap(),
-            team_id,
+    } else if let (Some(key_pem_file), Some(team_id)) = (&cli.key_pem_file, &cli.team_id) {
+        let key_id = match &cli.key_id {
+            Some(key_id) => key_id.clone(),
+            None => key_id_from_path(key_pem_file).ok_or_else(|| {
+                anyhow!("Missing --key-id; key file name {key_pem_file} does not match AuthKey_<key id>.p8")
+            })?,
+        };
+        builder.authentication = Some(if is_der(&Zeroizing::new(fs::read(key_pem_file)?)) {
+            Authentication::token_from_der_file(key_id, key_pem_file, team_id.as_str())?
+        } else {
+            Authentication::token_from_p8_file(key_id, key_pem_file, team_id.as_str())?
         });
     }
 
     let client = builder.build()?;
 
-    let sound = cli.sound.map(|name| {
-        let critical = cli.interruption_level == Some(InterruptionLevel::Critical);
-        let mut sound = Sound {
-            critical,
-            name,
-            ..Default::default()
-        };
-        if let Some(volume) = cli.volume {
-            sound.volume = volume;
-        }
-        sound
-    });
-
-    let request = Request {
-        device_token: cli.device_token,
+    let mut request = Request {
+        device_token: cli.device_token.unwrap_or_default().into(),
         push_type: cli.push_type,
         id: cli.id,
-        expiration: cli.expiration,
+        expiration: cli.expiration.unwrap_or_default(),
         priority: cli.priority,
         topic: cli.topic,
         collapse_id: cli.collapse_id,
         alert: Some(Alert {
-            title: cli.title.map(Into::into),
-            subtitle: cli.subtitle.map(Into::into),
-            body: cli.body.map(Into::into),
+            title: cli.title,
+            subtitle: cli.subtitle,
+            body: cli.body,
             launch_image: cli.launch_image,
             ..Default::default()
         }),
         badge: cli.badge,
-        sound,
         thread_id: cli.thread_id,
         category: cli.category,
         content_available: cli.content_available,
@@ -89,11 +92,126 @@ pub async fn main() -> Result<()> {
         target_content_id: cli.target_content_id,
         interruption_level: cli.interruption_level,
         relevance_score: cli.relevance_score,
+        event: cli.event,
+        timestamp: cli.timestamp,
+        stale_date: cli.stale_date,
+        dismissal_date: cli.dismissal_date,
+        filter_criteria: cli.filter_criteria,
         user_info: cli.user_info,
+        ..Default::default()
     };
 
-    let apns_id = client.post(request).await?;
+    // A sound that doesn't match the interruption level is left for the
+    // client to reject.
+    if cli.sound.is_some() || cli.sound_critical {
+        let mut sound = Sound {
+            critical: cli.sound_critical,
+            ..Default::default()
+        };
+        if let Some(name) = cli.sound {
+            sound.name = name;
+        }
+        if let Some(volume) = cli.sound_volume {
+            sound.volume = volume;
+        }
+        request.sound = Some(sound);
+    }
+
+    for lint in client.lint(&request) {
+        eprintln!("warning: {lint}");
+    }
+
+    if cli.dry_run {
+        let req = client.build_http_request(request).await?;
+        let headers = req
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                let value = if value.is_sensitive() {
+                    "<redacted>"
+                } else {
+                    value.to_str()?
+                };
+                Ok((name.as_str(), value))
+            })
+            .collect::<Result<BTreeMap<_, _>>>()?;
+        let body: serde_json::Value = serde_json::from_slice(req.body())?;
+        println!(
+            "{}",
+            json!({
+                "method": req.method().as_str(),
+                "url": req.uri().to_string(),
+                "headers": headers,
+                "body": body,
+                "payload_size": req.body().len(),
+            })
+        );
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if let Some(device_token_file) = &cli.device_token_file {
+        let device_tokens = fs::read_to_string(device_token_file)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect::<Vec<_>>();
+
+        let options = BatchOptions {
+            concurrency: cli.concurrency,
+            ordered: cli.ordered,
+            ..Default::default()
+        };
+        let results = client.post_batch(request, device_tokens, options);
+        tokio::pin!(results);
+
+        let mut stdout = std::io::stdout().lock();
+        let mut report = BatchReport::default();
+        while let Some(result) = results.next().await {
+            report.record(&result);
+            let line = match &result.result {
+                Ok(apns_id) => {
+                    json!({
+                        "token": result.device_token,
+                        "ok": true,
+                        "apns_id": apns_id,
+                        "payload_size": result.payload_size,
+                    })
+                }
+                Err(err) => match err.reason() {
+                    Some(reason) => json!({
+                        "token": result.device_token,
+                        "ok": false,
+                        "reason": reason.as_str(),
+                    }),
+                    None => json!({
+                        "token": result.device_token,
+                        "ok": false,
+                        "reason": Reason::Unknown.as_str(),
+                        "error": err.to_string(),
+                    }),
+                },
+            };
+            writeln!(stdout, "{line}")?;
+            stdout.flush()?;
+        }
+
+        writeln!(stdout, "{}", serde_json::to_string(&report)?)?;
+        stdout.flush()?;
+
+        return Ok(match (report.ok, report.failed) {
+            (_, 0) => ExitCode::SUCCESS,
+            (0, _) => ExitCode::FAILURE,
+            _ => ExitCode::from(2),
+        });
+    }
+
+    let redacted = request.redacted().to_string();
+    let apns_id = client
+        .post(request)
+        .await
+        .with_context(|| format!("sending {redacted}"))?;
     println!("{}", apns_id.as_hyphenated());
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
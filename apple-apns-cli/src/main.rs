@@ -1,4 +1,6 @@
+use std::process::ExitCode;
+
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
     apple_apns_cli::main().await.unwrap()
 }
@@ -0,0 +1,183 @@
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use assert_cmd::prelude::*;
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+const FAST_DEVICE_TOKEN: &str = "a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae";
+const SLOW_DEVICE_TOKEN: &str = "b863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae";
+const BAD_DEVICE_TOKEN: &str = "c863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae";
+const APS_ID: &str = "4d947500-498e-4524-8aa8-7220c4e65d75";
+
+/// Size of the `{"aps":{"alert":"Hello World!"}}` payload.
+const PAYLOAD_SIZE: usize = 32;
+
+async fn start_mock_server() -> MockServer {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{FAST_DEVICE_TOKEN}")))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{SLOW_DEVICE_TOKEN}")))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("apns-id", APS_ID)
+                .set_delay(Duration::from_secs(3)),
+        )
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{BAD_DEVICE_TOKEN}")))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "reason": "BadDeviceToken",
+        })))
+        .mount(&mock_server)
+        .await;
+
+    mock_server
+}
+
+fn write_device_token_file(name: &str, device_tokens: &[&str]) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::write(&path, device_tokens.join("\n")).unwrap();
+    path
+}
+
+fn apns(mock_server: &MockServer, device_token_file: &PathBuf) -> Command {
+    let mut command = Command::cargo_bin("apns").unwrap();
+    command
+        .arg("--endpoint")
+        .arg(format!("{}/3/device/", mock_server.uri()))
+        .arg("--allow-insecure-http")
+        .args(["--key-id", "ABC123DEF4", "--team-id", "DEF123GHIJ"])
+        .arg("--key-pem-file")
+        .arg(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../apple-apns/tests/fixtures/AuthKey_ABC123DEF4.p8"
+        ))
+        .args(["--topic", "com.example.myapp", "--body", "Hello World!"])
+        .arg("--device-token-file")
+        .arg(device_token_file);
+    command
+}
+
+fn parse_lines(stdout: &[u8]) -> Vec<Value> {
+    stdout
+        .lines()
+        .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn batch_streams_results_as_completed() {
+    let mock_server = start_mock_server().await;
+    let device_token_file = write_device_token_file(
+        "batch_streams_results_as_completed.txt",
+        &[SLOW_DEVICE_TOKEN, FAST_DEVICE_TOKEN],
+    );
+
+    let mut child = apns(&mock_server, &device_token_file)
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+
+    // The fast result is printed while the slow request is still in flight.
+    let mut line = String::new();
+    stdout.read_line(&mut line).unwrap();
+    assert!(child.try_wait().unwrap().is_none());
+    assert_eq!(
+        serde_json::from_str::<Value>(&line).unwrap(),
+        json!({ "token": FAST_DEVICE_TOKEN, "ok": true, "apns_id": APS_ID, "payload_size": PAYLOAD_SIZE })
+    );
+
+    let mut rest = Vec::new();
+    std::io::Read::read_to_end(&mut stdout, &mut rest).unwrap();
+    assert!(child.wait().unwrap().success());
+
+    assert_eq!(
+        parse_lines(&rest),
+        [
+            json!({ "token": SLOW_DEVICE_TOKEN, "ok": true, "apns_id": APS_ID, "payload_size": PAYLOAD_SIZE }),
+            json!({
+                "total": 2,
+                "ok": 2,
+                "failed": 0,
+                "reasons": {},
+                "payload_bytes": 2 * PAYLOAD_SIZE,
+                "max_payload_size": PAYLOAD_SIZE,
+            }),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn batch_ordered() {
+    let mock_server = start_mock_server().await;
+    let device_token_file =
+        write_device_token_file("batch_ordered.txt", &[SLOW_DEVICE_TOKEN, FAST_DEVICE_TOKEN]);
+
+    let output = apns(&mock_server, &device_token_file)
+        .arg("--ordered")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        parse_lines(&output.stdout),
+        [
+            json!({ "token": SLOW_DEVICE_TOKEN, "ok": true, "apns_id": APS_ID, "payload_size": PAYLOAD_SIZE }),
+            json!({ "token": FAST_DEVICE_TOKEN, "ok": true, "apns_id": APS_ID, "payload_size": PAYLOAD_SIZE }),
+            json!({
+                "total": 2,
+                "ok": 2,
+                "failed": 0,
+                "reasons": {},
+                "payload_bytes": 2 * PAYLOAD_SIZE,
+                "max_payload_size": PAYLOAD_SIZE,
+            }),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn batch_partial_failure() {
+    let mock_server = start_mock_server().await;
+    let device_token_file = write_device_token_file(
+        "batch_partial_failure.txt",
+        &[FAST_DEVICE_TOKEN, "", BAD_DEVICE_TOKEN],
+    );
+
+    let output = apns(&mock_server, &device_token_file)
+        .arg("--ordered")
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        parse_lines(&output.stdout),
+        [
+            json!({ "token": FAST_DEVICE_TOKEN, "ok": true, "apns_id": APS_ID, "payload_size": PAYLOAD_SIZE }),
+            json!({ "token": BAD_DEVICE_TOKEN, "ok": false, "reason": "BadDeviceToken" }),
+            json!({
+                "total": 2,
+                "ok": 1,
+                "failed": 1,
+                "reasons": { "BadDeviceToken": 1 },
+                "payload_bytes": PAYLOAD_SIZE,
+                "max_payload_size": PAYLOAD_SIZE,
+            }),
+        ]
+    );
+}
@@ -0,0 +1,247 @@
+use std::process::Command;
+
+use assert_cmd::prelude::*;
+use serde_json::{json, Value};
+
+const DEVICE_TOKEN: &str = "a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae";
+
+fn apns() -> Command {
+    apns_with_endpoint("http://localhost/3/device/")
+}
+
+fn apns_with_endpoint(endpoint: &str) -> Command {
+    let mut command = Command::cargo_bin("apns").unwrap();
+    command
+        .args(["--endpoint", endpoint, "--allow-insecure-http"])
+        .args(["--team-id", "DEF123GHIJ"])
+        .arg("--key-pem-file")
+        .arg(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../apple-apns/tests/fixtures/AuthKey_ABC123DEF4.p8"
+        ))
+        .args(["--device-token", DEVICE_TOKEN])
+        .args(["--topic", "com.example.myapp", "--body", "Hello World!"])
+        .arg("--dry-run");
+    command
+}
+
+#[test]
+fn dry_run() {
+    let output = apns().output().unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+    assert_eq!(
+        serde_json::from_slice::<Value>(&output.stdout).unwrap(),
+        json!({
+            "method": "POST",
+            "url": format!("http://localhost/3/device/{DEVICE_TOKEN}"),
+            "headers": {
+                "apns-push-type": "alert",
+                "apns-topic": "com.example.myapp",
+                "authorization": "<redacted>",
+                "content-type": "application/json",
+            },
+            "body": { "aps": { "alert": "Hello World!" } },
+            "payload_size": 32,
+        })
+    );
+}
+
+#[test]
+fn dry_run_badge_clear() {
+    for badge in ["clear", "0"] {
+        let output = apns().args(["--badge", badge]).output().unwrap();
+
+        assert!(output.status.success());
+        let req: Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(
+            req["body"],
+            json!({ "aps": { "alert": "Hello World!", "badge": 0 } })
+        );
+    }
+}
+
+#[test]
+fn dry_run_lints() {
+    let output = apns()
+        .args(["--interruption-level", "passive"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "warning: priority 10 with passive interruption level, consider priority 5 (ConsiderPower)\n"
+    );
+}
+
+#[test]
+fn dry_run_lints_expiration() {
+    let output = apns()
+        .args(["--priority", "1", "--expiration", "10m"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "warning: priority 1 notification expires in 600s, consider an expiration of at least 3600s or a higher priority\n"
+    );
+}
+
+#[test]
+fn dry_run_sound_critical() {
+    let output = apns()
+        .args(["--interruption-level", "critical", "--sound-critical"])
+        .args(["--sound", "siren.aiff", "--sound-volume", "0.5"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    let req: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        req["body"],
+        json!({
+            "aps": {
+                "alert": "Hello World!",
+                "interruption-level": "critical",
+                "sound": { "critical": 1, "name": "siren.aiff", "volume": 0.5 },
+            },
+        })
+    );
+
+    // The old flag and variable names still work, and the sound defaults to
+    // `default`.
+    for output in [
+        apns()
+            .args(["--sound-critical", "--volume", "0.5"])
+            .output()
+            .unwrap(),
+        apns()
+            .arg("--sound-critical")
+            .env("VOLUME", "0.5")
+            .output()
+            .unwrap(),
+    ] {
+        assert!(output.status.success(), "{output:?}");
+        let req: Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(
+            req["body"]["aps"]["sound"],
+            json!({ "critical": 1, "name": "default", "volume": 0.5 })
+        );
+    }
+
+    // A volume without a sound is an error rather than ignored.
+    let output = apns().args(["--sound-volume", "0.5"]).output().unwrap();
+    assert!(!output.status.success(), "{output:?}");
+
+    // A mismatched interruption level is the client's error to report.
+    for args in [
+        &["--interruption-level", "critical", "--sound", "siren.aiff"][..],
+        &["--interruption-level", "active", "--sound-critical"][..],
+    ] {
+        let output = apns().args(args).output().unwrap();
+
+        assert!(!output.status.success(), "{args:?}");
+        assert!(
+            String::from_utf8(output.stderr)
+                .unwrap()
+                .contains("interruption level does not match sound critical flag"),
+            "{args:?}"
+        );
+    }
+}
+
+#[test]
+fn dry_run_live_activity_dates() {
+    let output = apns()
+        .args(["--stale-date", "2023-06-05T08:00:00Z"])
+        .args(["--dismissal-date", "2023-06-05T08:30:00+00:30"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let req: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        req["body"],
+        json!({
+            "aps": {
+                "alert": "Hello World!",
+                "stale-date": 1685952000,
+                "dismissal-date": 1685952000,
+            },
+        })
+    );
+
+    let output = apns().args(["--stale-date", "1h"]).output().unwrap();
+
+    assert!(output.status.success());
+    let req: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let stale_date = req["body"]["aps"]["stale-date"].as_i64().unwrap();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    assert!(
+        (now + 3590..=now + 3610).contains(&stale_date),
+        "{stale_date}"
+    );
+
+    let output = apns().args(["--stale-date", "soon"]).output().unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("Invalid date"));
+}
+
+#[test]
+fn dry_run_filter_criteria() {
+    let output = apns()
+        .args([
+            "--push-type",
+            "liveactivity",
+            "--filter-criteria",
+            "team == 'Blue'",
+        ])
+        .args(["--event", "update", "--timestamp", "2023-06-05T08:00:00Z"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    let req: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(req["body"]["aps"]["filter-criteria"], "team == 'Blue'");
+
+    let output = apns()
+        .args(["--filter-criteria", "team == 'Blue'"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("alert push must not include filter_criteria"));
+}
+
+#[test]
+fn dry_run_expect_environment() {
+    let output = apns_with_endpoint("dev")
+        .args(["--expect-environment", "production"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains(
+        "endpoint https://api.sandbox.push.apple.com./3/device/ is not in the expected production environment"
+    ));
+
+    for endpoint in ["dev", "http://localhost/3/device/"] {
+        let output = apns_with_endpoint(endpoint)
+            .args(["--expect-environment", "sandbox"])
+            .output()
+            .unwrap();
+
+        assert!(output.status.success(), "{endpoint}");
+    }
+}
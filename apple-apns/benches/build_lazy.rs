@@ -0,0 +1,34 @@
+//! Compares the startup cost of `ClientBuilder::build` and
+//! `ClientBuilder::build_lazy` for clients that never send.
+//!
+//! Run with `cargo bench --bench build_lazy`.
+
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use apple_apns::{Client, ClientBuilder, Result};
+
+const ITERATIONS: u32 = 100;
+
+/// Returns the average duration of a call of `f`.
+fn duration_per_call(f: impl Fn(ClientBuilder<'static>) -> Result<Client>) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        let builder = ClientBuilder {
+            allow_unauthenticated: true,
+            ..Default::default()
+        };
+        f(builder).unwrap();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let eager = duration_per_call(ClientBuilder::build);
+    let lazy = duration_per_call(ClientBuilder::build_lazy);
+
+    let mut stdout = std::io::stdout().lock();
+    writeln!(stdout, "time per client built:").unwrap();
+    writeln!(stdout, "  build:      {eager:?}").unwrap();
+    writeln!(stdout, "  build_lazy: {lazy:?}").unwrap();
+}
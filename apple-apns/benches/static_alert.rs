@@ -0,0 +1,75 @@
+//! Counts the allocations of serializing a notification template.
+//!
+//! Run with `cargo bench --bench static_alert`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use apple_apns::payload::StaticAlert;
+use apple_apns::Alert;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+static TEMPLATE: StaticAlert = StaticAlert::new("Your order has shipped.")
+    .title("Order update")
+    .subtitle("Acme Store");
+
+const ITERATIONS: usize = 10_000;
+
+/// Returns the average number of allocations per call of `f`.
+fn allocations_per_call(mut f: impl FnMut()) -> f64 {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let after = ALLOCATIONS.load(Ordering::Relaxed);
+    (after - before) as f64 / ITERATIONS as f64
+}
+
+fn main() {
+    let mut buf = Vec::with_capacity(4096);
+
+    let owned = allocations_per_call(|| {
+        buf.clear();
+        let alert = Alert {
+            title: Some("Order update".into()),
+            subtitle: Some("Acme Store".into()),
+            body: Some("Your order has shipped.".into()),
+            ..Default::default()
+        };
+        serde_json::to_writer(&mut buf, &alert).unwrap();
+    });
+
+    let converted = allocations_per_call(|| {
+        buf.clear();
+        serde_json::to_writer(&mut buf, &Alert::from(&TEMPLATE)).unwrap();
+    });
+
+    let r#static = allocations_per_call(|| {
+        buf.clear();
+        serde_json::to_writer(&mut buf, &TEMPLATE).unwrap();
+    });
+
+    let mut stdout = std::io::stdout().lock();
+    writeln!(stdout, "allocations per serialized alert:").unwrap();
+    writeln!(stdout, "  owned Alert:            {owned}").unwrap();
+    writeln!(stdout, "  Alert from StaticAlert: {converted}").unwrap();
+    writeln!(stdout, "  StaticAlert:            {static}", static = r#static).unwrap();
+}
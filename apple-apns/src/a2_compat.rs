@@ -0,0 +1,540 @@
+//! Conversions from and to the [`a2`] crate's payload types.
+
+use std::collections::BTreeMap;
+
+use a2::request::notification::{
+    CollapseId, DefaultNotificationBuilder, NotificationBuilder, NotificationOptions,
+};
+use serde_json::Value;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::header::{Expiration, Priority, PushType};
+use crate::payload::{Alert, Aps, Badge, Payload};
+use crate::request::Request;
+use crate::result::{Error, Result};
+
+impl TryFrom<a2::request::payload::Payload<'_>> for Payload<Value> {
+    type Error = Error;
+
+    fn try_from(payload: a2::request::payload::Payload<'_>) -> Result<Self> {
+        if payload.aps.url_args.is_some() {
+            return Err(Error::InvalidRequest("url-args is not supported".into()));
+        }
+
+        // The fields of a2's alert and sound are private, so go through their
+        // serialized form. Keys that `Aps` does not know, like
+        // `action-loc-key`, are kept in the `extra` maps.
+        let aps: Aps = serde_json::from_slice(&serde_json::to_vec(&payload.aps)?)?;
+
+        let user_info = if payload.data.is_empty() {
+            None
+        } else {
+            Some(Value::Object(
+                payload
+                    .data
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v))
+                    .collect(),
+            ))
+        };
+
+        Ok(Self { aps, user_info })
+    }
+}
+
+impl TryFrom<a2::request::payload::Payload<'_>> for Request<Value> {
+    type Error = Error;
+
+    fn try_from(payload: a2::request::payload::Payload<'_>) -> Result<Self> {
+        let device_token = payload.device_token.to_string().into();
+        let options = payload.options.clone();
+        let Payload { aps, user_info } = payload.try_into()?;
+
+        Ok(Self {
+            device_token,
+            push_type: options.apns_push_type.map(Into::into).unwrap_or_default(),
+            id: options
+                .apns_id
+                .map(Uuid::parse_str)
+                .transpose()
+                .map_err(|err| Error::InvalidRequest(format!("invalid apns-id: {err}")))?,
+            expiration: match options.apns_expiration {
+                None => Expiration::Default,
+                Some(0) => Expiration::Immediate,
+                Some(expiration) => i64::try_from(expiration)
+                    .ok()
+                    .and_then(|expiration| OffsetDateTime::from_unix_timestamp(expiration).ok())
+                    .map(Expiration::At)
+                    .ok_or_else(|| {
+                        Error::InvalidRequest(format!("invalid apns-expiration {expiration}"))
+                    })?,
+            },
+            priority: options.apns_priority.map(|priority| match priority {
+                a2::request::notification::Priority::High => Priority::Immediate,
+                a2::request::notification::Priority::Normal => Priority::ConsiderPower,
+            }),
+            topic: options.apns_topic.map(Into::into),
+            collapse_id: options.apns_collapse_id.map(|id| id.value.into()),
+            deadline: None,
+            alert: aps.alert,
+            badge: aps.badge,
+            sound: aps.sound,
+            thread_id: aps.thread_id,
+            category: aps.category,
+            content_available: aps.content_available,
+            mutable_content: aps.mutable_content,
+            target_content_id: aps.target_content_id,
+            interruption_level: aps.interruption_level,
+            relevance_score: aps.relevance_score,
+            timestamp: aps.timestamp,
+            event: aps.event,
+            content_state: aps.content_state,
+            stale_date: aps.stale_date,
+            dismissal_date: aps.dismissal_date,
+            attributes_type: aps.attributes_type,
+            attributes: aps.attributes,
+            input_push_token: aps.input_push_token,
+            filter_criteria: aps.filter_criteria,
+            user_info,
+        })
+    }
+}
+
+impl From<a2::request::notification::PushType> for PushType {
+    fn from(push_type: a2::request::notification::PushType) -> Self {
+        use a2::request::notification::PushType as A2PushType;
+
+        match push_type {
+            A2PushType::Alert => Self::Alert,
+            A2PushType::Background => Self::Background,
+            A2PushType::Location => Self::Location,
+            A2PushType::Voip => Self::Voip,
+            A2PushType::FileProvider => Self::Fileprovider,
+            A2PushType::Mdm => Self::Mdm,
+            A2PushType::LiveActivity => Self::Liveactivity,
+            A2PushType::PushToTalk => Self::Pushtotalk,
+        }
+    }
+}
+
+impl TryFrom<PushType> for a2::request::notification::PushType {
+    type Error = Error;
+
+    fn try_from(push_type: PushType) -> Result<Self> {
+        Ok(match push_type {
+            PushType::Alert => Self::Alert,
+            PushType::Background => Self::Background,
+            PushType::Location => Self::Location,
+            PushType::Voip => Self::Voip,
+            PushType::Fileprovider => Self::FileProvider,
+            PushType::Mdm => Self::Mdm,
+            PushType::Liveactivity => Self::LiveActivity,
+            PushType::Pushtotalk => Self::PushToTalk,
+            PushType::Complication | PushType::Widgets => {
+                return Err(unsupported(&format!("push type {push_type}")))
+            }
+        })
+    }
+}
+
+/// Converts a request into an [`a2`] payload. Fails if the request has fields
+/// that [`a2`] cannot represent.
+impl<'a> TryFrom<&'a Request<Value>> for a2::request::payload::Payload<'a> {
+    type Error = Error;
+
+    fn try_from(request: &'a Request<Value>) -> Result<Self> {
+        if request.id.is_some() {
+            return Err(unsupported("apns-id"));
+        }
+        let priority = request.effective_priority();
+        if priority == Priority::PrioritizePower {
+            return Err(unsupported("priority 1"));
+        }
+        if request.thread_id.is_some() {
+            return Err(unsupported("thread-id"));
+        }
+        if request.target_content_id.is_some() {
+            return Err(unsupported("target-content-id"));
+        }
+        if request.interruption_level.is_some() {
+            return Err(unsupported("interruption-level"));
+        }
+        if request.relevance_score.is_some() {
+            return Err(unsupported("relevance-score"));
+        }
+        if request.timestamp.is_some()
+            || request.event.is_some()
+            || request.content_state.is_some()
+            || request.stale_date.is_some()
+            || request.dismissal_date.is_some()
+            || request.attributes_type.is_some()
+            || request.attributes.is_some()
+            || request.input_push_token
+            || request.filter_criteria.is_some()
+        {
+            return Err(unsupported("Live Activity keys"));
+        }
+
+        let mut builder = DefaultNotificationBuilder::new();
+
+        if let Some(alert) = &request.alert {
+            if alert.subtitle_loc_key.is_some() || alert.subtitle_loc_args.is_some() {
+                return Err(unsupported("subtitle-loc-key"));
+            }
+            if let Some(title) = &alert.title {
+                builder = builder.set_title(title);
+            }
+            if let Some(subtitle) = &alert.subtitle {
+                builder = builder.set_subtitle(subtitle);
+            }
+            if let Some(title_loc_key) = &alert.title_loc_key {
+                builder = builder.set_title_loc_key(title_loc_key);
+            }
+            if let Some(title_loc_args) = &alert.title_loc_args {
+                builder = builder.set_title_loc_args(title_loc_args);
+            }
+            if let Some(loc_key) = &alert.loc_key {
+                builder = builder.set_loc_key(loc_key);
+            }
+            if let Some(loc_args) = &alert.loc_args {
+                builder = builder.set_loc_args(loc_args);
+            }
+            if let Some(launch_image) = &alert.launch_image {
+                builder = builder.set_launch_image(launch_image);
+            }
+            // Like `Alert`, a2 sends an alert with only a body as a string.
+            if let Some(body) = &alert.body {
+                builder = builder.set_body(body);
+            } else if alert == &Alert::default() {
+                builder = builder.set_body("");
+            }
+        }
+        if let Some(badge) = request.badge {
+            if badge == Badge::Increment {
+                return Err(unsupported("badge increment"));
+            }
+            builder = builder.set_badge(badge.into());
+        }
+        if let Some(sound) = &request.sound {
+            if sound.critical && !sound.has_valid_volume() {
+                return Err(Error::CriticalSound);
+            }
+            builder = builder
+                .set_sound(&sound.name)
+                .set_critical(sound.critical, Some(sound.volume.clamp(0., 1.)));
+        }
+        if let Some(category) = &request.category {
+            builder = builder.set_category(category);
+        }
+        if request.content_available {
+            builder = builder.set_content_available();
+        }
+        if request.mutable_content {
+            builder = builder.set_mutable_content();
+        }
+
+        let options = NotificationOptions {
+            apns_id: None,
+            apns_push_type: Some(request.push_type.try_into()?),
+            apns_expiration: request
+                .expiration
+                .unix_timestamp_at(OffsetDateTime::now_utc())?
+                .map(|expiration| {
+                    u64::try_from(expiration).map_err(|_| {
+                        Error::InvalidRequest(format!("invalid apns-expiration {expiration}"))
+                    })
+                })
+                .transpose()?,
+            apns_priority: (request.priority.is_some() || priority != Priority::default())
+                .then_some(match priority {
+                    Priority::Immediate => a2::request::notification::Priority::High,
+                    _ => a2::request::notification::Priority::Normal,
+                }),
+            apns_topic: request.topic.as_deref(),
+            apns_collapse_id: request
+                .collapse_id
+                .as_deref()
+                .map(CollapseId::new)
+                .transpose()
+                .map_err(|err| Error::InvalidRequest(err.to_string()))?,
+        };
+
+        let mut payload = builder.build(request.device_token.as_str(), options);
+        payload.data = match &request.user_info {
+            Some(Value::Object(user_info)) => user_info
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.clone()))
+                .collect(),
+            Some(Value::Null) | None => BTreeMap::new(),
+            Some(_) => {
+                return Err(Error::InvalidRequest(
+                    "user info must be a JSON object".into(),
+                ))
+            }
+        };
+
+        Ok(payload)
+    }
+}
+
+fn unsupported(what: &str) -> Error {
+    Error::InvalidRequest(format!("{what} is not supported by a2"))
+}
+
+#[cfg(test)]
+mod test {
+    use a2::request::payload::PayloadLike;
+    use serde_json::json;
+
+    use super::*;
+    use crate::payload::Sound;
+
+    fn to_value(payload: &impl serde::Serialize) -> Value {
+        let value = serde_json::to_value(payload).unwrap();
+        // a2 always sends `mutable-content`, this crate omits it when `0`.
+        strip_zero_mutable_content(value)
+    }
+
+    fn strip_zero_mutable_content(mut value: Value) -> Value {
+        let aps = value["aps"].as_object_mut().unwrap();
+        if aps.get("mutable-content") == Some(&json!(0)) {
+            aps.remove("mutable-content");
+        }
+        value
+    }
+
+    #[test]
+    fn from_a2_payload() {
+        let title_loc_args = ["Alice"];
+        let loc_args = ["Alice", "Bob"];
+        let mut a2_payload = DefaultNotificationBuilder::new()
+            .set_title_loc_key("TITLE")
+            .set_title_loc_args(&title_loc_args)
+            .set_subtitle("Subtitle")
+            .set_loc_key("BODY")
+            .set_loc_args(&loc_args)
+            .set_launch_image("launch.png")
+            .set_badge(3)
+            .set_sound("ping.caf")
+            .set_critical(true, Some(0.5))
+            .set_category("MESSAGE")
+            .set_mutable_content()
+            .build("device-token", Default::default());
+        a2_payload
+            .add_custom_data("custom", &json!({ "foo": [1, 2], "bar": "baz" }))
+            .unwrap();
+        let expected = strip_zero_mutable_content(
+            serde_json::from_str(&a2_payload.to_json_string().unwrap()).unwrap(),
+        );
+
+        let payload = Payload::try_from(a2_payload).unwrap();
+        assert_eq!(to_value(&payload), expected);
+        assert_eq!(
+            payload.aps.sound,
+            Some(Sound {
+                critical: true,
+                name: "ping.caf".into(),
+                volume: 0.5,
+                extra: None,
+            })
+        );
+        assert_eq!(
+            payload.user_info,
+            Some(json!({ "custom": { "foo": [1, 2], "bar": "baz" } }))
+        );
+    }
+
+    #[test]
+    fn from_a2_payload_body() {
+        let a2_payload = DefaultNotificationBuilder::new()
+            .set_body("Hello \"world\"")
+            .set_content_available()
+            .build("device-token", Default::default());
+        let expected = to_value(&a2_payload);
+
+        let payload = Payload::try_from(a2_payload).unwrap();
+        assert_eq!(to_value(&payload), expected);
+        assert_eq!(payload.aps.alert, Some("Hello \"world\"".into()));
+        assert!(payload.aps.content_available);
+        assert_eq!(payload.user_info, None);
+    }
+
+    #[test]
+    fn from_a2_payload_unknown_keys() {
+        let a2_payload = DefaultNotificationBuilder::new()
+            .set_title("Title")
+            .set_action_loc_key("VIEW")
+            .build("device-token", Default::default());
+        let expected = to_value(&a2_payload);
+
+        let payload = Payload::try_from(a2_payload).unwrap();
+        assert_eq!(to_value(&payload), expected);
+        let alert = payload.aps.alert.unwrap();
+        assert_eq!(alert.title.as_deref(), Some("Title"));
+        assert_eq!(
+            alert.extra.unwrap().get("action-loc-key"),
+            Some(&Value::from("VIEW"))
+        );
+
+        let url_args = ["a"];
+        let a2_payload = a2::WebNotificationBuilder::new(
+            a2::WebPushAlert {
+                title: "Title",
+                body: "Body",
+                action: "View",
+            },
+            &url_args,
+        )
+        .build("device-token", Default::default());
+        assert!(matches!(
+            Payload::try_from(a2_payload),
+            Err(Error::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn from_a2_request() {
+        let a2_payload = DefaultNotificationBuilder::new()
+            .set_title("Title")
+            .set_body("Body")
+            .build(
+                "device-token",
+                NotificationOptions {
+                    apns_id: Some("0a4f3e1c-5d2b-4c6a-9e8f-7b1d2c3e4f50"),
+                    apns_push_type: Some(a2::request::notification::PushType::Alert),
+                    apns_expiration: Some(1_700_000_000),
+                    apns_priority: Some(a2::request::notification::Priority::Normal),
+                    apns_topic: Some("com.example.app"),
+                    apns_collapse_id: Some(CollapseId::new("collapse").unwrap()),
+                },
+            );
+
+        let request = Request::try_from(a2_payload).unwrap();
+        assert_eq!(
+            request,
+            Request {
+                device_token: String::from("device-token").into(),
+                push_type: PushType::Alert,
+                id: Some("0a4f3e1c-5d2b-4c6a-9e8f-7b1d2c3e4f50".parse().unwrap()),
+                expiration: Expiration::At(
+                    OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap()
+                ),
+                priority: Some(Priority::ConsiderPower),
+                topic: Some("com.example.app".into()),
+                collapse_id: Some("collapse".into()),
+                alert: Some(Alert {
+                    title: Some("Title".into()),
+                    body: Some("Body".into()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn into_a2_payload() {
+        let request = Request {
+            device_token: String::from("device-token").into(),
+            push_type: PushType::Voip,
+            expiration: Expiration::At(OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap()),
+            priority: Some(Priority::ConsiderPower),
+            topic: Some("com.example.app.voip".into()),
+            collapse_id: Some("collapse".into()),
+            alert: Some(Alert {
+                title_loc_key: Some("TITLE".into()),
+                title_loc_args: Some(vec!["Alice".into()]),
+                subtitle: Some("Subtitle \\ with backslash".into()),
+                loc_key: Some("BODY".into()),
+                loc_args: Some(vec!["Alice".into(), "Bob".into()]),
+                launch_image: Some("launch.png".into()),
+                ..Default::default()
+            }),
+            badge: Some(Badge::Count(1)),
+            sound: Some("ping.caf".into()),
+            category: Some("MESSAGE".into()),
+            mutable_content: true,
+            user_info: Some(json!({ "custom": { "foo": true } })),
+            ..Default::default()
+        };
+
+        let a2_payload = a2::request::payload::Payload::try_from(&request).unwrap();
+        let (_, payload): (_, Payload<Value>) = request.clone().try_into().unwrap();
+        assert_eq!(to_value(&a2_payload), to_value(&payload));
+
+        assert_eq!(a2_payload.device_token, "device-token");
+        assert_eq!(a2_payload.options.apns_topic, Some("com.example.app.voip"));
+        assert_eq!(a2_payload.options.apns_expiration, Some(1_700_000_000));
+        assert_eq!(
+            a2_payload
+                .options
+                .apns_collapse_id
+                .as_ref()
+                .map(|id| id.value),
+            Some("collapse")
+        );
+
+        let roundtrip = Request::try_from(a2_payload).unwrap();
+        assert_eq!(roundtrip, request);
+    }
+
+    #[test]
+    fn into_a2_payload_body() {
+        let request = Request {
+            device_token: String::from("device-token").into(),
+            alert: Some("Hello 😀".into()),
+            sound: Some("default".into()),
+            content_available: true,
+            ..Default::default()
+        };
+
+        let a2_payload = a2::request::payload::Payload::try_from(&request).unwrap();
+        let (_, payload): (_, Payload<Value>) = request.clone().try_into().unwrap();
+        assert_eq!(to_value(&a2_payload), to_value(&payload));
+        assert_eq!(Request::try_from(a2_payload).unwrap(), request);
+    }
+
+    #[test]
+    fn into_a2_payload_lossy() {
+        let requests = [
+            Request {
+                id: Some(Uuid::nil()),
+                ..Default::default()
+            },
+            Request {
+                priority: Some(Priority::PrioritizePower),
+                ..Default::default()
+            },
+            Request {
+                push_type: PushType::Widgets,
+                ..Default::default()
+            },
+            Request {
+                thread_id: Some("thread".into()),
+                ..Default::default()
+            },
+            Request {
+                alert: Some(Alert {
+                    subtitle_loc_key: Some("SUBTITLE".into()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            Request {
+                user_info: Some(json!([1, 2, 3])),
+                ..Default::default()
+            },
+        ];
+
+        for request in &requests {
+            assert!(
+                matches!(
+                    a2::request::payload::Payload::try_from(request),
+                    Err(Error::InvalidRequest(_))
+                ),
+                "{request:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,383 @@
+//! Audit records of sent push notifications.
+//!
+//! An [`AuditRecord`] notes what was sent and how it went without the
+//! content of the notification: the topic, push type, interruption level,
+//! timestamps, payload size and outcome, but no alert text or user info. The
+//! device token is masked like [`Redaction`](crate::Redaction) does by
+//! default. Set [`ClientBuilder::audit_sink`] to receive a record after every
+//! [`Client::post`] and [`Client::post_mdm`], also of the
+//! [`blocking`](crate::blocking) client.
+//!
+//! [`ClientBuilder::audit_sink`]: crate::ClientBuilder::audit_sink
+//! [`Client::post`]: crate::Client::post
+//! [`Client::post_mdm`]: crate::Client::post_mdm
+
+use serde::Serialize;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::header::{Expiration, PushType};
+use crate::mdm::MdmRequest;
+use crate::payload::InterruptionLevel;
+use crate::request::{redact_device_token, Request};
+use crate::result::{Error, Result};
+
+/// Receives an [`AuditRecord`] after every [`Client::post`] and
+/// [`Client::post_mdm`], see [`ClientBuilder::audit_sink`].
+///
+/// Called on the task that posted the request, so a slow sink slows down
+/// sending. Implemented for `Fn(AuditRecord)` closures.
+///
+/// [`Client::post`]: crate::Client::post
+/// [`Client::post_mdm`]: crate::Client::post_mdm
+/// [`ClientBuilder::audit_sink`]: crate::ClientBuilder::audit_sink
+pub trait AuditSink {
+    /// Records the outcome of a push notification.
+    fn record(&self, record: AuditRecord);
+}
+
+impl<F> AuditSink for F
+where
+    F: Fn(AuditRecord),
+{
+    fn record(&self, record: AuditRecord) {
+        self(record)
+    }
+}
+
+impl std::fmt::Debug for dyn AuditSink + Send + Sync {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AuditSink")
+    }
+}
+
+/// When a push notification was sent and how large it was, see
+/// [`AuditRecord::from_request_and_outcome`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResponseMeta {
+    /// When sending started.
+    pub sent_at: OffsetDateTime,
+
+    /// When the response arrived or sending failed.
+    pub completed_at: OffsetDateTime,
+
+    /// The size in bytes of the payload, if it was encoded.
+    pub payload_size: Option<usize>,
+}
+
+/// How a push notification ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuditOutcome {
+    /// APNs accepted the notification.
+    Accepted,
+
+    /// APNs rejected the notification, see [`AuditRecord::reason`].
+    Rejected,
+
+    /// The client refused to send the request, e.g. because it failed
+    /// validation or its payload was too large.
+    Invalid,
+
+    /// The [`deadline`](crate::Request::deadline) passed before a response.
+    DeadlineExceeded,
+
+    /// Sending failed otherwise, e.g. the connection failed or the response
+    /// was unexpected.
+    Failed,
+}
+
+impl AuditOutcome {
+    /// Classifies the result of [`Client::post`](crate::Client::post).
+    pub fn from_result(result: Result<Uuid, &Error>) -> Self {
+        let err = match result {
+            Ok(_) => return Self::Accepted,
+            Err(err) => err,
+        };
+        if err.reason().is_some() {
+            return Self::Rejected;
+        }
+        if err.is_deadline_exceeded() {
+            return Self::DeadlineExceeded;
+        }
+        match without_apns_id(err) {
+            Error::CriticalSound
+            | Error::InvalidDeviceToken
+            | Error::InvalidHeader { .. }
+            | Error::InvalidRequest(_)
+            | Error::Mutator(_)
+            | Error::NilApnsId
+            | Error::PayloadTooLarge { .. }
+            | Error::SerdeJson(_)
+            | Error::Validation(_) => Self::Invalid,
+            _ => Self::Failed,
+        }
+    }
+}
+
+/// Record of a sent push notification without its content, see the
+/// [module](self) docs.
+///
+/// The serialized field names are a stable interface for audit logs. Fields
+/// without a value serialize as `null`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AuditRecord {
+    /// The device token with all but its first and last four characters
+    /// masked.
+    pub device_token: String,
+
+    /// The `apns-topic` of the request.
+    pub topic: Option<String>,
+
+    /// The `apns-push-type` of the request.
+    pub push_type: PushType,
+
+    /// The interruption level of the notification.
+    pub interruption_level: Option<InterruptionLevel>,
+
+    /// The `apns-expiration` of the request.
+    pub expiration: Expiration,
+
+    /// When sending started.
+    #[serde(with = "time::serde::rfc3339")]
+    pub sent_at: OffsetDateTime,
+
+    /// When the response arrived or sending failed.
+    #[serde(with = "time::serde::rfc3339")]
+    pub completed_at: OffsetDateTime,
+
+    /// The size in bytes of the payload, if it was encoded.
+    pub payload_size: Option<usize>,
+
+    /// The `apns-id` of the notification, if known.
+    pub apns_id: Option<Uuid>,
+
+    /// How the push notification ended.
+    pub outcome: AuditOutcome,
+
+    /// The APNs error reason of a [`AuditOutcome::Rejected`] notification.
+    pub reason: Option<&'static str>,
+}
+
+impl AuditRecord {
+    /// Creates the record of posting `request` with `outcome`.
+    ///
+    /// ```
+    /// use apple_apns::audit::{AuditOutcome, AuditRecord, ResponseMeta};
+    /// use apple_apns::time::OffsetDateTime;
+    /// use apple_apns::{Reason, Request};
+    ///
+    /// let request = Request::builder()
+    ///     .device_token("a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae")
+    ///     .alert("Hello World!")
+    ///     .build()?;
+    /// let meta = ResponseMeta {
+    ///     sent_at: OffsetDateTime::now_utc(),
+    ///     completed_at: OffsetDateTime::now_utc(),
+    ///     payload_size: Some(38),
+    /// };
+    ///
+    /// let record = AuditRecord::from_request_and_outcome(
+    ///     &request,
+    ///     &Err(Reason::BadDeviceToken.into()),
+    ///     &meta,
+    /// );
+    /// assert_eq!(record.device_token, "a863…e5ae");
+    /// assert_eq!(record.outcome, AuditOutcome::Rejected);
+    /// assert_eq!(record.reason, Some("BadDeviceToken"));
+    /// # Ok::<_, apple_apns::Error>(())
+    /// ```
+    pub fn from_request_and_outcome<T>(
+        request: &Request<T>,
+        outcome: &Result<Uuid>,
+        meta: &ResponseMeta,
+    ) -> Self {
+        let mut record = Self::new(request, request.push_type, meta.sent_at);
+        record.complete(outcome.as_ref().copied(), meta);
+        record
+    }
+
+    /// Creates the record of `request` before it is sent, with
+    /// `push_type` as sent.
+    pub(crate) fn new<T>(
+        request: &Request<T>,
+        push_type: PushType,
+        sent_at: OffsetDateTime,
+    ) -> Self {
+        Self {
+            device_token: redact_device_token(request.device_token.as_str()),
+            topic: request.topic.clone(),
+            push_type,
+            interruption_level: request.interruption_level,
+            expiration: request.expiration,
+            sent_at,
+            completed_at: sent_at,
+            payload_size: None,
+            apns_id: request.id,
+            outcome: AuditOutcome::Failed,
+            reason: None,
+        }
+    }
+
+    /// Creates the record of an MDM push before it is sent.
+    pub(crate) fn from_mdm(request: &MdmRequest, sent_at: OffsetDateTime) -> Self {
+        Self {
+            device_token: redact_device_token(request.device_token.as_str()),
+            topic: request.topic.clone(),
+            push_type: PushType::Mdm,
+            interruption_level: None,
+            expiration: request.expiration,
+            sent_at,
+            completed_at: sent_at,
+            payload_size: None,
+            apns_id: request.id,
+            outcome: AuditOutcome::Failed,
+            reason: None,
+        }
+    }
+
+    /// Completes the record with `outcome` now and passes it to `sink`.
+    pub(crate) fn record_to(
+        mut self,
+        sink: &(dyn AuditSink + Send + Sync),
+        outcome: Result<Uuid, &Error>,
+        payload_size: Option<usize>,
+    ) {
+        let meta = ResponseMeta {
+            sent_at: self.sent_at,
+            completed_at: OffsetDateTime::now_utc(),
+            payload_size,
+        };
+        self.complete(outcome, &meta);
+        sink.record(self);
+    }
+
+    /// Sets the outcome of the record.
+    pub(crate) fn complete(&mut self, outcome: Result<Uuid, &Error>, meta: &ResponseMeta) {
+        self.sent_at = meta.sent_at;
+        self.completed_at = meta.completed_at;
+        self.payload_size = meta.payload_size;
+        self.apns_id = match outcome {
+            Ok(apns_id) => Some(apns_id),
+            Err(err) => err.apns_id().or(self.apns_id),
+        };
+        self.outcome = AuditOutcome::from_result(outcome);
+        self.reason = outcome
+            .err()
+            .and_then(Error::reason)
+            .map(|reason| reason.as_str());
+        if let Err(Error::PayloadTooLarge { size, .. }) = outcome.map_err(without_apns_id) {
+            self.payload_size = self.payload_size.or(Some(*size));
+        }
+    }
+}
+
+/// Returns the error wrapped by [`Error::WithApnsId`].
+fn without_apns_id(mut err: &Error) -> &Error {
+    while let Error::WithApnsId { source, .. } = err {
+        err = source;
+    }
+    err
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use http::StatusCode;
+    use serde_json::json;
+
+    use super::*;
+    use crate::reason::Reason;
+
+    const DEVICE_TOKEN: &str = "a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae";
+    const APNS_ID: &str = "4d947500-498e-4524-8aa8-7220c4e65d75";
+
+    #[test]
+    fn outcome_from_result() {
+        let apns_id: Uuid = APNS_ID.parse().unwrap();
+        let with_apns_id = |err| Error::WithApnsId {
+            apns_id,
+            source: Box::new(err),
+        };
+        let cases = [
+            (Error::Apns(Reason::BadDeviceToken), AuditOutcome::Rejected),
+            (
+                with_apns_id(Error::Apns(Reason::BadTopic)),
+                AuditOutcome::Rejected,
+            ),
+            (Error::Validation(Vec::new()), AuditOutcome::Invalid),
+            (
+                with_apns_id(Error::PayloadTooLarge {
+                    size: 5000,
+                    limit: 4096,
+                }),
+                AuditOutcome::Invalid,
+            ),
+            (Error::NilApnsId, AuditOutcome::Invalid),
+            (
+                with_apns_id(Error::DeadlineExceeded),
+                AuditOutcome::DeadlineExceeded,
+            ),
+            (
+                Error::UnexpectedResponse {
+                    status: StatusCode::BAD_GATEWAY,
+                    body: None,
+                },
+                AuditOutcome::Failed,
+            ),
+            (Error::Unknown, AuditOutcome::Failed),
+        ];
+        assert_eq!(
+            AuditOutcome::from_result(Ok(apns_id)),
+            AuditOutcome::Accepted
+        );
+        for (err, outcome) in cases {
+            assert_eq!(AuditOutcome::from_result(Err(&err)), outcome, "{err}");
+        }
+    }
+
+    #[test]
+    fn from_request_and_outcome() {
+        let request = Request::builder()
+            .device_token(DEVICE_TOKEN)
+            .topic("com.example.myapp")
+            .alert("Top secret")
+            .interruption_level(InterruptionLevel::TimeSensitive)
+            .expiration(Expiration::In(Duration::from_secs(60)))
+            .user_info(json!({ "acme": "classified" }))
+            .build()
+            .unwrap();
+        let meta = ResponseMeta {
+            sent_at: OffsetDateTime::from_unix_timestamp(1685952000).unwrap(),
+            completed_at: OffsetDateTime::from_unix_timestamp(1685952001).unwrap(),
+            payload_size: None,
+        };
+        let outcome = Err(Error::WithApnsId {
+            apns_id: APNS_ID.parse().unwrap(),
+            source: Box::new(Error::PayloadTooLarge {
+                size: 5000,
+                limit: 4096,
+            }),
+        });
+
+        let record = AuditRecord::from_request_and_outcome(&request, &outcome, &meta);
+        assert_eq!(
+            serde_json::to_value(record).unwrap(),
+            json!({
+                "device-token": "a863…e5ae",
+                "topic": "com.example.myapp",
+                "push-type": "alert",
+                "interruption-level": "time-sensitive",
+                "expiration": { "in": 60.0 },
+                "sent-at": "2023-06-05T08:00:00Z",
+                "completed-at": "2023-06-05T08:00:01Z",
+                "payload-size": 5000,
+                "apns-id": APNS_ID,
+                "outcome": "invalid",
+                "reason": null,
+            })
+        );
+    }
+}
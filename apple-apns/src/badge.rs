@@ -0,0 +1,129 @@
+//! Server-side badge counters for [`Badge::Increment`].
+//!
+//! APNs can only set the badge to a number, so a server that wants to add
+//! one to the badge has to know the current count. A [`BadgeCounter`] set as
+//! [`ClientBuilder::badge_counter`] keeps that count, and
+//! [`Client::post`] replaces [`Badge::Increment`] with its next number.
+//!
+//! [`ClientBuilder::badge_counter`]: crate::ClientBuilder::badge_counter
+//! [`Client::post`]: crate::Client::post
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use futures_util::future::BoxFuture;
+
+use crate::payload::Badge;
+
+/// Badge count per device token, see the [module](self) docs.
+///
+/// Implement it on a shared store, like a database, when several instances
+/// send to the same devices.
+pub trait BadgeCounter {
+    /// Increments the badge count of a device and returns it. Called once
+    /// per [`Client::post`](crate::Client::post) of a request with
+    /// [`Badge::Increment`], after the request passed validation and its
+    /// payload is known to fit, so a rejected request isn't counted. `0`
+    /// clears the badge.
+    fn next<'a>(&'a self, device_token: &'a str) -> BoxFuture<'a, u32>;
+}
+
+impl std::fmt::Debug for dyn BadgeCounter + Send + Sync {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BadgeCounter")
+    }
+}
+
+/// In-memory [`BadgeCounter`] for tests and single-instance deployments.
+///
+/// Counts are lost on restart and not shared between processes.
+///
+/// ```
+/// use apple_apns::{BadgeCounter, MemoryBadgeCounter};
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let counter = MemoryBadgeCounter::default();
+/// assert_eq!(counter.next("a863a50a").await, 1);
+/// assert_eq!(counter.next("a863a50a").await, 2);
+///
+/// // The user opened the app, which cleared the badge.
+/// counter.reset("a863a50a");
+/// assert_eq!(counter.next("a863a50a").await, 1);
+/// # });
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryBadgeCounter {
+    counts: Mutex<HashMap<String, u32>>,
+}
+
+impl MemoryBadgeCounter {
+    /// Returns the current badge count of a device, `0` if unknown.
+    pub fn get(&self, device_token: &str) -> u32 {
+        let counts = self.counts.lock().unwrap();
+        counts.get(device_token).copied().unwrap_or_default()
+    }
+
+    /// Resets the badge count of a device, e.g. when the app reports that
+    /// the user has seen the notifications.
+    pub fn reset(&self, device_token: &str) {
+        self.counts.lock().unwrap().remove(device_token);
+    }
+}
+
+impl BadgeCounter for MemoryBadgeCounter {
+    fn next<'a>(&'a self, device_token: &'a str) -> BoxFuture<'a, u32> {
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts.entry(device_token.into()).or_default();
+        *count = count.saturating_add(1);
+        let count = *count;
+        Box::pin(async move { count })
+    }
+}
+
+/// Replaces [`Badge::Increment`] with the next number of `counter`.
+pub(crate) async fn resolve(
+    counter: &(dyn BadgeCounter + Send + Sync),
+    device_token: &str,
+    badge: &mut Option<Badge>,
+) {
+    if *badge == Some(Badge::Increment) {
+        *badge = Some(Badge::from(counter.next(device_token).await));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn memory_badge_counter() {
+        let counter = MemoryBadgeCounter::default();
+        assert_eq!(counter.get("a"), 0);
+        assert_eq!(counter.next("a").await, 1);
+        assert_eq!(counter.next("a").await, 2);
+        assert_eq!(counter.next("b").await, 1);
+        assert_eq!(counter.get("a"), 2);
+
+        counter.reset("a");
+        assert_eq!(counter.get("a"), 0);
+        assert_eq!(counter.next("a").await, 1);
+        assert_eq!(counter.get("b"), 1);
+    }
+
+    #[tokio::test]
+    async fn resolve_increment() {
+        let counter = MemoryBadgeCounter::default();
+        for (badge, resolved) in [
+            (None, None),
+            (Some(Badge::Clear), Some(Badge::Clear)),
+            (Some(Badge::Count(5)), Some(Badge::Count(5))),
+            (Some(Badge::Increment), Some(Badge::Count(1))),
+            (Some(Badge::Increment), Some(Badge::Count(2))),
+        ] {
+            let mut badge = badge;
+            resolve(&counter, "a", &mut badge).await;
+            assert_eq!(badge, resolved);
+        }
+        assert_eq!(counter.get("a"), 2);
+    }
+}
@@ -0,0 +1,323 @@
+//! Sending one notification to many device tokens.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::future::Either;
+use futures_util::stream::{self, Stream, StreamExt};
+use serde::Serialize;
+use tokio::time::Instant;
+use uuid::Uuid;
+
+use crate::client::Client;
+use crate::reason::Reason;
+use crate::request::Request;
+use crate::result::{Error, Result};
+
+/// [`Client::post_batch`] options.
+#[derive(Clone, Copy, Debug)]
+pub struct BatchOptions {
+    /// Maximum number of requests in flight at once.
+    pub concurrency: usize,
+
+    /// Yields results in the order of the device tokens instead of as they
+    /// complete.
+    pub ordered: bool,
+
+    /// How long the whole batch pauses sending after a
+    /// [`TooManyRequests`](Reason::TooManyRequests) error that may not be
+    /// limited to one device token, see [`ThrottleScope::is_global`], when
+    /// the response has no `Retry-After`. Defaults to one second.
+    ///
+    /// [`ThrottleScope::is_global`]: crate::ThrottleScope::is_global
+    pub throttle_backoff: Duration,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 16,
+            ordered: false,
+            throttle_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Result of sending a notification to one device token of a batch.
+#[derive(Debug)]
+pub struct BatchResult {
+    /// The device token as given to [`Client::post_batch`].
+    pub device_token: String,
+
+    /// The APNS ID or the error for this device token.
+    pub result: Result<Uuid>,
+
+    /// The HTTP version of the connection of a successful request, see
+    /// [`Response::version`](crate::Response::version).
+    pub version: Option<http::Version>,
+
+    /// The remote address of the connection of a successful request, see
+    /// [`Response::remote_addr`](crate::Response::remote_addr).
+    pub remote_addr: Option<SocketAddr>,
+
+    /// The payload size of a successful request, see
+    /// [`Response::payload_size`](crate::Response::payload_size).
+    pub payload_size: Option<usize>,
+}
+
+/// Summary of the results of a batch.
+///
+/// The serialized field names are a stable interface for admin endpoints.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct BatchReport {
+    /// Number of device tokens the notification was sent to.
+    pub total: usize,
+
+    /// Number of successful requests.
+    pub ok: usize,
+
+    /// Number of failed requests.
+    pub failed: usize,
+
+    /// Number of failed requests by APNs error reason, with
+    /// `DeadlineExceeded` for requests skipped or timed out because of
+    /// [`Request::deadline`] and `Unknown` for other errors without a reason.
+    pub reasons: BTreeMap<&'static str, usize>,
+
+    /// Total payload size in bytes of the successful requests.
+    pub payload_bytes: usize,
+
+    /// Largest payload size in bytes of the successful requests.
+    pub max_payload_size: usize,
+}
+
+impl BatchReport {
+    /// Adds a result to the report.
+    pub fn record(&mut self, result: &BatchResult) {
+        self.total += 1;
+        match &result.result {
+            Ok(_) => {
+                self.ok += 1;
+                if let Some(payload_size) = result.payload_size {
+                    self.payload_bytes += payload_size;
+                    self.max_payload_size = self.max_payload_size.max(payload_size);
+                }
+            }
+            Err(err) => {
+                self.failed += 1;
+                let reason = match err.reason() {
+                    Some(reason) => reason.as_str(),
+                    None if err.is_deadline_exceeded() => "DeadlineExceeded",
+                    None => Reason::Unknown.as_str(),
+                };
+                *self.reasons.entry(reason).or_default() += 1;
+            }
+        }
+    }
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} sent, {} ok, {} failed",
+            self.total, self.ok, self.failed
+        )?;
+        for (i, (reason, count)) in self.reasons.iter().enumerate() {
+            let separator = if i == 0 { " (" } else { ", " };
+            write!(f, "{separator}{count} {reason}")?;
+        }
+        if !self.reasons.is_empty() {
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+impl Client {
+    /// Sends `request` to each of `device_tokens` and yields a
+    /// [`BatchResult`] for each device token as the requests complete.
+    ///
+    /// The `device_token` of `request` is ignored. Once the
+    /// [`deadline`](Request::deadline) of `request` passes, the device tokens
+    /// not yet sent to fail with [`Error::DeadlineExceeded`](crate::Error::DeadlineExceeded)
+    /// without sending.
+    ///
+    /// After a `TooManyRequests` error whose
+    /// [`ThrottleScope`](crate::ThrottleScope) is not limited to the device
+    /// token, no new requests are sent until its `Retry-After` or
+    /// [`BatchOptions::throttle_backoff`] passes. Throttling of a single
+    /// device token doesn't slow down the batch.
+    pub fn post_batch<'a, T, I>(
+        &'a self,
+        request: Request<T>,
+        device_tokens: I,
+        options: BatchOptions,
+    ) -> impl Stream<Item = BatchResult> + 'a
+    where
+        T: Clone + Serialize + 'a,
+        I: IntoIterator<Item = String>,
+        I::IntoIter: 'a,
+    {
+        let pause = Arc::new(Pause::default());
+        let requests = stream::iter(device_tokens).map(move |device_token| {
+            let request = Request {
+                device_token: device_token.clone().into(),
+                ..request.clone()
+            };
+            let pause = pause.clone();
+            async move {
+                pause.wait().await;
+                let result = self.post_with_response(request).await;
+                if let Err(err) = &result {
+                    pause.on_error(err, options.throttle_backoff);
+                }
+                match result {
+                    Ok(res) => BatchResult {
+                        device_token,
+                        result: Ok(res.apns_id),
+                        version: Some(res.version),
+                        remote_addr: res.remote_addr,
+                        payload_size: Some(res.payload_size),
+                    },
+                    Err(err) => BatchResult {
+                        device_token,
+                        result: Err(err),
+                        version: None,
+                        remote_addr: None,
+                        payload_size: None,
+                    },
+                }
+            }
+        });
+
+        let concurrency = options.concurrency.max(1);
+        if options.ordered {
+            Either::Left(requests.buffered(concurrency))
+        } else {
+            Either::Right(requests.buffer_unordered(concurrency))
+        }
+    }
+}
+
+/// When a batch may send again after provider-level throttling.
+#[derive(Default)]
+struct Pause(Mutex<Option<Instant>>);
+
+impl Pause {
+    /// Waits until the pause, which may be extended while waiting, is over.
+    async fn wait(&self) {
+        loop {
+            let until = *self.0.lock().unwrap();
+            match until {
+                Some(until) if until > Instant::now() => tokio::time::sleep_until(until).await,
+                _ => return,
+            }
+        }
+    }
+
+    /// Pauses the batch if `err` is throttling that may not be limited to
+    /// one device token.
+    fn on_error(&self, err: &Error, backoff: Duration) {
+        let throttle = match err.throttle() {
+            Some(throttle) if throttle.scope.is_global() => throttle,
+            _ => return,
+        };
+        let until = Instant::now() + throttle.retry_after.unwrap_or(backoff);
+        let mut pause = self.0.lock().unwrap();
+        if !matches!(*pause, Some(pause) if pause >= until) {
+            *pause = Some(until);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::reason::{ThrottleInfo, ThrottleScope};
+
+    fn result(result: Result<Uuid>) -> BatchResult {
+        BatchResult {
+            device_token: String::new(),
+            payload_size: result.is_ok().then_some(100),
+            result,
+            version: None,
+            remote_addr: None,
+        }
+    }
+
+    #[test]
+    fn batch_report() {
+        let mut report = BatchReport::default();
+        assert_eq!(report.to_string(), "0 sent, 0 ok, 0 failed");
+
+        report.record(&result(Ok(Uuid::nil())));
+        report.record(&BatchResult {
+            payload_size: Some(250),
+            ..result(Ok(Uuid::nil()))
+        });
+        report.record(&result(Err(Error::Apns(Reason::BadDeviceToken))));
+        report.record(&result(Err(Error::WithApnsId {
+            apns_id: Uuid::nil(),
+            source: Box::new(Error::Apns(Reason::BadDeviceToken)),
+        })));
+        report.record(&result(Err(Error::Unknown)));
+        report.record(&result(Err(Error::DeadlineExceeded)));
+
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            json!({
+                "total": 6,
+                "ok": 2,
+                "failed": 4,
+                "reasons": {
+                    "BadDeviceToken": 2,
+                    "DeadlineExceeded": 1,
+                    "Unknown": 1,
+                },
+                "payload_bytes": 350,
+                "max_payload_size": 250,
+            })
+        );
+        assert_eq!(
+            report.to_string(),
+            "6 sent, 2 ok, 4 failed (2 BadDeviceToken, 1 DeadlineExceeded, 1 Unknown)"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pause_on_throttling() {
+        let throttled = |scope, retry_after| Error::WithApnsId {
+            apns_id: Uuid::nil(),
+            source: Box::new(Error::Throttled {
+                throttle: ThrottleInfo { scope, retry_after },
+                source: Box::new(Error::Apns(Reason::TooManyRequests)),
+            }),
+        };
+        let backoff = Duration::from_secs(1);
+        let pause = Pause::default();
+
+        let start = Instant::now();
+        pause.on_error(&Error::Apns(Reason::BadDeviceToken), backoff);
+        pause.on_error(&throttled(ThrottleScope::Device, None), backoff);
+        pause.wait().await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        pause.on_error(&throttled(ThrottleScope::Unknown, None), backoff);
+        pause.wait().await;
+        assert_eq!(start.elapsed(), backoff);
+
+        let start = Instant::now();
+        let retry_after = Some(Duration::from_secs(30));
+        pause.on_error(&throttled(ThrottleScope::Provider, retry_after), backoff);
+        // A shorter pause doesn't cut a longer one short.
+        pause.on_error(&throttled(ThrottleScope::Unknown, None), backoff);
+        pause.wait().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(30));
+    }
+}
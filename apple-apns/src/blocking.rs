@@ -0,0 +1,286 @@
+//! Blocking Apple Push Notification service client.
+//!
+//! The blocking [`Client`] sends requests synchronously and does not require
+//! an async runtime. It must not be used from within an async runtime.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use reqwest::tls::Version;
+use serde::Serialize;
+use time::OffsetDateTime;
+use url::Url;
+use uuid::Uuid;
+
+use crate::audit::{AuditRecord, AuditSink};
+use crate::client::{
+    build_http_request, decode_apns_id, decode_error, is_error_status, is_unprocessed, lint,
+    mutate_request, remaining_until, ClientBuilder, RequestMutator, RequestPolicy,
+};
+#[cfg(feature = "jwt")]
+use crate::client::{clone_http_request, is_provider_token_error, with_bearer_token};
+use crate::header::PushType;
+use crate::metrics::Metrics;
+use crate::reason::ThrottleInfo;
+use crate::request::{Lint, Request, ValidationError};
+use crate::result::{Error, Result};
+use crate::strings::LocalizableStrings;
+#[cfg(feature = "jwt")]
+use crate::token::TokenFactory;
+
+impl<'a> ClientBuilder<'a> {
+    /// Builds a blocking [`Client`].
+    ///
+    /// The blocking client does not support
+    /// [`ClientBuilder::max_inflight_bytes`] because each request blocks the
+    /// calling thread.
+    #[cfg_attr(docsrs, doc(cfg(feature = "blocking")))]
+    pub fn build_blocking(self) -> Result<Client> {
+        self.check_endpoint()?;
+        self.check_authentication()?;
+        self.check_http2()?;
+
+        #[allow(unused_mut)]
+        let mut builder = reqwest::blocking::Client::builder()
+            .user_agent(self.user_agent)
+            .pool_idle_timeout(None::<Duration>)
+            .min_tls_version(Version::TLS_1_2);
+
+        #[cfg(not(feature = "http1"))]
+        {
+            builder = builder.http2_prior_knowledge();
+        }
+
+        if let Some(enabled) = self.http2_adaptive_window {
+            builder = builder.http2_adaptive_window(enabled);
+        }
+        if let Some(size) = self.http2_max_frame_size {
+            builder = builder.http2_max_frame_size(size);
+        }
+
+        if let Some(host) = self.pinned_host() {
+            builder = builder.resolve_to_addrs(host, &self.pin_addresses);
+        }
+
+        #[cfg(feature = "rustls")]
+        {
+            // Force rustls
+            builder = builder.use_rustls_tls();
+
+            // Add root certificate
+            if let Some(cert) = self.root_certificate()? {
+                builder = builder.add_root_certificate(cert);
+            }
+
+            // Configure certificate authentication
+            if let Some(identity) = self.identity()? {
+                builder = builder.identity(identity);
+            }
+        }
+
+        Ok(Client {
+            base_url: self.endpoint.as_url().clone(),
+            client: builder.build()?,
+            metrics: self.metrics.clone(),
+            request_mutator: self.request_mutator.clone(),
+            audit_sink: self.audit_sink.clone(),
+            generate_apns_id: self.generate_apns_id,
+            policy: self.request_policy(),
+            localizable_strings: self.localizable_strings.clone(),
+            #[cfg(feature = "jwt")]
+            token_factory: self.token_factory()?,
+        })
+    }
+}
+
+/// Blocking Apple Push Notification service client.
+///
+/// The [`Client`] is safe to use from multiple threads, but is not [`Clone`].
+/// To share a [`Client`], wrap it in an [`std::sync::Arc`].
+pub struct Client {
+    base_url: Url,
+    client: reqwest::blocking::Client,
+    metrics: Arc<dyn Metrics + Send + Sync>,
+    request_mutator: Option<Arc<dyn RequestMutator + Send + Sync>>,
+    audit_sink: Option<Arc<dyn AuditSink + Send + Sync>>,
+    generate_apns_id: bool,
+    policy: RequestPolicy,
+    localizable_strings: Option<Arc<LocalizableStrings>>,
+
+    #[cfg(feature = "jwt")]
+    token_factory: Option<TokenFactory>,
+}
+
+impl Client {
+    /// Creates a [`ClientBuilder`].
+    pub fn builder<'a>() -> ClientBuilder<'a> {
+        ClientBuilder::new()
+    }
+
+    /// See [`crate::Client::validate`].
+    pub fn validate<T>(&self, request: &Request<T>) -> std::result::Result<(), Vec<ValidationError>>
+    where
+        T: Serialize,
+    {
+        self.policy.validate(request)
+    }
+
+    /// See [`crate::Client::lint`].
+    pub fn lint<T>(&self, request: &Request<T>) -> Vec<Lint>
+    where
+        T: Serialize,
+    {
+        lint(&self.policy, self.localizable_strings.as_deref(), request)
+    }
+
+    /// Builds the HTTP request that [`Client::post`] sends for a push
+    /// notification, without sending it.
+    pub fn build_http_request<T>(&self, request: Request<T>) -> Result<http::Request<Vec<u8>>>
+    where
+        T: Serialize,
+    {
+        let req = build_http_request(&self.base_url, &self.policy, None, request)?;
+        self.authorize(req)
+    }
+
+    /// Adds the provider token to a request and applies the request mutator.
+    fn authorize(&self, req: http::Request<Vec<u8>>) -> Result<http::Request<Vec<u8>>> {
+        #[cfg(feature = "jwt")]
+        let req = match &self.token_factory {
+            Some(token_factory) => with_bearer_token(req, &token_factory.get_blocking()?)?,
+            None => req,
+        };
+        mutate_request(self.request_mutator.as_deref(), req)
+    }
+
+    /// Sends a push notification and returns the APNS ID.
+    pub fn post<T>(&self, request: Request<T>) -> Result<Uuid>
+    where
+        T: Serialize,
+    {
+        let audit_sink = match &self.audit_sink {
+            Some(audit_sink) => audit_sink,
+            None => return self.post_request(request, &mut None),
+        };
+
+        let push_type = self.policy.push_type(&request);
+        let record = AuditRecord::new(&request, push_type, OffsetDateTime::now_utc());
+        let mut payload_size = None;
+        let result = self.post_request(request, &mut payload_size);
+        record.record_to(&**audit_sink, result.as_ref().copied(), payload_size);
+        result
+    }
+
+    /// Sends a push notification, setting `payload_size` once the payload
+    /// is encoded.
+    fn post_request<T>(
+        &self,
+        mut request: Request<T>,
+        payload_size: &mut Option<usize>,
+    ) -> Result<Uuid>
+    where
+        T: Serialize,
+    {
+        self.policy.check_id(&mut request.id)?;
+
+        if !self.generate_apns_id {
+            return self.execute(request, payload_size);
+        }
+
+        let apns_id = *request.id.get_or_insert_with(Uuid::new_v4);
+
+        match self.execute(request, payload_size) {
+            Ok(_) => Ok(apns_id),
+            Err(err) => Err(Error::WithApnsId {
+                apns_id,
+                source: Box::new(err),
+            }),
+        }
+    }
+
+    fn execute<T>(&self, request: Request<T>, payload_size: &mut Option<usize>) -> Result<Uuid>
+    where
+        T: Serialize,
+    {
+        let push_type = self.policy.push_type(&request);
+        let timeout = remaining_until(request.deadline)?;
+        let unauthorized = build_http_request(&self.base_url, &self.policy, None, request)?;
+
+        // A retry with a new provider token is authorized from scratch, so
+        // that the request mutator sees the new token.
+        #[cfg(feature = "jwt")]
+        let retry = self
+            .token_factory
+            .as_ref()
+            .map(|token_factory| (token_factory, clone_http_request(&unauthorized)));
+
+        let req = self.authorize(unauthorized)?;
+        self.metrics.on_payload(push_type, req.body().len());
+        *payload_size = Some(req.body().len());
+
+        #[cfg(feature = "jwt")]
+        if let Some((token_factory, retry)) = retry {
+            return match self.send(push_type, req, timeout) {
+                Err(err) if is_provider_token_error(&err) => {
+                    token_factory.invalidate_blocking()?;
+                    let retry = self.authorize(retry)?;
+                    self.send(push_type, retry, timeout).map_err(|_| err)
+                }
+                result => result,
+            };
+        }
+
+        self.send(push_type, req, timeout)
+    }
+
+    fn send(
+        &self,
+        push_type: PushType,
+        req: http::Request<Vec<u8>>,
+        timeout: Option<Duration>,
+    ) -> Result<Uuid> {
+        let mut req = reqwest::blocking::Request::try_from(req)?;
+        *req.timeout_mut() = timeout;
+
+        // APNs did not process a request that failed because the connection
+        // was going away, so it is always safe to send it once more.
+        let retry = req.try_clone();
+
+        self.metrics.on_request(push_type);
+        let mut start = Instant::now();
+        let res = match (self.client.execute(req), retry) {
+            (Err(err), Some(retry)) if is_unprocessed(&err) => {
+                self.metrics.on_error(&err.into(), start.elapsed());
+                self.metrics.on_request(push_type);
+                start = Instant::now();
+                self.client.execute(retry)
+            }
+            (res, _) => res,
+        };
+        let res = match res {
+            Ok(res) => res,
+            Err(err) => {
+                let err = if timeout.is_some() && err.is_timeout() {
+                    Error::DeadlineExceeded
+                } else {
+                    err.into()
+                };
+                self.metrics.on_error(&err, start.elapsed());
+                return Err(err);
+            }
+        };
+        let latency = start.elapsed();
+        let status = res.status();
+
+        if is_error_status(status) {
+            let throttle = ThrottleInfo::from_response(status, res.headers());
+            let body = res.bytes().ok();
+            let err = decode_error(status, body.as_deref(), throttle);
+            self.metrics.on_response(status, err.reason(), latency);
+            Err(err)
+        } else {
+            self.metrics.on_response(status, None, latency);
+            Ok(decode_apns_id(res.headers()))
+        }
+    }
+}
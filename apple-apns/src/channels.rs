@@ -0,0 +1,164 @@
+//! Broadcast channel management.
+//!
+//! Broadcast channels deliver Live Activity updates to every device subscribed
+//! to a channel. Channels are managed per bundle ID on a dedicated APNs
+//! endpoint, see [`Endpoint::channels_url`](crate::Endpoint::channels_url),
+//! and [`Client::post_broadcast`] sends an update to a channel, see
+//! [`broadcast_url`](crate::broadcast_url).
+
+use http::{HeaderMap, HeaderValue, Method};
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use uuid::Uuid;
+
+use crate::client::Client;
+use crate::header::APNS_CHANNEL_ID;
+use crate::request::Request;
+use crate::result::{Error, Result};
+
+/// Whether APNs stores the most recent message for a channel to deliver to
+/// devices that are offline.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize_repr, Serialize_repr)]
+#[repr(u8)]
+pub enum MessageStoragePolicy {
+    /// Do not store messages.
+    #[default]
+    NoMessageStored = 0,
+
+    /// Store the most recent message.
+    MostRecentMessageStored = 1,
+}
+
+/// Push type of the notifications sent to a channel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum ChannelPushType {
+    #[default]
+    LiveActivity,
+}
+
+/// Broadcast channel configuration.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ChannelConfig {
+    pub message_storage_policy: MessageStoragePolicy,
+    pub push_type: ChannelPushType,
+}
+
+#[derive(Deserialize)]
+struct Channels {
+    channels: Vec<String>,
+}
+
+impl Client {
+    /// Creates a broadcast channel for `bundle_id` and returns its channel ID.
+    pub async fn create_channel(&self, bundle_id: &str, config: &ChannelConfig) -> Result<String> {
+        let body = serde_json::to_vec(config)?;
+        let res = self
+            .execute_channel_request(
+                Method::POST,
+                &channels_path(bundle_id),
+                HeaderMap::new(),
+                Some(body),
+            )
+            .await?;
+
+        res.headers()
+            .get(&APNS_CHANNEL_ID)
+            .and_then(|v| v.to_str().ok())
+            .map(Into::into)
+            .ok_or(Error::Unknown)
+    }
+
+    /// Reads the configuration of a broadcast channel.
+    pub async fn read_channel(&self, bundle_id: &str, channel_id: &str) -> Result<ChannelConfig> {
+        let res = self
+            .execute_channel_request(
+                Method::GET,
+                &channels_path(bundle_id),
+                channel_headers(channel_id)?,
+                None,
+            )
+            .await?;
+
+        Ok(serde_json::from_slice(&res.bytes().await?)?)
+    }
+
+    /// Returns the IDs of all broadcast channels for `bundle_id`.
+    pub async fn read_all_channels(&self, bundle_id: &str) -> Result<Vec<String>> {
+        let res = self
+            .execute_channel_request(
+                Method::GET,
+                &format!("{bundle_id}/all-channels"),
+                HeaderMap::new(),
+                None,
+            )
+            .await?;
+
+        let channels: Channels = serde_json::from_slice(&res.bytes().await?)?;
+        Ok(channels.channels)
+    }
+
+    /// Deletes a broadcast channel.
+    pub async fn delete_channel(&self, bundle_id: &str, channel_id: &str) -> Result<()> {
+        self.execute_channel_request(
+            Method::DELETE,
+            &channels_path(bundle_id),
+            channel_headers(channel_id)?,
+            None,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Sends a Live Activity update to every device subscribed to a
+    /// broadcast channel and returns the APNS ID.
+    ///
+    /// The request must have the `liveactivity` push type. Its device token
+    /// and topic are ignored: the update is sent to
+    /// [`broadcast_url`](crate::broadcast_url) for `bundle_id` with
+    /// `channel_id` as the `apns-channel-id` header.
+    pub async fn post_broadcast<T>(
+        &self,
+        bundle_id: &str,
+        channel_id: &str,
+        request: Request<T>,
+    ) -> Result<Uuid>
+    where
+        T: Serialize,
+    {
+        self.execute_broadcast(bundle_id, channel_id, request).await
+    }
+}
+
+fn channels_path(bundle_id: &str) -> String {
+    format!("{bundle_id}/channels")
+}
+
+fn channel_headers(channel_id: &str) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    headers.insert(APNS_CHANNEL_ID.clone(), HeaderValue::from_str(channel_id)?);
+    Ok(headers)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn channel_config_ser() {
+        assert_eq!(
+            serde_json::to_value(ChannelConfig {
+                message_storage_policy: MessageStoragePolicy::MostRecentMessageStored,
+                push_type: ChannelPushType::LiveActivity,
+            })
+            .unwrap(),
+            json!({
+                "message-storage-policy": 1,
+                "push-type": "LiveActivity",
+            })
+        );
+    }
+}
@@ -1,29 +1,55 @@
-use std::time::Duration;
+#[cfg(feature = "jwt")]
+use std::borrow::Cow;
+use std::net::SocketAddr;
+#[cfg(feature = "jwt")]
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use http::{header, HeaderMap, HeaderValue, Method, StatusCode};
 use reqwest::tls::Version;
 #[cfg(feature = "rustls")]
 use reqwest::{Certificate, Identity};
 use reqwest_middleware::ClientWithMiddleware;
 use serde::Serialize;
+use time::OffsetDateTime;
 use url::Url;
 use uuid::Uuid;
 
-use crate::endpoint::Endpoint;
-use crate::header::APNS_ID;
+use crate::audit::{AuditRecord, AuditSink};
+use crate::badge::{self, BadgeCounter};
+use crate::endpoint::{join_bundle_id, join_device_token, Endpoint, EnvironmentHint};
+use crate::header::{PushType, APNS_CHANNEL_ID, APNS_ID, APNS_TOPIC};
+use crate::limits::{FieldLimits, PRIORITIZE_POWER_MIN_EXPIRATION};
+use crate::mdm::{MdmPayload, MdmRequest};
+use crate::metrics::{Metrics, NoopMetrics};
+use crate::once::Lazy;
 use crate::payload::*;
-use crate::reason::Reason;
-use crate::request::Request;
+use crate::reason::{Reason, ThrottleInfo};
+use crate::request::{Lint, LintOptions, Request, ValidationError};
 use crate::result::{Error, Result};
+use crate::stats::{ClientStats, InflightBytes, RecentTarget, RecentTargets};
+use crate::strings::LocalizableStrings;
 #[cfg(feature = "jwt")]
-use crate::token::TokenFactory;
+use crate::token::{
+    key_id_from_path, read_der, read_p8, RefresherHandle, SigningKey, TokenFactory,
+};
 
 /// Default user agent.
 pub const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
 
+/// Valid [`ClientBuilder::http2_max_frame_size`] values, from the initial
+/// `SETTINGS_MAX_FRAME_SIZE` to the largest frame size HTTP/2 allows.
+const HTTP2_MAX_FRAME_SIZE_RANGE: std::ops::RangeInclusive<u32> = 16_384..=16_777_215;
+
 /// Authentication options.
+///
+/// The [`Debug`] output omits the keys, and owned token keys are zeroized
+/// when dropped, see [`SigningKey`].
 #[cfg(any(feature = "rustls", feature = "jwt"))]
 #[cfg_attr(docsrs, doc(cfg(any(feature = "rustls", feature = "jwt"))))]
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub enum Authentication<'a> {
     /// If you’re using certificate-based authentication, you send your provider
     /// certificate to APNs when setting up your TLS connection. For more
@@ -42,12 +68,147 @@ pub enum Authentication<'a> {
     #[cfg(feature = "jwt")]
     #[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
     Token {
-        key_id: &'a str,
-        key_pem: &'a [u8],
-        team_id: &'a str,
+        key_id: Cow<'a, str>,
+        key_pem: SigningKey<'a>,
+        team_id: Cow<'a, str>,
+    },
+
+    /// Like [`Token`](Self::Token), with the PKCS#8 key DER- instead of
+    /// PEM-encoded, as kept by some secret stores.
+    #[cfg(feature = "jwt")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
+    TokenDer {
+        key_id: Cow<'a, str>,
+        key_der: SigningKey<'a>,
+        team_id: Cow<'a, str>,
     },
 }
 
+#[cfg(feature = "jwt")]
+impl Authentication<'static> {
+    /// Reads a `.p8` key file for token-based authentication, taking the key
+    /// ID from the file name, e.g. `AuthKey_ABC123DEF4.p8`. See
+    /// [`key_id_from_path`](crate::token::key_id_from_path) and
+    /// [`token_from_p8_file`](Self::token_from_p8_file).
+    pub fn token_from_p8_path(path: impl AsRef<Path>, team_id: impl Into<String>) -> Result<Self> {
+        let path = path.as_ref();
+        let key_id =
+            key_id_from_path(path).ok_or_else(|| Error::InvalidKeyFileName(path.to_path_buf()))?;
+        Self::token_from_p8_file(key_id, path, team_id)
+    }
+
+    /// Reads a `.p8` key file for token-based authentication.
+    ///
+    /// Windows line endings are accepted. Returns
+    /// [`Error::InvalidSigningKey`] naming the path and the mistake for a
+    /// file that is not PEM, a PEM file without a `PRIVATE KEY` block, an RSA
+    /// key, or an EC key on another curve than P-256.
+    pub fn token_from_p8_file(
+        key_id: impl Into<String>,
+        path: impl AsRef<Path>,
+        team_id: impl Into<String>,
+    ) -> Result<Self> {
+        Ok(Self::Token {
+            key_id: key_id.into().into(),
+            key_pem: read_p8(path.as_ref())?.into(),
+            team_id: team_id.into().into(),
+        })
+    }
+
+    /// Reads a DER-encoded PKCS#8 key file for token-based authentication.
+    ///
+    /// Returns [`Error::InvalidSigningKey`] naming the path and the mistake
+    /// for a file that is not DER, an RSA key, or an EC key on another curve
+    /// than P-256. See [`is_der`](crate::token::is_der) to tell DER from PEM
+    /// key files.
+    pub fn token_from_der_file(
+        key_id: impl Into<String>,
+        path: impl AsRef<Path>,
+        team_id: impl Into<String>,
+    ) -> Result<Self> {
+        Ok(Self::TokenDer {
+            key_id: key_id.into().into(),
+            key_der: read_der(path.as_ref())?.into(),
+            team_id: team_id.into().into(),
+        })
+    }
+}
+
+#[cfg(any(feature = "rustls", feature = "jwt"))]
+impl std::fmt::Debug for Authentication<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            #[cfg(feature = "rustls")]
+            Self::Certificate { .. } => f.debug_struct("Certificate").finish_non_exhaustive(),
+            #[cfg(feature = "jwt")]
+            Self::Token {
+                key_id, team_id, ..
+            } => f
+                .debug_struct("Token")
+                .field("key_id", key_id)
+                .field("team_id", team_id)
+                .finish_non_exhaustive(),
+            #[cfg(feature = "jwt")]
+            Self::TokenDer {
+                key_id, team_id, ..
+            } => f
+                .debug_struct("TokenDer")
+                .field("key_id", key_id)
+                .field("team_id", team_id)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+#[cfg(feature = "jwt")]
+impl Authentication<'_> {
+    /// Returns the token-based authentication with owned keys, or `None`
+    /// for certificate-based authentication.
+    fn to_owned_token(&self) -> Option<Authentication<'static>> {
+        match self {
+            Self::Token {
+                key_id,
+                key_pem,
+                team_id,
+            } => Some(Authentication::Token {
+                key_id: key_id.to_string().into(),
+                key_pem: key_pem.clone().into_owned(),
+                team_id: team_id.to_string().into(),
+            }),
+            Self::TokenDer {
+                key_id,
+                key_der,
+                team_id,
+            } => Some(Authentication::TokenDer {
+                key_id: key_id.to_string().into(),
+                key_der: key_der.clone().into_owned(),
+                team_id: team_id.to_string().into(),
+            }),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    /// Creates the [`TokenFactory`] of token-based authentication, or returns
+    /// `None` for certificate-based authentication.
+    fn token_factory(&self) -> Option<Result<TokenFactory>> {
+        match self {
+            Self::Token {
+                key_id,
+                key_pem,
+                team_id,
+            } => Some(TokenFactory::with_defaults(key_id, key_pem, team_id)),
+            Self::TokenDer {
+                key_id,
+                key_der,
+                team_id,
+            } => Some(TokenFactory::from_der(key_id, key_der, team_id)),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+}
+
 /// Certificate authority options.
 #[cfg(feature = "rustls")]
 #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
@@ -57,12 +218,146 @@ pub enum CertificateAuthority<'a> {
     Der(&'a [u8]),
 }
 
+/// Request mutator hook, see [`ClientBuilder::request_mutator`].
+///
+/// Implemented for closures taking the headers and body of a request.
+pub trait RequestMutator {
+    /// Adds or changes headers of a request with `body` before it is sent.
+    fn mutate(&self, headers: &mut HeaderMap, body: &[u8]) -> Result<()>;
+}
+
+impl<F> RequestMutator for F
+where
+    F: Fn(&mut HeaderMap, &[u8]) -> Result<()>,
+{
+    fn mutate(&self, headers: &mut HeaderMap, body: &[u8]) -> Result<()> {
+        self(headers, body)
+    }
+}
+
+impl std::fmt::Debug for dyn RequestMutator + Send + Sync {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RequestMutator")
+    }
+}
+
 /// [`Client`] builder.
 #[derive(Debug, Clone)]
 pub struct ClientBuilder<'a> {
     pub endpoint: Endpoint,
     pub user_agent: &'a str,
 
+    /// The environment the client is expected to send to. When set,
+    /// [`build`](Self::build) fails with [`Error::EnvironmentMismatch`] if
+    /// the endpoint belongs to the other environment, e.g. to catch a
+    /// development build configured with the production endpoint.
+    pub environment_hint: Option<EnvironmentHint>,
+
+    /// Allows an `http://` endpoint, e.g. a local mock server or proxy that
+    /// doesn't terminate TLS. Otherwise [`build`](Self::build) fails with
+    /// [`Error::InsecureEndpoint`] for such endpoints.
+    pub allow_insecure_http: bool,
+
+    /// Allows building a client without [`authentication`](Self::authentication),
+    /// e.g. for a mock server. Otherwise [`build`](Self::build) fails with
+    /// [`Error::NoAuthentication`], since APNs rejects every request without
+    /// a provider certificate or token.
+    pub allow_unauthenticated: bool,
+
+    /// Metrics hook called around every HTTP request.
+    pub metrics: Arc<dyn Metrics + Send + Sync>,
+
+    /// Hook called with the headers and body of every push notification
+    /// request after the APNs and `authorization` headers are set, e.g. to
+    /// add a signature header for an egress proxy. An error from the hook
+    /// aborts the request with [`Error::Mutator`], as does adding, removing
+    /// or changing an `apns-*` header.
+    pub request_mutator: Option<Arc<dyn RequestMutator + Send + Sync>>,
+
+    /// Counter that [`Client::post`] resolves [`Badge::Increment`] with,
+    /// once per request. Without it, posting a request with
+    /// [`Badge::Increment`] fails with [`Error::InvalidRequest`]. The
+    /// [`blocking`](crate::blocking) client doesn't support it.
+    pub badge_counter: Option<Arc<dyn BadgeCounter + Send + Sync>>,
+
+    /// Sink that receives an [`AuditRecord`] after every [`Client::post`] and
+    /// [`Client::post_mdm`], whether it succeeded or not. The record has no
+    /// notification content, see [`audit`](crate::audit).
+    pub audit_sink: Option<Arc<dyn AuditSink + Send + Sync>>,
+
+    /// Maximum number of request body bytes in flight at once. When set,
+    /// [`Client::post`] waits until enough in-flight requests complete before
+    /// sending a new request.
+    pub max_inflight_bytes: Option<usize>,
+
+    /// Generates a random `apns-id` for requests without [`Request::id`]. When
+    /// set, [`Client::post`] returns the generated ID on success and wraps any
+    /// error in [`Error::WithApnsId`].
+    pub generate_apns_id: bool,
+
+    /// Rejects requests that APNs is known to reject or throttle, see
+    /// [`Request::validate`]. When unset, such requests are sent anyway and,
+    /// with the `tracing` feature, logged as warnings. Defaults to `true`.
+    pub validate_requests: bool,
+
+    /// Clamps a relevance score outside `0..=1` into range instead of
+    /// rejecting the request, see [`Request::clamp_relevance_score`]. A NaN
+    /// relevance score is always rejected.
+    pub clamp_relevance_score: bool,
+
+    /// Makes a request with either a critical interruption level or a
+    /// critical sound a critical alert instead of rejecting the mismatch with
+    /// [`Error::CriticalSound`], see [`Request::reconcile_critical_sound`].
+    pub reconcile_critical_sound: bool,
+
+    /// Replaces the default [`PushType::Alert`] with
+    /// [`Request::inferred_push_type`], so that silent payloads are sent as
+    /// [`PushType::Background`]. Other push types are sent as given.
+    pub infer_push_type: bool,
+
+    /// Limits on free-form payload fields checked by request validation, see
+    /// [`Request::validate_with_limits`].
+    pub field_limits: FieldLimits,
+
+    /// Rejects [`Priority::PrioritizePower`](crate::Priority::PrioritizePower)
+    /// requests that expire sooner than this, which
+    /// [`Request::lint`] only warns about, e.g.
+    /// [`PRIORITIZE_POWER_MIN_EXPIRATION`](crate::limits::PRIORITIZE_POWER_MIN_EXPIRATION).
+    /// Defaults to `None`.
+    pub prioritize_power_min_expiration: Option<Duration>,
+
+    /// Number of distinct topic and push type combinations that
+    /// [`Client::recent_targets`] tracks. Defaults to `16`.
+    pub recent_targets: usize,
+
+    /// Topics the client is expected to send to. Requests with any other
+    /// topic are counted in [`ClientStats::unexpected_topics`] and, with the
+    /// `tracing` feature, logged as warnings. They are still sent.
+    pub expected_topics: Option<Vec<String>>,
+
+    /// Connects to these addresses instead of resolving the endpoint host, for
+    /// networks that only allow connections to known APNs addresses. TLS
+    /// still verifies the endpoint host name, and the endpoint port replaces
+    /// the port of each address. Defaults to resolving the host with DNS, see
+    /// [`Client::resolve_endpoints`].
+    pub pin_addresses: Vec<SocketAddr>,
+
+    /// Enables or disables HTTP/2 adaptive flow control, which sizes the
+    /// connection and stream windows by the measured bandwidth-delay
+    /// product. Disabling it can avoid head-of-line stalls on high-latency
+    /// links, like satellite uplinks. Defaults to reqwest's default.
+    pub http2_adaptive_window: Option<bool>,
+
+    /// Maximum HTTP/2 frame size in bytes, between 16384 and 16777215 as
+    /// required by RFC 9113. Building fails with
+    /// [`Error::InvalidHttp2MaxFrameSize`] otherwise. Defaults to reqwest's
+    /// default.
+    pub http2_max_frame_size: Option<u32>,
+
+    /// The app's `Localizable.strings`, see [`crate::strings::parse`]. When
+    /// set, [`Client::lint`] checks localized alerts against them.
+    pub localizable_strings: Option<Arc<LocalizableStrings>>,
+
     #[cfg(feature = "rustls")]
     #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
     pub ca: Option<CertificateAuthority<'a>>,
@@ -70,6 +365,15 @@ pub struct ClientBuilder<'a> {
     #[cfg(any(feature = "rustls", feature = "jwt"))]
     #[cfg_attr(docsrs, doc(cfg(any(feature = "rustls", feature = "jwt"))))]
     pub authentication: Option<Authentication<'a>>,
+
+    /// With token-based authentication, refreshes the JWT in a background
+    /// task shortly before it's due, see [`TokenFactory::spawn_refresher`].
+    /// The task stops when the client is dropped. Building a client with this
+    /// set requires a Tokio runtime. Ignored by the blocking client. Defaults
+    /// to `false`.
+    #[cfg(feature = "jwt")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
+    pub proactive_token_refresh: bool,
 }
 
 impl<'a> Default for ClientBuilder<'a> {
@@ -77,12 +381,36 @@ impl<'a> Default for ClientBuilder<'a> {
         Self {
             endpoint: Endpoint::default(),
             user_agent: USER_AGENT,
+            environment_hint: None,
+            allow_insecure_http: false,
+            allow_unauthenticated: false,
+            metrics: Arc::new(NoopMetrics),
+            request_mutator: None,
+            badge_counter: None,
+            audit_sink: None,
+            max_inflight_bytes: None,
+            generate_apns_id: false,
+            validate_requests: true,
+            clamp_relevance_score: false,
+            reconcile_critical_sound: false,
+            infer_push_type: false,
+            field_limits: FieldLimits::default(),
+            prioritize_power_min_expiration: None,
+            recent_targets: 16,
+            expected_topics: None,
+            pin_addresses: Vec::new(),
+            http2_adaptive_window: None,
+            http2_max_frame_size: None,
+            localizable_strings: None,
 
             #[cfg(feature = "rustls")]
             ca: None,
 
             #[cfg(any(feature = "rustls", feature = "jwt"))]
             authentication: None,
+
+            #[cfg(feature = "jwt")]
+            proactive_token_refresh: false,
         }
     }
 }
@@ -95,8 +423,11 @@ impl<'a> ClientBuilder<'a> {
 
     /// Builds a `Client`.
     pub fn build(self) -> Result<Client> {
-        let client = self.reqwest_client_builder()?.build();
-        self.with_reqwest_middleware_client(client)
+        self.check_endpoint()?;
+        self.check_authentication()?;
+        self.check_http2()?;
+        let client = self.reqwest_builder()?.build()?;
+        self.with_http_client(HttpClient::Reqwest(client))
     }
 
     /// Builds a `Client` with middleware.
@@ -104,7 +435,11 @@ impl<'a> ClientBuilder<'a> {
     /// ```rust
     /// use reqwest_tracing::{SpanBackendWithUrl, TracingMiddleware};
     ///
-    /// let _client = apple_apns::ClientBuilder::new().build_with_middleware(|builder| {
+    /// let builder = apple_apns::ClientBuilder {
+    ///     allow_unauthenticated: true,
+    ///     ..Default::default()
+    /// };
+    /// let _client = builder.build_with_middleware(|builder| {
     ///     Ok(builder.with(TracingMiddleware::<SpanBackendWithUrl>::new()))
     /// }).unwrap();
     /// ```
@@ -112,35 +447,141 @@ impl<'a> ClientBuilder<'a> {
     where
         F: FnOnce(reqwest_middleware::ClientBuilder) -> Result<reqwest_middleware::ClientBuilder>,
     {
-        let builder = self.reqwest_client_builder()?;
-        let builder = f(builder)?;
-        self.with_reqwest_middleware_client(builder.build())
+        self.check_endpoint()?;
+        self.check_authentication()?;
+        self.check_http2()?;
+        let client = self.reqwest_builder()?.build()?;
+        let builder = f(reqwest_middleware::ClientBuilder::new(client))?;
+        self.with_http_client(HttpClient::Middleware(builder.build()))
     }
 
-    fn with_reqwest_middleware_client(&self, client: ClientWithMiddleware) -> Result<Client> {
-        let base_url = self.endpoint.as_url().clone();
+    /// Builds a `Client` that defers building its HTTP client, TLS
+    /// configuration and token factory until its first request, e.g. to
+    /// create clients at startup that may never send.
+    ///
+    /// The endpoint and authentication are checked and certificates are
+    /// parsed right away. Any other error surfaces as [`Error::Build`] from
+    /// the first request and every request after it.
+    pub fn build_lazy(self) -> Result<Client> {
+        self.check_endpoint()?;
+        self.check_authentication()?;
+        self.check_http2()?;
+        let builder = self.reqwest_builder()?;
 
         #[cfg(feature = "jwt")]
-        let token_factory = if let Some(Authentication::Token {
-            key_id,
-            key_pem,
-            team_id,
-        }) = self.authentication
-        {
-            Some(TokenFactory::new(key_id, key_pem, team_id)?)
-        } else {
-            None
+        let token_authentication = self
+            .authentication
+            .as_ref()
+            .and_then(Authentication::to_owned_token);
+
+        #[cfg(feature = "jwt")]
+        let proactive_token_refresh = self.proactive_token_refresh;
+
+        let connect = move || -> Result<Connection> {
+            #[cfg(feature = "jwt")]
+            let token_factory = token_authentication
+                .as_ref()
+                .and_then(Authentication::token_factory)
+                .transpose()?
+                .map(Arc::new);
+
+            Ok(Connection {
+                client: HttpClient::Reqwest(builder.build()?),
+                #[cfg(feature = "jwt")]
+                _token_refresher: token_refresher(&token_factory, proactive_token_refresh),
+                #[cfg(feature = "jwt")]
+                token_factory,
+            })
         };
+        self.with_connection(Lazy::new(Box::new(move || connect().map_err(Arc::new))))
+    }
 
-        Ok(Client {
-            base_url,
+    fn with_http_client(&self, client: HttpClient) -> Result<Client> {
+        #[cfg(feature = "jwt")]
+        let token_factory = self.token_factory()?.map(Arc::new);
+
+        let connection = Connection {
             client,
             #[cfg(feature = "jwt")]
+            _token_refresher: token_refresher(&token_factory, self.proactive_token_refresh),
+            #[cfg(feature = "jwt")]
             token_factory,
+        };
+        self.with_connection(Lazy::new(Box::new(move || Ok(connection))))
+    }
+
+    fn with_connection(
+        &self,
+        connection: Lazy<ConnectionResult, ConnectionInit>,
+    ) -> Result<Client> {
+        let base_url = self.endpoint.as_url().clone();
+
+        Ok(Client {
+            base_url,
+            channels_url: self.endpoint.channels_url()?,
+            broadcasts_url: self.endpoint.broadcasts_url()?,
+            connection,
+            metrics: self.metrics.clone(),
+            request_mutator: self.request_mutator.clone(),
+            badge_counter: self.badge_counter.clone(),
+            audit_sink: self.audit_sink.clone(),
+            inflight_bytes: InflightBytes::new(self.max_inflight_bytes),
+            goaway_retries: Default::default(),
+            recent_targets: RecentTargets::new(
+                self.recent_targets,
+                self.expected_topics.as_deref(),
+            ),
+            generate_apns_id: self.generate_apns_id,
+            policy: self.request_policy(),
+            pin_addresses: self.pin_addresses.clone(),
+            localizable_strings: self.localizable_strings.clone(),
         })
     }
 
-    fn reqwest_client_builder(&self) -> Result<reqwest_middleware::ClientBuilder> {
+    /// Returns [`Error::InsecureEndpoint`] if the endpoint uses plain HTTP
+    /// without [`allow_insecure_http`](Self::allow_insecure_http), or
+    /// [`Error::EnvironmentMismatch`] if it doesn't match
+    /// [`environment_hint`](Self::environment_hint).
+    pub(crate) fn check_endpoint(&self) -> Result<()> {
+        if self.endpoint.as_url().scheme() == "http" && !self.allow_insecure_http {
+            return Err(Error::InsecureEndpoint(self.endpoint.clone()));
+        }
+        match self.environment_hint {
+            Some(expected) if !self.endpoint.matches(expected) => Err(Error::EnvironmentMismatch {
+                endpoint: self.endpoint.clone(),
+                expected,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns [`Error::NoAuthentication`] if no authentication is configured
+    /// and [`allow_unauthenticated`](Self::allow_unauthenticated) is unset.
+    pub(crate) fn check_authentication(&self) -> Result<()> {
+        #[cfg(any(feature = "rustls", feature = "jwt"))]
+        let authenticated = self.authentication.is_some();
+        #[cfg(not(any(feature = "rustls", feature = "jwt")))]
+        let authenticated = false;
+
+        if authenticated || self.allow_unauthenticated {
+            Ok(())
+        } else {
+            Err(Error::NoAuthentication)
+        }
+    }
+
+    /// Returns [`Error::InvalidHttp2MaxFrameSize`] if
+    /// [`http2_max_frame_size`](Self::http2_max_frame_size) is out of range.
+    pub(crate) fn check_http2(&self) -> Result<()> {
+        match self.http2_max_frame_size {
+            Some(size) if !HTTP2_MAX_FRAME_SIZE_RANGE.contains(&size) => {
+                Err(Error::InvalidHttp2MaxFrameSize(size))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn reqwest_builder(&self) -> Result<reqwest::ClientBuilder> {
         #[allow(unused_mut)]
         let mut builder = reqwest::Client::builder()
             .user_agent(self.user_agent)
@@ -155,99 +596,1729 @@ impl<'a> ClientBuilder<'a> {
             builder = builder.http2_prior_knowledge();
         }
 
+        if let Some(enabled) = self.http2_adaptive_window {
+            builder = builder.http2_adaptive_window(enabled);
+        }
+        if let Some(size) = self.http2_max_frame_size {
+            builder = builder.http2_max_frame_size(size);
+        }
+
+        if let Some(host) = self.pinned_host() {
+            builder = builder.resolve_to_addrs(host, &self.pin_addresses);
+        }
+
         #[cfg(feature = "rustls")]
         {
             // Force rustls
             builder = builder.use_rustls_tls();
 
             // Add root certificate
-            if let Some(ca) = &self.ca {
-                let cert = match ca {
-                    CertificateAuthority::Pem(pem) => Certificate::from_pem(pem)?,
-                    CertificateAuthority::Der(der) => Certificate::from_der(der)?,
-                };
+            if let Some(cert) = self.root_certificate()? {
                 builder = builder.add_root_certificate(cert);
             }
 
             // Configure certificate authentication
-            if let Some(Authentication::Certificate { client_pem }) = self.authentication {
-                let identity = Identity::from_pem(client_pem)?;
+            if let Some(identity) = self.identity()? {
                 builder = builder.identity(identity);
             }
         }
 
-        let client = builder.build()?;
-        let builder = reqwest_middleware::ClientBuilder::new(client);
         Ok(builder)
     }
+
+    /// Returns the endpoint host to resolve to
+    /// [`pin_addresses`](Self::pin_addresses), if any are set.
+    pub(crate) fn pinned_host(&self) -> Option<&str> {
+        if self.pin_addresses.is_empty() {
+            return None;
+        }
+        self.endpoint.as_url().host_str()
+    }
+
+    #[cfg(feature = "jwt")]
+    pub(crate) fn token_factory(&self) -> Result<Option<TokenFactory>> {
+        self.authentication
+            .as_ref()
+            .and_then(Authentication::token_factory)
+            .transpose()
+    }
+
+    #[cfg(feature = "rustls")]
+    pub(crate) fn root_certificate(&self) -> Result<Option<Certificate>> {
+        let cert = match &self.ca {
+            Some(CertificateAuthority::Pem(pem)) => Some(Certificate::from_pem(pem)?),
+            Some(CertificateAuthority::Der(der)) => Some(Certificate::from_der(der)?),
+            None => None,
+        };
+        Ok(cert)
+    }
+
+    pub(crate) fn request_policy(&self) -> RequestPolicy {
+        #[cfg(feature = "rustls")]
+        let certificate_authentication = matches!(
+            self.authentication,
+            Some(Authentication::Certificate { .. })
+        );
+        #[cfg(not(feature = "rustls"))]
+        let certificate_authentication = false;
+
+        #[cfg(feature = "jwt")]
+        let token_authentication = matches!(
+            self.authentication,
+            Some(Authentication::Token { .. } | Authentication::TokenDer { .. })
+        );
+        #[cfg(not(feature = "jwt"))]
+        let token_authentication = false;
+
+        RequestPolicy {
+            validate_requests: self.validate_requests,
+            clamp_relevance_score: self.clamp_relevance_score,
+            reconcile_critical_sound: self.reconcile_critical_sound,
+            infer_push_type: self.infer_push_type,
+            field_limits: self.field_limits,
+            prioritize_power_min_expiration: self.prioritize_power_min_expiration,
+            certificate_authentication,
+            token_authentication,
+        }
+    }
+
+    #[cfg(feature = "rustls")]
+    pub(crate) fn identity(&self) -> Result<Option<Identity>> {
+        if let Some(Authentication::Certificate { client_pem }) = self.authentication {
+            Ok(Some(Identity::from_pem(client_pem)?))
+        } else {
+            Ok(None)
+        }
+    }
 }
 
 /// Apple Push Notification service client.
 ///
-/// The [`Client`] is safe to use from multiple threads. However, [`Client`]
-/// uses a [`std::sync::RwLock`] and is not [`Clone`]. To pass [`Client`] to
-/// multiple threads, use [`std::sync::Arc`] for OS threads, or [`std::rc::Rc`]
-/// for green threads.
+/// The [`Client`] is safe to use from multiple threads and tasks, but is not
+/// [`Clone`]. To share a [`Client`], wrap it in an [`std::sync::Arc`].
 pub struct Client {
     base_url: Url,
-    client: ClientWithMiddleware,
+    channels_url: Url,
+    broadcasts_url: Url,
+    connection: Lazy<ConnectionResult, ConnectionInit>,
+    metrics: Arc<dyn Metrics + Send + Sync>,
+    request_mutator: Option<Arc<dyn RequestMutator + Send + Sync>>,
+    badge_counter: Option<Arc<dyn BadgeCounter + Send + Sync>>,
+    audit_sink: Option<Arc<dyn AuditSink + Send + Sync>>,
+    inflight_bytes: InflightBytes,
+    goaway_retries: AtomicUsize,
+    recent_targets: RecentTargets,
+    generate_apns_id: bool,
+    policy: RequestPolicy,
+    pin_addresses: Vec<SocketAddr>,
+    localizable_strings: Option<Arc<LocalizableStrings>>,
+}
+
+/// The parts of a [`Client`] that [`ClientBuilder::build_lazy`] defers.
+struct Connection {
+    client: HttpClient,
+
+    #[cfg(feature = "jwt")]
+    token_factory: Option<Arc<TokenFactory>>,
 
+    /// Stops the background JWT refresh when the client is dropped.
     #[cfg(feature = "jwt")]
-    token_factory: Option<TokenFactory>,
+    _token_refresher: Option<RefresherHandle>,
+}
+
+/// Starts the background JWT refresh of
+/// [`ClientBuilder::proactive_token_refresh`].
+#[cfg(feature = "jwt")]
+fn token_refresher(
+    token_factory: &Option<Arc<TokenFactory>>,
+    proactive_token_refresh: bool,
+) -> Option<RefresherHandle> {
+    token_factory
+        .as_ref()
+        .filter(|_| proactive_token_refresh)
+        .map(|token_factory| token_factory.clone().spawn_refresher())
 }
 
+/// The HTTP client of a [`Client`]. Without middleware, requests skip the
+/// middleware stack, which boxes every request future.
+enum HttpClient {
+    Reqwest(reqwest::Client),
+    Middleware(ClientWithMiddleware),
+}
+
+impl HttpClient {
+    async fn execute(
+        &self,
+        req: reqwest::Request,
+    ) -> std::result::Result<reqwest::Response, reqwest_middleware::Error> {
+        match self {
+            Self::Reqwest(client) => Ok(client.execute(req).await?),
+            Self::Middleware(client) => client.execute(req).await,
+        }
+    }
+}
+
+type ConnectionResult = std::result::Result<Connection, Arc<Error>>;
+type ConnectionInit = Box<dyn FnOnce() -> ConnectionResult + Send>;
+
 impl Client {
     /// Creates a [`ClientBuilder`].
     pub fn builder<'a>() -> ClientBuilder<'a> {
         ClientBuilder::new()
     }
 
+    /// Returns a snapshot of the client statistics.
+    pub fn stats(&self) -> ClientStats {
+        ClientStats {
+            inflight_bytes: self.inflight_bytes.current(),
+            peak_inflight_bytes: self.inflight_bytes.peak(),
+            goaway_retries: self.goaway_retries.load(Ordering::Relaxed),
+            unexpected_topics: self.recent_targets.unexpected_topics(),
+        }
+    }
+
+    /// Returns the most recently used topic and push type combinations, most
+    /// recently used first. Helps to spot a client that sends to a stale
+    /// topic. See [`ClientBuilder::recent_targets`].
+    pub fn recent_targets(&self) -> Vec<RecentTarget> {
+        self.recent_targets.get()
+    }
+
+    /// Returns the addresses the client connects to: the
+    /// [`pin_addresses`](ClientBuilder::pin_addresses) if set, and otherwise
+    /// the current DNS records of the endpoint host.
+    ///
+    /// Resolving ahead of time lets a deployment behind a firewall check or
+    /// allowlist the APNs addresses, or pin them with
+    /// [`ClientBuilder::pin_addresses`].
+    pub async fn resolve_endpoints(&self) -> Result<Vec<SocketAddr>> {
+        let port = self
+            .base_url
+            .port_or_known_default()
+            .ok_or(url::ParseError::InvalidPort)?;
+        if !self.pin_addresses.is_empty() {
+            return Ok(self
+                .pin_addresses
+                .iter()
+                .map(|addr| SocketAddr::new(addr.ip(), port))
+                .collect());
+        }
+        match self.base_url.host() {
+            Some(url::Host::Domain(domain)) => {
+                Ok(tokio::net::lookup_host((domain, port)).await?.collect())
+            }
+            Some(url::Host::Ipv4(ip)) => Ok(vec![SocketAddr::new(ip.into(), port)]),
+            Some(url::Host::Ipv6(ip)) => Ok(vec![SocketAddr::new(ip.into(), port)]),
+            None => Err(url::ParseError::EmptyHost.into()),
+        }
+    }
+
+    /// Checks a request against [`Request::validate`] and the rules that
+    /// depend on this client's authentication: token-based authentication
+    /// requires a topic, and location pushes require token-based
+    /// authentication.
+    pub fn validate<T>(&self, request: &Request<T>) -> std::result::Result<(), Vec<ValidationError>>
+    where
+        T: Serialize,
+    {
+        self.policy.validate(request)
+    }
+
+    /// Checks a request against [`Request::lint_with`], using
+    /// [`ClientBuilder::prioritize_power_min_expiration`] if set, and its
+    /// alert against [`ClientBuilder::localizable_strings`] if set, see
+    /// [`Alert::validate_against_strings`].
+    pub fn lint<T>(&self, request: &Request<T>) -> Vec<Lint>
+    where
+        T: Serialize,
+    {
+        lint(&self.policy, self.localizable_strings.as_deref(), request)
+    }
+
+    /// Builds the HTTP request that [`Client::post`] sends for a push
+    /// notification, without sending it.
+    ///
+    /// The returned request has the device URL, APNs headers, JSON body, and,
+    /// when using token-based authentication, the `authorization` header
+    /// marked as sensitive.
+    pub async fn build_http_request<T>(&self, request: Request<T>) -> Result<http::Request<Vec<u8>>>
+    where
+        T: Serialize,
+    {
+        let (url, headers, body) = self.encode(request).await?;
+        http_request(url, headers, body, None)
+    }
+
+    /// Encodes a request with the provider token and the request mutator
+    /// applied.
+    async fn encode<T>(&self, request: Request<T>) -> Result<(Url, HeaderMap, Vec<u8>)>
+    where
+        T: Serialize,
+    {
+        let (url, headers, body) = encode_request(&self.base_url, &self.policy, request)?;
+        self.authorize(url, headers, body).await
+    }
+
+    /// Encodes an MDM request like [`Client::encode`].
+    async fn encode_mdm(&self, request: MdmRequest) -> Result<(Url, HeaderMap, Vec<u8>)> {
+        let (url, headers, body) = encode_mdm_request(&self.base_url, &self.policy, request)?;
+        self.authorize(url, headers, body).await
+    }
+
+    /// Adds the provider token to encoded request headers and applies the
+    /// request mutator.
+    async fn authorize(
+        &self,
+        url: Url,
+        mut headers: HeaderMap,
+        body: Vec<u8>,
+    ) -> Result<(Url, HeaderMap, Vec<u8>)> {
+        self.authorize_headers(&mut headers, &body).await?;
+        Ok((url, headers, body))
+    }
+
+    /// Authorizes a request to send like [`Client::authorize`].
+    async fn authorize_request(&self, mut req: reqwest::Request) -> Result<reqwest::Request> {
+        let mut headers = std::mem::take(req.headers_mut());
+        let body = req
+            .body()
+            .and_then(reqwest::Body::as_bytes)
+            .unwrap_or_default();
+        self.authorize_headers(&mut headers, body).await?;
+        *req.headers_mut() = headers;
+        Ok(req)
+    }
+
+    async fn authorize_headers(&self, headers: &mut HeaderMap, body: &[u8]) -> Result<()> {
+        if let Some(bearer_token) = self.bearer_token().await? {
+            headers.insert(header::AUTHORIZATION, authorization(&bearer_token)?);
+        }
+        mutate_headers(self.request_mutator.as_deref(), headers, body)
+    }
+
+    /// Returns the HTTP client and token factory, building them on first use
+    /// for a client from [`ClientBuilder::build_lazy`].
+    fn connection(&self) -> Result<&Connection> {
+        self.connection
+            .force()
+            .as_ref()
+            .map_err(|err| Error::Build(err.clone()))
+    }
+
+    /// Returns the provider token when using token-based authentication.
+    async fn bearer_token(&self) -> Result<Option<Arc<String>>> {
+        #[cfg(feature = "jwt")]
+        if let Some(token_factory) = &self.connection()?.token_factory {
+            return token_factory.get().await.map(Some);
+        }
+        Ok(None)
+    }
+
+    /// Sends a broadcast channel management request and returns the response,
+    /// or the APNs error reason for an error response.
+    pub(crate) async fn execute_channel_request(
+        &self,
+        method: Method,
+        path: &str,
+        mut headers: HeaderMap,
+        body: Option<Vec<u8>>,
+    ) -> Result<reqwest::Response> {
+        let url = self.channels_url.join(path)?;
+
+        if let Some(bearer_token) = self.bearer_token().await? {
+            headers.insert(header::AUTHORIZATION, authorization(&bearer_token)?);
+        }
+
+        let mut req = reqwest::Request::new(method, url);
+        if let Some(body) = body {
+            headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/json"),
+            );
+            *req.body_mut() = Some(body.into());
+        }
+        *req.headers_mut() = headers;
+
+        let res = self.connection()?.client.execute(req).await?;
+        let status = res.status();
+        if is_error_status(status) {
+            let throttle = ThrottleInfo::from_response(status, res.headers());
+            let body = res.bytes().await.ok();
+            Err(decode_error(status, body.as_deref(), throttle))
+        } else {
+            Ok(res)
+        }
+    }
+
+    /// Sends a Live Activity broadcast for `bundle_id` to `channel_id`, see
+    /// [`Client::post_broadcast`].
+    pub(crate) async fn execute_broadcast<T>(
+        &self,
+        bundle_id: &str,
+        channel_id: &str,
+        mut request: Request<T>,
+    ) -> Result<Uuid>
+    where
+        T: Serialize,
+    {
+        let timeout = remaining_until(request.deadline)?;
+        self.policy.check_id(&mut request.id)?;
+        let apns_id = self.generate_apns_id(&mut request.id);
+
+        let result = match encode_broadcast_request(
+            &self.broadcasts_url,
+            &self.policy,
+            bundle_id,
+            channel_id,
+            request,
+        ) {
+            Ok((url, headers, body)) => match self.authorize(url, headers, body).await {
+                Ok((url, headers, body)) => {
+                    self.metrics.on_payload(PushType::Liveactivity, body.len());
+                    let req = reqwest_request(url, headers, body);
+                    self.send(PushType::Liveactivity, req, timeout).await
+                }
+                Err(err) => Err(err),
+            },
+            Err(err) => Err(err),
+        };
+        with_apns_id(apns_id, result).map(|res| res.apns_id)
+    }
+
     /// Sends a push notification and returns the APNS ID.
     pub async fn post<T>(&self, request: Request<T>) -> Result<Uuid>
     where
         T: Serialize,
     {
-        let url = self.base_url.join(&request.device_token)?;
-        let payload_size_limit = request.push_type.payload_size_limit();
-        let (headers, payload): (_, Payload<T>) = request.try_into()?;
+        self.post_with_response(request)
+            .await
+            .map(|res| res.apns_id)
+    }
+
+    /// Sends a push notification and returns the APNS ID along with the
+    /// HTTP version and remote address of the connection it was sent on.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "apns.post",
+            skip_all,
+            fields(
+                device_token = %crate::request::redact_device_token(request.device_token.as_str()),
+                apns_push_type = tracing::field::Empty,
+                apns_topic = request.topic.as_deref(),
+                apns_id = request.id.map(tracing::field::display),
+                status = tracing::field::Empty,
+                reason = tracing::field::Empty,
+                http_version = tracing::field::Empty,
+                remote_addr = tracing::field::Empty,
+            ),
+        )
+    )]
+    pub async fn post_with_response<T>(&self, request: Request<T>) -> Result<Response>
+    where
+        T: Serialize,
+    {
+        let audit_sink = match &self.audit_sink {
+            Some(audit_sink) => audit_sink,
+            None => return self.post_request(request, &mut None).await,
+        };
 
-        let body = serde_json::to_vec(&payload)?;
-        if body.len() > payload_size_limit {
-            return Err(Error::PayloadTooLarge {
-                size: body.len(),
-                limit: payload_size_limit,
+        let push_type = self.policy.push_type(&request);
+        let record = AuditRecord::new(&request, push_type, OffsetDateTime::now_utc());
+        let mut payload_size = None;
+        let result = self.post_request(request, &mut payload_size).await;
+        record.record_to(
+            &**audit_sink,
+            result.as_ref().map(|res| res.apns_id),
+            payload_size,
+        );
+        result
+    }
+
+    /// Sends a push notification, setting `payload_size` once the payload
+    /// is encoded.
+    async fn post_request<T>(
+        &self,
+        mut request: Request<T>,
+        payload_size: &mut Option<usize>,
+    ) -> Result<Response>
+    where
+        T: Serialize,
+    {
+        let timeout = remaining_until(request.deadline)?;
+        self.policy.check_id(&mut request.id)?;
+        let apns_id = self.generate_apns_id(&mut request.id);
+        with_apns_id(apns_id, self.execute(request, timeout, payload_size).await)
+    }
+
+    /// Sends an MDM push and returns the APNS ID.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "apns.post",
+            skip_all,
+            fields(
+                device_token = %crate::request::redact_device_token(request.device_token.as_str()),
+                apns_push_type = %PushType::Mdm,
+                apns_topic = request.topic.as_deref(),
+                apns_id = request.id.map(tracing::field::display),
+                status = tracing::field::Empty,
+                reason = tracing::field::Empty,
+                http_version = tracing::field::Empty,
+                remote_addr = tracing::field::Empty,
+            ),
+        )
+    )]
+    pub async fn post_mdm(&self, request: MdmRequest) -> Result<Uuid> {
+        let audit_sink = match &self.audit_sink {
+            Some(audit_sink) => audit_sink,
+            None => return self.post_mdm_request(request, &mut None).await,
+        };
+
+        let record = AuditRecord::from_mdm(&request, OffsetDateTime::now_utc());
+        let mut payload_size = None;
+        let result = self.post_mdm_request(request, &mut payload_size).await;
+        record.record_to(&**audit_sink, result.as_ref().copied(), payload_size);
+        result
+    }
+
+    /// Sends an MDM push, setting `payload_size` once the payload is
+    /// encoded.
+    async fn post_mdm_request(
+        &self,
+        mut request: MdmRequest,
+        payload_size: &mut Option<usize>,
+    ) -> Result<Uuid> {
+        self.policy.check_id(&mut request.id)?;
+        let apns_id = self.generate_apns_id(&mut request.id);
+
+        self.recent_targets
+            .record(request.topic.as_deref(), PushType::Mdm);
+        let result = match self.encode_mdm(request).await {
+            Ok((url, headers, body)) => {
+                self.metrics.on_payload(PushType::Mdm, body.len());
+                *payload_size = Some(body.len());
+                let req = reqwest_request(url, headers, body);
+                self.send(PushType::Mdm, req, None).await
+            }
+            Err(err) => Err(err),
+        };
+        with_apns_id(apns_id, result).map(|res| res.apns_id)
+    }
+
+    /// Builds the HTTP request that [`Client::post_mdm`] sends for an MDM
+    /// push, without sending it.
+    pub async fn build_mdm_http_request(
+        &self,
+        request: MdmRequest,
+    ) -> Result<http::Request<Vec<u8>>> {
+        let (url, headers, body) = self.encode_mdm(request).await?;
+        http_request(url, headers, body, None)
+    }
+
+    /// Generates an `apns-id` for a request without one when
+    /// [`ClientBuilder::generate_apns_id`] is set.
+    fn generate_apns_id(&self, id: &mut Option<Uuid>) -> Option<Uuid> {
+        if !self.generate_apns_id {
+            return None;
+        }
+
+        let apns_id = *id.get_or_insert_with(Uuid::new_v4);
+
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("apns_id", tracing::field::display(apns_id));
+
+        Some(apns_id)
+    }
+
+    async fn execute<T>(
+        &self,
+        request: Request<T>,
+        timeout: Option<Duration>,
+        payload_size: &mut Option<usize>,
+    ) -> Result<Response>
+    where
+        T: Serialize,
+    {
+        let push_type = self.policy.push_type(&request);
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("apns_push_type", tracing::field::display(push_type));
+        self.recent_targets
+            .record(request.topic.as_deref(), push_type);
+        let device_token = match request.badge {
+            Some(Badge::Increment) => Some(request.device_token.to_string()),
+            _ => None,
+        };
+        let (url, headers, mut payload) = prepare_request(&self.base_url, &self.policy, request)?;
+        if let Some(device_token) = device_token {
+            self.resolve_badge(&device_token, &mut payload).await?;
+        }
+        let body = payload.to_body()?;
+        let body_len = body.len();
+        let unauthorized = reqwest_request(url, headers, body);
+
+        // A retry with a new provider token is authorized from scratch, so
+        // that the request mutator sees the new token.
+        #[cfg(feature = "jwt")]
+        let retry = match &self.connection()?.token_factory {
+            Some(token_factory) => unauthorized.try_clone().map(|req| (token_factory, req)),
+            None => None,
+        };
+
+        let req = self.authorize_request(unauthorized).await?;
+        self.metrics.on_payload(push_type, body_len);
+        *payload_size = Some(body_len);
+
+        #[cfg(feature = "jwt")]
+        if let Some((token_factory, retry)) = retry {
+            return match self.send(push_type, req, timeout).await {
+                Err(err) if is_provider_token_error(&err) => {
+                    token_factory.invalidate().await?;
+                    let retry = self.authorize_request(retry).await?;
+                    self.send(push_type, retry, timeout).await.map_err(|_| err)
+                }
+                result => result,
+            };
+        }
+
+        self.send(push_type, req, timeout).await
+    }
+
+    /// Replaces [`Badge::Increment`] with the next number of the
+    /// [`ClientBuilder::badge_counter`], once the payload is known to encode
+    /// within its size limit with any number, so that a request that fails
+    /// doesn't count.
+    async fn resolve_badge<T>(
+        &self,
+        device_token: &str,
+        payload: &mut PreparedPayload<T>,
+    ) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let counter = self.badge_counter.as_deref().ok_or_else(|| {
+            Error::InvalidRequest("badge increment requires a badge counter".into())
+        })?;
+        payload.payload.aps.badge = Some(Badge::Count(u32::MAX));
+        payload.to_body()?;
+        payload.payload.aps.badge = Some(Badge::Increment);
+        badge::resolve(counter, device_token, &mut payload.payload.aps.badge).await;
+        Ok(())
+    }
+
+    async fn send(
+        &self,
+        push_type: PushType,
+        mut req: reqwest::Request,
+        timeout: Option<Duration>,
+    ) -> Result<Response> {
+        let client = &self.connection()?.client;
+
+        // Hold the reservation until the response is fully read.
+        let payload_size = req
+            .body()
+            .and_then(reqwest::Body::as_bytes)
+            .map_or(0, <[u8]>::len);
+        let _inflight = self.inflight_bytes.acquire(payload_size).await;
+
+        *req.timeout_mut() = timeout;
+
+        // APNs did not process a request that failed because the connection
+        // was going away, so it is always safe to send it once more.
+        let retry = req.try_clone();
+
+        self.metrics.on_request(push_type);
+        let mut start = Instant::now();
+        let res = match (client.execute(req).await, retry) {
+            (Err(err), Some(retry)) if is_unprocessed(&err) => {
+                self.goaway_retries.fetch_add(1, Ordering::Relaxed);
+                self.metrics.on_error(&err.into(), start.elapsed());
+                self.metrics.on_request(push_type);
+                start = Instant::now();
+                client.execute(retry).await
+            }
+            (res, _) => res,
+        };
+        let res = match res {
+            Ok(res) => res,
+            Err(err) => {
+                let err = match err {
+                    reqwest_middleware::Error::Reqwest(err)
+                        if timeout.is_some() && err.is_timeout() =>
+                    {
+                        Error::DeadlineExceeded
+                    }
+                    err => err.into(),
+                };
+                self.metrics.on_error(&err, start.elapsed());
+                return Err(err);
+            }
+        };
+        let latency = start.elapsed();
+        let status = res.status();
+        let version = res.version();
+        let remote_addr = res.remote_addr();
+
+        #[cfg(feature = "tracing")]
+        {
+            let span = tracing::Span::current();
+            span.record("status", status.as_u16());
+            span.record("http_version", tracing::field::debug(version));
+            if let Some(remote_addr) = remote_addr {
+                span.record("remote_addr", tracing::field::display(remote_addr));
+            }
+            if let Some(apns_id) = res.headers().get(&APNS_ID).and_then(|v| v.to_str().ok()) {
+                span.record("apns_id", apns_id);
+            }
+        }
+
+        if is_error_status(status) {
+            let throttle = ThrottleInfo::from_response(status, res.headers());
+            let body = res.bytes().await.ok();
+            let err = decode_error(status, body.as_deref(), throttle);
+
+            #[cfg(feature = "tracing")]
+            if let Some(reason) = err.reason() {
+                tracing::Span::current().record("reason", reason.as_str());
+            }
+
+            self.metrics.on_response(status, err.reason(), latency);
+            Err(err)
+        } else {
+            self.metrics.on_response(status, None, latency);
+            Ok(Response {
+                apns_id: decode_apns_id(res.headers()),
+                version,
+                remote_addr,
+                payload_size,
+            })
+        }
+    }
+}
+
+/// A successful response to a push notification, see
+/// [`Client::post_with_response`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Response {
+    /// The APNS ID of the notification.
+    pub apns_id: Uuid,
+
+    /// The HTTP version of the connection, HTTP/2 unless the `http1`
+    /// feature is enabled.
+    pub version: http::Version,
+
+    /// The address of the server the connection was made to, if known.
+    pub remote_addr: Option<SocketAddr>,
+
+    /// The size in bytes of the payload as sent.
+    pub payload_size: usize,
+}
+
+/// Client-wide request validation settings.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RequestPolicy {
+    pub validate_requests: bool,
+    pub clamp_relevance_score: bool,
+    pub reconcile_critical_sound: bool,
+    pub infer_push_type: bool,
+    pub field_limits: FieldLimits,
+    pub prioritize_power_min_expiration: Option<Duration>,
+    pub certificate_authentication: bool,
+    pub token_authentication: bool,
+}
+
+impl RequestPolicy {
+    /// Checks a request against [`Request::validate`] and the rules that
+    /// depend on the client's authentication.
+    pub fn validate<T>(&self, request: &Request<T>) -> std::result::Result<(), Vec<ValidationError>>
+    where
+        T: Serialize,
+    {
+        let mut errors = request
+            .validate_with_limits(&self.field_limits)
+            .err()
+            .unwrap_or_default();
+
+        if self.token_authentication && request.topic.is_none() {
+            errors.push(ValidationError::Topic);
+        }
+
+        if self.certificate_authentication && request.push_type == PushType::Location {
+            errors.push(ValidationError::Authentication {
+                push_type: request.push_type,
             });
         }
 
-        let mut req = self.client.post(url).body(body);
-        for (name, value) in headers {
-            if let Some(name) = name {
-                req = req.header(name, value);
+        if let Some(min) = self.prioritize_power_min_expiration {
+            if let Some(expires_in) = request.short_expiration(min, OffsetDateTime::now_utc()) {
+                errors.push(ValidationError::Expiration { expires_in, min });
             }
         }
 
-        #[cfg(feature = "jwt")]
-        if let Some(token_factory) = &self.token_factory {
-            let jwt = token_factory.get()?;
-            req = req.bearer_auth(jwt);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
         }
+    }
 
-        let res = req.send().await?;
+    /// Returns the push type to send a request with.
+    pub fn push_type<T>(&self, request: &Request<T>) -> PushType {
+        if self.infer_push_type && request.push_type == PushType::default() {
+            request.inferred_push_type()
+        } else {
+            request.push_type
+        }
+    }
 
-        if let Err(err) = res.error_for_status_ref() {
-            if let Ok(reason) = res.json::<Reason>().await {
-                Err(reason.into())
-            } else {
-                Err(err.into())
+    /// Rejects a nil `apns-id`, or drops it when request validation is
+    /// disabled so that APNs generates one.
+    pub fn check_id(&self, id: &mut Option<Uuid>) -> Result<()> {
+        if id.map(|id| id.is_nil()).unwrap_or_default() {
+            if self.validate_requests {
+                return Err(Error::NilApnsId);
             }
+
+            #[cfg(feature = "tracing")]
+            tracing::warn!("dropping nil apns-id");
+            *id = None;
+        }
+        Ok(())
+    }
+
+    /// Checks an MDM request against [`MdmRequest::validate`] and the rules
+    /// that depend on the client's authentication.
+    pub fn validate_mdm(
+        &self,
+        request: &MdmRequest,
+    ) -> std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = request.validate().err().unwrap_or_default();
+
+        if self.token_authentication && request.topic.is_none() {
+            errors.push(ValidationError::Topic);
+        }
+
+        if errors.is_empty() {
+            Ok(())
         } else {
-            let apns_id = res
-                .headers()
-                .get(&APNS_ID)
-                .and_then(|v| v.to_str().ok())
-                .and_then(|s| s.parse().ok())
-                .unwrap_or_default();
-            Ok(apns_id)
+            Err(errors)
+        }
+    }
+
+    /// Validates a request, logging instead of failing when request
+    /// validation is disabled.
+    pub fn check<T>(&self, request: &Request<T>) -> Result<()>
+    where
+        T: Serialize,
+    {
+        self.enforce(self.validate(request))
+    }
+
+    /// Checks a broadcast like [`RequestPolicy::check`], except for its
+    /// device token and topic, which a broadcast does not send.
+    pub fn check_broadcast<T>(&self, request: &Request<T>) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let result = self.validate(request).or_else(|errors| {
+            let errors = errors
+                .into_iter()
+                .filter(|err| !matches!(err, ValidationError::DeviceToken | ValidationError::Topic))
+                .collect::<Vec<_>>();
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                Err(errors)
+            }
+        });
+        self.enforce(result)
+    }
+
+    /// Returns validation errors as [`Error::Validation`], or logs them when
+    /// request validation is disabled.
+    pub fn enforce(&self, result: std::result::Result<(), Vec<ValidationError>>) -> Result<()> {
+        match result {
+            Err(errors) if !self.validate_requests => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(
+                    error = %Error::Validation(errors),
+                    "sending invalid APNs request"
+                );
+                #[cfg(not(feature = "tracing"))]
+                let _ = errors;
+                Ok(())
+            }
+            result => Ok(result?),
         }
     }
 }
+
+/// Lints a request with the client's settings, see [`Client::lint`].
+pub(crate) fn lint<T>(
+    policy: &RequestPolicy,
+    localizable_strings: Option<&LocalizableStrings>,
+    request: &Request<T>,
+) -> Vec<Lint>
+where
+    T: Serialize,
+{
+    let options = LintOptions {
+        prioritize_power_min_expiration: policy
+            .prioritize_power_min_expiration
+            .unwrap_or(PRIORITIZE_POWER_MIN_EXPIRATION),
+    };
+    let mut lints = request.lint_with(&options, OffsetDateTime::now_utc());
+    if let (Some(strings), Some(alert)) = (localizable_strings, &request.alert) {
+        lints.extend(alert.validate_against_strings(strings));
+    }
+    lints
+}
+
+/// Initial capacity of a serialized payload, enough for most alerts to
+/// serialize without reallocating.
+const PAYLOAD_CAPACITY: usize = 512;
+
+/// Encodes a request into the device URL, headers, and JSON body to send to
+/// APNs.
+pub(crate) fn encode_request<T>(
+    base_url: &Url,
+    policy: &RequestPolicy,
+    request: Request<T>,
+) -> Result<(Url, HeaderMap, Vec<u8>)>
+where
+    T: Serialize,
+{
+    let (url, headers, payload) = prepare_request(base_url, policy, request)?;
+    Ok((url, headers, payload.to_body()?))
+}
+
+/// Checks a request and converts it into the device URL, headers, and payload
+/// to send to APNs, like [`encode_request`] without serializing the payload.
+fn prepare_request<T>(
+    base_url: &Url,
+    policy: &RequestPolicy,
+    request: Request<T>,
+) -> Result<(Url, HeaderMap, PreparedPayload<T>)>
+where
+    T: Serialize,
+{
+    let url = join_device_token(base_url, request.device_token.as_str())?;
+    let (headers, payload) = prepare_payload(policy, request, RequestPolicy::check)?;
+    Ok((url, headers, payload))
+}
+
+/// Encodes a Live Activity broadcast into the broadcast URL, headers, and
+/// JSON body to send to APNs. The device token and topic of the request are
+/// not sent: the bundle ID is part of the URL, and the `apns-channel-id`
+/// header selects the devices.
+pub(crate) fn encode_broadcast_request<T>(
+    base_url: &Url,
+    policy: &RequestPolicy,
+    bundle_id: &str,
+    channel_id: &str,
+    request: Request<T>,
+) -> Result<(Url, HeaderMap, Vec<u8>)>
+where
+    T: Serialize,
+{
+    let url = join_bundle_id(base_url, bundle_id)?;
+    if policy.push_type(&request) != PushType::Liveactivity {
+        return Err(Error::InvalidRequest(
+            "a broadcast must be a liveactivity push notification".into(),
+        ));
+    }
+    let (mut headers, body) = encode_payload(policy, request, RequestPolicy::check_broadcast)?;
+    headers.remove(&APNS_TOPIC);
+    headers.insert(APNS_CHANNEL_ID.clone(), HeaderValue::from_str(channel_id)?);
+    Ok((url, headers, body))
+}
+
+/// Encodes a request into the headers and JSON body to send to APNs, after
+/// applying the policy and checking the request with `check`.
+fn encode_payload<T>(
+    policy: &RequestPolicy,
+    request: Request<T>,
+    check: fn(&RequestPolicy, &Request<T>) -> Result<()>,
+) -> Result<(HeaderMap, Vec<u8>)>
+where
+    T: Serialize,
+{
+    let (headers, payload) = prepare_payload(policy, request, check)?;
+    Ok((headers, payload.to_body()?))
+}
+
+/// A checked payload with the size limit of its push type, before it is
+/// serialized.
+struct PreparedPayload<T>
+where
+    T: Serialize,
+{
+    payload: Payload<T>,
+    size_limit: usize,
+}
+
+impl<T> PreparedPayload<T>
+where
+    T: Serialize,
+{
+    /// Serializes the payload, or returns [`Error::PayloadTooLarge`] if it
+    /// exceeds the size limit.
+    fn to_body(&self) -> Result<Vec<u8>> {
+        let mut body = Vec::with_capacity(PAYLOAD_CAPACITY);
+        serde_json::to_writer(&mut body, &self.payload)?;
+        if body.len() > self.size_limit {
+            return Err(Error::PayloadTooLarge {
+                size: body.len(),
+                limit: self.size_limit,
+            });
+        }
+        Ok(body)
+    }
+}
+
+/// Applies the policy to a request, checks it with `check` and converts it
+/// into the headers and payload to send to APNs.
+fn prepare_payload<T>(
+    policy: &RequestPolicy,
+    mut request: Request<T>,
+    check: fn(&RequestPolicy, &Request<T>) -> Result<()>,
+) -> Result<(HeaderMap, PreparedPayload<T>)>
+where
+    T: Serialize,
+{
+    request.push_type = policy.push_type(&request);
+    if policy.clamp_relevance_score {
+        request.clamp_relevance_score();
+    }
+    if policy.reconcile_critical_sound {
+        request.reconcile_critical_sound();
+    }
+    policy.check_id(&mut request.id)?;
+    check(policy, &request)?;
+
+    let size_limit = request.push_type.payload_size_limit();
+    let (headers, payload) = request.try_into()?;
+    Ok((
+        headers,
+        PreparedPayload {
+            payload,
+            size_limit,
+        },
+    ))
+}
+
+/// Builds the HTTP request for a push notification.
+#[cfg(feature = "blocking")]
+pub(crate) fn build_http_request<T>(
+    base_url: &Url,
+    policy: &RequestPolicy,
+    bearer_token: Option<&str>,
+    request: Request<T>,
+) -> Result<http::Request<Vec<u8>>>
+where
+    T: Serialize,
+{
+    let (url, headers, body) = encode_request(base_url, policy, request)?;
+    http_request(url, headers, body, bearer_token)
+}
+
+/// Encodes an MDM request into the device URL, headers, and JSON body to send
+/// to APNs.
+pub(crate) fn encode_mdm_request(
+    base_url: &Url,
+    policy: &RequestPolicy,
+    mut request: MdmRequest,
+) -> Result<(Url, HeaderMap, Vec<u8>)> {
+    let url = join_device_token(base_url, request.device_token.as_str())?;
+    policy.check_id(&mut request.id)?;
+    policy.enforce(policy.validate_mdm(&request))?;
+
+    let (headers, payload): (_, MdmPayload) = request.try_into()?;
+    let body = serde_json::to_vec(&payload)?;
+
+    Ok((url, headers, body))
+}
+
+/// Builds a `POST` request with an optional provider token.
+fn http_request(
+    url: Url,
+    mut headers: HeaderMap,
+    body: Vec<u8>,
+    bearer_token: Option<&str>,
+) -> Result<http::Request<Vec<u8>>> {
+    if let Some(bearer_token) = bearer_token {
+        headers.insert(header::AUTHORIZATION, authorization(bearer_token)?);
+    }
+
+    let mut req = http::Request::new(body);
+    *req.method_mut() = Method::POST;
+    *req.uri_mut() = url.as_str().parse()?;
+    *req.headers_mut() = headers;
+    Ok(req)
+}
+
+/// Builds a `POST` request for reqwest directly from the device URL, which
+/// spares converting an [`http::Request`] and parsing its URI back into a URL.
+fn reqwest_request(url: Url, headers: HeaderMap, body: Vec<u8>) -> reqwest::Request {
+    let mut req = reqwest::Request::new(Method::POST, url);
+    *req.headers_mut() = headers;
+    *req.body_mut() = Some(body.into());
+    req
+}
+
+/// Returns the `apns-id` generated for a request on success and attaches it
+/// to errors.
+pub(crate) fn with_apns_id(apns_id: Option<Uuid>, result: Result<Response>) -> Result<Response> {
+    match (apns_id, result) {
+        (Some(apns_id), Ok(res)) => Ok(Response { apns_id, ..res }),
+        (Some(apns_id), Err(err)) => Err(Error::WithApnsId {
+            apns_id,
+            source: Box::new(err),
+        }),
+        (None, result) => result,
+    }
+}
+
+/// Calls `mutator` with the headers and body of `req`, see
+/// [`mutate_headers`].
+#[cfg(feature = "blocking")]
+pub(crate) fn mutate_request(
+    mutator: Option<&(dyn RequestMutator + Send + Sync)>,
+    req: http::Request<Vec<u8>>,
+) -> Result<http::Request<Vec<u8>>> {
+    let (mut parts, body) = req.into_parts();
+    mutate_headers(mutator, &mut parts.headers, &body)?;
+    Ok(http::Request::from_parts(parts, body))
+}
+
+/// Calls `mutator` with the headers and body of an encoded request, checking
+/// that it leaves the `apns-*` headers as they are.
+fn mutate_headers(
+    mutator: Option<&(dyn RequestMutator + Send + Sync)>,
+    headers: &mut HeaderMap,
+    body: &[u8],
+) -> Result<()> {
+    let mutator = match mutator {
+        Some(mutator) => mutator,
+        None => return Ok(()),
+    };
+
+    let apns_headers = |headers: &HeaderMap| {
+        headers
+            .iter()
+            .filter(|(name, _)| name.as_str().starts_with("apns-"))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect::<Vec<_>>()
+    };
+
+    let reserved = apns_headers(headers);
+    mutator
+        .mutate(headers, body)
+        .map_err(|err| Error::Mutator(Box::new(err)))?;
+    if apns_headers(headers) != reserved {
+        return Err(Error::Mutator(Box::new(Error::InvalidRequest(
+            "apns-* headers must not be changed".into(),
+        ))));
+    }
+    Ok(())
+}
+
+/// Returns the time left until `deadline`, or [`Error::DeadlineExceeded`] if
+/// it has passed.
+pub(crate) fn remaining_until(deadline: Option<Instant>) -> Result<Option<Duration>> {
+    match deadline {
+        Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+            Some(remaining) if !remaining.is_zero() => Ok(Some(remaining)),
+            _ => Err(Error::DeadlineExceeded),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Returns `true` if a request failed before APNs processed it: APNs refused
+/// its stream, it was above the last stream id of a graceful GOAWAY, or it was
+/// canceled before being sent.
+///
+/// A connection that closes without a GOAWAY, or after a GOAWAY that covers
+/// the stream, may have delivered the notification, so it is not retried. h2
+/// only fails streams above the last stream id with the remote GOAWAY error;
+/// the streams it covers fail with a broken pipe if the connection closes.
+pub(crate) fn is_unprocessed(err: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<h2::Error>() {
+            let graceful_go_away =
+                err.is_go_away() && err.is_remote() && err.reason() == Some(h2::Reason::NO_ERROR);
+            let refused_stream = err.is_reset()
+                && err.is_remote()
+                && err.reason() == Some(h2::Reason::REFUSED_STREAM);
+            return graceful_go_away || refused_stream;
+        }
+        if let Some(err) = err.downcast_ref::<hyper::Error>() {
+            if err.is_canceled() {
+                return true;
+            }
+        }
+        source = err.source();
+    }
+    false
+}
+
+/// Copies a request, e.g. to resend it with a new provider token.
+#[cfg(all(feature = "jwt", feature = "blocking"))]
+pub(crate) fn clone_http_request(req: &http::Request<Vec<u8>>) -> http::Request<Vec<u8>> {
+    let mut clone = http::Request::new(req.body().clone());
+    *clone.method_mut() = req.method().clone();
+    *clone.uri_mut() = req.uri().clone();
+    *clone.version_mut() = req.version();
+    *clone.headers_mut() = req.headers().clone();
+    clone
+}
+
+/// Returns `true` for the APNs errors that a new provider token may fix.
+#[cfg(feature = "jwt")]
+pub(crate) fn is_provider_token_error(err: &Error) -> bool {
+    matches!(
+        err,
+        Error::Apns(Reason::ExpiredProviderToken | Reason::InvalidProviderToken)
+    )
+}
+
+/// Replaces the provider token of a request.
+#[cfg(all(feature = "jwt", feature = "blocking"))]
+pub(crate) fn with_bearer_token(
+    mut req: http::Request<Vec<u8>>,
+    bearer_token: &str,
+) -> Result<http::Request<Vec<u8>>> {
+    req.headers_mut()
+        .insert(header::AUTHORIZATION, authorization(bearer_token)?);
+    Ok(req)
+}
+
+/// Builds a sensitive `authorization` header value for a provider token.
+fn authorization(bearer_token: &str) -> Result<HeaderValue> {
+    let mut authorization: HeaderValue = format!("Bearer {bearer_token}").parse()?;
+    authorization.set_sensitive(true);
+    Ok(authorization)
+}
+
+/// Decodes the reason from an APNs error response body.
+pub(crate) fn decode_reason(body: &[u8]) -> Option<Reason> {
+    Reason::from_body(body).ok()
+}
+
+/// Returns `true` for the client and server error statuses that APNs
+/// explains in the response body.
+pub(crate) fn is_error_status(status: StatusCode) -> bool {
+    status.is_client_error() || status.is_server_error()
+}
+
+/// Returns the error for an error response whose body was read once: the
+/// APNs reason, or [`Error::UnexpectedResponse`] with the raw body if it
+/// has none. `body` is `None` if reading it failed. `throttle` is attached
+/// to a [`Reason::TooManyRequests`] error.
+pub(crate) fn decode_error(
+    status: StatusCode,
+    body: Option<&[u8]>,
+    throttle: ThrottleInfo,
+) -> Error {
+    match body.and_then(decode_reason) {
+        Some(Reason::TooManyRequests) => Error::Throttled {
+            throttle,
+            source: Box::new(Reason::TooManyRequests.into()),
+        },
+        Some(reason) => reason.into(),
+        None => Error::UnexpectedResponse {
+            status,
+            body: body.map(<[u8]>::to_vec),
+        },
+    }
+}
+
+/// Decodes the APNs ID from the response headers.
+pub(crate) fn decode_apns_id(headers: &HeaderMap) -> Uuid {
+    headers
+        .get(&APNS_ID)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DEVICE_TOKEN: &str = "a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae";
+
+    fn policy(validate_requests: bool) -> RequestPolicy {
+        RequestPolicy {
+            validate_requests,
+            clamp_relevance_score: false,
+            reconcile_critical_sound: false,
+            infer_push_type: false,
+            field_limits: FieldLimits::default(),
+            prioritize_power_min_expiration: None,
+            certificate_authentication: false,
+            token_authentication: false,
+        }
+    }
+
+    fn encode_headers(policy: &RequestPolicy, id: Option<Uuid>) -> Result<HeaderMap> {
+        let request = Request::<()> {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            id,
+            alert: Some("Hello World!".into()),
+            ..Default::default()
+        };
+        let base_url = Endpoint::Development.as_url();
+        let (_, headers, _) = encode_request(base_url, policy, request)?;
+        Ok(headers)
+    }
+
+    #[test]
+    fn apns_id_nil_rejected() {
+        let err = encode_headers(&policy(true), Some(Uuid::nil())).unwrap_err();
+        assert!(matches!(err, Error::NilApnsId));
+    }
+
+    #[test]
+    fn apns_id_nil_dropped() {
+        let headers = encode_headers(&policy(false), Some(Uuid::nil())).unwrap();
+        assert_eq!(headers.get(&APNS_ID), None);
+    }
+
+    #[test]
+    fn apns_id_lowercase() {
+        let id = Uuid::parse_str("4D947500-498E-4524-8AA8-7220C4E65D75").unwrap();
+        for validate_requests in [true, false] {
+            let headers = encode_headers(&policy(validate_requests), Some(id)).unwrap();
+            assert_eq!(
+                headers.get(&APNS_ID).unwrap(),
+                "4d947500-498e-4524-8aa8-7220c4e65d75"
+            );
+        }
+    }
+
+    #[test]
+    fn apns_id_none() {
+        for validate_requests in [true, false] {
+            let headers = encode_headers(&policy(validate_requests), None).unwrap();
+            assert_eq!(headers.get(&APNS_ID), None);
+        }
+    }
+
+    #[test]
+    fn infer_push_type() {
+        let policy = RequestPolicy {
+            infer_push_type: true,
+            ..policy(true)
+        };
+        let silent = Request::<()> {
+            content_available: true,
+            ..Default::default()
+        };
+
+        assert_eq!(policy.push_type(&silent), PushType::Background);
+        assert_eq!(self::policy(true).push_type(&silent), PushType::Alert);
+
+        // Explicit push types are left alone.
+        let voip = Request::<()> {
+            push_type: PushType::Voip,
+            ..silent
+        };
+        assert_eq!(policy.push_type(&voip), PushType::Voip);
+    }
+
+    #[test]
+    fn prioritize_power_min_expiration() {
+        let hour = Duration::from_secs(60 * 60);
+        let request = Request::<()> {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            priority: Some(crate::Priority::PrioritizePower),
+            expiration: crate::Expiration::In(Duration::from_secs(60)),
+            alert: Some("Hello World!".into()),
+            ..Default::default()
+        };
+        assert_eq!(policy(true).validate(&request), Ok(()));
+
+        let strict = RequestPolicy {
+            prioritize_power_min_expiration: Some(hour),
+            ..policy(true)
+        };
+        assert_eq!(
+            strict.validate(&request),
+            Err(vec![ValidationError::Expiration {
+                expires_in: Duration::from_secs(60),
+                min: hour,
+            }])
+        );
+
+        let request = Request {
+            expiration: crate::Expiration::In(hour),
+            ..request
+        };
+        assert_eq!(strict.validate(&request), Ok(()));
+    }
+
+    #[test]
+    fn clamp_relevance_score() {
+        let encode = |policy: &RequestPolicy, relevance_score| {
+            let request = Request::<()> {
+                device_token: DEVICE_TOKEN.parse().unwrap(),
+                alert: Some("Hello World!".into()),
+                relevance_score: Some(relevance_score),
+                ..Default::default()
+            };
+            let base_url = Endpoint::Development.as_url();
+            encode_request(base_url, policy, request)
+                .map(|(_, _, body)| serde_json::from_slice::<serde_json::Value>(&body).unwrap())
+        };
+        let clamp = RequestPolicy {
+            clamp_relevance_score: true,
+            ..policy(true)
+        };
+
+        assert!(matches!(
+            encode(&policy(true), 1.5),
+            Err(Error::Validation(_))
+        ));
+        assert_eq!(encode(&clamp, 1.5).unwrap()["aps"]["relevance-score"], 1.);
+        assert_eq!(encode(&clamp, -1.).unwrap()["aps"]["relevance-score"], 0.);
+        assert!(matches!(
+            encode(&clamp, f64::NAN),
+            Err(Error::Validation(_))
+        ));
+
+        // Not even sent when validation is disabled.
+        let unvalidated = RequestPolicy {
+            clamp_relevance_score: true,
+            ..policy(false)
+        };
+        assert!(matches!(
+            encode(&unvalidated, f64::NAN),
+            Err(Error::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn reconcile_critical_sound() {
+        let request = Request::<()> {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            alert: Some("Hello World!".into()),
+            interruption_level: Some(InterruptionLevel::Critical),
+            sound: Some("siren.aiff".into()),
+            ..Default::default()
+        };
+        let base_url = Endpoint::Development.as_url();
+
+        assert!(matches!(
+            encode_request(base_url, &policy(false), request.clone()),
+            Err(Error::CriticalSound)
+        ));
+        assert!(matches!(
+            encode_request(base_url, &policy(true), request.clone()),
+            Err(Error::Validation(_))
+        ));
+
+        let lenient = RequestPolicy {
+            reconcile_critical_sound: true,
+            ..policy(true)
+        };
+        let (_, _, body) = encode_request(base_url, &lenient, request).unwrap();
+        let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            payload["aps"]["sound"],
+            serde_json::json!({ "critical": 1, "name": "siren.aiff", "volume": 1.0 })
+        );
+    }
+
+    #[test]
+    fn environment_hint() {
+        for (endpoint, environment_hint, ok) in [
+            (Endpoint::Production, None, true),
+            (Endpoint::Development, None, true),
+            (
+                Endpoint::Production,
+                Some(EnvironmentHint::Production),
+                true,
+            ),
+            (Endpoint::Development, Some(EnvironmentHint::Sandbox), true),
+            (Endpoint::Production, Some(EnvironmentHint::Sandbox), false),
+            (
+                Endpoint::Development,
+                Some(EnvironmentHint::Production),
+                false,
+            ),
+        ] {
+            let builder = ClientBuilder {
+                endpoint: endpoint.clone(),
+                environment_hint,
+                ..Default::default()
+            };
+            assert_eq!(
+                builder.check_endpoint().is_ok(),
+                ok,
+                "{endpoint:?} {environment_hint:?}"
+            );
+        }
+
+        let err = ClientBuilder {
+            endpoint: Endpoint::Production,
+            environment_hint: Some(EnvironmentHint::Sandbox),
+            ..Default::default()
+        }
+        .build()
+        .err()
+        .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "endpoint https://api.push.apple.com./3/device/ is not in the expected sandbox environment"
+        );
+    }
+
+    #[test]
+    fn allow_insecure_http() {
+        let endpoint: Endpoint = "http://localhost:8080/3/device/".parse().unwrap();
+        let err = ClientBuilder {
+            endpoint: endpoint.clone(),
+            ..Default::default()
+        }
+        .build()
+        .err()
+        .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "endpoint http://localhost:8080/3/device/ uses http without TLS, set allow_insecure_http to use it"
+        );
+
+        let builder = ClientBuilder {
+            endpoint,
+            allow_insecure_http: true,
+            ..Default::default()
+        };
+        assert!(builder.check_endpoint().is_ok());
+    }
+
+    #[test]
+    fn http2_max_frame_size() {
+        for (size, ok) in [
+            (None, true),
+            (Some(0), false),
+            (Some(16_383), false),
+            (Some(16_384), true),
+            (Some(1 << 20), true),
+            (Some(16_777_215), true),
+            (Some(16_777_216), false),
+            (Some(u32::MAX), false),
+        ] {
+            let builder = ClientBuilder {
+                http2_max_frame_size: size,
+                ..Default::default()
+            };
+            assert_eq!(builder.check_http2().is_ok(), ok, "{size:?}");
+        }
+
+        let err = ClientBuilder {
+            allow_unauthenticated: true,
+            http2_max_frame_size: Some(16_383),
+            ..Default::default()
+        }
+        .build()
+        .err()
+        .unwrap();
+        assert!(
+            matches!(err, Error::InvalidHttp2MaxFrameSize(16_383)),
+            "{err:?}"
+        );
+        assert_eq!(
+            err.to_string(),
+            "HTTP/2 max frame size 16383 is not between 16384 and 16777215"
+        );
+
+        let builder = ClientBuilder {
+            allow_unauthenticated: true,
+            http2_adaptive_window: Some(false),
+            http2_max_frame_size: Some(16_777_215),
+            ..Default::default()
+        };
+        assert!(builder.build().is_ok());
+    }
+
+    #[test]
+    fn allow_unauthenticated() {
+        let err = ClientBuilder::new().build().err().unwrap();
+        assert!(matches!(err, Error::NoAuthentication), "{err:?}");
+        assert_eq!(
+            err.to_string(),
+            "no authentication configured, set authentication or allow_unauthenticated"
+        );
+
+        let builder = ClientBuilder {
+            allow_unauthenticated: true,
+            ..Default::default()
+        };
+        assert!(builder.build().is_ok());
+    }
+
+    #[test]
+    fn check_id() {
+        let id = Uuid::new_v4();
+
+        let mut some = Some(id);
+        policy(true).check_id(&mut some).unwrap();
+        assert_eq!(some, Some(id));
+
+        let mut nil = Some(Uuid::nil());
+        assert!(matches!(
+            policy(true).check_id(&mut nil),
+            Err(Error::NilApnsId)
+        ));
+        assert_eq!(nil, Some(Uuid::nil()));
+
+        policy(false).check_id(&mut nil).unwrap();
+        assert_eq!(nil, None);
+    }
+
+    #[test]
+    fn build_lazy() {
+        let client = ClientBuilder {
+            allow_unauthenticated: true,
+            ..Default::default()
+        }
+        .build_lazy()
+        .unwrap();
+        assert!(client.connection.get().is_none());
+
+        // Concurrent first use builds a single connection.
+        let connections = std::thread::scope(|scope| {
+            let threads = (0..32)
+                .map(|_| scope.spawn(|| client.connection().unwrap() as *const _ as usize))
+                .collect::<Vec<_>>();
+            threads
+                .into_iter()
+                .map(|thread| thread.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        assert!(connections.iter().all(|&c| c == connections[0]));
+        assert!(client.connection.get().is_some());
+    }
+
+    /// Sends a request on a real HTTP/2 connection, to which the server
+    /// answers with the raw `frames` after receiving its headers, and returns
+    /// the error of the response.
+    async fn h2_error(frames: &[&[u8]]) -> h2::Error {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (io, mut server) = tokio::io::duplex(4096);
+        let frames = frames.concat();
+        tokio::spawn(async move {
+            let mut preface = [0; 24];
+            server.read_exact(&mut preface).await.unwrap();
+            // An empty SETTINGS frame.
+            server
+                .write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+            loop {
+                let mut head = [0; 9];
+                server.read_exact(&mut head).await.unwrap();
+                let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+                server.read_exact(&mut vec![0; len]).await.unwrap();
+                // HEADERS
+                if head[3] == 1 {
+                    break;
+                }
+            }
+            server.write_all(&frames).await.unwrap();
+            // Closes the connection.
+        });
+
+        let (mut client, connection) = h2::client::handshake(io).await.unwrap();
+        tokio::spawn(connection);
+        let request = http::Request::post("https://api.push.apple.com/3/device/a863")
+            .body(())
+            .unwrap();
+        let (response, _) = client.send_request(request, true).unwrap();
+        response.await.unwrap_err()
+    }
+
+    /// A GOAWAY frame with `last_stream_id` and NO_ERROR.
+    fn go_away(last_stream_id: u8) -> [u8; 17] {
+        [
+            0,
+            0,
+            8,
+            7,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            last_stream_id,
+            0,
+            0,
+            0,
+            0,
+        ]
+    }
+
+    #[tokio::test]
+    async fn unprocessed_refused_stream() {
+        // RST_STREAM with REFUSED_STREAM on stream 1.
+        let err = h2_error(&[&[0, 0, 4, 3, 0, 0, 0, 0, 1, 0, 0, 0, 7]]).await;
+        assert!(is_unprocessed(&err), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn processed_internal_error() {
+        // RST_STREAM with INTERNAL_ERROR on stream 1.
+        let err = h2_error(&[&[0, 0, 4, 3, 0, 0, 0, 0, 1, 0, 0, 0, 2]]).await;
+        assert!(!is_unprocessed(&err), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn unprocessed_above_go_away() {
+        let err = h2_error(&[&go_away(0)]).await;
+        assert!(is_unprocessed(&err), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn processed_below_go_away() {
+        let err = h2_error(&[&go_away(1)]).await;
+        assert!(!is_unprocessed(&err), "{err:?}");
+    }
+
+    #[cfg(feature = "jwt")]
+    #[test]
+    fn build_lazy_error() {
+        let builder = ClientBuilder {
+            authentication: Some(Authentication::Token {
+                key_id: "ABC123DEF4".into(),
+                key_pem: b"not a key"[..].into(),
+                team_id: "DEF123GHI4".into(),
+            }),
+            ..Default::default()
+        };
+        assert!(matches!(builder.clone().build(), Err(Error::Jwt(_))));
+        let client = builder.build_lazy().unwrap();
+
+        // Every request fails with the error of the single build attempt.
+        let errors = std::thread::scope(|scope| {
+            let threads = (0..32)
+                .map(|_| scope.spawn(|| client.connection().err().unwrap()))
+                .collect::<Vec<_>>();
+            threads
+                .into_iter()
+                .map(|thread| match thread.join().unwrap() {
+                    Error::Build(err) => err,
+                    err => panic!("{err:?}"),
+                })
+                .collect::<Vec<_>>()
+        });
+        assert!(matches!(*errors[0], Error::Jwt(_)));
+        assert!(errors.iter().all(|err| Arc::ptr_eq(err, &errors[0])));
+    }
+}
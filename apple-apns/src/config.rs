@@ -0,0 +1,263 @@
+//! Deserializable [`ClientBuilder`] configuration.
+//!
+//! ```yaml
+//! endpoint: development
+//! user_agent: my-service/1.0.0
+//! authentication:
+//!   type: token
+//!   key_id: ABC123DEF4
+//!   key_pem:
+//!     file: /etc/apns/AuthKey_ABC123DEF4.p8
+//!   team_id: DEF123GHIJ
+//! ```
+
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use zeroize::Zeroizing;
+
+#[cfg(any(feature = "rustls", feature = "jwt"))]
+use crate::client::Authentication;
+#[cfg(feature = "rustls")]
+use crate::client::CertificateAuthority;
+use crate::client::{ClientBuilder, USER_AGENT};
+use crate::endpoint::{Endpoint, EnvironmentHint};
+use crate::limits::FieldLimits;
+use crate::once::OnceCell;
+use crate::result::Result;
+use crate::strings;
+
+/// [`ClientBuilder`] configuration.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientConfig {
+    pub endpoint: Endpoint,
+    pub user_agent: Option<String>,
+
+    /// See [`ClientBuilder::environment_hint`].
+    pub environment_hint: Option<EnvironmentHint>,
+
+    /// See [`ClientBuilder::allow_insecure_http`].
+    pub allow_insecure_http: bool,
+
+    /// See [`ClientBuilder::allow_unauthenticated`].
+    pub allow_unauthenticated: bool,
+
+    /// See [`ClientBuilder::max_inflight_bytes`].
+    pub max_inflight_bytes: Option<usize>,
+
+    /// See [`ClientBuilder::generate_apns_id`].
+    pub generate_apns_id: bool,
+
+    /// See [`ClientBuilder::validate_requests`].
+    pub validate_requests: bool,
+
+    /// See [`ClientBuilder::clamp_relevance_score`].
+    pub clamp_relevance_score: bool,
+
+    /// See [`ClientBuilder::reconcile_critical_sound`].
+    pub reconcile_critical_sound: bool,
+
+    /// See [`ClientBuilder::infer_push_type`].
+    pub infer_push_type: bool,
+
+    /// See [`ClientBuilder::field_limits`].
+    pub field_limits: FieldLimits,
+
+    /// See [`ClientBuilder::recent_targets`].
+    pub recent_targets: usize,
+
+    /// See [`ClientBuilder::expected_topics`].
+    pub expected_topics: Option<Vec<String>>,
+
+    /// See [`ClientBuilder::pin_addresses`].
+    pub pin_addresses: Vec<SocketAddr>,
+
+    /// See [`ClientBuilder::http2_adaptive_window`].
+    pub http2_adaptive_window: Option<bool>,
+
+    /// See [`ClientBuilder::http2_max_frame_size`].
+    pub http2_max_frame_size: Option<u32>,
+
+    /// A `Localizable.strings` file, see
+    /// [`ClientBuilder::localizable_strings`].
+    pub localizable_strings: Option<PathBuf>,
+
+    /// PEM-encoded certificate authority.
+    #[cfg(feature = "rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    pub ca: Option<PemSource>,
+
+    #[cfg(any(feature = "rustls", feature = "jwt"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "rustls", feature = "jwt"))))]
+    pub authentication: Option<AuthenticationConfig>,
+
+    /// See [`ClientBuilder::proactive_token_refresh`].
+    #[cfg(feature = "jwt")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
+    pub proactive_token_refresh: bool,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: Endpoint::default(),
+            user_agent: None,
+            environment_hint: None,
+            allow_insecure_http: false,
+            allow_unauthenticated: false,
+            max_inflight_bytes: None,
+            generate_apns_id: false,
+            validate_requests: true,
+            clamp_relevance_score: false,
+            reconcile_critical_sound: false,
+            infer_push_type: false,
+            field_limits: FieldLimits::default(),
+            recent_targets: 16,
+            expected_topics: None,
+            pin_addresses: Vec::new(),
+            http2_adaptive_window: None,
+            http2_max_frame_size: None,
+            localizable_strings: None,
+
+            #[cfg(feature = "rustls")]
+            ca: None,
+
+            #[cfg(any(feature = "rustls", feature = "jwt"))]
+            authentication: None,
+
+            #[cfg(feature = "jwt")]
+            proactive_token_refresh: false,
+        }
+    }
+}
+
+/// Authentication configuration. See [`Authentication`].
+#[cfg(any(feature = "rustls", feature = "jwt"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "rustls", feature = "jwt"))))]
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum AuthenticationConfig {
+    #[cfg(feature = "rustls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
+    Certificate { client_pem: PemSource },
+
+    #[cfg(feature = "jwt")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
+    Token {
+        key_id: String,
+        key_pem: PemSource,
+        team_id: String,
+    },
+}
+
+/// PEM data given inline or read from a file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PemSource {
+    Inline(String),
+    File(PemFile),
+}
+
+/// PEM file that is read once, when first used. If reading fails, every use
+/// fails with that error.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(from = "PathBuf")]
+pub struct PemFile {
+    path: PathBuf,
+    /// Zeroized when dropped, as it may hold a private key.
+    contents: OnceCell<std::result::Result<Zeroizing<Vec<u8>>, Arc<io::Error>>>,
+}
+
+impl From<PathBuf> for PemFile {
+    fn from(path: PathBuf) -> Self {
+        Self {
+            path,
+            contents: OnceCell::new(),
+        }
+    }
+}
+
+impl PemSource {
+    /// Returns the PEM data, reading it from the file if needed.
+    pub fn read(&self) -> Result<&[u8]> {
+        match self {
+            Self::Inline(pem) => Ok(pem.as_bytes()),
+            Self::File(file) => file
+                .contents
+                .get_or_init(|| {
+                    std::fs::read(&file.path)
+                        .map(Zeroizing::new)
+                        .map_err(Arc::new)
+                })
+                .as_deref()
+                .map(Vec::as_slice)
+                .map_err(|err| io::Error::new(err.kind(), Arc::clone(err)).into()),
+        }
+    }
+}
+
+impl ClientConfig {
+    /// Creates a [`ClientBuilder`] from the configuration, reading any
+    /// referenced PEM and `Localizable.strings` files.
+    pub fn into_builder(&self) -> Result<ClientBuilder<'_>> {
+        Ok(ClientBuilder {
+            endpoint: self.endpoint.clone(),
+            user_agent: self.user_agent.as_deref().unwrap_or(USER_AGENT),
+            environment_hint: self.environment_hint,
+            allow_insecure_http: self.allow_insecure_http,
+            allow_unauthenticated: self.allow_unauthenticated,
+            max_inflight_bytes: self.max_inflight_bytes,
+            generate_apns_id: self.generate_apns_id,
+            validate_requests: self.validate_requests,
+            clamp_relevance_score: self.clamp_relevance_score,
+            reconcile_critical_sound: self.reconcile_critical_sound,
+            infer_push_type: self.infer_push_type,
+            field_limits: self.field_limits,
+            recent_targets: self.recent_targets,
+            expected_topics: self.expected_topics.clone(),
+            pin_addresses: self.pin_addresses.clone(),
+            http2_adaptive_window: self.http2_adaptive_window,
+            http2_max_frame_size: self.http2_max_frame_size,
+            localizable_strings: match &self.localizable_strings {
+                Some(path) => Some(Arc::new(strings::parse(&std::fs::read_to_string(path)?)?)),
+                None => None,
+            },
+
+            #[cfg(feature = "rustls")]
+            ca: match &self.ca {
+                Some(ca) => Some(CertificateAuthority::Pem(ca.read()?)),
+                None => None,
+            },
+
+            #[cfg(any(feature = "rustls", feature = "jwt"))]
+            authentication: match &self.authentication {
+                #[cfg(feature = "rustls")]
+                Some(AuthenticationConfig::Certificate { client_pem }) => {
+                    Some(Authentication::Certificate {
+                        client_pem: client_pem.read()?,
+                    })
+                }
+                #[cfg(feature = "jwt")]
+                Some(AuthenticationConfig::Token {
+                    key_id,
+                    key_pem,
+                    team_id,
+                }) => Some(Authentication::Token {
+                    key_id: key_id.into(),
+                    key_pem: key_pem.read()?.into(),
+                    team_id: team_id.into(),
+                }),
+                None => None,
+            },
+
+            #[cfg(feature = "jwt")]
+            proactive_token_refresh: self.proactive_token_refresh,
+
+            ..Default::default()
+        })
+    }
+}
@@ -1,18 +1,63 @@
 use std::{fmt::Debug, str::FromStr};
 
-use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use serde_plain::{derive_display_from_serialize, derive_fromstr_from_deserialize};
 use url::Url;
 
-static PRODUCTION_SERVER: Lazy<Url> =
-    Lazy::new(|| Url::parse("https://api.push.apple.com./3/device/").unwrap());
+use crate::once::OnceCell;
+use crate::request::normalize_device_token;
+use crate::result::{Error, Result};
 
-static DEVELOPMENT_SERVER: Lazy<Url> =
-    Lazy::new(|| Url::parse("https://api.sandbox.push.apple.com./3/device/").unwrap());
+static PRODUCTION_SERVER: CachedUrl = CachedUrl::new("https://api.push.apple.com./3/device/");
+
+static DEVELOPMENT_SERVER: CachedUrl =
+    CachedUrl::new("https://api.sandbox.push.apple.com./3/device/");
+
+static PRODUCTION_CHANNELS_SERVER: CachedUrl =
+    CachedUrl::new("https://api-manage-broadcast.push.apple.com.:2196/1/apps/");
+
+static DEVELOPMENT_CHANNELS_SERVER: CachedUrl =
+    CachedUrl::new("https://api-manage-broadcast.sandbox.push.apple.com.:2195/1/apps/");
+
+static PRODUCTION_BROADCASTS_SERVER: CachedUrl =
+    CachedUrl::new("https://api.push.apple.com./4/broadcasts/apps/");
+
+static DEVELOPMENT_BROADCASTS_SERVER: CachedUrl =
+    CachedUrl::new("https://api.sandbox.push.apple.com./4/broadcasts/apps/");
+
+/// A URL that is parsed when first used.
+///
+/// Every caller gets the same `&'static Url`, and only the first one waits
+/// for parsing.
+struct CachedUrl {
+    url: &'static str,
+    parsed: OnceCell<Url>,
+}
+
+impl CachedUrl {
+    const fn new(url: &'static str) -> Self {
+        Self {
+            url,
+            parsed: OnceCell::new(),
+        }
+    }
+
+    fn get(&'static self) -> &'static Url {
+        self.parsed.get_or_init(|| Url::parse(self.url).unwrap())
+    }
+}
 
 /// Apple Push Notification service endpoint.
-#[derive(Clone, PartialEq, Eq, Hash)]
+///
+/// The named variants are canonical: parsing or deserializing the production
+/// or development URL, with or without the trailing dot of the host, yields
+/// [`Endpoint::Production`] or [`Endpoint::Development`], so endpoints
+/// round-trip through [`Serialize`] and [`Deserialize`]. Equality compares
+/// variants, so an `Endpoint::Custom` constructed directly with the
+/// production URL is not equal to `Endpoint::Production`.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
 pub enum Endpoint {
+    #[default]
     Production,
     Development,
     Custom(Url),
@@ -21,11 +66,114 @@ pub enum Endpoint {
 impl Endpoint {
     pub fn as_url(&self) -> &Url {
         match self {
-            Self::Production => &PRODUCTION_SERVER,
-            Self::Development => &DEVELOPMENT_SERVER,
+            Self::Production => PRODUCTION_SERVER.get(),
+            Self::Development => DEVELOPMENT_SERVER.get(),
             Self::Custom(url) => url,
         }
     }
+
+    /// Returns the broadcast channel management URL for this environment.
+    ///
+    /// For [`Endpoint::Custom`], this is `/1/apps/` on the same host.
+    pub fn channels_url(&self) -> std::result::Result<Url, url::ParseError> {
+        match self {
+            Self::Production => Ok(PRODUCTION_CHANNELS_SERVER.get().clone()),
+            Self::Development => Ok(DEVELOPMENT_CHANNELS_SERVER.get().clone()),
+            Self::Custom(url) => url.join("/1/apps/"),
+        }
+    }
+
+    /// Returns the Live Activity broadcast URL for this environment.
+    ///
+    /// For [`Endpoint::Custom`], this is `/4/broadcasts/apps/` on the same
+    /// host.
+    pub fn broadcasts_url(&self) -> std::result::Result<Url, url::ParseError> {
+        match self {
+            Self::Production => Ok(PRODUCTION_BROADCASTS_SERVER.get().clone()),
+            Self::Development => Ok(DEVELOPMENT_BROADCASTS_SERVER.get().clone()),
+            Self::Custom(url) => url.join("/4/broadcasts/apps/"),
+        }
+    }
+}
+
+/// The APNs environment a client is expected to send to, see
+/// [`ClientBuilder::environment_hint`](crate::ClientBuilder::environment_hint).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvironmentHint {
+    /// The development environment, [`Endpoint::Development`].
+    Sandbox,
+
+    /// The production environment, [`Endpoint::Production`].
+    Production,
+}
+
+derive_fromstr_from_deserialize!(EnvironmentHint);
+derive_display_from_serialize!(EnvironmentHint);
+
+impl Endpoint {
+    /// Returns whether the endpoint belongs to the `hint` environment. A
+    /// custom endpoint, e.g. a proxy, may belong to either.
+    pub fn matches(&self, hint: EnvironmentHint) -> bool {
+        match self {
+            Self::Production => hint == EnvironmentHint::Production,
+            Self::Development => hint == EnvironmentHint::Sandbox,
+            Self::Custom(_) => true,
+        }
+    }
+}
+
+/// Returns the URL that [`Client::post`](crate::Client::post) sends a push
+/// notification for `device_token` to, without building a client.
+///
+/// The device token is normalized like [`DeviceToken::parse`] does, and
+/// [`Error::InvalidDeviceToken`](crate::Error::InvalidDeviceToken) is returned
+/// if it is empty or contains whitespace other than the group spaces of an
+/// Xcode token.
+///
+/// [`DeviceToken::parse`]: crate::DeviceToken::parse The token is appended as a path
+/// segment to the endpoint URL, whether or not a custom URL ends with a
+/// slash.
+pub fn device_url(endpoint: &Endpoint, device_token: &str) -> Result<Url> {
+    join_device_token(endpoint.as_url(), device_token)
+}
+
+/// Returns the URL that [`Client::post_broadcast`](crate::Client::post_broadcast)
+/// sends a Live Activity broadcast for `bundle_id` to, without building a
+/// client.
+///
+/// The bundle ID is trimmed, and
+/// [`Error::InvalidRequest`](crate::Error::InvalidRequest) is returned if it
+/// is empty or contains whitespace.
+pub fn broadcast_url(endpoint: &Endpoint, bundle_id: &str) -> Result<Url> {
+    join_bundle_id(&endpoint.broadcasts_url()?, bundle_id)
+}
+
+/// Appends the trimmed `bundle_id` to `base_url` as a path segment.
+pub(crate) fn join_bundle_id(base_url: &Url, bundle_id: &str) -> Result<Url> {
+    let bundle_id = bundle_id.trim();
+    if bundle_id.is_empty() || bundle_id.contains(char::is_whitespace) {
+        return Err(Error::InvalidRequest(format!(
+            "invalid bundle ID: {bundle_id:?}"
+        )));
+    }
+    let mut url = base_url.clone();
+    url.path_segments_mut()
+        .map_err(|()| url::ParseError::RelativeUrlWithCannotBeABaseBase)?
+        .pop_if_empty()
+        .push(bundle_id);
+    Ok(url)
+}
+
+/// Appends the normalized `device_token` to `base_url` as a path segment.
+pub(crate) fn join_device_token(base_url: &Url, device_token: &str) -> Result<Url> {
+    let device_token = normalize_device_token(device_token)?;
+    let mut url = base_url.clone();
+    url.path_segments_mut()
+        .map_err(|()| url::ParseError::RelativeUrlWithCannotBeABaseBase)?
+        .pop_if_empty()
+        .push(&device_token);
+    Ok(url)
 }
 
 impl Debug for Endpoint {
@@ -38,28 +186,55 @@ impl Debug for Endpoint {
     }
 }
 
-impl Default for Endpoint {
-    fn default() -> Self {
-        Self::Production
-    }
-}
-
 impl FromStr for Endpoint {
     type Err = url::ParseError;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         if s.eq_ignore_ascii_case("prod") || s.eq_ignore_ascii_case("production") {
             Ok(Self::Production)
         } else if s.eq_ignore_ascii_case("dev") || s.eq_ignore_ascii_case("development") {
             Ok(Self::Development)
         } else {
-            Url::parse(s).map(Self::Custom)
+            Url::parse(s).map(Self::from)
         }
     }
 }
 
+impl From<Url> for Endpoint {
+    /// Maps the production and development URLs to the named variants, and
+    /// any other URL to [`Endpoint::Custom`].
+    fn from(url: Url) -> Self {
+        if is_server_url(&url, PRODUCTION_SERVER.get()) {
+            Self::Production
+        } else if is_server_url(&url, DEVELOPMENT_SERVER.get()) {
+            Self::Development
+        } else {
+            Self::Custom(url)
+        }
+    }
+}
+
+/// Returns whether `url` is `server`, ignoring the trailing dot of the host
+/// and the trailing slash of the path.
+fn is_server_url(url: &Url, server: &Url) -> bool {
+    let host = |url: &Url| {
+        url.host_str()
+            .map(|host| host.trim_end_matches('.').to_owned())
+    };
+    let path = |url: &Url| url.path().trim_end_matches('/').to_owned();
+
+    url.scheme() == server.scheme()
+        && url.username().is_empty()
+        && url.password().is_none()
+        && host(url) == host(server)
+        && url.port_or_known_default() == server.port_or_known_default()
+        && path(url) == path(server)
+        && url.query().is_none()
+        && url.fragment().is_none()
+}
+
 impl<'de> Deserialize<'de> for Endpoint {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
@@ -72,10 +247,267 @@ impl<'de> Deserialize<'de> for Endpoint {
 }
 
 impl Serialize for Endpoint {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
         serializer.serialize_str(self.as_url().as_str())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn endpoint() -> impl Strategy<Value = Endpoint> {
+        prop_oneof![
+            Just(Endpoint::Production),
+            Just(Endpoint::Development),
+            (
+                prop_oneof![Just("http"), Just("https")],
+                "[a-z][a-z0-9-]{0,15}(\\.[a-z]{2,8}){0,2}\\.?",
+                proptest::option::of(1u16..),
+                "(/[a-z0-9]{1,8}){0,3}/?",
+            )
+                .prop_map(|(scheme, host, port, path)| {
+                    let port = port.map(|port| format!(":{port}")).unwrap_or_default();
+                    format!("{scheme}://{host}{port}{path}").parse().unwrap()
+                }),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(endpoint in endpoint()) {
+            let json = serde_json::to_string(&endpoint).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Endpoint>(&json).unwrap(), endpoint.clone());
+            prop_assert_eq!(endpoint.as_url().as_str().parse::<Endpoint>().unwrap(), endpoint);
+        }
+    }
+
+    #[test]
+    fn from_str_server_urls() {
+        for (url, expected) in [
+            (
+                "https://api.push.apple.com./3/device/",
+                Some(Endpoint::Production),
+            ),
+            (
+                "https://api.push.apple.com/3/device/",
+                Some(Endpoint::Production),
+            ),
+            (
+                "https://api.push.apple.com:443/3/device",
+                Some(Endpoint::Production),
+            ),
+            (
+                "HTTPS://API.PUSH.APPLE.COM/3/device/",
+                Some(Endpoint::Production),
+            ),
+            (
+                "https://api.sandbox.push.apple.com./3/device/",
+                Some(Endpoint::Development),
+            ),
+            (
+                "https://api.sandbox.push.apple.com/3/device",
+                Some(Endpoint::Development),
+            ),
+            ("http://api.push.apple.com/3/device/", None),
+            ("https://api.push.apple.com:2197/3/device/", None),
+            ("https://api.push.apple.com/3/device/?debug", None),
+            ("https://api.push.apple.com/", None),
+            ("https://api.development.push.apple.com/3/device/", None),
+        ] {
+            let endpoint: Endpoint = url.parse().unwrap();
+            let expected = expected.unwrap_or_else(|| Endpoint::Custom(url.parse().unwrap()));
+            assert_eq!(endpoint, expected, "{url}");
+        }
+
+        // Only the named variants are equal to each other.
+        assert_ne!(
+            Endpoint::Custom(Endpoint::Production.as_url().clone()),
+            Endpoint::Production
+        );
+    }
+
+    #[test]
+    fn as_url() {
+        assert_eq!(
+            Endpoint::Production.as_url().as_str(),
+            "https://api.push.apple.com./3/device/"
+        );
+        assert_eq!(
+            Endpoint::Development.as_url().as_str(),
+            "https://api.sandbox.push.apple.com./3/device/"
+        );
+        assert_eq!(
+            Endpoint::Production.channels_url().unwrap().as_str(),
+            "https://api-manage-broadcast.push.apple.com.:2196/1/apps/"
+        );
+        assert_eq!(
+            Endpoint::Development.channels_url().unwrap().as_str(),
+            "https://api-manage-broadcast.sandbox.push.apple.com.:2195/1/apps/"
+        );
+        assert_eq!(
+            Endpoint::Production.broadcasts_url().unwrap().as_str(),
+            "https://api.push.apple.com./4/broadcasts/apps/"
+        );
+        assert_eq!(
+            Endpoint::Development.broadcasts_url().unwrap().as_str(),
+            "https://api.sandbox.push.apple.com./4/broadcasts/apps/"
+        );
+    }
+
+    #[test]
+    fn broadcast_url() {
+        const BUNDLE_ID: &str = "com.example.myapp";
+
+        for (endpoint, expected) in [
+            (
+                Endpoint::Production,
+                format!("https://api.push.apple.com./4/broadcasts/apps/{BUNDLE_ID}"),
+            ),
+            (
+                Endpoint::Development,
+                format!("https://api.sandbox.push.apple.com./4/broadcasts/apps/{BUNDLE_ID}"),
+            ),
+            (
+                "http://localhost:8080/3/device/".parse().unwrap(),
+                format!("http://localhost:8080/4/broadcasts/apps/{BUNDLE_ID}"),
+            ),
+        ] {
+            for bundle_id in [BUNDLE_ID.to_string(), format!(" {BUNDLE_ID}\n")] {
+                assert_eq!(
+                    super::broadcast_url(&endpoint, &bundle_id)
+                        .unwrap()
+                        .as_str(),
+                    expected,
+                    "{endpoint:?} {bundle_id:?}"
+                );
+            }
+        }
+
+        assert_eq!(
+            super::broadcast_url(&Endpoint::Production, "../../3/device")
+                .unwrap()
+                .as_str(),
+            "https://api.push.apple.com./4/broadcasts/apps/..%2F..%2F3%2Fdevice"
+        );
+
+        for bundle_id in ["", " ", "com.example myapp"] {
+            assert!(matches!(
+                super::broadcast_url(&Endpoint::Production, bundle_id),
+                Err(crate::Error::InvalidRequest(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn device_url() {
+        const DEVICE_TOKEN: &str =
+            "a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae";
+
+        for (endpoint, expected) in [
+            (
+                Endpoint::Production,
+                format!("https://api.push.apple.com./3/device/{DEVICE_TOKEN}"),
+            ),
+            (
+                Endpoint::Development,
+                format!("https://api.sandbox.push.apple.com./3/device/{DEVICE_TOKEN}"),
+            ),
+            (
+                "http://localhost:8080/3/device/".parse().unwrap(),
+                format!("http://localhost:8080/3/device/{DEVICE_TOKEN}"),
+            ),
+            (
+                "http://localhost:8080/3/device".parse().unwrap(),
+                format!("http://localhost:8080/3/device/{DEVICE_TOKEN}"),
+            ),
+            (
+                "http://localhost:8080".parse().unwrap(),
+                format!("http://localhost:8080/{DEVICE_TOKEN}"),
+            ),
+        ] {
+            for device_token in [
+                DEVICE_TOKEN.to_string(),
+                DEVICE_TOKEN.to_uppercase(),
+                format!(" {DEVICE_TOKEN}\n"),
+                format!(
+                    "<{}>",
+                    DEVICE_TOKEN
+                        .as_bytes()
+                        .chunks(8)
+                        .map(|chunk| std::str::from_utf8(chunk).unwrap())
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                ),
+            ] {
+                assert_eq!(
+                    super::device_url(&endpoint, &device_token)
+                        .unwrap()
+                        .as_str(),
+                    expected,
+                    "{endpoint:?} {device_token:?}"
+                );
+            }
+        }
+
+        // Path separators can't escape the device path.
+        assert_eq!(
+            super::device_url(&Endpoint::Production, "../1/apps")
+                .unwrap()
+                .as_str(),
+            "https://api.push.apple.com./3/device/..%2F1%2Fapps"
+        );
+
+        for device_token in ["", " ", "a863a50a 904a4bb7", "<a863a50a\t904a4bb7>"] {
+            assert!(matches!(
+                super::device_url(&Endpoint::Production, device_token),
+                Err(crate::Error::InvalidDeviceToken)
+            ));
+        }
+    }
+
+    #[test]
+    fn matches_environment() {
+        let custom: Endpoint = "http://localhost/3/device/".parse().unwrap();
+        for (endpoint, hint, matches) in [
+            (&Endpoint::Production, EnvironmentHint::Production, true),
+            (&Endpoint::Production, EnvironmentHint::Sandbox, false),
+            (&Endpoint::Development, EnvironmentHint::Production, false),
+            (&Endpoint::Development, EnvironmentHint::Sandbox, true),
+            (&custom, EnvironmentHint::Production, true),
+            (&custom, EnvironmentHint::Sandbox, true),
+        ] {
+            assert_eq!(endpoint.matches(hint), matches, "{endpoint:?} {hint}");
+        }
+
+        assert_eq!(
+            "sandbox".parse::<EnvironmentHint>().unwrap(),
+            EnvironmentHint::Sandbox
+        );
+        assert_eq!(EnvironmentHint::Production.to_string(), "production");
+    }
+
+    #[test]
+    fn as_url_stable() {
+        let as_urls = || {
+            (
+                Endpoint::Production.as_url() as *const Url as usize,
+                Endpoint::Development.as_url() as *const Url as usize,
+            )
+        };
+
+        let threads = (0..8)
+            .map(|_| std::thread::spawn(as_urls))
+            .collect::<Vec<_>>();
+        let expected = as_urls();
+        assert_eq!(as_urls(), expected);
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), expected);
+        }
+    }
+}
@@ -1,7 +1,14 @@
+use std::time::Duration;
+
 use http::header::{HeaderName, HeaderValue};
-use serde::{Deserialize, Serialize};
+use serde::ser::SerializeMap;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use serde_plain::{derive_display_from_serialize, derive_fromstr_from_deserialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::result::{Error, Result};
 
 /// Payload size limit for message types other than VoIP.
 pub const PAYLOAD_SIZE_LIMIT: usize = 4096;
@@ -9,6 +16,9 @@ pub const PAYLOAD_SIZE_LIMIT: usize = 4096;
 /// Payload size limit for the VoIP message type.
 pub const VOIP_PAYLOAD_SIZE_LIMIT: usize = 5120;
 
+/// Size limit of the `apns-collapse-id` header.
+pub const COLLAPSE_ID_LIMIT: usize = 64;
+
 /// (Required for watchOS 6 and later; recommended for macOS, iOS, tvOS, and
 /// iPadOS) The value of this header must accurately reflect the contents of
 /// your notification’s payload. If there’s a mismatch, or if the header is
@@ -70,6 +80,50 @@ pub static APNS_TOPIC: HeaderName = HeaderName::from_static("apns-topic");
 /// the requests. The value of this key must not exceed 64 bytes.
 pub static APNS_COLLAPSE_ID: HeaderName = HeaderName::from_static("apns-collapse-id");
 
+/// The base64-encoded ID of a broadcast channel. APNs returns this header when
+/// creating a channel, and channel management requests use it to identify the
+/// channel.
+pub static APNS_CHANNEL_ID: HeaderName = HeaderName::from_static("apns-channel-id");
+
+/// Returns [`APNS_PUSH_TYPE`].
+///
+/// The header name statics rely on `HeaderName::from_static` being a `const
+/// fn`. These accessors give dependent crates a stable way to refer to the
+/// header names without defining their own.
+pub fn apns_push_type() -> &'static HeaderName {
+    &APNS_PUSH_TYPE
+}
+
+/// Returns [`APNS_ID`].
+pub fn apns_id() -> &'static HeaderName {
+    &APNS_ID
+}
+
+/// Returns [`APNS_EXPIRATION`].
+pub fn apns_expiration() -> &'static HeaderName {
+    &APNS_EXPIRATION
+}
+
+/// Returns [`APNS_PRIORITY`].
+pub fn apns_priority() -> &'static HeaderName {
+    &APNS_PRIORITY
+}
+
+/// Returns [`APNS_TOPIC`].
+pub fn apns_topic() -> &'static HeaderName {
+    &APNS_TOPIC
+}
+
+/// Returns [`APNS_COLLAPSE_ID`].
+pub fn apns_collapse_id() -> &'static HeaderName {
+    &APNS_COLLAPSE_ID
+}
+
+/// Returns [`APNS_CHANNEL_ID`].
+pub fn apns_channel_id() -> &'static HeaderName {
+    &APNS_CHANNEL_ID
+}
+
 /// Use the `alert` push type for notifications that trigger a user
 /// interaction—for example, an alert, badge, or sound. If you set this push
 /// type, the `apns-topic` header field must use your app’s bundle ID as the
@@ -156,6 +210,36 @@ pub static FILEPROVIDER: HeaderValue = HeaderValue::from_static("fileprovider");
 /// iOS, tvOS, and iPadOS.
 pub static MDM: HeaderValue = HeaderValue::from_static("mdm");
 
+/// Use the `liveactivity` push type for notifications that start, update, or
+/// end a Live Activity. If you set this push type, the `apns-topic` header
+/// field must use your app’s bundle ID with `.push-type.liveactivity` appended
+/// to the end. For more information, see [Starting and updating Live
+/// Activities with ActivityKit push
+/// notifications](https://developer.apple.com/documentation/activitykit/starting-and-updating-live-activities-with-activitykit-push-notifications).
+///
+/// The `liveactivity` push type is available on iOS 16.1 and iPadOS 17 and
+/// later. It supports only token-based authentication.
+pub static LIVEACTIVITY: HeaderValue = HeaderValue::from_static("liveactivity");
+
+/// Use the `pushtotalk` push type for notifications that provide information
+/// about an incoming Push to Talk (Ptt). If you set this push type, the
+/// `apns-topic` header field must use your app’s bundle ID with `.voip-ptt`
+/// appended to the end. For more information, see [Push to
+/// Talk](https://developer.apple.com/documentation/pushtotalk).
+///
+/// The `pushtotalk` push type is available on iOS 16 and later. It isn’t
+/// available on macOS, tvOS, and watchOS.
+pub static PUSHTOTALK: HeaderValue = HeaderValue::from_static("pushtotalk");
+
+/// Use the `widgets` push type for notifications that reload your app’s
+/// WidgetKit widgets. If you set this push type, the `apns-topic` header field
+/// must use your app’s bundle ID with `.push-type.widgets` appended to the end.
+/// Always use priority 5. Using priority 10 is an error.
+///
+/// The `widgets` push type is available on iOS 17, iPadOS 17, and macOS 14
+/// and later.
+pub static WIDGETS: HeaderValue = HeaderValue::from_static("widgets");
+
 /// Send the notification immediately.
 pub static PRIORITY_IMMEDIATE: HeaderValue = HeaderValue::from_static("10");
 
@@ -168,7 +252,8 @@ pub static PRIORITY_PRIORITIZE_POWER: HeaderValue = HeaderValue::from_static("1"
 
 /// The `apns-push-type` header field has the following valid values. The
 /// descriptions below describe when and how to use these values.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "kebab-case")]
 pub enum PushType {
     /// Use the `alert` push type for notifications that trigger a user
@@ -182,6 +267,7 @@ pub enum PushType {
     ///
     /// The `alert` push type is required on watchOS 6 and later. It is
     /// recommended on macOS, iOS, tvOS, and iPadOS.
+    #[default]
     Alert,
 
     /// Use the `background` push type for notifications that deliver content in
@@ -258,12 +344,36 @@ pub enum PushType {
     /// The mdm push type is not available on watchOS. It is recommended on
     /// macOS, iOS, tvOS, and iPadOS.
     Mdm,
-}
 
-impl Default for PushType {
-    fn default() -> Self {
-        Self::Alert
-    }
+    /// Use the `liveactivity` push type for notifications that start, update,
+    /// or end a Live Activity. If you set this push type, the `apns-topic`
+    /// header field must use your app’s bundle ID with
+    /// `.push-type.liveactivity` appended to the end. For more information, see
+    /// [Starting and updating Live Activities with ActivityKit push
+    /// notifications](https://developer.apple.com/documentation/activitykit/starting-and-updating-live-activities-with-activitykit-push-notifications).
+    ///
+    /// The `liveactivity` push type is available on iOS 16.1 and iPadOS 17 and
+    /// later. It supports only token-based authentication.
+    Liveactivity,
+
+    /// Use the `pushtotalk` push type for notifications that provide
+    /// information about an incoming Push to Talk (Ptt). If you set this push
+    /// type, the `apns-topic` header field must use your app’s bundle ID with
+    /// `.voip-ptt` appended to the end. For more information, see [Push to
+    /// Talk](https://developer.apple.com/documentation/pushtotalk).
+    ///
+    /// The `pushtotalk` push type is available on iOS 16 and later. It isn’t
+    /// available on macOS, tvOS, and watchOS.
+    Pushtotalk,
+
+    /// Use the `widgets` push type for notifications that reload your app’s
+    /// WidgetKit widgets. If you set this push type, the `apns-topic` header
+    /// field must use your app’s bundle ID with `.push-type.widgets` appended
+    /// to the end. Always use priority 5. Using priority 10 is an error.
+    ///
+    /// The `widgets` push type is available on iOS 17, iPadOS 17, and macOS 14
+    /// and later.
+    Widgets,
 }
 
 impl From<PushType> for HeaderValue {
@@ -276,6 +386,9 @@ impl From<PushType> for HeaderValue {
             PushType::Complication => COMPLICATION.clone(),
             PushType::Fileprovider => FILEPROVIDER.clone(),
             PushType::Mdm => MDM.clone(),
+            PushType::Liveactivity => LIVEACTIVITY.clone(),
+            PushType::Pushtotalk => PUSHTOTALK.clone(),
+            PushType::Widgets => WIDGETS.clone(),
         }
     }
 }
@@ -284,6 +397,38 @@ derive_fromstr_from_deserialize!(PushType);
 derive_display_from_serialize!(PushType);
 
 impl PushType {
+    /// Returns the suffix that must be appended to the app’s bundle ID to form
+    /// the `apns-topic` for this push type, if any.
+    pub fn topic_suffix(&self) -> Option<&'static str> {
+        match self {
+            PushType::Location => Some(".location-query"),
+            PushType::Voip => Some(".voip"),
+            PushType::Complication => Some(".complication"),
+            PushType::Fileprovider => Some(".pushkit.fileprovider"),
+            PushType::Liveactivity => Some(".push-type.liveactivity"),
+            PushType::Pushtotalk => Some(".voip-ptt"),
+            PushType::Widgets => Some(".push-type.widgets"),
+            PushType::Alert | PushType::Background | PushType::Mdm => None,
+        }
+    }
+
+    /// Returns the priority to send this push type with when the request
+    /// doesn't set one: [`Priority::ConsiderPower`] for background, file
+    /// provider and widgets pushes, as Apple requires or recommends, and
+    /// [`Priority::Immediate`], the APNs default, otherwise.
+    pub fn default_priority(&self) -> Priority {
+        match self {
+            PushType::Background | PushType::Fileprovider | PushType::Widgets => {
+                Priority::ConsiderPower
+            }
+            _ => Priority::Immediate,
+        }
+    }
+
+    /// Returns the payload size limit for this push type.
+    ///
+    /// Push to Talk notifications have the same limit as other non-VoIP push
+    /// types.
     pub fn payload_size_limit(&self) -> usize {
         if *self == PushType::Voip {
             VOIP_PAYLOAD_SIZE_LIMIT
@@ -294,10 +439,11 @@ impl PushType {
 }
 
 /// Push notification priority.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize_repr, Serialize_repr)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize_repr, Serialize_repr)]
 #[repr(u8)]
 pub enum Priority {
     /// Send the notification immediately.
+    #[default]
     Immediate = 10,
 
     /// Send the notification based on power considerations on the user’s device
@@ -308,12 +454,6 @@ pub enum Priority {
     PrioritizePower = 1,
 }
 
-impl Default for Priority {
-    fn default() -> Self {
-        Self::Immediate
-    }
-}
-
 impl From<Priority> for HeaderValue {
     fn from(this: Priority) -> Self {
         match this {
@@ -326,3 +466,295 @@ impl From<Priority> for HeaderValue {
 
 derive_fromstr_from_deserialize!(Priority);
 derive_display_from_serialize!(Priority);
+
+/// When APNs should stop trying to deliver the notification.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Expiration {
+    /// Omit the `apns-expiration` header.
+    #[default]
+    Default,
+
+    /// Attempt delivery only once and don’t store the notification.
+    Immediate,
+
+    /// Store the notification and retry delivery until the given date.
+    At(OffsetDateTime),
+
+    /// Store the notification and retry delivery for the given duration,
+    /// measured from when the request is sent.
+    In(Duration),
+}
+
+impl Expiration {
+    /// Returns `true` for [`Expiration::Default`].
+    pub fn is_default(&self) -> bool {
+        *self == Self::Default
+    }
+
+    /// Returns the `apns-expiration` value as UNIX epoch seconds, resolving
+    /// [`Expiration::In`] relative to `now`.
+    ///
+    /// Returns [`Error::InvalidRequest`] if `now` plus the duration of
+    /// [`Expiration::In`] is out of range.
+    pub fn unix_timestamp_at(&self, now: OffsetDateTime) -> Result<Option<i64>> {
+        match self {
+            Self::Default => Ok(None),
+            Self::Immediate => Ok(Some(0)),
+            Self::At(at) => Ok(Some(at.unix_timestamp())),
+            Self::In(duration) => time::Duration::try_from(*duration)
+                .ok()
+                .and_then(|duration| now.checked_add(duration))
+                .map(|at| Some(at.unix_timestamp()))
+                .ok_or_else(|| {
+                    Error::InvalidRequest(format!(
+                        "apns-expiration in {duration:?} is out of range"
+                    ))
+                }),
+        }
+    }
+
+    /// Returns how long after `now` APNs stops trying to deliver the
+    /// notification, or `None` for [`Expiration::Default`]. A date in the
+    /// past and [`Expiration::Immediate`] give zero.
+    pub fn remaining_at(&self, now: OffsetDateTime) -> Option<Duration> {
+        match self {
+            Self::Default => None,
+            Self::Immediate => Some(Duration::ZERO),
+            Self::At(at) => Some((*at - now).try_into().unwrap_or_default()),
+            Self::In(duration) => Some(*duration),
+        }
+    }
+
+    /// Returns the `apns-expiration` header value, if any, or
+    /// [`Error::InvalidRequest`] like [`unix_timestamp_at`](Self::unix_timestamp_at).
+    pub fn header_value(&self) -> Result<Option<HeaderValue>> {
+        Ok(self
+            .unix_timestamp_at(OffsetDateTime::now_utc())?
+            .map(HeaderValue::from))
+    }
+}
+
+/// Serializes [`Expiration::Default`] as `null`, [`Expiration::Immediate`]
+/// as `"never-store"`, [`Expiration::At`] as an RFC 3339 date and
+/// [`Expiration::In`] as `{"in": <seconds>}`.
+impl Serialize for Expiration {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Default => serializer.serialize_none(),
+            Self::Immediate => serializer.serialize_str(NEVER_STORE),
+            Self::At(at) => time::serde::rfc3339::serialize(at, serializer),
+            Self::In(duration) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("in", &duration.as_secs_f64())?;
+                map.end()
+            }
+        }
+    }
+}
+
+/// Deserializes the format written by [`Serialize`], also accepting `0` for
+/// [`Expiration::Immediate`].
+impl<'de> Deserialize<'de> for Expiration {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct In {
+            #[serde(rename = "in")]
+            secs: f64,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Zero(u8),
+            Str(String),
+            In(In),
+        }
+
+        match Option::<Repr>::deserialize(deserializer)? {
+            None => Ok(Self::Default),
+            Some(Repr::Zero(0)) => Ok(Self::Immediate),
+            Some(Repr::Zero(n)) => Err(de::Error::invalid_value(
+                de::Unexpected::Unsigned(n.into()),
+                &"0 or an RFC 3339 date",
+            )),
+            Some(Repr::Str(s)) if s == NEVER_STORE => Ok(Self::Immediate),
+            Some(Repr::Str(s)) => OffsetDateTime::parse(&s, &Rfc3339)
+                .map(Self::At)
+                .map_err(de::Error::custom),
+            Some(Repr::In(In { secs })) => Duration::try_from_secs_f64(secs)
+                .map(Self::In)
+                .map_err(de::Error::custom),
+        }
+    }
+}
+
+const NEVER_STORE: &str = "never-store";
+
+impl From<OffsetDateTime> for Expiration {
+    fn from(at: OffsetDateTime) -> Self {
+        Self::At(at)
+    }
+}
+
+impl From<Duration> for Expiration {
+    fn from(duration: Duration) -> Self {
+        Self::In(duration)
+    }
+}
+
+impl From<Option<OffsetDateTime>> for Expiration {
+    fn from(at: Option<OffsetDateTime>) -> Self {
+        at.map(Self::At).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn header_names() {
+        for (name, expected) in [
+            (apns_push_type(), "apns-push-type"),
+            (apns_id(), "apns-id"),
+            (apns_expiration(), "apns-expiration"),
+            (apns_priority(), "apns-priority"),
+            (apns_topic(), "apns-topic"),
+            (apns_collapse_id(), "apns-collapse-id"),
+            (apns_channel_id(), "apns-channel-id"),
+        ] {
+            assert_eq!(name, &HeaderName::from_bytes(expected.as_bytes()).unwrap());
+        }
+    }
+
+    #[test]
+    fn expiration_unix_timestamp() {
+        let now = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        assert_eq!(Expiration::Default.unix_timestamp_at(now).unwrap(), None);
+        assert_eq!(
+            Expiration::Immediate.unix_timestamp_at(now).unwrap(),
+            Some(0)
+        );
+        assert_eq!(
+            Expiration::At(now).unix_timestamp_at(now).unwrap(),
+            Some(1_700_000_000)
+        );
+        assert_eq!(
+            Expiration::In(Duration::from_secs(3600))
+                .unix_timestamp_at(now)
+                .unwrap(),
+            Some(1_700_003_600)
+        );
+
+        for duration in [Duration::MAX, Duration::from_secs(u64::MAX / 2)] {
+            assert!(matches!(
+                Expiration::In(duration).unix_timestamp_at(now),
+                Err(Error::InvalidRequest(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn expiration_remaining() {
+        let now = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        let hour = Duration::from_secs(3600);
+        assert_eq!(Expiration::Default.remaining_at(now), None);
+        assert_eq!(
+            Expiration::Immediate.remaining_at(now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(Expiration::At(now + hour).remaining_at(now), Some(hour));
+        assert_eq!(
+            Expiration::At(now - hour).remaining_at(now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(Expiration::In(hour).remaining_at(now), Some(hour));
+    }
+
+    #[test]
+    fn expiration_serde() {
+        let at = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        for (expiration, json) in [
+            (Expiration::Default, serde_json::json!(null)),
+            (Expiration::Immediate, serde_json::json!("never-store")),
+            (
+                Expiration::At(at),
+                serde_json::json!("2023-11-14T22:13:20Z"),
+            ),
+            (
+                Expiration::In(Duration::from_secs(3600)),
+                serde_json::json!({ "in": 3600. }),
+            ),
+        ] {
+            assert_eq!(serde_json::to_value(expiration).unwrap(), json);
+            assert_eq!(
+                serde_json::from_value::<Expiration>(json).unwrap(),
+                expiration
+            );
+        }
+
+        assert_eq!(
+            serde_json::from_value::<Expiration>(serde_json::json!(0)).unwrap(),
+            Expiration::Immediate
+        );
+        for json in [
+            serde_json::json!(1),
+            serde_json::json!("tomorrow"),
+            serde_json::json!({ "in": -1. }),
+        ] {
+            assert!(serde_json::from_value::<Expiration>(json).is_err());
+        }
+    }
+
+    #[test]
+    fn push_type_liveactivity() {
+        assert_eq!(PushType::Liveactivity.to_string(), "liveactivity");
+        assert_eq!(
+            "liveactivity".parse::<PushType>().unwrap(),
+            PushType::Liveactivity
+        );
+        assert_eq!(HeaderValue::from(PushType::Liveactivity), "liveactivity");
+        assert_eq!(
+            PushType::Liveactivity.topic_suffix(),
+            Some(".push-type.liveactivity")
+        );
+    }
+
+    #[test]
+    fn push_type_serde() {
+        for (push_type, value) in [
+            (PushType::Alert, "alert"),
+            (PushType::Background, "background"),
+            (PushType::Location, "location"),
+            (PushType::Voip, "voip"),
+            (PushType::Complication, "complication"),
+            (PushType::Fileprovider, "fileprovider"),
+            (PushType::Mdm, "mdm"),
+            (PushType::Liveactivity, "liveactivity"),
+            (PushType::Pushtotalk, "pushtotalk"),
+            (PushType::Widgets, "widgets"),
+        ] {
+            assert_eq!(push_type.to_string(), value);
+            assert_eq!(value.parse::<PushType>().unwrap(), push_type);
+            assert_eq!(HeaderValue::from(push_type), value);
+            assert_eq!(
+                serde_json::from_value::<PushType>(serde_json::to_value(push_type).unwrap())
+                    .unwrap(),
+                push_type
+            );
+        }
+        assert_eq!(PushType::Pushtotalk.topic_suffix(), Some(".voip-ptt"));
+        assert_eq!(PushType::Widgets.topic_suffix(), Some(".push-type.widgets"));
+        assert_eq!(
+            PushType::Pushtotalk.payload_size_limit(),
+            PAYLOAD_SIZE_LIMIT
+        );
+    }
+}
@@ -1,21 +1,67 @@
 #![doc = include_str!("../README.md")]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+#[cfg(feature = "a2-compat")]
+mod a2_compat;
+pub mod audit;
+pub mod badge;
+pub mod batch;
+#[cfg(feature = "blocking")]
+#[cfg_attr(docsrs, doc(cfg(feature = "blocking")))]
+pub mod blocking;
+pub mod channels;
 pub mod client;
+#[cfg(feature = "config")]
+#[cfg_attr(docsrs, doc(cfg(feature = "config")))]
+pub mod config;
 pub mod endpoint;
 pub mod header;
+pub mod limits;
+pub mod mdm;
+pub mod metrics;
+mod once;
 pub mod payload;
+pub mod persist;
+pub mod presets;
 pub mod reason;
+pub mod redact;
+#[cfg(feature = "relay")]
+#[cfg_attr(docsrs, doc(cfg(feature = "relay")))]
+pub mod relay;
 pub mod request;
 pub mod result;
+#[cfg(feature = "schemars")]
+mod schema;
+pub mod stats;
+pub mod strings;
 #[cfg(feature = "jwt")]
 #[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
 pub mod token;
 
+pub use audit::{AuditRecord, AuditSink};
+pub use badge::{BadgeCounter, MemoryBadgeCounter};
+pub use batch::{BatchOptions, BatchReport, BatchResult};
+pub use channels::{ChannelConfig, ChannelPushType, MessageStoragePolicy};
 pub use client::*;
+#[cfg(feature = "config")]
+pub use config::ClientConfig;
 pub use endpoint::*;
-pub use header::{Priority, PushType};
-pub use payload::{Alert, InterruptionLevel, Sound};
+pub use header::{Expiration, Priority, PushType};
+pub use mdm::MdmRequest;
+pub use metrics::*;
+pub use payload::{
+    Alert, AlertBuilder, Badge, Event, InterruptionLevel, InterruptionLevelRequirements,
+    ParseWarning, Sound, StaticAlert, UserInfo,
+};
 pub use reason::*;
+pub use redact::{Redaction, ValueRedaction};
 pub use request::*;
 pub use result::*;
+pub use stats::{ClientStats, RecentTarget};
+
+// Dependencies that appear in the public API, so that downstream crates can
+// name the exact versions this crate uses.
+pub use http;
+pub use time;
+pub use url;
+pub use uuid;
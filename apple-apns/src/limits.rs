@@ -0,0 +1,63 @@
+//! Limits that APNs enforces and soft limits that Apple recommends.
+//!
+//! Requests that exceed a hard limit fail [`Request::validate`]; requests that
+//! exceed a recommended limit are only flagged by [`Request::lint`].
+//!
+//! [`Request::validate`]: crate::Request::validate
+//! [`Request::lint`]: crate::Request::lint
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+pub use crate::header::{COLLAPSE_ID_LIMIT, PAYLOAD_SIZE_LIMIT, VOIP_PAYLOAD_SIZE_LIMIT};
+
+/// Recommended maximum length of an alert title, in grapheme clusters, before
+/// Apple Watch truncates it in the short-look interface.
+pub const WATCHOS_TITLE_RECOMMENDED_MAX: usize = 20;
+
+/// Recommended maximum length of an alert subtitle, in grapheme clusters,
+/// before Apple Watch truncates it in the short-look interface.
+pub const WATCHOS_SUBTITLE_RECOMMENDED_MAX: usize = 20;
+
+/// Relevance score below which a notification is likely to be delivered in
+/// the notification summary, so Apple recommends priority 5 over 10.
+pub const LOW_RELEVANCE_SCORE: f64 = 0.25;
+
+/// Default minimum time to expiration of a [`Priority::PrioritizePower`]
+/// notification. The device isn't woken up for such notifications, so one
+/// that expires sooner may never be delivered.
+///
+/// [`Priority::PrioritizePower`]: crate::Priority::PrioritizePower
+pub const PRIORITIZE_POWER_MIN_EXPIRATION: Duration = Duration::from_secs(60 * 60);
+
+/// Soft limits on the size of free-form payload fields, in bytes.
+///
+/// These fields are meant to hold short identifiers, so a long value is most
+/// likely user data stored in the wrong field. [`Request::validate`] reports
+/// fields over their limit, rather than only the payload as a whole being too
+/// large.
+///
+/// [`Request::validate`]: crate::Request::validate
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FieldLimits {
+    /// Limit of [`Request::thread_id`](crate::Request::thread_id).
+    pub thread_id: usize,
+
+    /// Limit of [`Request::category`](crate::Request::category).
+    pub category: usize,
+
+    /// Limit of [`Request::target_content_id`](crate::Request::target_content_id).
+    pub target_content_id: usize,
+}
+
+impl Default for FieldLimits {
+    fn default() -> Self {
+        Self {
+            thread_id: 256,
+            category: 256,
+            target_content_id: 256,
+        }
+    }
+}
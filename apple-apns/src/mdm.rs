@@ -0,0 +1,145 @@
+//! Mobile device management (MDM) pushes.
+//!
+//! MDM pushes tell a managed device to contact its MDM server. Unlike other
+//! push types, the payload has no `aps` dictionary, only the `PushMagic`
+//! string the device sent when it enrolled. See [Sending MDM Commands to a
+//! Device](https://developer.apple.com/documentation/devicemanagement/sending_mdm_commands_to_a_device).
+
+use http::{HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::header::{Expiration, Priority, PushType};
+use crate::request::{DeviceToken, Request, ValidationError};
+use crate::result::{Error, Result};
+
+/// An MDM push, sent with [`Client::post_mdm`](crate::Client::post_mdm).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MdmRequest {
+    /// The device token of the managed device.
+    pub device_token: DeviceToken,
+
+    /// The `PushMagic` string from the device's `TokenUpdate` check-in.
+    pub push_magic: String,
+
+    /// A canonical UUID that is the unique ID for the notification, see
+    /// [`Request::id`].
+    pub id: Option<Uuid>,
+
+    /// When APNs should stop trying to deliver the notification, see
+    /// [`Request::expiration`].
+    pub expiration: Expiration,
+
+    /// The priority of the notification, see [`Request::priority`].
+    pub priority: Option<Priority>,
+
+    /// The topic from the UID attribute in the subject of the MDM push
+    /// certificate.
+    pub topic: Option<String>,
+}
+
+impl MdmRequest {
+    /// Creates an MDM push for a device.
+    pub fn new(device_token: impl Into<String>, push_magic: impl Into<String>) -> Self {
+        Self {
+            device_token: device_token.into().into(),
+            push_magic: push_magic.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Checks that the device token is valid and the push magic is not
+    /// empty.
+    pub fn validate(&self) -> std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+
+        if !self.device_token.is_valid() {
+            errors.push(ValidationError::DeviceToken);
+        }
+
+        if self.push_magic.is_empty() {
+            errors.push(ValidationError::MissingKey {
+                push_type: PushType::Mdm,
+                key: "mdm",
+            });
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// The JSON payload of an MDM push.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MdmPayload {
+    /// The `PushMagic` string of the device.
+    pub mdm: String,
+}
+
+impl TryFrom<MdmRequest> for (HeaderMap<HeaderValue>, MdmPayload) {
+    type Error = Error;
+
+    fn try_from(this: MdmRequest) -> Result<Self> {
+        let headers = Request::<()> {
+            push_type: PushType::Mdm,
+            id: this.id,
+            expiration: this.expiration,
+            priority: this.priority,
+            topic: this.topic,
+            ..Default::default()
+        }
+        .headers()?;
+
+        Ok((
+            headers,
+            MdmPayload {
+                mdm: this.push_magic,
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::header::*;
+
+    const DEVICE_TOKEN: &str = "a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae";
+
+    #[test]
+    fn validate() {
+        MdmRequest::new(DEVICE_TOKEN, "magic").validate().unwrap();
+
+        assert_eq!(
+            MdmRequest::new("", "").validate().unwrap_err(),
+            [
+                ValidationError::DeviceToken,
+                ValidationError::MissingKey {
+                    push_type: PushType::Mdm,
+                    key: "mdm"
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn into_parts() {
+        let request = MdmRequest {
+            priority: Some(Priority::ConsiderPower),
+            topic: Some("com.apple.mgmt.External.example".into()),
+            ..MdmRequest::new(DEVICE_TOKEN, "magic")
+        };
+        let (headers, payload): (HeaderMap, MdmPayload) = request.try_into().unwrap();
+
+        assert_eq!(headers[&APNS_PUSH_TYPE], "mdm");
+        assert_eq!(headers[&APNS_PRIORITY], "5");
+        assert_eq!(headers[&APNS_TOPIC], "com.apple.mgmt.External.example");
+        assert_eq!(
+            serde_json::to_value(payload).unwrap(),
+            serde_json::json!({ "mdm": "magic" })
+        );
+    }
+}
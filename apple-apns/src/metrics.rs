@@ -0,0 +1,165 @@
+use std::fmt::Debug;
+use std::time::Duration;
+
+use http::StatusCode;
+
+use crate::header::PushType;
+use crate::reason::Reason;
+use crate::result::Error;
+
+/// Client metrics hook.
+///
+/// [`Client::post`](crate::Client::post) calls [`Metrics::on_payload`] once
+/// per notification, [`Metrics::on_request`] before sending every HTTP
+/// request and either [`Metrics::on_response`] for every HTTP response it
+/// receives, including error responses, or [`Metrics::on_error`] for every
+/// HTTP request that failed without one. All methods default to doing
+/// nothing.
+pub trait Metrics {
+    /// Called once before a notification is sent to APNs with the exact size
+    /// in bytes of its serialized payload, e.g. to record the payload size
+    /// distribution. A request retried on a new connection is only counted
+    /// once.
+    fn on_payload(&self, push_type: PushType, payload_size: usize) {
+        let _ = (push_type, payload_size);
+    }
+
+    /// Called before an HTTP request is sent to APNs.
+    fn on_request(&self, push_type: PushType) {
+        let _ = push_type;
+    }
+
+    /// Called after an HTTP response is received from APNs. The `reason` is
+    /// set if the response body contained an APNs error reason.
+    fn on_response(&self, status: StatusCode, reason: Option<&Reason>, latency: Duration) {
+        let _ = (status, reason, latency);
+    }
+
+    /// Called after an HTTP request to APNs failed without a response, e.g.
+    /// because the connection failed or the
+    /// [`deadline`](crate::Request::deadline) passed. A request that APNs
+    /// refused or dropped with a GOAWAY before processing it is reported here
+    /// too, before it is sent again with another [`Metrics::on_request`].
+    fn on_error(&self, error: &Error, latency: Duration) {
+        let _ = (error, latency);
+    }
+}
+
+impl Debug for dyn Metrics + Send + Sync {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Metrics")
+    }
+}
+
+/// [`Metrics`] that does nothing.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopMetrics;
+
+impl Metrics for NoopMetrics {}
+
+/// [`Metrics`] that records Prometheus metrics.
+///
+/// Registers the following metrics:
+///
+/// - `apns_requests_total{reason}`: counter of APNs responses by reason, where
+///   `reason` is `Success` for successful responses, `DeadlineExceeded` for
+///   requests cut off at their deadline and `TransportError` for other
+///   requests that failed without a response.
+/// - `apns_request_duration_seconds`: histogram of APNs request latency.
+#[cfg(feature = "metrics-prometheus")]
+#[cfg_attr(docsrs, doc(cfg(feature = "metrics-prometheus")))]
+#[derive(Clone, Debug)]
+pub struct PrometheusMetrics {
+    requests_total: prometheus::IntCounterVec,
+    request_duration_seconds: prometheus::Histogram,
+}
+
+#[cfg(feature = "metrics-prometheus")]
+impl PrometheusMetrics {
+    /// Creates a new [`PrometheusMetrics`] and registers its metrics with
+    /// `registry`.
+    pub fn new(registry: &prometheus::Registry) -> crate::result::Result<Self> {
+        let requests_total = prometheus::IntCounterVec::new(
+            prometheus::Opts::new("apns_requests_total", "Number of APNs responses by reason."),
+            &["reason"],
+        )?;
+        let request_duration_seconds =
+            prometheus::Histogram::with_opts(prometheus::HistogramOpts::new(
+                "apns_request_duration_seconds",
+                "APNs request latency in seconds.",
+            ))?;
+
+        registry.register(Box::new(requests_total.clone()))?;
+        registry.register(Box::new(request_duration_seconds.clone()))?;
+
+        Ok(Self {
+            requests_total,
+            request_duration_seconds,
+        })
+    }
+}
+
+#[cfg(feature = "metrics-prometheus")]
+impl Metrics for PrometheusMetrics {
+    fn on_response(&self, status: StatusCode, reason: Option<&Reason>, latency: Duration) {
+        let reason = match reason {
+            Some(reason) => reason.as_str(),
+            None if status.is_success() => "Success",
+            None => Reason::Unknown.as_str(),
+        };
+        self.requests_total.with_label_values(&[reason]).inc();
+        self.request_duration_seconds.observe(latency.as_secs_f64());
+    }
+
+    fn on_error(&self, error: &Error, latency: Duration) {
+        let reason = match error {
+            Error::DeadlineExceeded => "DeadlineExceeded",
+            _ => "TransportError",
+        };
+        self.requests_total.with_label_values(&[reason]).inc();
+        self.request_duration_seconds.observe(latency.as_secs_f64());
+    }
+}
+
+#[cfg(all(test, feature = "metrics-prometheus"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn prometheus_metrics() {
+        let registry = prometheus::Registry::new();
+        let metrics = PrometheusMetrics::new(&registry).unwrap();
+
+        metrics.on_response(StatusCode::OK, None, Duration::from_millis(10));
+        metrics.on_response(
+            StatusCode::GONE,
+            Some(&Reason::Unregistered { timestamp: None }),
+            Duration::from_millis(20),
+        );
+        metrics.on_error(&Error::DeadlineExceeded, Duration::from_millis(30));
+
+        let families = registry.gather();
+        let requests_total = families
+            .iter()
+            .find(|family| family.get_name() == "apns_requests_total")
+            .unwrap();
+        let mut reasons = requests_total
+            .get_metric()
+            .iter()
+            .map(|metric| metric.get_label()[0].get_value().to_string())
+            .collect::<Vec<_>>();
+        reasons.sort();
+        assert_eq!(reasons, ["DeadlineExceeded", "Success", "Unregistered"]);
+
+        let request_duration_seconds = families
+            .iter()
+            .find(|family| family.get_name() == "apns_request_duration_seconds")
+            .unwrap();
+        assert_eq!(
+            request_duration_seconds.get_metric()[0]
+                .get_histogram()
+                .get_sample_count(),
+            3
+        );
+    }
+}
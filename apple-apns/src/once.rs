@@ -0,0 +1,163 @@
+//! One-time initialization that doesn't lock once initialized.
+
+use std::cell::UnsafeCell;
+use std::fmt::Debug;
+use std::mem::MaybeUninit;
+use std::sync::{Mutex, Once, PoisonError};
+
+/// A cell that is written once, like `std::sync::OnceLock`, which needs a
+/// newer Rust than this crate supports.
+///
+/// Reading an initialized cell is a single atomic load.
+pub(crate) struct OnceCell<T> {
+    once: Once,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// SAFETY: the value is only written by the one `Once::call_once` closure that
+// runs, and only read after the `Once` completed, so it is shared like `&T`.
+unsafe impl<T: Send + Sync> Sync for OnceCell<T> {}
+unsafe impl<T: Send> Send for OnceCell<T> {}
+
+impl<T> OnceCell<T> {
+    pub(crate) const fn new() -> Self {
+        Self {
+            once: Once::new(),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Returns the value, if initialized.
+    pub(crate) fn get(&self) -> Option<&T> {
+        if self.once.is_completed() {
+            // SAFETY: the value was written before the `Once` completed.
+            Some(unsafe { (*self.value.get()).assume_init_ref() })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the value, initializing it with `init` first if needed. Other
+    /// threads calling this meanwhile wait for `init` to finish.
+    pub(crate) fn get_or_init(&self, init: impl FnOnce() -> T) -> &T {
+        self.once.call_once(|| {
+            // SAFETY: only the first `call_once` runs, and nothing reads the
+            // value before it completes.
+            unsafe { (*self.value.get()).write(init()) };
+        });
+        self.get().expect("initialized")
+    }
+}
+
+impl<T> Default for OnceCell<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> Clone for OnceCell<T> {
+    fn clone(&self) -> Self {
+        let clone = Self::new();
+        if let Some(value) = self.get() {
+            clone.get_or_init(|| value.clone());
+        }
+        clone
+    }
+}
+
+impl<T: Debug> Debug for OnceCell<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("OnceCell").field(&self.get()).finish()
+    }
+}
+
+impl<T> Drop for OnceCell<T> {
+    fn drop(&mut self) {
+        if self.once.is_completed() {
+            // SAFETY: the value was written, and is not used after this.
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+/// A value that is computed by its initializer when first used.
+///
+/// The initializer is only locked while it runs, reading an initialized value
+/// doesn't lock.
+pub(crate) struct Lazy<T, F> {
+    cell: OnceCell<T>,
+    init: Mutex<Option<F>>,
+}
+
+impl<T, F> Lazy<T, F>
+where
+    F: FnOnce() -> T,
+{
+    pub(crate) fn new(init: F) -> Self {
+        Self {
+            cell: OnceCell::new(),
+            init: Mutex::new(Some(init)),
+        }
+    }
+
+    /// Returns the value, calling the initializer first if needed.
+    pub(crate) fn force(&self) -> &T {
+        self.cell.get_or_init(|| {
+            let init = self
+                .init
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take()
+                .expect("initializer called once");
+            init()
+        })
+    }
+
+    /// Returns the value, if initialized.
+    #[cfg(test)]
+    pub(crate) fn get(&self) -> Option<&T> {
+        self.cell.get()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn once_cell_drops_value() {
+        let value = Arc::new(());
+        let cell = OnceCell::new();
+        assert!(cell.get().is_none());
+        assert!(Arc::ptr_eq(cell.get_or_init(|| value.clone()), &value));
+        assert!(Arc::ptr_eq(cell.get_or_init(|| unreachable!()), &value));
+
+        let clone = cell.clone();
+        assert_eq!(Arc::strong_count(&value), 3);
+        drop((cell, clone));
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn lazy_initializes_once() {
+        let calls = AtomicUsize::new(0);
+        let lazy = Lazy::new(|| calls.fetch_add(1, Ordering::Relaxed));
+        assert!(lazy.get().is_none());
+
+        let values = std::thread::scope(|scope| {
+            let threads = (0..8)
+                .map(|_| scope.spawn(|| *lazy.force()))
+                .collect::<Vec<_>>();
+            threads
+                .into_iter()
+                .map(|thread| thread.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(values, [0; 8]);
+        assert_eq!(lazy.get(), Some(&0));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+}
@@ -1,15 +1,30 @@
+use std::collections::HashMap;
+
 use serde::{
     de::{self, MapAccess, Visitor},
-    ser::{SerializeMap, SerializeStruct},
-    Deserialize, Serialize,
+    ser::SerializeMap,
+    Deserialize, Serialize, Serializer,
 };
+use serde_json::{Map, Value};
 use serde_plain::{derive_display_from_serialize, derive_fromstr_from_deserialize};
-use serde_with::{serde_as, skip_serializing_none, BoolFromInt};
+use serde_with::{
+    serde_as, skip_serializing_none, BoolFromInt, DeserializeAs, SerializeAs, TimestampSeconds,
+};
+use time::OffsetDateTime;
 
-fn is_false(v: &bool) -> bool {
+use crate::redact::Redaction;
+use crate::request::Lint;
+use crate::result::Error;
+use crate::strings::placeholder_count;
+
+pub(crate) fn is_false(v: &bool) -> bool {
     !v
 }
 
+/// Custom keys without a dedicated type, the default user info of [`Payload`]
+/// and [`Request`](crate::Request).
+pub type UserInfo = Map<String, Value>;
+
 /// Put the JSON payload with the notification’s content into the body of your
 /// request. The JSON payload must not be compressed and is limited to a maximum
 /// size of 4 KB (4096 bytes). For a Voice over Internet Protocol (VoIP)
@@ -17,31 +32,258 @@ fn is_false(v: &bool) -> bool {
 #[serde_as]
 #[skip_serializing_none]
 #[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "kebab-case")]
-pub struct Payload<T = ()>
+pub struct Payload<T = UserInfo>
 where
     T: Serialize,
 {
-    /// Apple-defined keys.
+    /// Apple-defined keys. An `aps` dictionary without keys is omitted, so a
+    /// payload can consist of only the user info, see [`Payload::raw`].
+    #[serde(default, skip_serializing_if = "Aps::is_empty")]
     pub aps: Aps,
 
-    /// Additional data to send.
+    /// Additional data to send. Its keys are flattened next to `aps`, so
+    /// empty user info is omitted.
+    ///
+    /// Deserializing puts every key outside `aps` into the user info. The
+    /// default [`UserInfo`] keeps all of them; a typed `T` drops the keys it
+    /// doesn't declare unless it has a `#[serde(flatten)]` map field for
+    /// them. See [`Payload::split_known`].
     #[serde(flatten)]
     pub user_info: Option<T>,
 }
 
+impl Payload {
+    /// Inserts a custom key, returning its previous value.
+    pub fn insert_user_info(
+        &mut self,
+        key: impl Into<String>,
+        value: impl Into<Value>,
+    ) -> Option<Value> {
+        self.user_info
+            .get_or_insert_with(UserInfo::new)
+            .insert(key.into(), value.into())
+    }
+
+    /// Extracts a typed `U` from the user info, returning it with the keys
+    /// `U` doesn't know, e.g. for a payload captured on a device.
+    ///
+    /// A key is known if `U` serializes it. Returns [`Error::SerdeJson`] if
+    /// the user info doesn't deserialize as `U`.
+    ///
+    /// ```
+    /// # use apple_apns::payload::Payload;
+    /// # use serde::{Deserialize, Serialize};
+    /// #[derive(Deserialize, Serialize)]
+    /// struct Game {
+    ///     game_id: u32,
+    /// }
+    ///
+    /// let payload: Payload = serde_json::from_str(
+    ///     r#"{ "aps": { "alert": "Your turn" }, "game_id": 7, "campaign": "spring" }"#,
+    /// )?;
+    /// let (game, unknown) = payload.split_known::<Game>()?;
+    /// assert_eq!(game.game_id, 7);
+    /// assert_eq!(unknown["campaign"], "spring");
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn split_known<U>(&self) -> crate::result::Result<(U, UserInfo)>
+    where
+        U: de::DeserializeOwned + Serialize,
+    {
+        let mut user_info = self.user_info.clone().unwrap_or_default();
+        let known = U::deserialize(&Value::Object(user_info.clone()))?;
+        if let Value::Object(keys) = serde_json::to_value(&known)? {
+            for key in keys.keys() {
+                user_info.remove(key);
+            }
+        }
+        Ok((known, user_info))
+    }
+
+    /// Parses a payload like [`serde_json::from_slice`], but coerces values
+    /// that legacy senders put in place of the expected type, e.g. to
+    /// analyze logged payloads. Returns a warning for each coercion.
+    ///
+    /// An `alert` that is a number or a boolean, like `"alert": 1`, becomes
+    /// an alert with that value as its body.
+    ///
+    /// ```
+    /// # use apple_apns::payload::Payload;
+    /// # use apple_apns::{Alert, ParseWarning};
+    /// let (payload, warnings) = Payload::from_slice_lenient(br#"{ "aps": { "alert": 1 } }"#)?;
+    /// assert_eq!(payload.aps.alert, Some(Alert::from("1")));
+    /// assert_eq!(warnings, [ParseWarning::AlertCoerced(1.into())]);
+    /// # Ok::<_, apple_apns::Error>(())
+    /// ```
+    pub fn from_slice_lenient(json: &[u8]) -> crate::result::Result<(Self, Vec<ParseWarning>)> {
+        let mut value: Value = serde_json::from_slice(json)?;
+        let mut warnings = Vec::new();
+
+        if let Some(alert) = value.get_mut("aps").and_then(|aps| aps.get_mut("alert")) {
+            if alert.is_number() || alert.is_boolean() {
+                warnings.push(ParseWarning::AlertCoerced(alert.clone()));
+                *alert = Value::String(alert.to_string());
+            }
+        }
+
+        Ok((serde_json::from_value(value)?, warnings))
+    }
+}
+
+/// A value that [`Payload::from_slice_lenient`] coerced to the expected type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseWarning {
+    /// An `alert` that was a number or a boolean, with the original value.
+    AlertCoerced(Value),
+}
+
+impl std::fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AlertCoerced(value) => write!(f, "alert {value} was coerced to a string body"),
+        }
+    }
+}
+
+impl<T> Payload<T>
+where
+    T: Serialize,
+{
+    /// Creates a payload that serializes as `user_info` alone, without an
+    /// `aps` dictionary, as commonly sent with PushKit VoIP notifications.
+    pub fn raw(user_info: T) -> Self {
+        Self {
+            aps: Aps::default(),
+            user_info: Some(user_info),
+        }
+    }
+
+    /// Clones the payload without its [`user_info`](Self::user_info), which
+    /// doesn't need to implement [`Clone`].
+    pub fn clone_without_user_info(&self) -> Self {
+        Self {
+            aps: self.aps.clone(),
+            user_info: None,
+        }
+    }
+
+    /// Serializes the payload to JSON with all object keys, including the
+    /// flattened `user_info` keys, in sorted order.
+    ///
+    /// Unlike [`serde_json::to_vec`], the output is identical for equal
+    /// payloads even when `user_info` is a `HashMap`.
+    pub fn serialize_sorted(&self) -> serde_json::Result<Vec<u8>> {
+        let value = serde_json::to_value(self)?;
+        serde_json::to_vec(&SortedValue(&value))
+    }
+
+    /// Serializes the payload as sent to APNs and redacts it for logging,
+    /// see [`Redaction`].
+    pub fn to_redacted_json(&self, redaction: &Redaction) -> crate::result::Result<Value> {
+        let mut value = serde_json::to_value(self)?;
+        redaction.redact_payload(&mut value);
+        Ok(value)
+    }
+
+    /// Returns the size in bytes of the payload as sent to APNs.
+    pub fn serialized_size(&self) -> crate::result::Result<usize> {
+        Ok(serde_json::to_vec(self)?.len())
+    }
+
+    /// Returns the number of bytes left before the serialized payload reaches
+    /// `limit`.
+    ///
+    /// Returns [`Error::PayloadTooLarge`] if the payload is already larger
+    /// than `limit`.
+    pub fn remaining_budget(&self, limit: usize) -> crate::result::Result<usize> {
+        let size = self.serialized_size()?;
+        limit
+            .checked_sub(size)
+            .ok_or(Error::PayloadTooLarge { size, limit })
+    }
+
+    /// Returns [`Error::InvalidRequest`] if the `aps` dictionary, its alert
+    /// or its sound has keys this crate doesn't know, for callers that want
+    /// to reject them like `#[serde(deny_unknown_fields)]` instead of
+    /// passing them on.
+    pub fn deny_unknown_fields(&self) -> crate::result::Result<()> {
+        let aps = self.aps.extra.keys().map(|key| format!("aps.{key}"));
+        let alert = self
+            .aps
+            .alert
+            .iter()
+            .flat_map(|alert| alert.extra.iter().flatten())
+            .map(|(key, _)| format!("aps.alert.{key}"));
+        let sound = self
+            .aps
+            .sound
+            .iter()
+            .flat_map(|sound| sound.extra.iter().flatten())
+            .map(|(key, _)| format!("aps.sound.{key}"));
+
+        let unknown = aps.chain(alert).chain(sound).collect::<Vec<_>>();
+        if unknown.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidRequest(format!(
+                "unknown keys {}",
+                unknown.join(", ")
+            )))
+        }
+    }
+}
+
+/// Pretty-prints the payload redacted with the default [`Redaction`].
+impl<T> std::fmt::Display for Payload<T>
+where
+    T: Serialize,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = self
+            .to_redacted_json(&Redaction::default())
+            .map_err(|_| std::fmt::Error)?;
+        write!(f, "{value:#}")
+    }
+}
+
+/// Serializes a JSON value with object keys in sorted order.
+struct SortedValue<'a>(&'a Value);
+
+impl Serialize for SortedValue<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.0 {
+            Value::Array(values) => serializer.collect_seq(values.iter().map(SortedValue)),
+            Value::Object(map) => {
+                let mut entries = map.iter().collect::<Vec<_>>();
+                entries.sort_unstable_by_key(|(k, _)| *k);
+                serializer.collect_map(entries.into_iter().map(|(k, v)| (k, SortedValue(v))))
+            }
+            value => value.serialize(serializer),
+        }
+    }
+}
+
 /// Apple-defined keys.
 #[serde_as]
 #[skip_serializing_none]
 #[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "kebab-case")]
 pub struct Aps {
     /// The information for displaying an alert.
     pub alert: Option<Alert>,
 
-    /// The number to display in a badge on your app’s icon. Specify `0` to
-    /// remove the current badge, if any.
-    pub badge: Option<u32>,
+    /// The number to display in a badge on your app’s icon. Specify
+    /// [`Badge::Clear`] to remove the current badge, if any, and `None` to
+    /// leave it as is.
+    #[serde_as(as = "Option<WireBadge>")]
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<u32>"))]
+    pub badge: Option<Badge>,
 
     /// The name of a sound file in your app’s main bundle or in the
     /// `Library/Sounds` folder of your app’s container directory or a
@@ -67,6 +309,7 @@ pub struct Aps {
     /// App](https://developer.apple.com/documentation/usernotifications/setting_up_a_remote_notification_server/pushing_background_updates_to_your_app).
     #[serde(default, skip_serializing_if = "is_false")]
     #[serde_as(as = "BoolFromInt")]
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::BoolInt"))]
     pub content_available: bool,
 
     /// The notification service app extension flag. If the value is `1`, the
@@ -76,6 +319,7 @@ pub struct Aps {
     /// Notifications](https://developer.apple.com/documentation/usernotifications/modifying_content_in_newly_delivered_notifications).
     #[serde(default, skip_serializing_if = "is_false")]
     #[serde_as(as = "BoolFromInt")]
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::BoolInt"))]
     pub mutable_content: bool,
 
     /// The identifier of the window brought forward. The value of this key will
@@ -99,6 +343,219 @@ pub struct Aps {
     /// in the notification summary. See
     /// [`relevanceScore`](https://developer.apple.com/documentation/usernotifications/unnotificationcontent/3821031-relevancescore).
     pub relevance_score: Option<f64>,
+
+    /// (Live Activity) The time at which the Live Activity content was
+    /// updated. The system ignores updates with an older timestamp than the
+    /// latest update it received.
+    #[serde_as(as = "Option<TimestampSeconds<i64>>")]
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(with = "Option<crate::schema::UnixTimestamp>")
+    )]
+    pub timestamp: Option<OffsetDateTime>,
+
+    /// (Live Activity) Whether the notification starts, updates, or ends a
+    /// Live Activity.
+    pub event: Option<Event>,
+
+    /// (Live Activity) The dynamic content of the Live Activity. It must match
+    /// the `ContentState` of the Live Activity’s `ActivityAttributes`.
+    pub content_state: Option<Value>,
+
+    /// (Live Activity) The time at which the system considers the Live
+    /// Activity content outdated.
+    #[serde_as(as = "Option<TimestampSeconds<i64>>")]
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(with = "Option<crate::schema::UnixTimestamp>")
+    )]
+    pub stale_date: Option<OffsetDateTime>,
+
+    /// (Live Activity) The time at which the system removes an ended Live
+    /// Activity from the Lock Screen.
+    #[serde_as(as = "Option<TimestampSeconds<i64>>")]
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(with = "Option<crate::schema::UnixTimestamp>")
+    )]
+    pub dismissal_date: Option<OffsetDateTime>,
+
+    /// (Live Activity start) The name of the Live Activity’s
+    /// `ActivityAttributes` type.
+    pub attributes_type: Option<String>,
+
+    /// (Live Activity start) The static content of the Live Activity. It must
+    /// match the Live Activity’s `ActivityAttributes`.
+    pub attributes: Option<Value>,
+
+    /// (Live Activity start) The flag that requests a push token to update the
+    /// started Live Activity.
+    #[serde(default, skip_serializing_if = "is_false")]
+    #[serde_as(as = "BoolFromInt")]
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::BoolInt"))]
+    pub input_push_token: bool,
+
+    /// (Live Activity broadcast update) A predicate that limits the update to
+    /// the Live Activities that match it.
+    pub filter_criteria: Option<String>,
+
+    /// Keys this crate doesn't know, e.g. ones Apple added after this
+    /// version. They are kept when deserializing and sent as is.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Aps {
+    /// Returns `true` if no Apple-defined key is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// The badge on the app’s icon.
+///
+/// Serializes as a number, with [`Badge::Clear`] as `0`, and
+/// [`Badge::Increment`] as `"increment"`, e.g. in a queued
+/// [`Request`](crate::Request). `0` deserializes as [`Badge::Clear`], and so
+/// does `Badge::from(0)`. The APNs payload, [`Aps::badge`], has only numbers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Badge {
+    /// Remove the current badge, if any.
+    Clear,
+
+    /// Display the given number.
+    Count(u32),
+
+    /// Display one more than the last number sent to the device, as counted
+    /// by the [`ClientBuilder::badge_counter`](crate::ClientBuilder::badge_counter),
+    /// which [`Client::post`](crate::Client::post) replaces it with. An
+    /// [`Aps`] with it fails to serialize, since APNs has no such badge.
+    Increment,
+}
+
+impl Badge {
+    /// Returns the number sent to APNs, `0` for an unresolved
+    /// [`Badge::Increment`].
+    pub fn as_u32(&self) -> u32 {
+        match self {
+            Self::Clear | Self::Increment => 0,
+            Self::Count(count) => *count,
+        }
+    }
+}
+
+impl From<u32> for Badge {
+    fn from(count: u32) -> Self {
+        match count {
+            0 => Self::Clear,
+            count => Self::Count(count),
+        }
+    }
+}
+
+impl From<Badge> for u32 {
+    fn from(badge: Badge) -> Self {
+        badge.as_u32()
+    }
+}
+
+/// Parses `clear`, `increment` or a number.
+impl std::str::FromStr for Badge {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clear" => Ok(Self::Clear),
+            "increment" => Ok(Self::Increment),
+            s => s.parse::<u32>().map(Self::from),
+        }
+    }
+}
+
+impl std::fmt::Display for Badge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Clear => f.write_str("clear"),
+            Self::Count(count) => write!(f, "{count}"),
+            Self::Increment => f.write_str("increment"),
+        }
+    }
+}
+
+impl Serialize for Badge {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Increment => serializer.serialize_str("increment"),
+            badge => serializer.serialize_u32(badge.as_u32()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Badge {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct BadgeVisitor;
+
+        impl<'de> Visitor<'de> for BadgeVisitor {
+            type Value = Badge;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a badge number or \"increment\"")
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                u32::try_from(v)
+                    .map(Badge::from)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &self))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                match v {
+                    "increment" => Ok(Badge::Increment),
+                    v => Err(E::invalid_value(de::Unexpected::Str(v), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(BadgeVisitor)
+    }
+}
+
+/// The badge as sent to APNs: a number, with no [`Badge::Increment`].
+struct WireBadge;
+
+impl SerializeAs<Badge> for WireBadge {
+    fn serialize_as<S>(badge: &Badge, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match badge {
+            Badge::Increment => Err(serde::ser::Error::custom(
+                "badge increment must be resolved by a badge counter",
+            )),
+            badge => serializer.serialize_u32(badge.as_u32()),
+        }
+    }
+}
+
+impl<'de> DeserializeAs<'de, Badge> for WireBadge {
+    fn deserialize_as<D>(deserializer: D) -> Result<Badge, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        u32::deserialize(deserializer).map(Badge::from)
+    }
 }
 
 /// Alert options.
@@ -160,6 +617,10 @@ pub struct Alert {
     /// the array replaces the first instance of the `%@` character in the
     /// string, the second item replaces the second instance, and so on.
     pub loc_args: Option<Vec<String>>,
+
+    /// Keys this crate doesn't know. They are kept when deserializing and
+    /// sent as is.
+    pub extra: Option<Map<String, Value>>,
 }
 
 impl From<String> for Alert {
@@ -180,6 +641,320 @@ impl<'a> From<&'a str> for Alert {
     }
 }
 
+impl From<AlertBuilder> for Alert {
+    fn from(builder: AlertBuilder) -> Self {
+        builder.build()
+    }
+}
+
+/// [`Alert`] builder.
+///
+/// The localized setters set a localization key together with its arguments.
+///
+/// ```rust
+/// use apple_apns::Alert;
+///
+/// let alert = Alert::builder()
+///     .localized_title("GAME_PLAY_REQUEST_FORMAT", ["Jenna", "Frank"])
+///     .body("Shall we play a game?")
+///     .build();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AlertBuilder {
+    alert: Alert,
+}
+
+impl AlertBuilder {
+    /// Sets [`Alert::title`].
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.alert.title = Some(title.into());
+        self
+    }
+
+    /// Sets [`Alert::subtitle`].
+    pub fn subtitle(mut self, subtitle: impl Into<String>) -> Self {
+        self.alert.subtitle = Some(subtitle.into());
+        self
+    }
+
+    /// Sets [`Alert::body`].
+    pub fn body(mut self, body: impl Into<String>) -> Self {
+        self.alert.body = Some(body.into());
+        self
+    }
+
+    /// Sets [`Alert::launch_image`].
+    pub fn launch_image(mut self, launch_image: impl Into<String>) -> Self {
+        self.alert.launch_image = Some(launch_image.into());
+        self
+    }
+
+    /// Sets [`Alert::title_loc_key`] and [`Alert::title_loc_args`], or
+    /// clears the arguments if there are none.
+    pub fn localized_title<I>(mut self, key: impl Into<String>, args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.alert.title_loc_key = Some(key.into());
+        self.alert.title_loc_args = loc_args(args);
+        self
+    }
+
+    /// Sets [`Alert::subtitle_loc_key`] and [`Alert::subtitle_loc_args`], or
+    /// clears the arguments if there are none.
+    pub fn localized_subtitle<I>(mut self, key: impl Into<String>, args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.alert.subtitle_loc_key = Some(key.into());
+        self.alert.subtitle_loc_args = loc_args(args);
+        self
+    }
+
+    /// Sets [`Alert::loc_key`] and [`Alert::loc_args`], or clears the
+    /// arguments if there are none.
+    pub fn localized_body<I>(mut self, key: impl Into<String>, args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.alert.loc_key = Some(key.into());
+        self.alert.loc_args = loc_args(args);
+        self
+    }
+
+    /// Builds the alert.
+    pub fn build(self) -> Alert {
+        self.alert
+    }
+}
+
+/// Collects localization arguments, returning `None` if there are none.
+fn loc_args<I>(args: I) -> Option<Vec<String>>
+where
+    I: IntoIterator,
+    I::Item: Into<String>,
+{
+    let args: Vec<String> = args.into_iter().map(Into::into).collect();
+    if args.is_empty() {
+        None
+    } else {
+        Some(args)
+    }
+}
+
+impl Alert {
+    /// Creates an [`AlertBuilder`].
+    pub fn builder() -> AlertBuilder {
+        AlertBuilder::default()
+    }
+
+    /// Checks the localization keys of the alert against the app's
+    /// `Localizable.strings`, see [`strings::parse`](crate::strings::parse).
+    /// Returns a [`Lint::LocArgs`] for each key whose string takes a different
+    /// number of arguments than given, which garbles the text on the device,
+    /// and a [`Lint::UnknownLocKey`] for each key that isn't in `strings`.
+    pub fn validate_against_strings(&self, strings: &HashMap<String, String>) -> Vec<Lint> {
+        let keys = [
+            ("title-loc-key", &self.title_loc_key, &self.title_loc_args),
+            (
+                "subtitle-loc-key",
+                &self.subtitle_loc_key,
+                &self.subtitle_loc_args,
+            ),
+            ("loc-key", &self.loc_key, &self.loc_args),
+        ];
+
+        let mut lints = Vec::new();
+        for (field, key, args) in keys {
+            let key = match key {
+                Some(key) => key,
+                None => continue,
+            };
+            let args = args.as_ref().map(Vec::len).unwrap_or_default();
+            match strings.get(key) {
+                Some(format) => {
+                    let placeholders = placeholder_count(format);
+                    if placeholders != args {
+                        lints.push(Lint::LocArgs {
+                            field,
+                            key: key.clone(),
+                            placeholders,
+                            args,
+                        });
+                    }
+                }
+                None => lints.push(Lint::UnknownLocKey {
+                    field,
+                    key: key.clone(),
+                }),
+            }
+        }
+        lints
+    }
+
+    /// Creates an alert whose body the device looks up with `key` in the
+    /// app's `Localizable.strings` and formats with `args`.
+    ///
+    /// ```rust
+    /// use apple_apns::Alert;
+    /// use serde_json::json;
+    ///
+    /// let alert = Alert::localized("GAME_PLAY_REQUEST_FORMAT", ["Jenna", "Frank"])
+    ///     .with_localized_title("GAME_PLAY_REQUEST_TITLE", [""; 0]);
+    /// assert_eq!(
+    ///     serde_json::to_value(alert).unwrap(),
+    ///     json!({
+    ///         "title-loc-key": "GAME_PLAY_REQUEST_TITLE",
+    ///         "loc-key": "GAME_PLAY_REQUEST_FORMAT",
+    ///         "loc-args": ["Jenna", "Frank"],
+    ///     })
+    /// );
+    /// ```
+    pub fn localized<I>(key: impl Into<String>, args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Self::builder().localized_body(key, args).build()
+    }
+
+    /// Sets [`Alert::title_loc_key`] and [`Alert::title_loc_args`] like
+    /// [`AlertBuilder::localized_title`].
+    pub fn with_localized_title<I>(self, key: impl Into<String>, args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        AlertBuilder { alert: self }
+            .localized_title(key, args)
+            .build()
+    }
+
+    /// Sets [`Alert::subtitle_loc_key`] and [`Alert::subtitle_loc_args`] like
+    /// [`AlertBuilder::localized_subtitle`].
+    pub fn with_localized_subtitle<I>(self, key: impl Into<String>, args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        AlertBuilder { alert: self }
+            .localized_subtitle(key, args)
+            .build()
+    }
+
+    /// Returns the number of leading characters of the alert body of
+    /// `payload` that fit when the payload is serialized within `limit` bytes.
+    ///
+    /// The size is measured on the serialized payload, so characters that
+    /// JSON escapes (quotes, backslashes and control characters) and
+    /// multi-byte UTF-8 characters count for as many bytes as they take on
+    /// the wire. Truncating the body to the returned number of characters
+    /// yields a payload that fits.
+    ///
+    /// Returns [`Error::InvalidRequest`] if the payload has no alert, and
+    /// [`Error::PayloadTooLarge`] if the payload does not fit even with an
+    /// empty body. An alert without a body is measured with an empty one.
+    pub fn max_body_len_within<T>(
+        payload: &Payload<T>,
+        limit: usize,
+    ) -> crate::result::Result<usize>
+    where
+        T: Serialize,
+    {
+        let alert = payload
+            .aps
+            .alert
+            .as_ref()
+            .ok_or_else(|| Error::InvalidRequest("payload has no alert".into()))?;
+
+        let value = serde_json::to_value(payload)?;
+        max_body_len(value, alert.body.as_deref().unwrap_or_default(), "", limit)
+    }
+
+    /// Truncates the body so that `payload`, with this alert in place of its
+    /// own, serializes within `limit` bytes.
+    ///
+    /// The body is cut on a character boundary and ends with an ellipsis
+    /// (`…`) if it was truncated. Sizes are measured like
+    /// [`Alert::max_body_len_within`], so escaped and multi-byte characters
+    /// count for as many bytes as they take on the wire.
+    ///
+    /// Returns [`Error::PayloadTooLarge`] if the payload does not fit even
+    /// with a body of just the ellipsis.
+    pub fn truncate_body_to_fit<T>(
+        &mut self,
+        payload: &Payload<T>,
+        limit: usize,
+    ) -> crate::result::Result<()>
+    where
+        T: Serialize,
+    {
+        const ELLIPSIS: &str = "\u{2026}";
+
+        let mut value = serde_json::to_value(payload)?;
+        let aps = value
+            .get_mut("aps")
+            .and_then(Value::as_object_mut)
+            .ok_or(Error::Unknown)?;
+        aps.insert("alert".into(), serde_json::to_value(&*self)?);
+        let size = serde_json::to_vec(&value)?.len();
+        let body = match &self.body {
+            Some(body) if size > limit => body,
+            _ if size > limit => return Err(Error::PayloadTooLarge { size, limit }),
+            _ => return Ok(()),
+        };
+
+        let len = max_body_len(value, body, ELLIPSIS, limit)?;
+        let truncated = body.chars().take(len).chain(ELLIPSIS.chars()).collect();
+        self.body = Some(truncated);
+        Ok(())
+    }
+}
+
+/// Returns the largest number of leading characters of `body` that, followed
+/// by `suffix` as the alert body of the serialized payload `value`, fit
+/// within `limit` bytes.
+fn max_body_len(
+    mut value: Value,
+    body: &str,
+    suffix: &str,
+    limit: usize,
+) -> crate::result::Result<usize> {
+    // Serialized size with the body truncated to `len` characters.
+    let mut size_with = |len: usize| -> crate::result::Result<usize> {
+        let end = body.char_indices().nth(len).map_or(body.len(), |(i, _)| i);
+        let body_value = match value.pointer_mut("/aps/alert") {
+            Some(Value::Object(alert)) => Some(alert.entry("body").or_insert(Value::Null)),
+            alert => alert,
+        }
+        .ok_or(Error::Unknown)?;
+        *body_value = Value::String(format!("{}{suffix}", &body[..end]));
+        Ok(serde_json::to_vec(&value)?.len())
+    };
+
+    let size = size_with(0)?;
+    if size > limit {
+        return Err(Error::PayloadTooLarge { size, limit });
+    }
+
+    // Find the longest prefix that fits, the serialized size grows
+    // monotonically with the number of characters.
+    let (mut lo, mut hi) = (0, body.chars().count() + 1);
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        if size_with(mid)? <= limit {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    Ok(lo)
+}
+
 impl<'de> Deserialize<'de> for Alert {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -220,8 +995,8 @@ impl<'de> Deserialize<'de> for Alert {
             {
                 let mut alert = Alert::default();
 
-                while let Some(key) = map.next_key::<&str>()? {
-                    match key {
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
                         "title" => alert.title = map.next_value()?,
                         "title-loc-key" => alert.title_loc_key = map.next_value()?,
                         "title-loc-args" => alert.title_loc_args = map.next_value()?,
@@ -232,22 +1007,9 @@ impl<'de> Deserialize<'de> for Alert {
                         "loc-key" => alert.loc_key = map.next_value()?,
                         "loc-args" => alert.loc_args = map.next_value()?,
                         "launch-image" => alert.launch_image = map.next_value()?,
-                        field => {
-                            return Err(de::Error::unknown_field(
-                                field,
-                                &[
-                                    "title",
-                                    "title-loc-key",
-                                    "title-loc-args",
-                                    "subtitle",
-                                    "subtitle-loc-key",
-                                    "subtitle-loc-args",
-                                    "body",
-                                    "loc-key",
-                                    "loc-args",
-                                    "launch-image",
-                                ],
-                            ));
+                        _ => {
+                            let value = map.next_value()?;
+                            alert.extra.get_or_insert_with(Map::new).insert(key, value);
                         }
                     }
                 }
@@ -274,77 +1036,63 @@ impl Serialize for Alert {
             && self.loc_key.is_none()
             && self.loc_args.is_none()
             && self.launch_image.is_none()
+            && self.extra.is_none()
         {
             return serializer.serialize_str(self.body.as_deref().unwrap_or_default());
         }
 
-        let mut len = 0;
-
-        // title
-        if self.title_loc_key.is_some() {
-            len += 1;
-            if self.title_loc_args.is_some() {
-                len += 1;
-            }
-        } else if self.title.is_some() {
-            len += 1;
-        }
-
-        // subtitle
-        if self.subtitle_loc_key.is_some() {
-            len += 1;
-            if self.subtitle_loc_args.is_some() {
-                len += 1;
-            }
-        } else if self.subtitle.is_some() {
-            len += 1;
-        }
-
-        // body
-        if self.loc_key.is_some() {
-            len += 1;
-            if self.loc_args.is_some() {
-                len += 1;
-            }
-        } else {
-            len += 1;
-        }
-
-        // launch-image
-        if self.launch_image.is_some() {
-            len += 1;
-        }
+        // Every populated field is sent. Apple uses the literal strings as a
+        // fallback when the localization keys are missing from the app.
+        let len = [
+            self.title.is_some(),
+            self.title_loc_key.is_some(),
+            self.title_loc_args.is_some(),
+            self.subtitle.is_some(),
+            self.subtitle_loc_key.is_some(),
+            self.subtitle_loc_args.is_some(),
+            self.body.is_some(),
+            self.loc_key.is_some(),
+            self.loc_args.is_some(),
+            self.launch_image.is_some(),
+        ]
+        .into_iter()
+        .filter(|is_some| *is_some)
+        .count()
+            + self.extra.as_ref().map_or(0, Map::len);
 
         let mut alert = serializer.serialize_map(Some(len))?;
 
         // title
+        if let Some(title) = &self.title {
+            alert.serialize_entry("title", title)?;
+        }
         if let Some(title_loc_key) = &self.title_loc_key {
             alert.serialize_entry("title-loc-key", title_loc_key)?;
-            if let Some(title_loc_args) = &self.title_loc_args {
-                alert.serialize_entry("title-loc-args", title_loc_args)?;
-            }
-        } else if let Some(title) = &self.title {
-            alert.serialize_entry("title", title)?;
+        }
+        if let Some(title_loc_args) = &self.title_loc_args {
+            alert.serialize_entry("title-loc-args", title_loc_args)?;
         }
 
         // subtitle
+        if let Some(subtitle) = &self.subtitle {
+            alert.serialize_entry("subtitle", subtitle)?;
+        }
         if let Some(subtitle_loc_key) = &self.subtitle_loc_key {
             alert.serialize_entry("subtitle-loc-key", subtitle_loc_key)?;
-            if let Some(subtitle_loc_args) = &self.subtitle_loc_args {
-                alert.serialize_entry("subtitle-loc-args", subtitle_loc_args)?;
-            }
-        } else if let Some(subtitle) = &self.subtitle {
-            alert.serialize_entry("subtitle", subtitle)?;
+        }
+        if let Some(subtitle_loc_args) = &self.subtitle_loc_args {
+            alert.serialize_entry("subtitle-loc-args", subtitle_loc_args)?;
         }
 
         // body
+        if let Some(body) = &self.body {
+            alert.serialize_entry("body", body)?;
+        }
         if let Some(loc_key) = &self.loc_key {
             alert.serialize_entry("loc-key", loc_key)?;
-            if let Some(loc_args) = &self.loc_args {
-                alert.serialize_entry("loc-args", loc_args)?;
-            }
-        } else {
-            alert.serialize_entry("body", &self.body)?;
+        }
+        if let Some(loc_args) = &self.loc_args {
+            alert.serialize_entry("loc-args", loc_args)?;
         }
 
         // launch-image
@@ -352,26 +1100,129 @@ impl Serialize for Alert {
             alert.serialize_entry("launch-image", launch_image)?;
         }
 
+        for (key, value) in self.extra.iter().flatten() {
+            alert.serialize_entry(key, value)?;
+        }
+
         alert.end()
     }
 }
 
-/// Sound options.
-#[derive(Clone, Debug, PartialEq)]
-pub struct Sound {
-    /// The critical alert flag. Set to `1` to enable the critical alert.
-    pub critical: bool,
+/// Alert with static strings, for notification templates defined as `static`
+/// or `const` data.
+///
+/// A [`StaticAlert`] serializes like the equivalent [`Alert`] without
+/// allocating, and converts into an [`Alert`] to use in a [`Request`].
+///
+/// ```rust
+/// use apple_apns::payload::StaticAlert;
+/// use apple_apns::Alert;
+///
+/// static WELCOME: StaticAlert = StaticAlert::new("Thanks for signing up.").title("Welcome");
+///
+/// let alert = Alert::from(&WELCOME);
+/// assert_eq!(alert.title.as_deref(), Some("Welcome"));
+/// ```
+///
+/// [`Request`]: crate::Request
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StaticAlert {
+    /// See [`Alert::title`].
+    pub title: Option<&'static str>,
+
+    /// See [`Alert::subtitle`].
+    pub subtitle: Option<&'static str>,
+
+    /// See [`Alert::body`].
+    pub body: &'static str,
+}
 
-    /// The name of a sound file in your app’s main bundle or in the
-    /// `Library/Sounds` folder of your app’s container directory. Specify
-    /// the string `default` to play the system sound. For information about
-    /// how to prepare sounds, see
-    /// [`UNNotificationSound`](https://developer.apple.com/documentation/usernotifications/unnotificationsound).
-    pub name: String,
+impl StaticAlert {
+    /// Creates an alert with the given body.
+    pub const fn new(body: &'static str) -> Self {
+        Self {
+            title: None,
+            subtitle: None,
+            body,
+        }
+    }
+
+    /// Sets the title.
+    pub const fn title(mut self, title: &'static str) -> Self {
+        self.title = Some(title);
+        self
+    }
+
+    /// Sets the subtitle.
+    pub const fn subtitle(mut self, subtitle: &'static str) -> Self {
+        self.subtitle = Some(subtitle);
+        self
+    }
+}
+
+impl From<&StaticAlert> for Alert {
+    fn from(alert: &StaticAlert) -> Self {
+        Alert {
+            title: alert.title.map(Into::into),
+            subtitle: alert.subtitle.map(Into::into),
+            body: Some(alert.body.into()),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<StaticAlert> for Alert {
+    fn from(alert: StaticAlert) -> Self {
+        Alert::from(&alert)
+    }
+}
+
+impl Serialize for StaticAlert {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if self.title.is_none() && self.subtitle.is_none() {
+            return serializer.serialize_str(self.body);
+        }
+
+        let len = 1 + usize::from(self.title.is_some()) + usize::from(self.subtitle.is_some());
+        let mut alert = serializer.serialize_map(Some(len))?;
+        if let Some(title) = self.title {
+            alert.serialize_entry("title", title)?;
+        }
+        if let Some(subtitle) = self.subtitle {
+            alert.serialize_entry("subtitle", subtitle)?;
+        }
+        alert.serialize_entry("body", self.body)?;
+        alert.end()
+    }
+}
+
+/// Sound options.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sound {
+    /// The critical alert flag. Set to `1` to enable the critical alert.
+    pub critical: bool,
+
+    /// The name of a sound file in your app’s main bundle or in the
+    /// `Library/Sounds` folder of your app’s container directory. Specify
+    /// the string `default` to play the system sound. For information about
+    /// how to prepare sounds, see
+    /// [`UNNotificationSound`](https://developer.apple.com/documentation/usernotifications/unnotificationsound).
+    pub name: String,
 
     /// The volume for the critical alert’s sound. Set this to a value
-    /// between `0` (silent) and `1` (full volume).
+    /// between `0` (silent) and `1` (full volume). Values above `1` are sent as
+    /// `1`, and negative values are an error.
+    ///
+    /// The volume only applies to critical alerts. A sound that isn’t critical
+    /// is sent as its name alone and the volume is ignored.
     pub volume: f64,
+
+    /// Keys this crate doesn't know. They are kept when deserializing and
+    /// sent as is, in a dictionary even if the sound isn't critical.
+    pub extra: Option<Map<String, Value>>,
 }
 
 impl Default for Sound {
@@ -380,28 +1231,54 @@ impl Default for Sound {
             critical: false,
             name: "default".into(),
             volume: 1.,
+            extra: None,
         }
     }
 }
 
 impl From<String> for Sound {
     fn from(value: String) -> Self {
-        Self {
-            critical: false,
-            name: value,
-            volume: 0.,
-        }
+        Self::named(value)
     }
 }
 
 impl<'a> From<&'a str> for Sound {
     fn from(value: &'a str) -> Self {
+        Self::named(value)
+    }
+}
+
+impl Sound {
+    /// Creates a sound that isn't critical, equal to a sound deserialized
+    /// from `name` alone.
+    pub fn named(name: impl Into<String>) -> Self {
         Self {
-            critical: false,
-            name: value.to_string(),
-            volume: 0.,
+            name: name.into(),
+            ..Default::default()
         }
     }
+
+    /// Creates a critical sound that plays `name` at `volume`, clamped to
+    /// `0..=1`.
+    pub fn critical(name: impl Into<String>, volume: f64) -> Self {
+        Self {
+            critical: true,
+            name: name.into(),
+            volume: volume.clamp(0., 1.),
+            extra: None,
+        }
+    }
+
+    /// Creates a critical sound that plays the system sound at `volume`,
+    /// clamped to `0..=1`.
+    pub fn default_critical(volume: f64) -> Self {
+        Self::critical("default", volume)
+    }
+
+    /// Returns `false` if the volume is negative or not a number.
+    pub(crate) fn has_valid_volume(&self) -> bool {
+        self.volume >= 0.
+    }
 }
 
 impl<'de> Deserialize<'de> for Sound {
@@ -422,22 +1299,14 @@ impl<'de> Deserialize<'de> for Sound {
             where
                 E: de::Error,
             {
-                Ok(Sound {
-                    critical: false,
-                    name: v.into(),
-                    volume: 0.,
-                })
+                Ok(Sound::named(v))
             }
 
             fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
             where
                 E: de::Error,
             {
-                Ok(Sound {
-                    critical: false,
-                    name: v,
-                    volume: 0.,
-                })
+                Ok(Sound::named(v))
             }
 
             fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
@@ -449,8 +1318,8 @@ impl<'de> Deserialize<'de> for Sound {
                 let mut match_name = false;
                 let mut match_volume = false;
 
-                while let Some(key) = map.next_key::<&str>()? {
-                    match key {
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
                         "critical" => {
                             let critical: i64 = map.next_value()?;
                             sound.critical = critical != 0;
@@ -461,14 +1330,21 @@ impl<'de> Deserialize<'de> for Sound {
                             match_name = true;
                         }
                         "volume" => {
-                            sound.volume = map.next_value()?;
+                            let volume: f64 = map.next_value()?;
+                            // Like the serializer, clamp values above 1 and
+                            // reject negative values.
+                            if volume.is_nan() || volume < 0. {
+                                return Err(de::Error::invalid_value(
+                                    de::Unexpected::Float(volume),
+                                    &"a volume between 0 and 1",
+                                ));
+                            }
+                            sound.volume = volume.min(1.);
                             match_volume = true;
                         }
-                        field => {
-                            return Err(de::Error::unknown_field(
-                                field,
-                                &["critical", "name", "volume"],
-                            ));
+                        _ => {
+                            let value = map.next_value()?;
+                            sound.extra.get_or_insert_with(Map::new).insert(key, value);
                         }
                     }
                 }
@@ -491,17 +1367,38 @@ impl<'de> Deserialize<'de> for Sound {
     }
 }
 
+impl Sound {
+    fn serialize_dict<S>(&self, serializer: S, critical: u8, volume: f64) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let extra = self.extra.iter().flatten();
+        let mut sound = serializer.serialize_map(Some(3 + extra.clone().count()))?;
+        sound.serialize_entry("critical", &critical)?;
+        sound.serialize_entry("name", &self.name)?;
+        sound.serialize_entry("volume", &volume)?;
+        for (key, value) in extra {
+            sound.serialize_entry(key, value)?;
+        }
+        sound.end()
+    }
+}
+
 impl Serialize for Sound {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
         if self.critical {
-            let mut sound = serializer.serialize_struct("Sound", 3)?;
-            sound.serialize_field("critical", &1)?;
-            sound.serialize_field("name", &self.name)?;
-            sound.serialize_field("volume", &self.volume.clamp(0., 1.))?;
-            sound.end()
+            if !self.has_valid_volume() {
+                return Err(serde::ser::Error::custom(
+                    "critical sound volume must not be negative",
+                ));
+            }
+
+            self.serialize_dict(serializer, 1, self.volume.clamp(0., 1.))
+        } else if self.extra.is_some() {
+            self.serialize_dict(serializer, 0, self.volume)
         } else {
             self.name.serialize(serializer)
         }
@@ -509,30 +1406,90 @@ impl Serialize for Sound {
 }
 
 /// Alert interruption level.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+///
+/// Levels are ordered by escalation, from [`Passive`](Self::Passive) to
+/// [`Critical`](Self::Critical), e.g. to cap the level at a user's
+/// preference with [`Ord::min`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "kebab-case")]
 pub enum InterruptionLevel {
-    /// The system presents the notification immediately, lights up the screen,
-    /// and can play a sound.
-    Active,
-
-    /// The system presents the notification immediately, lights up the screen,
-    /// and bypasses the mute switch to play a sound.
-    Critical,
-
     /// The system adds the notification to the notification list without
     /// lighting up the screen or playing a sound.
     Passive,
 
+    /// The system presents the notification immediately, lights up the screen,
+    /// and can play a sound.
+    Active,
+
     /// The system presents the notification immediately, lights up the screen,
     /// and can play a sound, but won’t break through system notification
     /// controls.
     TimeSensitive,
+
+    /// The system presents the notification immediately, lights up the screen,
+    /// and bypasses the mute switch to play a sound.
+    Critical,
+}
+
+impl InterruptionLevel {
+    /// Returns what the app and the request need for the level to take
+    /// effect. Without them, the device silently delivers the notification
+    /// at a lower level.
+    pub fn requirements(&self) -> InterruptionLevelRequirements {
+        match self {
+            Self::Passive | Self::Active => InterruptionLevelRequirements::default(),
+            Self::TimeSensitive => InterruptionLevelRequirements {
+                entitlement: Some("com.apple.developer.usernotifications.time-sensitive"),
+                alert: true,
+                critical_sound: false,
+            },
+            Self::Critical => InterruptionLevelRequirements {
+                entitlement: Some("com.apple.developer.usernotifications.critical-alerts"),
+                alert: false,
+                critical_sound: true,
+            },
+        }
+    }
 }
 
 derive_fromstr_from_deserialize!(InterruptionLevel);
 derive_display_from_serialize!(InterruptionLevel);
 
+/// What an [`InterruptionLevel`] needs to take effect, see
+/// [`InterruptionLevel::requirements`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InterruptionLevelRequirements {
+    /// The entitlement the app needs, if any.
+    pub entitlement: Option<&'static str>,
+
+    /// Whether the request needs an alert with content, checked by
+    /// [`Request::validate`](crate::Request::validate).
+    pub alert: bool,
+
+    /// Whether the request needs a critical sound, checked by
+    /// [`Request::validate`](crate::Request::validate).
+    pub critical_sound: bool,
+}
+
+/// Live Activity event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum Event {
+    /// Starts a Live Activity.
+    Start,
+
+    /// Updates a Live Activity.
+    Update,
+
+    /// Ends a Live Activity.
+    End,
+}
+
+derive_fromstr_from_deserialize!(Event);
+derive_display_from_serialize!(Event);
+
 #[cfg(test)]
 mod test {
     use std::str::FromStr;
@@ -571,7 +1528,7 @@ mod test {
             Payload {
                 aps: Aps {
                     alert: Some("Hello World!".into()),
-                    badge: Some(11),
+                    badge: Some(Badge::Count(11)),
                     sound: Some("default".into()),
                     thread_id: Some("my-thread-id".into()),
                     category: Some("my-category".into()),
@@ -580,8 +1537,9 @@ mod test {
                     target_content_id: Some("my-target-id".into()),
                     interruption_level: Some(InterruptionLevel::Active),
                     relevance_score: Some(0.5),
+                    ..Default::default()
                 },
-                user_info: Some(())
+                user_info: Some(UserInfo::new())
             }
         );
         assert_eq!(
@@ -609,13 +1567,299 @@ mod test {
         );
     }
 
+    #[test]
+    fn payload_from_slice_lenient() {
+        let cases = [
+            (json!(1), "1"),
+            (json!(1.5), "1.5"),
+            (json!(true), "true"),
+            (json!(false), "false"),
+        ];
+        for (alert, body) in cases {
+            let json =
+                serde_json::to_vec(&json!({ "aps": { "alert": alert, "badge": 1 } })).unwrap();
+
+            let strict = serde_json::from_slice::<Payload>(&json);
+            assert!(strict.is_err(), "{alert}");
+
+            let (payload, warnings) = Payload::from_slice_lenient(&json).unwrap();
+            assert_eq!(payload.aps.alert, Some(body.into()), "{alert}");
+            assert_eq!(payload.aps.badge, Some(Badge::Count(1)), "{alert}");
+            assert_eq!(warnings, [ParseWarning::AlertCoerced(alert.clone())]);
+            assert_eq!(
+                warnings[0].to_string(),
+                format!("alert {alert} was coerced to a string body")
+            );
+        }
+
+        // Valid payloads parse as with the strict deserializer.
+        for json in [
+            json!({ "aps": { "alert": "Hello" } }),
+            json!({ "aps": { "alert": { "title": "Hi", "body": "Hello" } }, "acme": 1 }),
+            json!({ "aps": {} }),
+            json!({ "acme": true }),
+        ] {
+            let bytes = serde_json::to_vec(&json).unwrap();
+            let (payload, warnings) = Payload::from_slice_lenient(&bytes).unwrap();
+            assert_eq!(payload, serde_json::from_slice::<Payload>(&bytes).unwrap());
+            assert!(warnings.is_empty(), "{json}");
+        }
+
+        // Other type errors are still errors.
+        for json in [
+            &br#"{ "aps": { "alert": [1] } }"#[..],
+            b"{ \"aps\": ",
+            b"[]",
+        ] {
+            assert!(Payload::from_slice_lenient(json).is_err());
+        }
+    }
+
+    #[test]
+    fn payload_display() {
+        let payload = Payload {
+            aps: Aps {
+                alert: Some("Hello World!".into()),
+                content_state: Some(json!({ "player": "jane@example.com" })),
+                ..Default::default()
+            },
+            user_info: Some(json!({ "email": "jane@example.com", "count": 2 })),
+        };
+
+        let output = payload.to_string();
+        assert!(!output.contains("jane@example.com"), "{output}");
+        let value = serde_json::from_str::<Value>(&output).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "aps": {
+                    "alert": "Hello World!",
+                    "content-state": { "player": "<string>" },
+                },
+                "email": "<string>",
+                "count": "<number>",
+            })
+        );
+        assert!(output.contains('\n'), "not pretty-printed: {output}");
+
+        let redacted = payload
+            .to_redacted_json(&Redaction {
+                user_info: crate::ValueRedaction::Omit,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(redacted.as_object().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn payload_split_known() {
+        #[derive(Debug, PartialEq, Deserialize, Serialize)]
+        struct TestUserInfoExtra {
+            foo: bool,
+            #[serde(flatten)]
+            extra: UserInfo,
+        }
+
+        let json = json!({
+            "aps": { "alert": "Hello World!" },
+            "foo": true,
+            "bar": -10,
+            "unknown": { "nested": [1, 2] },
+        });
+        let payload = serde_json::from_value::<Payload>(json.clone()).unwrap();
+        assert_eq!(
+            payload.user_info,
+            Some(UserInfo::from_iter([
+                ("foo".into(), json!(true)),
+                ("bar".into(), json!(-10)),
+                ("unknown".into(), json!({ "nested": [1, 2] })),
+            ]))
+        );
+
+        let (known, unknown) = payload.split_known::<TestUserInfo>().unwrap();
+        assert_eq!(
+            known,
+            TestUserInfo {
+                foo: true,
+                bar: -10
+            }
+        );
+        assert_eq!(
+            unknown,
+            UserInfo::from_iter([("unknown".into(), json!({ "nested": [1, 2] }))])
+        );
+
+        // A flattened map keeps the unknown keys in the typed user info.
+        let (known, unknown) = payload.split_known::<TestUserInfoExtra>().unwrap();
+        assert!(known.foo);
+        assert_eq!(known.extra.len(), 2);
+        assert!(unknown.is_empty());
+        let typed = serde_json::from_value::<Payload<TestUserInfoExtra>>(json).unwrap();
+        assert_eq!(typed.user_info, Some(known));
+
+        // User info without the required keys fails to deserialize.
+        let payload = serde_json::from_value::<Payload>(json!({ "bar": 1 })).unwrap();
+        assert!(matches!(
+            payload.split_known::<TestUserInfo>(),
+            Err(Error::SerdeJson(_))
+        ));
+    }
+
+    #[test]
+    fn payload_user_info() {
+        let mut payload = Payload {
+            aps: Aps {
+                alert: Some("Hello World!".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let aps_only = json!({ "aps": { "alert": "Hello World!" } });
+        assert_eq!(serde_json::to_value(&payload).unwrap(), aps_only);
+
+        // Empty user info doesn't change the wire format.
+        payload.user_info = Some(UserInfo::new());
+        assert_eq!(serde_json::to_value(&payload).unwrap(), aps_only);
+
+        assert_eq!(payload.insert_user_info("foo", true), None);
+        assert_eq!(payload.insert_user_info("bar", -10), None);
+        let json = json!({
+            "aps": { "alert": "Hello World!" },
+            "foo": true,
+            "bar": -10,
+        });
+        assert_eq!(serde_json::to_value(&payload).unwrap(), json);
+        assert_eq!(
+            serde_json::from_value::<Payload>(json.clone()).unwrap(),
+            payload
+        );
+
+        // Typed user info serializes the same way.
+        let typed = Payload {
+            aps: payload.aps.clone(),
+            user_info: Some(TestUserInfo {
+                foo: true,
+                bar: -10,
+            }),
+        };
+        assert_eq!(serde_json::to_value(&typed).unwrap(), json);
+        assert_eq!(
+            serde_json::from_value::<Payload<TestUserInfo>>(json).unwrap(),
+            typed
+        );
+    }
+
+    #[test]
+    fn payload_unknown_fields() {
+        let json = json!({
+            "aps": {
+                "alert": {
+                    "title": "Hello",
+                    "body": "World",
+                    "future-alert-key": "x",
+                },
+                "sound": {
+                    "critical": 1,
+                    "name": "default",
+                    "volume": 0.5,
+                    "future-sound-key": [1, 2],
+                },
+                "future-aps-key": { "a": 1 },
+            },
+            "acme": true,
+        });
+
+        let payload = serde_json::from_str::<Payload>(&json.to_string()).unwrap();
+        assert_eq!(payload.aps.extra["future-aps-key"], json!({ "a": 1 }));
+        assert_eq!(
+            payload.aps.alert.as_ref().unwrap().extra.as_ref().unwrap()["future-alert-key"],
+            "x"
+        );
+        assert_eq!(
+            payload.aps.sound.as_ref().unwrap().extra.as_ref().unwrap()["future-sound-key"],
+            json!([1, 2])
+        );
+        assert_eq!(payload.user_info.as_ref().unwrap()["acme"], true);
+        assert_eq!(serde_json::to_value(&payload).unwrap(), json);
+
+        assert_eq!(
+            payload.deny_unknown_fields().unwrap_err().to_string(),
+            "invalid request: unknown keys aps.future-aps-key, aps.alert.future-alert-key, aps.sound.future-sound-key"
+        );
+        let known = Payload::<()> {
+            aps: Aps {
+                alert: Some("Hello World!".into()),
+                sound: Some(Sound::default_critical(0.5)),
+                ..Default::default()
+            },
+            user_info: None,
+        };
+        assert!(known.deny_unknown_fields().is_ok());
+
+        // A sound that isn't critical is sent as a dictionary to keep the
+        // unknown keys.
+        let json = json!({ "critical": 0, "name": "ping.aiff", "volume": 1., "future": 1 });
+        let sound = serde_json::from_str::<Sound>(&json.to_string()).unwrap();
+        assert!(!sound.critical);
+        assert_eq!(serde_json::to_value(&sound).unwrap(), json);
+
+        // An alert with only a body and unknown keys is a dictionary too.
+        let json = json!({ "body": "Hello World!", "future": 1 });
+        let alert = serde_json::from_str::<Alert>(&json.to_string()).unwrap();
+        assert_eq!(serde_json::to_value(&alert).unwrap(), json);
+    }
+
+    #[test]
+    fn badge_serde() {
+        for (badge, json) in [
+            (Badge::Clear, json!({ "badge": 0 })),
+            (Badge::Count(1), json!({ "badge": 1 })),
+        ] {
+            let aps = Aps {
+                badge: Some(badge),
+                ..Default::default()
+            };
+            assert_eq!(serde_json::to_value(&aps).unwrap(), json);
+            assert_eq!(serde_json::from_value::<Aps>(json).unwrap(), aps);
+        }
+        assert_eq!(serde_json::to_value(Aps::default()).unwrap(), json!({}));
+
+        assert_eq!(Badge::from(0), Badge::Clear);
+        assert_eq!(Badge::from(3), Badge::Count(3));
+        assert_eq!("clear".parse::<Badge>().unwrap(), Badge::Clear);
+        assert_eq!("0".parse::<Badge>().unwrap(), Badge::Clear);
+        assert_eq!("3".parse::<Badge>().unwrap(), Badge::Count(3));
+        assert!("-1".parse::<Badge>().is_err());
+        assert_eq!("increment".parse::<Badge>().unwrap(), Badge::Increment);
+        assert_eq!(Badge::Clear.to_string(), "clear");
+        assert_eq!(Badge::Count(3).to_string(), "3");
+
+        // Only the request form has increments.
+        assert_eq!(
+            serde_json::to_value(Badge::Increment).unwrap(),
+            json!("increment")
+        );
+        assert_eq!(
+            serde_json::from_value::<Badge>(json!("increment")).unwrap(),
+            Badge::Increment
+        );
+        assert!(serde_json::from_value::<Aps>(json!({ "badge": "increment" })).is_err());
+
+        let aps = Aps {
+            badge: Some(Badge::Increment),
+            ..Default::default()
+        };
+        let err = serde_json::to_value(aps).unwrap_err();
+        assert!(err.to_string().contains("badge counter"), "{err}");
+    }
+
     #[test]
     fn payload_ser() {
         assert_eq!(
             serde_json::to_value(&Payload {
                 aps: Aps {
                     alert: Some("Hello World!".into()),
-                    badge: Some(11),
+                    badge: Some(Badge::Count(11)),
                     sound: Some("default".into()),
                     thread_id: Some("my-thread-id".into()),
                     category: Some("my-category".into()),
@@ -624,6 +1868,7 @@ mod test {
                     target_content_id: Some("my-target-id".into()),
                     interruption_level: Some(InterruptionLevel::Active),
                     relevance_score: Some(0.5),
+                    ..Default::default()
                 },
                 user_info: Some(()),
             })
@@ -665,6 +1910,33 @@ mod test {
         );
     }
 
+    #[test]
+    fn payload_raw() {
+        let payload = Payload::raw(TestUserInfo {
+            foo: true,
+            bar: -10,
+        });
+        assert_eq!(
+            serde_json::to_string(&payload).unwrap(),
+            r#"{"foo":true,"bar":-10}"#
+        );
+
+        // An `aps` key from the user info is kept as is.
+        let payload = Payload::raw(json!({ "aps": { "call-id": "1" }, "caller": "Alice" }));
+        assert_eq!(
+            serde_json::to_value(&payload).unwrap(),
+            json!({ "aps": { "call-id": "1" }, "caller": "Alice" })
+        );
+
+        assert_eq!(
+            serde_json::from_str::<Payload<TestUserInfo>>(r#"{"foo":true,"bar":-10}"#).unwrap(),
+            Payload::raw(TestUserInfo {
+                foo: true,
+                bar: -10,
+            })
+        );
+    }
+
     #[test]
     fn alert_de() {
         assert_eq!(
@@ -734,6 +2006,7 @@ mod test {
                 subtitle_loc_args: Some(vec!["Bar".into(), "Baz".into()]),
                 loc_key: Some("BODY_FORMAT".into()),
                 loc_args: Some(vec!["Apple".into(), "Pie".into()]),
+                extra: None,
             }
         );
     }
@@ -776,18 +2049,70 @@ mod test {
                 subtitle_loc_args: Some(vec!["Bar".into(), "Baz".into()]),
                 loc_key: Some("BODY_FORMAT".into()),
                 loc_args: Some(vec!["Apple".into(), "Pie".into()]),
+                extra: None,
             })
             .unwrap(),
             json!({
+                "title": "Title",
                 "title-loc-key": "REQUEST_FORMAT",
                 "title-loc-args": ["Foo", "Bar"],
+                "subtitle": "Subtitle",
                 "subtitle-loc-key": "SUBTITLE_FORMAT",
                 "subtitle-loc-args": ["Bar", "Baz"],
+                "body": "Hello World!",
                 "loc-key": "BODY_FORMAT",
                 "loc-args": ["Apple", "Pie"],
                 "launch-image": "http://example.com/img.png",
             })
         );
+        assert_eq!(
+            serde_json::to_value(&Alert {
+                title: Some("Title".into()),
+                ..Default::default()
+            })
+            .unwrap(),
+            json!({ "title": "Title" })
+        );
+        assert_eq!(
+            serde_json::to_value(&Alert {
+                loc_args: Some(vec!["Apple".into()]),
+                ..Default::default()
+            })
+            .unwrap(),
+            json!({ "loc-args": ["Apple"] })
+        );
+    }
+
+    #[test]
+    fn alert_serde() {
+        let full = Alert {
+            title: Some("Title".into()),
+            subtitle: Some("Subtitle".into()),
+            body: Some("Hello World!".into()),
+            launch_image: Some("http://example.com/img.png".into()),
+            title_loc_key: Some("REQUEST_FORMAT".into()),
+            title_loc_args: Some(vec!["Foo".into(), "Bar".into()]),
+            subtitle_loc_key: Some("SUBTITLE_FORMAT".into()),
+            subtitle_loc_args: Some(vec!["Bar".into(), "Baz".into()]),
+            loc_key: Some("BODY_FORMAT".into()),
+            loc_args: Some(vec!["Apple".into(), "Pie".into()]),
+            extra: None,
+        };
+        let only_args = Alert {
+            title_loc_args: Some(vec!["Foo".into()]),
+            subtitle_loc_args: Some(vec!["Bar".into()]),
+            loc_args: Some(vec!["Apple".into()]),
+            ..Default::default()
+        };
+
+        for alert in [full, only_args, "Hello World!".into()] {
+            let json = serde_json::to_string(&alert).unwrap();
+            assert_eq!(
+                serde_json::from_str::<Alert>(&json).unwrap(),
+                alert,
+                "{json}"
+            );
+        }
     }
 
     #[test]
@@ -797,9 +2122,14 @@ mod test {
             Sound {
                 critical: false,
                 name: "default".into(),
-                volume: 0.
+                volume: 1.,
+                extra: None,
             }
         );
+        assert_eq!(
+            serde_json::from_str::<Sound>(&json!("default").to_string()).unwrap(),
+            Sound::default()
+        );
         assert_eq!(
             serde_json::from_str::<Sound>(
                 &json!({
@@ -813,7 +2143,8 @@ mod test {
             Sound {
                 critical: true,
                 name: "custom".into(),
-                volume: 0.5
+                volume: 0.5,
+                extra: None,
             }
         );
         assert_eq!(
@@ -829,7 +2160,8 @@ mod test {
             Sound {
                 critical: false,
                 name: "default".into(),
-                volume: 1.
+                volume: 1.,
+                extra: None,
             }
         );
         assert!(serde_json::from_str::<Sound>(
@@ -842,13 +2174,32 @@ mod test {
         .is_err());
     }
 
+    #[test]
+    fn sound_de_volume() {
+        let sound = |volume: &str| {
+            serde_yaml::from_str::<Sound>(&format!(
+                "{{ critical: 1, name: default, volume: {volume} }}"
+            ))
+            .map(|sound| sound.volume)
+        };
+
+        assert_eq!(sound("0").unwrap(), 0.);
+        assert_eq!(sound("1").unwrap(), 1.);
+        assert_eq!(sound("1.5").unwrap(), 1.);
+        assert_eq!(sound(".inf").unwrap(), 1.);
+        for volume in ["-0.1", "-.inf", ".nan"] {
+            assert!(sound(volume).is_err(), "{volume}");
+        }
+    }
+
     #[test]
     fn sound_ser() {
         assert_eq!(
             serde_json::to_string(&Sound {
                 critical: false,
                 name: "default".into(),
-                volume: 0.
+                volume: 1.,
+                extra: None,
             })
             .unwrap(),
             json!("default").to_string(),
@@ -857,7 +2208,8 @@ mod test {
             serde_json::to_string(&Sound {
                 critical: true,
                 name: "custom".into(),
-                volume: 0.5
+                volume: 0.5,
+                extra: None,
             })
             .unwrap(),
             json!({
@@ -871,7 +2223,8 @@ mod test {
             serde_json::to_string(&Sound {
                 critical: true,
                 name: "default".into(),
-                volume: 1.
+                volume: 1.,
+                extra: None,
             })
             .unwrap(),
             json!({
@@ -885,7 +2238,8 @@ mod test {
             serde_json::to_string(&Sound {
                 critical: true,
                 name: "default".into(),
-                volume: 2.
+                volume: 2.,
+                extra: None,
             })
             .unwrap(),
             json!({
@@ -897,6 +2251,132 @@ mod test {
         );
     }
 
+    #[test]
+    fn sound_named() {
+        let sound = Sound::named("default");
+        assert_eq!(sound, Sound::default());
+        assert_eq!(Sound::from("default"), sound);
+        assert_eq!(Sound::from("default".to_string()), sound);
+
+        let json = serde_json::to_string(&sound).unwrap();
+        assert_eq!(json, r#""default""#);
+        assert_eq!(serde_json::from_str::<Sound>(&json).unwrap(), sound);
+
+        let sound = Sound::named("ping.aiff");
+        let json = serde_json::to_string(&sound).unwrap();
+        assert_eq!(serde_json::from_str::<Sound>(&json).unwrap(), sound);
+    }
+
+    #[test]
+    fn sound_critical() {
+        assert_eq!(
+            Sound::critical("siren.aiff", 0.5),
+            Sound {
+                critical: true,
+                name: "siren.aiff".into(),
+                volume: 0.5,
+                extra: None,
+            }
+        );
+        assert_eq!(
+            Sound::default_critical(0.5),
+            Sound {
+                critical: true,
+                name: "default".into(),
+                volume: 0.5,
+                extra: None,
+            }
+        );
+        assert_eq!(Sound::critical("siren.aiff", 1.5).volume, 1.);
+        assert_eq!(Sound::critical("siren.aiff", -1.).volume, 0.);
+        assert_eq!(Sound::default_critical(f64::INFINITY).volume, 1.);
+
+        let sound = Sound::default_critical(0.5);
+        let json = serde_json::to_string(&sound).unwrap();
+        assert_eq!(serde_json::from_str::<Sound>(&json).unwrap(), sound);
+    }
+
+    #[test]
+    fn sound_ser_quadrants() {
+        // Not critical, default volume.
+        assert_eq!(
+            serde_json::to_value(&Sound {
+                critical: false,
+                name: "default".into(),
+                ..Default::default()
+            })
+            .unwrap(),
+            json!("default")
+        );
+
+        // Not critical, custom volume: the volume is ignored.
+        assert_eq!(
+            serde_json::to_value(&Sound {
+                critical: false,
+                name: "default".into(),
+                volume: 0.5,
+                extra: None,
+            })
+            .unwrap(),
+            json!("default")
+        );
+        assert_eq!(
+            serde_json::to_value(&Sound {
+                critical: false,
+                name: "default".into(),
+                volume: -3.,
+                extra: None,
+            })
+            .unwrap(),
+            json!("default")
+        );
+
+        // Critical, default volume.
+        assert_eq!(
+            serde_json::to_value(&Sound {
+                critical: true,
+                name: "default".into(),
+                ..Default::default()
+            })
+            .unwrap(),
+            json!({
+                "critical": 1,
+                "name": "default",
+                "volume": 1.,
+            })
+        );
+
+        // Critical, custom volume: clamped above 1, an error below 0.
+        assert_eq!(
+            serde_json::to_value(&Sound {
+                critical: true,
+                name: "default".into(),
+                volume: 0.,
+                extra: None,
+            })
+            .unwrap(),
+            json!({
+                "critical": 1,
+                "name": "default",
+                "volume": 0.,
+            })
+        );
+        assert!(serde_json::to_value(&Sound {
+            critical: true,
+            name: "default".into(),
+            volume: -3.,
+            extra: None,
+        })
+        .is_err());
+        assert!(serde_json::to_value(&Sound {
+            critical: true,
+            name: "default".into(),
+            volume: f64::NAN,
+            extra: None,
+        })
+        .is_err());
+    }
+
     #[test]
     fn interruption_level_de() {
         assert_eq!(
@@ -969,4 +2449,586 @@ mod test {
             "time-sensitive"
         );
     }
+
+    #[test]
+    fn interruption_level_ord() {
+        use InterruptionLevel::*;
+
+        let mut levels = [Critical, Passive, TimeSensitive, Active];
+        levels.sort();
+        assert_eq!(levels, [Passive, Active, TimeSensitive, Critical]);
+
+        // Cap the level at a user's preference.
+        let preference = Active;
+        assert_eq!(Critical.min(preference), Active);
+        assert_eq!(Passive.min(preference), Passive);
+        assert!(TimeSensitive > Active);
+    }
+
+    #[test]
+    fn interruption_level_requirements() {
+        assert_eq!(
+            InterruptionLevel::Passive.requirements(),
+            InterruptionLevelRequirements::default()
+        );
+        assert_eq!(
+            InterruptionLevel::Active.requirements(),
+            InterruptionLevelRequirements::default()
+        );
+        assert_eq!(
+            InterruptionLevel::TimeSensitive.requirements(),
+            InterruptionLevelRequirements {
+                entitlement: Some("com.apple.developer.usernotifications.time-sensitive"),
+                alert: true,
+                critical_sound: false,
+            }
+        );
+        assert_eq!(
+            InterruptionLevel::Critical.requirements(),
+            InterruptionLevelRequirements {
+                entitlement: Some("com.apple.developer.usernotifications.critical-alerts"),
+                alert: false,
+                critical_sound: true,
+            }
+        );
+    }
+
+    #[test]
+    fn payload_serialize_sorted() {
+        let serialize = || {
+            let user_info = (0..32)
+                .map(|i| {
+                    (
+                        format!("key-{i}"),
+                        json!({ "z": i, "a": [{ "y": 1, "b": 2 }] }),
+                    )
+                })
+                .collect::<std::collections::HashMap<_, _>>();
+            Payload {
+                aps: Aps {
+                    alert: Some("Hello World!".into()),
+                    ..Default::default()
+                },
+                user_info: Some(user_info),
+            }
+            .serialize_sorted()
+            .unwrap()
+        };
+
+        let expected = serialize();
+        for _ in 0..100 {
+            assert_eq!(serialize(), expected);
+        }
+
+        let value = serde_json::from_slice::<Value>(&expected).unwrap();
+        assert_eq!(value["key-0"], json!({ "a": [{ "b": 2, "y": 1 }], "z": 0 }));
+        assert!(String::from_utf8(expected).unwrap().starts_with(
+            r#"{"aps":{"alert":"Hello World!"},"key-0":{"a":[{"b":2,"y":1}],"z":0},"key-1":"#
+        ));
+    }
+
+    #[test]
+    fn payload_liveactivity() {
+        let timestamp = OffsetDateTime::from_unix_timestamp(1168364460).unwrap();
+
+        let start = json!({
+            "aps": {
+                "timestamp": 1168364460,
+                "event": "start",
+                "content-state": {
+                    "currentHealthLevel": 100,
+                    "eventDescription": "Adventure has begun!",
+                },
+                "attributes-type": "AdventureAttributes",
+                "attributes": {
+                    "currentHealthLevel": 100,
+                    "eventDescription": "Adventure has begun!",
+                },
+                "alert": {
+                    "title": "Adventure started",
+                    "body": "Power Panda is on an adventure!",
+                },
+                "input-push-token": 1,
+            },
+        });
+        let payload = Payload::<()> {
+            aps: Aps {
+                alert: Some(Alert {
+                    title: Some("Adventure started".into()),
+                    body: Some("Power Panda is on an adventure!".into()),
+                    ..Default::default()
+                }),
+                timestamp: Some(timestamp),
+                event: Some(Event::Start),
+                content_state: Some(json!({
+                    "currentHealthLevel": 100,
+                    "eventDescription": "Adventure has begun!",
+                })),
+                attributes_type: Some("AdventureAttributes".into()),
+                attributes: Some(json!({
+                    "currentHealthLevel": 100,
+                    "eventDescription": "Adventure has begun!",
+                })),
+                input_push_token: true,
+                ..Default::default()
+            },
+            user_info: None,
+        };
+        assert_eq!(serde_json::to_value(&payload).unwrap(), start);
+        assert_eq!(
+            serde_json::from_str::<Payload>(&start.to_string())
+                .unwrap()
+                .aps,
+            payload.aps
+        );
+
+        let end = json!({
+            "aps": {
+                "timestamp": 1168364460,
+                "event": "end",
+                "content-state": {
+                    "currentHealthLevel": 0.941,
+                    "eventDescription": "Power Panda found a sword!",
+                },
+                "stale-date": 1168364520,
+                "dismissal-date": 1168364580,
+            },
+        });
+        let payload = Payload::<()> {
+            aps: Aps {
+                timestamp: Some(timestamp),
+                event: Some(Event::End),
+                content_state: Some(json!({
+                    "currentHealthLevel": 0.941,
+                    "eventDescription": "Power Panda found a sword!",
+                })),
+                stale_date: Some(timestamp + time::Duration::minutes(1)),
+                dismissal_date: Some(timestamp + time::Duration::minutes(2)),
+                ..Default::default()
+            },
+            user_info: None,
+        };
+        assert_eq!(serde_json::to_value(&payload).unwrap(), end);
+        assert_eq!(
+            serde_json::from_str::<Payload>(&end.to_string())
+                .unwrap()
+                .aps,
+            payload.aps
+        );
+
+        let update = json!({
+            "aps": {
+                "timestamp": 1168364460,
+                "event": "update",
+                "content-state": { "currentHealthLevel": 0.5 },
+                "filter-criteria": "team == 'Blue'",
+            },
+        });
+        let payload = Payload::<()> {
+            aps: Aps {
+                timestamp: Some(timestamp),
+                event: Some(Event::Update),
+                content_state: Some(json!({ "currentHealthLevel": 0.5 })),
+                filter_criteria: Some("team == 'Blue'".into()),
+                ..Default::default()
+            },
+            user_info: None,
+        };
+        assert_eq!(serde_json::to_value(&payload).unwrap(), update);
+        assert_eq!(
+            serde_json::from_str::<Payload>(&update.to_string())
+                .unwrap()
+                .aps,
+            payload.aps
+        );
+    }
+
+    #[test]
+    fn payload_remaining_budget() {
+        let payload: Payload = Payload {
+            aps: Aps {
+                alert: Some("Hello".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let size = serde_json::to_vec(&payload).unwrap().len();
+
+        assert_eq!(payload.remaining_budget(4096).unwrap(), 4096 - size);
+        assert_eq!(payload.remaining_budget(size).unwrap(), 0);
+        assert!(matches!(
+            payload.remaining_budget(size - 1),
+            Err(Error::PayloadTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn payload_serialized_size() {
+        let payload = payload_with_body("Hello 😀");
+        assert_eq!(
+            payload.serialized_size().unwrap(),
+            serde_json::to_vec(&payload).unwrap().len()
+        );
+    }
+
+    fn payload_with_body(body: &str) -> Payload {
+        Payload {
+            aps: Aps {
+                alert: Some(Alert {
+                    title: Some("Title".into()),
+                    body: Some(body.into()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn truncated(body: &str, len: usize) -> Payload {
+        payload_with_body(&body.chars().take(len).collect::<String>())
+    }
+
+    #[test]
+    fn alert_builder() {
+        assert_eq!(Alert::builder().build(), Alert::default());
+
+        let alert = Alert::builder()
+            .title("Game Request")
+            .subtitle("Five Card Draw")
+            .body("Bob wants to play poker")
+            .launch_image("poker.png")
+            .build();
+        assert_eq!(
+            alert,
+            Alert {
+                title: Some("Game Request".into()),
+                subtitle: Some("Five Card Draw".into()),
+                body: Some("Bob wants to play poker".into()),
+                launch_image: Some("poker.png".into()),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn alert_builder_localized() {
+        let alert = Alert::builder()
+            .localized_title("GAME_PLAY_REQUEST_FORMAT", ["Jenna", "Frank"])
+            .localized_subtitle("GAME_NAME", Vec::<String>::new())
+            .localized_body("GAME_PLAY_REQUEST_BODY", vec![String::from("Jenna")])
+            .build();
+        assert_eq!(
+            alert,
+            Alert {
+                title_loc_key: Some("GAME_PLAY_REQUEST_FORMAT".into()),
+                title_loc_args: Some(vec!["Jenna".into(), "Frank".into()]),
+                subtitle_loc_key: Some("GAME_NAME".into()),
+                subtitle_loc_args: None,
+                loc_key: Some("GAME_PLAY_REQUEST_BODY".into()),
+                loc_args: Some(vec!["Jenna".into()]),
+                ..Default::default()
+            }
+        );
+
+        // Setting a key again replaces its arguments.
+        let alert = Alert::builder()
+            .localized_title("A", ["1"])
+            .localized_title("B", [""; 0])
+            .build();
+        assert_eq!(alert.title_loc_key.as_deref(), Some("B"));
+        assert_eq!(alert.title_loc_args, None);
+    }
+
+    #[test]
+    fn alert_localized() {
+        let alert = Alert::localized("GAME_PLAY_REQUEST_FORMAT", ["Jenna", "Frank"])
+            .with_localized_title("GAME_PLAY_REQUEST_TITLE", ["Jenna"])
+            .with_localized_subtitle("GAME_NAME", [""; 0]);
+        assert_eq!(
+            alert,
+            Alert::builder()
+                .localized_body("GAME_PLAY_REQUEST_FORMAT", ["Jenna", "Frank"])
+                .localized_title("GAME_PLAY_REQUEST_TITLE", ["Jenna"])
+                .localized_subtitle("GAME_NAME", [""; 0])
+                .build()
+        );
+        assert_eq!(
+            serde_json::to_value(&alert).unwrap(),
+            json!({
+                "title-loc-key": "GAME_PLAY_REQUEST_TITLE",
+                "title-loc-args": ["Jenna"],
+                "subtitle-loc-key": "GAME_NAME",
+                "loc-key": "GAME_PLAY_REQUEST_FORMAT",
+                "loc-args": ["Jenna", "Frank"],
+            })
+        );
+
+        // Literal strings are kept alongside the localization keys.
+        let alert = Alert::builder()
+            .title("Game Request")
+            .build()
+            .with_localized_title("GAME_PLAY_REQUEST_TITLE", [""; 0]);
+        assert_eq!(alert.title.as_deref(), Some("Game Request"));
+        assert_eq!(
+            alert.title_loc_key.as_deref(),
+            Some("GAME_PLAY_REQUEST_TITLE")
+        );
+    }
+
+    #[test]
+    fn alert_validate_against_strings() {
+        let strings = crate::strings::parse(concat!(
+            "\"GAME_PLAY_REQUEST_TITLE\" = \"Game Request\";\n",
+            "\"GAME_PLAY_REQUEST_FORMAT\" = \"%@ and %@ have invited you to play %@\";\n",
+            "\"GAME_SCORE_FORMAT\" = \"%2$@ scored %1$d%% of %1$d\";\n",
+        ))
+        .unwrap();
+
+        let alert = Alert::localized("GAME_PLAY_REQUEST_FORMAT", ["Jenna", "Frank", "Monopoly"])
+            .with_localized_title("GAME_PLAY_REQUEST_TITLE", [""; 0])
+            .with_localized_subtitle("GAME_SCORE_FORMAT", ["10", "Jenna"]);
+        assert_eq!(alert.validate_against_strings(&strings), vec![]);
+
+        let alert = Alert::localized("GAME_PLAY_REQUEST_FORMAT", ["Jenna", "Frank"])
+            .with_localized_title("GAME_PLAY_REQUEST_TITLE", ["Jenna"])
+            .with_localized_subtitle("GAME_NAME", [""; 0]);
+        assert_eq!(
+            alert.validate_against_strings(&strings),
+            vec![
+                Lint::LocArgs {
+                    field: "title-loc-key",
+                    key: "GAME_PLAY_REQUEST_TITLE".into(),
+                    placeholders: 0,
+                    args: 1,
+                },
+                Lint::UnknownLocKey {
+                    field: "subtitle-loc-key",
+                    key: "GAME_NAME".into(),
+                },
+                Lint::LocArgs {
+                    field: "loc-key",
+                    key: "GAME_PLAY_REQUEST_FORMAT".into(),
+                    placeholders: 3,
+                    args: 2,
+                },
+            ]
+        );
+
+        // Literal strings aren't looked up.
+        let alert = Alert::builder().title("Hi %@").body("100%").build();
+        assert_eq!(alert.validate_against_strings(&strings), vec![]);
+    }
+
+    #[test]
+    fn static_alert() {
+        const BODY: StaticAlert = StaticAlert::new("Hello World!");
+        const FULL: StaticAlert = StaticAlert::new("Hello World!")
+            .title("Greeting")
+            .subtitle("From Earth");
+
+        for alert in [
+            BODY,
+            BODY.title("Greeting"),
+            BODY.subtitle("From Earth"),
+            FULL,
+        ] {
+            assert_eq!(
+                serde_json::to_value(alert).unwrap(),
+                serde_json::to_value(Alert::from(alert)).unwrap()
+            );
+        }
+
+        assert_eq!(serde_json::to_value(BODY).unwrap(), json!("Hello World!"));
+        assert_eq!(
+            Alert::from(&FULL),
+            Alert {
+                title: Some("Greeting".into()),
+                subtitle: Some("From Earth".into()),
+                body: Some("Hello World!".into()),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn alert_max_body_len_within() {
+        let bodies = [
+            "plain ascii text",
+            "emoji 😀😀😀 and ünïcödé",
+            r#"quotes "like" "this""#,
+            r"back\slashes\\",
+            "control\nchars\t\u{1}\u{1f}",
+            "😀\"\\\n😀\"\\\n😀",
+        ];
+
+        for body in bodies {
+            let payload = payload_with_body(body);
+            let chars = body.chars().count();
+            let empty = serde_json::to_vec(&truncated(body, 0)).unwrap().len();
+            let full = serde_json::to_vec(&payload).unwrap().len();
+
+            for limit in empty..=full + 1 {
+                let len = Alert::max_body_len_within(&payload, limit).unwrap();
+                let size = serde_json::to_vec(&truncated(body, len)).unwrap().len();
+                assert!(size <= limit, "{body:?} {limit}");
+                if len < chars {
+                    let size = serde_json::to_vec(&truncated(body, len + 1)).unwrap().len();
+                    assert!(size > limit, "{body:?} {limit}");
+                }
+            }
+
+            assert_eq!(Alert::max_body_len_within(&payload, full).unwrap(), chars);
+            assert!(matches!(
+                Alert::max_body_len_within(&payload, empty - 1),
+                Err(Error::PayloadTooLarge { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn alert_max_body_len_within_title_only() {
+        let payload: Payload = Payload {
+            aps: Aps {
+                alert: Some(Alert {
+                    title: Some("Title".into()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let empty = payload_with_body("").serialized_size().unwrap();
+        assert_eq!(Alert::max_body_len_within(&payload, empty).unwrap(), 0);
+        assert!(matches!(
+            Alert::max_body_len_within(&payload, empty - 1),
+            Err(Error::PayloadTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn alert_truncate_body_to_fit() {
+        // Over 10 KB of emoji, quotes and newlines, which JSON escapes.
+        let body = "😀\"🎉\n".repeat(1001);
+        assert!(body.len() > 10_000);
+
+        let payload = payload_with_body(&body);
+        let mut alert = payload.aps.alert.clone().unwrap();
+        alert.truncate_body_to_fit(&payload, 4096).unwrap();
+
+        let truncated_body = alert.body.clone().unwrap();
+        assert!(truncated_body.ends_with('…'));
+        let prefix = truncated_body.strip_suffix('…').unwrap();
+        assert!(body.starts_with(prefix));
+
+        let size = payload_with_body(&truncated_body)
+            .serialized_size()
+            .unwrap();
+        assert!(size <= 4096, "{size}");
+
+        // One more character would not fit.
+        let next = body[prefix.len()..].chars().next().unwrap();
+        let longer = format!("{prefix}{next}…");
+        assert!(payload_with_body(&longer).serialized_size().unwrap() > 4096);
+
+        // A body that already fits is left alone.
+        let mut alert = payload.aps.alert.clone().unwrap();
+        let full = payload.serialized_size().unwrap();
+        alert.truncate_body_to_fit(&payload, full).unwrap();
+        assert_eq!(alert.body.as_deref(), Some(&body[..]));
+
+        // Nothing fits if the rest of the payload is too large.
+        let empty = payload_with_body("…").serialized_size().unwrap();
+        assert!(matches!(
+            alert.truncate_body_to_fit(&payload, empty - 1),
+            Err(Error::PayloadTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn alert_truncate_body_to_fit_simple_alert() {
+        let payload: Payload = Payload {
+            aps: Aps {
+                alert: Some("😀😀😀😀".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut alert = payload.aps.alert.clone().unwrap();
+        let limit = payload.serialized_size().unwrap() - 1;
+        alert.truncate_body_to_fit(&payload, limit).unwrap();
+        assert_eq!(alert.body.as_deref(), Some("😀😀😀…"));
+    }
+
+    #[test]
+    fn alert_truncate_body_to_fit_loc_key() {
+        // The body is sent alongside `loc-key` as a fallback.
+        let payload = payload_with_body("Hello");
+        let mut alert = Alert {
+            body: Some("Hello World!".into()),
+            loc_key: Some("GREETING".into()),
+            ..Default::default()
+        };
+        let mut full = payload.clone();
+        full.aps.alert = Some(alert.clone());
+        let limit = full.serialized_size().unwrap() - 1;
+        alert.truncate_body_to_fit(&payload, limit).unwrap();
+        assert_eq!(alert.body.as_deref(), Some("Hello Wo…"));
+        assert_eq!(alert.loc_key.as_deref(), Some("GREETING"));
+    }
+
+    #[test]
+    fn alert_max_body_len_within_simple_alert() {
+        let payload: Payload = Payload {
+            aps: Aps {
+                alert: Some("a\"😀".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let empty = serde_json::to_vec(&Payload::<()> {
+            aps: Aps {
+                alert: Some("".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap()
+        .len();
+
+        // `a` is 1 byte, `\"` is escaped to 2 bytes and `😀` is 4 bytes.
+        assert_eq!(Alert::max_body_len_within(&payload, empty).unwrap(), 0);
+        assert_eq!(Alert::max_body_len_within(&payload, empty + 2).unwrap(), 1);
+        assert_eq!(Alert::max_body_len_within(&payload, empty + 3).unwrap(), 2);
+        assert_eq!(Alert::max_body_len_within(&payload, empty + 6).unwrap(), 2);
+        assert_eq!(Alert::max_body_len_within(&payload, empty + 7).unwrap(), 3);
+    }
+
+    #[test]
+    fn alert_max_body_len_within_invalid() {
+        let payload: Payload = Payload::default();
+        assert!(matches!(
+            Alert::max_body_len_within(&payload, 4096),
+            Err(Error::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn alert_max_body_len_within_loc_key() {
+        // The body is sent alongside `loc-key` as a fallback.
+        let payload: Payload = Payload {
+            aps: Aps {
+                alert: Some(Alert {
+                    body: Some("Hello".into()),
+                    loc_key: Some("KEY".into()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let full = payload.serialized_size().unwrap();
+        assert_eq!(Alert::max_body_len_within(&payload, full).unwrap(), 5);
+        assert_eq!(Alert::max_body_len_within(&payload, full - 1).unwrap(), 4);
+    }
 }
@@ -0,0 +1,193 @@
+//! Versioned serialization of requests for queues and other storage.
+//!
+//! The JSON form of [`Request`] follows this crate, so a request queued by one
+//! version may not deserialize with the next. [`VersionedRequest`] stores the
+//! request with a [`SCHEMA_VERSION`] and migrates requests of older versions
+//! when reading them back.
+//!
+//! Schema versions:
+//!
+//! 1. `expiration` is the `apns-expiration` header value, UNIX epoch seconds
+//!    with `0` for immediate delivery.
+//! 2. `expiration` is an [`Expiration`], an RFC 3339 date, `"never-store"` or
+//!    `{ "in": <seconds> }`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::OffsetDateTime;
+
+use crate::header::Expiration;
+use crate::request::Request;
+use crate::result::{Error, Result};
+
+/// Migrations of the serialized request, where `MIGRATIONS[i]` migrates
+/// version `i + 1` to `i + 2`.
+const MIGRATIONS: [fn(&mut Value) -> Result<()>; 1] = [migrate_v1_expiration];
+
+/// The schema version written by [`VersionedRequest::to_persist_bytes`].
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32 + 1;
+
+/// A [`Request`] with the schema version it was persisted with.
+///
+/// ```
+/// use apple_apns::persist::{VersionedRequest, SCHEMA_VERSION};
+/// use apple_apns::Request;
+/// use serde_json::json;
+///
+/// let request = Request::builder()
+///     .device_token("a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae")
+///     .alert("Hello World!")
+///     .user_info(json!({ "acme": 1 }))
+///     .build()?;
+///
+/// let bytes = VersionedRequest::from(request.clone()).to_persist_bytes()?;
+/// let versioned = VersionedRequest::from_persist_bytes(&bytes)?;
+/// assert_eq!(versioned.schema_version, SCHEMA_VERSION);
+/// assert_eq!(versioned.request, request);
+/// # Ok::<_, apple_apns::Error>(())
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct VersionedRequest {
+    /// The schema version the request was persisted with.
+    /// [`to_persist_bytes`](Self::to_persist_bytes) always writes
+    /// [`SCHEMA_VERSION`].
+    pub schema_version: u32,
+
+    /// The request, migrated to the current schema version.
+    pub request: Request<Value>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Envelope<R> {
+    schema_version: u32,
+    request: R,
+}
+
+impl VersionedRequest {
+    /// Serializes the request as JSON with the current [`SCHEMA_VERSION`].
+    pub fn to_persist_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&Envelope {
+            schema_version: SCHEMA_VERSION,
+            request: &self.request,
+        })?)
+    }
+
+    /// Deserializes a request written by
+    /// [`to_persist_bytes`](Self::to_persist_bytes) of this or an older
+    /// version of the crate, migrating it to the current schema.
+    ///
+    /// Returns [`Error::UnsupportedSchemaVersion`] for an unknown version,
+    /// like one newer than [`SCHEMA_VERSION`], which this crate can't read
+    /// without losing fields.
+    pub fn from_persist_bytes(bytes: &[u8]) -> Result<Self> {
+        let Envelope {
+            schema_version,
+            mut request,
+        } = serde_json::from_slice::<Envelope<Value>>(bytes)?;
+
+        if !(1..=SCHEMA_VERSION).contains(&schema_version) {
+            return Err(Error::UnsupportedSchemaVersion(schema_version));
+        }
+        for migrate in &MIGRATIONS[schema_version as usize - 1..] {
+            migrate(&mut request)?;
+        }
+
+        Ok(Self {
+            schema_version,
+            request: serde_json::from_value(request)?,
+        })
+    }
+}
+
+impl From<Request<Value>> for VersionedRequest {
+    fn from(request: Request<Value>) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            request,
+        }
+    }
+}
+
+/// Migrates an `apns-expiration` header in UNIX epoch seconds to an
+/// [`Expiration`].
+fn migrate_v1_expiration(request: &mut Value) -> Result<()> {
+    let expiration = match request.get_mut("expiration") {
+        Some(expiration) => expiration,
+        None => return Ok(()),
+    };
+    let timestamp = match expiration.as_i64() {
+        Some(timestamp) => timestamp,
+        None if expiration.is_null() => return Ok(()),
+        None => {
+            return Err(Error::InvalidRequest(format!(
+                "invalid version 1 expiration {expiration}"
+            )))
+        }
+    };
+
+    let migrated = if timestamp == 0 {
+        Expiration::Immediate
+    } else {
+        OffsetDateTime::from_unix_timestamp(timestamp)
+            .map(Expiration::At)
+            .map_err(|err| {
+                Error::InvalidRequest(format!("invalid version 1 expiration {timestamp}: {err}"))
+            })?
+    };
+    *expiration = serde_json::to_value(migrated)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn migrate_expiration() {
+        let cases = [
+            (json!({}), json!({})),
+            (json!({ "expiration": null }), json!({ "expiration": null })),
+            (
+                json!({ "expiration": 0 }),
+                json!({ "expiration": "never-store" }),
+            ),
+            (
+                json!({ "expiration": 1685952000 }),
+                json!({ "expiration": "2023-06-05T08:00:00Z" }),
+            ),
+        ];
+        for (mut request, migrated) in cases {
+            migrate_v1_expiration(&mut request).unwrap();
+            assert_eq!(request, migrated);
+        }
+
+        for expiration in [json!("2023-06-05T08:00:00Z"), json!(1.5), json!(i64::MAX)] {
+            let mut request = json!({ "expiration": expiration });
+            assert!(
+                matches!(
+                    migrate_v1_expiration(&mut request),
+                    Err(Error::InvalidRequest(_))
+                ),
+                "{expiration}"
+            );
+        }
+    }
+
+    #[test]
+    fn from_persist_bytes_unsupported() {
+        for schema_version in [0, SCHEMA_VERSION + 1] {
+            let bytes = serde_json::to_vec(&json!({
+                "schema-version": schema_version,
+                "request": { "device-token": "a863a50a" },
+            }))
+            .unwrap();
+            assert!(matches!(
+                VersionedRequest::from_persist_bytes(&bytes),
+                Err(Error::UnsupportedSchemaVersion(version)) if version == schema_version
+            ));
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! Requests for the example payloads in Apple's documentation.
+//!
+//! Each preset returns a complete [`Request`] for one of the payloads in
+//! [Generating a remote notification](https://developer.apple.com/documentation/usernotifications/setting_up_a_remote_notification_server/generating_a_remote_notification)
+//! and [Pushing background updates to your App](https://developer.apple.com/documentation/usernotifications/setting_up_a_remote_notification_server/pushing_background_updates_to_your_app).
+//! Set the topic and any other fields on the returned request before sending
+//! it.
+
+use serde_json::Value;
+use time::OffsetDateTime;
+
+use crate::header::PushType;
+use crate::payload::{Alert, Event};
+use crate::request::Request;
+
+/// An alert with a title and a body.
+///
+/// ```json
+/// { "aps": { "alert": { "title": "Game Request", "body": "Bob wants to play poker" } } }
+/// ```
+pub fn simple_alert(
+    device_token: impl Into<String>,
+    title: impl Into<String>,
+    body: impl Into<String>,
+) -> Request<Value> {
+    Request {
+        device_token: device_token.into().into(),
+        alert: Some(Alert {
+            title: Some(title.into()),
+            body: Some(body.into()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// An alert whose text the device looks up in the app's `Localizable.strings`
+/// with `loc_key` and formats with `loc_args`.
+///
+/// ```json
+/// { "aps": { "alert": { "loc-key": "GAME_PLAY_REQUEST_FORMAT", "loc-args": ["Jenna", "Frank"] } } }
+/// ```
+pub fn localized_alert<I>(
+    device_token: impl Into<String>,
+    loc_key: impl Into<String>,
+    loc_args: I,
+) -> Request<Value>
+where
+    I: IntoIterator,
+    I::Item: Into<String>,
+{
+    Request {
+        device_token: device_token.into().into(),
+        alert: Some(Alert {
+            loc_key: Some(loc_key.into()),
+            loc_args: Some(loc_args.into_iter().map(Into::into).collect()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// A critical alert, which plays `sound_name` at `volume` even when the
+/// device is muted. The app needs the critical alerts entitlement.
+///
+/// ```json
+/// {
+///   "aps": {
+///     "alert": { "title": "Severe Weather Alert", "body": "Seek shelter now" },
+///     "sound": { "critical": 1, "name": "default", "volume": 1.0 },
+///     "interruption-level": "critical"
+///   }
+/// }
+/// ```
+pub fn critical_alert(
+    device_token: impl Into<String>,
+    title: impl Into<String>,
+    body: impl Into<String>,
+    sound_name: &str,
+    volume: f64,
+) -> Request<Value> {
+    let mut request = simple_alert(device_token, title, body);
+    request.critical(sound_name, volume);
+    request
+}
+
+/// A background update that wakes the app to process `user_info`, sent with
+/// [`PushType::Background`] at priority 5.
+///
+/// ```json
+/// { "aps": { "content-available": 1 }, "acme1": "bar", "acme2": 42 }
+/// ```
+pub fn background_update(device_token: impl Into<String>, user_info: Value) -> Request<Value> {
+    Request {
+        device_token: device_token.into().into(),
+        push_type: PushType::Background,
+        content_available: true,
+        user_info: Some(user_info),
+        ..Default::default()
+    }
+}
+
+/// An update to a Live Activity's `content_state` as of `timestamp`. The
+/// `.push-type.liveactivity` suffix is added to the topic when it's sent.
+///
+/// ```json
+/// {
+///   "aps": {
+///     "timestamp": 1685952000,
+///     "event": "update",
+///     "content-state": { "currentHealthLevel": 0.941, "eventDescription": "Power Panda found a sword!" }
+///   }
+/// }
+/// ```
+pub fn live_activity_update(
+    device_token: impl Into<String>,
+    timestamp: OffsetDateTime,
+    content_state: Value,
+) -> Request<Value> {
+    Request {
+        device_token: device_token.into().into(),
+        push_type: PushType::Liveactivity,
+        timestamp: Some(timestamp),
+        event: Some(Event::Update),
+        content_state: Some(content_state),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use http::HeaderMap;
+    use serde_json::json;
+
+    use super::*;
+    use crate::header::Priority;
+    use crate::payload::{InterruptionLevel, Payload};
+
+    const DEVICE_TOKEN: &str = "a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae";
+
+    fn payload(request: Request<Value>) -> Value {
+        assert_eq!(request.validate(), Ok(()));
+        let (_, payload): (HeaderMap, Payload<Value>) = request.try_into().unwrap();
+        serde_json::to_value(payload).unwrap()
+    }
+
+    #[test]
+    fn simple_alert() {
+        let request = super::simple_alert(DEVICE_TOKEN, "Game Request", "Bob wants to play poker");
+        assert_eq!(request.push_type, PushType::Alert);
+        assert_eq!(
+            payload(request),
+            json!({
+                "aps": {
+                    "alert": { "title": "Game Request", "body": "Bob wants to play poker" },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn localized_alert() {
+        let request =
+            super::localized_alert(DEVICE_TOKEN, "GAME_PLAY_REQUEST_FORMAT", ["Jenna", "Frank"]);
+        assert_eq!(
+            payload(request),
+            json!({
+                "aps": {
+                    "alert": {
+                        "loc-key": "GAME_PLAY_REQUEST_FORMAT",
+                        "loc-args": ["Jenna", "Frank"],
+                    },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn critical_alert() {
+        let request = super::critical_alert(
+            DEVICE_TOKEN,
+            "Severe Weather Alert",
+            "Seek shelter now",
+            "default",
+            1.,
+        );
+        assert_eq!(
+            request.interruption_level,
+            Some(InterruptionLevel::Critical)
+        );
+        assert_eq!(
+            payload(request),
+            json!({
+                "aps": {
+                    "alert": { "title": "Severe Weather Alert", "body": "Seek shelter now" },
+                    "sound": { "critical": 1, "name": "default", "volume": 1.0 },
+                    "interruption-level": "critical",
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn background_update() {
+        let request =
+            super::background_update(DEVICE_TOKEN, json!({ "acme1": "bar", "acme2": 42 }));
+        assert_eq!(request.effective_priority(), Priority::ConsiderPower);
+        assert_eq!(
+            payload(request),
+            json!({
+                "aps": { "content-available": 1 },
+                "acme1": "bar",
+                "acme2": 42,
+            })
+        );
+    }
+
+    #[test]
+    fn live_activity_update() {
+        let content_state = json!({
+            "currentHealthLevel": 0.941,
+            "eventDescription": "Power Panda found a sword!",
+        });
+        let request = super::live_activity_update(
+            DEVICE_TOKEN,
+            OffsetDateTime::from_unix_timestamp(1685952000).unwrap(),
+            content_state.clone(),
+        );
+        assert_eq!(
+            payload(request),
+            json!({
+                "aps": {
+                    "timestamp": 1685952000,
+                    "event": "update",
+                    "content-state": content_state,
+                },
+            })
+        );
+    }
+}
@@ -1,4 +1,7 @@
-use http::StatusCode;
+use std::fmt;
+use std::time::Duration;
+
+use http::{header, HeaderMap, HeaderValue, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, skip_serializing_none, TimestampMilliSeconds};
 use time::OffsetDateTime;
@@ -6,7 +9,7 @@ use time::OffsetDateTime;
 /// APNS error response reason JSON body.
 #[serde_as]
 #[skip_serializing_none]
-#[derive(thiserror::Error, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(tag = "reason")]
 pub enum Reason {
     #[error("The collapse identifier exceeds the maximum allowed size.")]
@@ -51,23 +54,23 @@ pub enum Reason {
     #[error("Pushing to this topic is not allowed.")]
     TopicDisallowed,
 
+    #[error("The apns-channel-id value is invalid.")]
+    BadChannelId,
+
+    #[error("The message-storage-policy value is invalid.")]
+    BadMessageStoragePolicy,
+
+    #[error("The apns-channel-id header is missing.")]
+    MissingChannelId,
+
+    #[error("The channel isn’t registered for the bundle ID.")]
+    ChannelNotRegistered,
+
     #[error("The certificate is invalid.")]
-    BadCertificate {
-        /// The time, in milliseconds since Epoch, at which APNs confirmed the token
-        /// was no longer valid for the topic. This key is included only when the
-        /// error in the `:status` field is 410.
-        #[serde_as(as = "Option<TimestampMilliSeconds>")]
-        timestamp: Option<OffsetDateTime>,
-    },
+    BadCertificate,
 
     #[error("The client certificate is for the wrong environment.")]
-    BadCertificateEnvironment {
-        /// The time, in milliseconds since Epoch, at which APNs confirmed the token
-        /// was no longer valid for the topic. This key is included only when the
-        /// error in the `:status` field is 410.
-        #[serde_as(as = "Option<TimestampMilliSeconds>")]
-        timestamp: Option<OffsetDateTime>,
-    },
+    BadCertificateEnvironment,
 
     #[error("The provider token is stale and a new token should be generated.")]
     ExpiredProviderToken,
@@ -75,6 +78,9 @@ pub enum Reason {
     #[error("The specified action is not allowed.")]
     Forbidden,
 
+    #[error("The maximum number of channels for the bundle ID has been reached.")]
+    CannotCreateChannelConfig,
+
     #[error("The provider token is not valid, or the token signature can't be verified.")]
     InvalidProviderToken,
 
@@ -88,10 +94,20 @@ pub enum Reason {
     MethodNotAllowed,
 
     #[error("The device token has expired.")]
-    ExpiredToken,
+    ExpiredToken {
+        /// The time, in milliseconds since Epoch, at which APNs confirmed the
+        /// token was no longer valid for the topic.
+        #[serde_as(as = "Option<TimestampMilliSeconds>")]
+        timestamp: Option<OffsetDateTime>,
+    },
 
     #[error("The device token is inactive for the specified topic. There is no need to send further pushes to the same device token, unless your application retrieves the same device token, see Registering Your App with APNs")]
-    Unregistered,
+    Unregistered {
+        /// The time, in milliseconds since Epoch, at which APNs confirmed the
+        /// token was no longer valid for the topic.
+        #[serde_as(as = "Option<TimestampMilliSeconds>")]
+        timestamp: Option<OffsetDateTime>,
+    },
 
     #[error("The message payload is too large. For information about the allowed payload size, see Create and Send a POST Request to APNs.")]
     PayloadTooLarge,
@@ -116,6 +132,59 @@ pub enum Reason {
     Unknown,
 }
 
+impl Reason {
+    /// Deserializes a [`Reason`] from an APNs error response body.
+    ///
+    /// Fields other than `reason` and `timestamp` are ignored. A leading UTF-8
+    /// byte order mark and surrounding whitespace are allowed.
+    pub fn from_body(body: &[u8]) -> serde_json::Result<Self> {
+        let body = body.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(body);
+        serde_json::from_slice(body)
+    }
+
+    /// Returns the reason string used in the APNs error response body.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Reason::BadCollapseId => "BadCollapseId",
+            Reason::BadDeviceToken => "BadDeviceToken",
+            Reason::BadExpirationDate => "BadExpirationDate",
+            Reason::BadMessageId => "BadMessageId",
+            Reason::BadPriority => "BadPriority",
+            Reason::BadTopic => "BadTopic",
+            Reason::DeviceTokenNotForTopic => "DeviceTokenNotForTopic",
+            Reason::DuplicateHeaders => "DuplicateHeaders",
+            Reason::IdleTimeout => "IdleTimeout",
+            Reason::InvalidPushType => "InvalidPushType",
+            Reason::MissingDeviceToken => "MissingDeviceToken",
+            Reason::MissingTopic => "MissingTopic",
+            Reason::PayloadEmpty => "PayloadEmpty",
+            Reason::TopicDisallowed => "TopicDisallowed",
+            Reason::BadChannelId => "BadChannelId",
+            Reason::BadMessageStoragePolicy => "BadMessageStoragePolicy",
+            Reason::MissingChannelId => "MissingChannelId",
+            Reason::ChannelNotRegistered => "ChannelNotRegistered",
+            Reason::BadCertificate => "BadCertificate",
+            Reason::BadCertificateEnvironment => "BadCertificateEnvironment",
+            Reason::ExpiredProviderToken => "ExpiredProviderToken",
+            Reason::Forbidden => "Forbidden",
+            Reason::CannotCreateChannelConfig => "CannotCreateChannelConfig",
+            Reason::InvalidProviderToken => "InvalidProviderToken",
+            Reason::MissingProviderToken => "MissingProviderToken",
+            Reason::BadPath => "BadPath",
+            Reason::MethodNotAllowed => "MethodNotAllowed",
+            Reason::ExpiredToken { .. } => "ExpiredToken",
+            Reason::Unregistered { .. } => "Unregistered",
+            Reason::PayloadTooLarge => "PayloadTooLarge",
+            Reason::TooManyProviderTokenUpdates => "TooManyProviderTokenUpdates",
+            Reason::TooManyRequests => "TooManyRequests",
+            Reason::InternalServerError => "InternalServerError",
+            Reason::ServiceUnavailable => "ServiceUnavailable",
+            Reason::Shutdown => "Shutdown",
+            Reason::Unknown => "Unknown",
+        }
+    }
+}
+
 impl From<Reason> for StatusCode {
     fn from(this: Reason) -> Self {
         match this {
@@ -133,16 +202,21 @@ impl From<Reason> for StatusCode {
             Reason::MissingTopic => StatusCode::BAD_REQUEST,
             Reason::PayloadEmpty => StatusCode::BAD_REQUEST,
             Reason::TopicDisallowed => StatusCode::BAD_REQUEST,
-            Reason::BadCertificate { .. } => StatusCode::FORBIDDEN,
-            Reason::BadCertificateEnvironment { .. } => StatusCode::FORBIDDEN,
+            Reason::BadChannelId => StatusCode::BAD_REQUEST,
+            Reason::BadMessageStoragePolicy => StatusCode::BAD_REQUEST,
+            Reason::MissingChannelId => StatusCode::BAD_REQUEST,
+            Reason::ChannelNotRegistered => StatusCode::NOT_FOUND,
+            Reason::BadCertificate => StatusCode::FORBIDDEN,
+            Reason::BadCertificateEnvironment => StatusCode::FORBIDDEN,
             Reason::ExpiredProviderToken => StatusCode::FORBIDDEN,
             Reason::Forbidden => StatusCode::FORBIDDEN,
+            Reason::CannotCreateChannelConfig => StatusCode::FORBIDDEN,
             Reason::InvalidProviderToken => StatusCode::FORBIDDEN,
             Reason::MissingProviderToken => StatusCode::FORBIDDEN,
             Reason::BadPath => StatusCode::NOT_FOUND,
             Reason::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
-            Reason::ExpiredToken => StatusCode::GONE,
-            Reason::Unregistered => StatusCode::GONE,
+            Reason::ExpiredToken { .. } => StatusCode::GONE,
+            Reason::Unregistered { .. } => StatusCode::GONE,
             Reason::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
             Reason::TooManyProviderTokenUpdates => StatusCode::TOO_MANY_REQUESTS,
             Reason::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
@@ -153,3 +227,551 @@ impl From<Reason> for StatusCode {
         }
     }
 }
+
+/// What APNs throttled with a [`Reason::TooManyRequests`] response, see
+/// [`ThrottleInfo`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThrottleScope {
+    /// Too many requests were sent to the same device token. Other device
+    /// tokens are unaffected.
+    Device,
+
+    /// APNs is pushing back on the provider as a whole.
+    Provider,
+
+    /// The response doesn't tell, e.g. because it came with an unexpected
+    /// status.
+    Unknown,
+}
+
+impl ThrottleScope {
+    /// Returns `true` if the throttling may affect requests to other device
+    /// tokens too, so that senders should slow down as a whole.
+    pub fn is_global(self) -> bool {
+        !matches!(self, Self::Device)
+    }
+}
+
+impl fmt::Display for ThrottleScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Device => "device",
+            Self::Provider => "provider",
+            Self::Unknown => "unknown",
+        })
+    }
+}
+
+/// Context of a [`Reason::TooManyRequests`] response, attached to the error
+/// as [`Error::Throttled`](crate::Error::Throttled).
+///
+/// APNs documents `TooManyRequests` only for requests to the same device
+/// token, but it occasionally uses it to push back on a provider. The
+/// [`scope`](Self::scope) is a best-effort guess from the response:
+///
+/// - A `Retry-After` header or `Connection: close` means
+///   [`ThrottleScope::Provider`], as APNs sends neither when throttling one
+///   device token.
+/// - A status other than `429 Too Many Requests`, e.g. from a proxy, means
+///   [`ThrottleScope::Unknown`].
+/// - Otherwise, the scope is [`ThrottleScope::Device`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ThrottleInfo {
+    /// The inferred scope of the throttling.
+    pub scope: ThrottleScope,
+
+    /// The `Retry-After` header in seconds, if any. HTTP dates are not
+    /// parsed but still count towards the provider scope.
+    pub retry_after: Option<Duration>,
+}
+
+impl ThrottleInfo {
+    /// Infers the throttling context from the status and headers of a
+    /// [`Reason::TooManyRequests`] response.
+    pub fn from_response(status: StatusCode, headers: &HeaderMap) -> Self {
+        let retry_after = headers.get(header::RETRY_AFTER);
+        let connection_close = matches!(
+            headers.get(header::CONNECTION).map(HeaderValue::to_str),
+            Some(Ok(value)) if value.eq_ignore_ascii_case("close")
+        );
+
+        let scope = if retry_after.is_some() || connection_close {
+            ThrottleScope::Provider
+        } else if status != StatusCode::TOO_MANY_REQUESTS {
+            ThrottleScope::Unknown
+        } else {
+            ThrottleScope::Device
+        };
+
+        Self {
+            scope,
+            retry_after: retry_after
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse().ok())
+                .map(Duration::from_secs),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Every reason with its name and status. Adding a variant fails to
+    /// compile until it's listed here, so that it's covered by the tests
+    /// below.
+    fn all_reasons() -> Vec<(Reason, &'static str, StatusCode)> {
+        let timestamp = Some(OffsetDateTime::from_unix_timestamp(1454402113).unwrap());
+        let reasons = vec![
+            (
+                Reason::BadCollapseId,
+                "BadCollapseId",
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                Reason::BadDeviceToken,
+                "BadDeviceToken",
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                Reason::BadExpirationDate,
+                "BadExpirationDate",
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                Reason::BadMessageId,
+                "BadMessageId",
+                StatusCode::BAD_REQUEST,
+            ),
+            (Reason::BadPriority, "BadPriority", StatusCode::BAD_REQUEST),
+            (Reason::BadTopic, "BadTopic", StatusCode::BAD_REQUEST),
+            (
+                Reason::DeviceTokenNotForTopic,
+                "DeviceTokenNotForTopic",
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                Reason::DuplicateHeaders,
+                "DuplicateHeaders",
+                StatusCode::BAD_REQUEST,
+            ),
+            (Reason::IdleTimeout, "IdleTimeout", StatusCode::BAD_REQUEST),
+            (
+                Reason::InvalidPushType,
+                "InvalidPushType",
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                Reason::MissingDeviceToken,
+                "MissingDeviceToken",
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                Reason::MissingTopic,
+                "MissingTopic",
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                Reason::PayloadEmpty,
+                "PayloadEmpty",
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                Reason::TopicDisallowed,
+                "TopicDisallowed",
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                Reason::BadChannelId,
+                "BadChannelId",
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                Reason::BadMessageStoragePolicy,
+                "BadMessageStoragePolicy",
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                Reason::MissingChannelId,
+                "MissingChannelId",
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                Reason::ChannelNotRegistered,
+                "ChannelNotRegistered",
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                Reason::BadCertificate,
+                "BadCertificate",
+                StatusCode::FORBIDDEN,
+            ),
+            (
+                Reason::BadCertificateEnvironment,
+                "BadCertificateEnvironment",
+                StatusCode::FORBIDDEN,
+            ),
+            (
+                Reason::ExpiredProviderToken,
+                "ExpiredProviderToken",
+                StatusCode::FORBIDDEN,
+            ),
+            (Reason::Forbidden, "Forbidden", StatusCode::FORBIDDEN),
+            (
+                Reason::CannotCreateChannelConfig,
+                "CannotCreateChannelConfig",
+                StatusCode::FORBIDDEN,
+            ),
+            (
+                Reason::InvalidProviderToken,
+                "InvalidProviderToken",
+                StatusCode::FORBIDDEN,
+            ),
+            (
+                Reason::MissingProviderToken,
+                "MissingProviderToken",
+                StatusCode::FORBIDDEN,
+            ),
+            (Reason::BadPath, "BadPath", StatusCode::NOT_FOUND),
+            (
+                Reason::MethodNotAllowed,
+                "MethodNotAllowed",
+                StatusCode::METHOD_NOT_ALLOWED,
+            ),
+            (
+                Reason::ExpiredToken { timestamp: None },
+                "ExpiredToken",
+                StatusCode::GONE,
+            ),
+            (
+                Reason::ExpiredToken { timestamp },
+                "ExpiredToken",
+                StatusCode::GONE,
+            ),
+            (
+                Reason::Unregistered { timestamp: None },
+                "Unregistered",
+                StatusCode::GONE,
+            ),
+            (
+                Reason::Unregistered { timestamp },
+                "Unregistered",
+                StatusCode::GONE,
+            ),
+            (
+                Reason::PayloadTooLarge,
+                "PayloadTooLarge",
+                StatusCode::PAYLOAD_TOO_LARGE,
+            ),
+            (
+                Reason::TooManyProviderTokenUpdates,
+                "TooManyProviderTokenUpdates",
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
+            (
+                Reason::TooManyRequests,
+                "TooManyRequests",
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
+            (
+                Reason::InternalServerError,
+                "InternalServerError",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                Reason::ServiceUnavailable,
+                "ServiceUnavailable",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (
+                Reason::Shutdown,
+                "Shutdown",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (
+                Reason::Unknown,
+                "Unknown",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        ];
+
+        for (reason, _, _) in &reasons {
+            match reason {
+                Reason::BadCollapseId
+                | Reason::BadDeviceToken
+                | Reason::BadExpirationDate
+                | Reason::BadMessageId
+                | Reason::BadPriority
+                | Reason::BadTopic
+                | Reason::DeviceTokenNotForTopic
+                | Reason::DuplicateHeaders
+                | Reason::IdleTimeout
+                | Reason::InvalidPushType
+                | Reason::MissingDeviceToken
+                | Reason::MissingTopic
+                | Reason::PayloadEmpty
+                | Reason::TopicDisallowed
+                | Reason::BadChannelId
+                | Reason::BadMessageStoragePolicy
+                | Reason::MissingChannelId
+                | Reason::ChannelNotRegistered
+                | Reason::BadCertificate
+                | Reason::BadCertificateEnvironment
+                | Reason::ExpiredProviderToken
+                | Reason::Forbidden
+                | Reason::CannotCreateChannelConfig
+                | Reason::InvalidProviderToken
+                | Reason::MissingProviderToken
+                | Reason::BadPath
+                | Reason::MethodNotAllowed
+                | Reason::ExpiredToken { .. }
+                | Reason::Unregistered { .. }
+                | Reason::PayloadTooLarge
+                | Reason::TooManyProviderTokenUpdates
+                | Reason::TooManyRequests
+                | Reason::InternalServerError
+                | Reason::ServiceUnavailable
+                | Reason::Shutdown
+                | Reason::Unknown => {}
+            }
+        }
+        reasons
+    }
+
+    #[test]
+    fn reason_all() {
+        for (reason, name, status) in all_reasons() {
+            assert_eq!(reason.as_str(), name);
+            assert_eq!(StatusCode::from(reason.clone()), status, "{name}");
+            assert!(!reason.to_string().is_empty(), "{name}");
+
+            // Reasons round-trip through the error response body.
+            let body = serde_json::to_vec(&reason).unwrap();
+            let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(value["reason"], name);
+            assert_eq!(Reason::from_body(&body).unwrap(), reason, "{name}");
+        }
+    }
+
+    #[test]
+    fn reason_de() {
+        assert_eq!(
+            serde_json::from_value::<Reason>(json!({
+                "reason": "BadDeviceToken",
+            }))
+            .unwrap(),
+            Reason::BadDeviceToken
+        );
+        assert_eq!(
+            serde_json::from_value::<Reason>(json!({
+                "reason": "Unregistered",
+                "timestamp": 1454402113000_i64,
+            }))
+            .unwrap(),
+            Reason::Unregistered {
+                timestamp: Some(OffsetDateTime::from_unix_timestamp(1454402113).unwrap()),
+            }
+        );
+        assert_eq!(
+            serde_json::from_value::<Reason>(json!({
+                "reason": "ExpiredToken",
+                "timestamp": 1454402113000_i64,
+            }))
+            .unwrap(),
+            Reason::ExpiredToken {
+                timestamp: Some(OffsetDateTime::from_unix_timestamp(1454402113).unwrap()),
+            }
+        );
+        assert_eq!(
+            serde_json::from_value::<Reason>(json!({
+                "reason": "Unregistered",
+            }))
+            .unwrap(),
+            Reason::Unregistered { timestamp: None }
+        );
+        assert_eq!(
+            serde_json::from_value::<Reason>(json!({
+                "reason": "NotARealReason",
+            }))
+            .unwrap(),
+            Reason::Unknown
+        );
+    }
+
+    #[test]
+    fn reason_de_ignores_stray_timestamp() {
+        assert_eq!(
+            serde_json::from_value::<Reason>(json!({
+                "reason": "BadCertificate",
+                "timestamp": 1454402113000_i64,
+            }))
+            .unwrap(),
+            Reason::BadCertificate
+        );
+        assert_eq!(
+            serde_json::from_value::<Reason>(json!({
+                "reason": "BadCertificateEnvironment",
+                "timestamp": 1454402113000_i64,
+            }))
+            .unwrap(),
+            Reason::BadCertificateEnvironment
+        );
+    }
+
+    #[test]
+    fn reason_de_ignores_extra_fields() {
+        for reason in [
+            "BadCollapseId",
+            "BadDeviceToken",
+            "BadExpirationDate",
+            "BadMessageId",
+            "BadPriority",
+            "BadTopic",
+            "DeviceTokenNotForTopic",
+            "DuplicateHeaders",
+            "IdleTimeout",
+            "InvalidPushType",
+            "MissingDeviceToken",
+            "MissingTopic",
+            "PayloadEmpty",
+            "TopicDisallowed",
+            "BadChannelId",
+            "BadMessageStoragePolicy",
+            "MissingChannelId",
+            "ChannelNotRegistered",
+            "BadCertificate",
+            "BadCertificateEnvironment",
+            "ExpiredProviderToken",
+            "Forbidden",
+            "CannotCreateChannelConfig",
+            "InvalidProviderToken",
+            "MissingProviderToken",
+            "BadPath",
+            "MethodNotAllowed",
+            "ExpiredToken",
+            "Unregistered",
+            "PayloadTooLarge",
+            "TooManyProviderTokenUpdates",
+            "TooManyRequests",
+            "InternalServerError",
+            "ServiceUnavailable",
+            "Shutdown",
+        ] {
+            let parsed = serde_json::from_value::<Reason>(json!({
+                "apns-id": "4d947500-498e-4524-8aa8-7220c4e65d75",
+                "reason": reason,
+                "extra": 1,
+                "nested": { "foo": ["bar"] },
+            }))
+            .unwrap();
+            assert_eq!(parsed.as_str(), reason);
+        }
+    }
+
+    #[test]
+    fn reason_from_body() {
+        assert_eq!(
+            Reason::from_body(br#"{"reason":"BadDeviceToken","apns-id":"4d947500-498e-4524-8aa8-7220c4e65d75","extra":1}"#)
+                .unwrap(),
+            Reason::BadDeviceToken
+        );
+        assert_eq!(
+            Reason::from_body(b"\xEF\xBB\xBF{\"reason\":\"BadTopic\"}").unwrap(),
+            Reason::BadTopic
+        );
+        assert_eq!(
+            Reason::from_body(b" {\"reason\":\"BadTopic\"}\r\n").unwrap(),
+            Reason::BadTopic
+        );
+        assert!(Reason::from_body(b"").is_err());
+        assert!(Reason::from_body(b"Bad Request").is_err());
+    }
+
+    #[test]
+    fn reason_ser() {
+        assert_eq!(
+            serde_json::to_value(Reason::Unregistered {
+                timestamp: Some(OffsetDateTime::from_unix_timestamp(1454402113).unwrap()),
+            })
+            .unwrap(),
+            json!({
+                "reason": "Unregistered",
+                "timestamp": 1454402113000_i64,
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(Reason::Unregistered { timestamp: None }).unwrap(),
+            json!({
+                "reason": "Unregistered",
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(Reason::BadCertificate).unwrap(),
+            json!({
+                "reason": "BadCertificate",
+            })
+        );
+    }
+
+    #[test]
+    fn throttle_info_from_response() {
+        let headers = |pairs: &[(header::HeaderName, &'static str)]| {
+            pairs
+                .iter()
+                .map(|(name, value)| (name.clone(), value.parse().unwrap()))
+                .collect::<HeaderMap>()
+        };
+
+        let cases = [
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                headers(&[]),
+                ThrottleScope::Device,
+                None,
+            ),
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                headers(&[(header::RETRY_AFTER, "30")]),
+                ThrottleScope::Provider,
+                Some(Duration::from_secs(30)),
+            ),
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                headers(&[(header::RETRY_AFTER, "Wed, 21 Oct 2015 07:28:00 GMT")]),
+                ThrottleScope::Provider,
+                None,
+            ),
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                headers(&[(header::CONNECTION, "Close")]),
+                ThrottleScope::Provider,
+                None,
+            ),
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                headers(&[]),
+                ThrottleScope::Unknown,
+                None,
+            ),
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                headers(&[(header::RETRY_AFTER, "5")]),
+                ThrottleScope::Provider,
+                Some(Duration::from_secs(5)),
+            ),
+        ];
+        for (status, headers, scope, retry_after) in cases {
+            assert_eq!(
+                ThrottleInfo::from_response(status, &headers),
+                ThrottleInfo { scope, retry_after },
+                "{status} {headers:?}"
+            );
+        }
+
+        assert!(!ThrottleScope::Device.is_global());
+        assert!(ThrottleScope::Provider.is_global());
+        assert!(ThrottleScope::Unknown.is_global());
+    }
+}
@@ -0,0 +1,155 @@
+//! Redacted JSON for logging requests and payloads.
+//!
+//! [`Request::to_redacted_json`] and [`Payload::to_redacted_json`] serialize
+//! with the same [`Serialize`](serde::Serialize) impls used to send, so the
+//! logged shape always matches the wire shape, and then mask the parts that
+//! may identify a user.
+//!
+//! [`Request::to_redacted_json`]: crate::Request::to_redacted_json
+//! [`Payload::to_redacted_json`]: crate::payload::Payload::to_redacted_json
+
+use serde_json::{Map, Value};
+
+/// How to redact an app-defined value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ValueRedaction {
+    /// Keeps the value as is.
+    Keep,
+
+    /// Keeps object keys and array lengths and replaces every other value
+    /// with a placeholder naming its type, like `"<string>"`.
+    #[default]
+    MaskValues,
+
+    /// Omits the value.
+    Omit,
+}
+
+/// What [`Request::to_redacted_json`] and [`Payload::to_redacted_json`]
+/// redact. The [`Default`] masks the device token and the values of the
+/// app-defined fields.
+///
+/// [`Request::to_redacted_json`]: crate::Request::to_redacted_json
+/// [`Payload::to_redacted_json`]: crate::payload::Payload::to_redacted_json
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Redaction {
+    /// Redacts all but the first and last four characters of the device
+    /// token. Defaults to `true`.
+    pub mask_device_token: bool,
+
+    /// Redaction of the user info. In a payload, that's every key next to
+    /// `aps`.
+    pub user_info: ValueRedaction,
+
+    /// Redaction of the Live Activity content state.
+    pub content_state: ValueRedaction,
+}
+
+impl Default for Redaction {
+    fn default() -> Self {
+        Self {
+            mask_device_token: true,
+            user_info: ValueRedaction::MaskValues,
+            content_state: ValueRedaction::MaskValues,
+        }
+    }
+}
+
+impl Redaction {
+    /// Redacts a serialized [`Request`](crate::Request).
+    pub(crate) fn redact_request(&self, value: &mut Value) {
+        let map = match value.as_object_mut() {
+            Some(map) => map,
+            None => return,
+        };
+        if self.mask_device_token {
+            if let Some(Value::String(device_token)) = map.get_mut("device-token") {
+                *device_token = crate::request::redact_device_token(device_token);
+            }
+        }
+        redact_entry(map, "user-info", self.user_info);
+        redact_entry(map, "content-state", self.content_state);
+    }
+
+    /// Redacts a serialized [`Payload`](crate::payload::Payload).
+    pub(crate) fn redact_payload(&self, value: &mut Value) {
+        let map = match value.as_object_mut() {
+            Some(map) => map,
+            None => return,
+        };
+        let user_info = map
+            .keys()
+            .filter(|key| *key != "aps")
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in user_info {
+            redact_entry(map, &key, self.user_info);
+        }
+        if let Some(Value::Object(aps)) = map.get_mut("aps") {
+            redact_entry(aps, "content-state", self.content_state);
+        }
+    }
+}
+
+fn redact_entry(map: &mut Map<String, Value>, key: &str, redaction: ValueRedaction) {
+    match redaction {
+        ValueRedaction::Keep => {}
+        ValueRedaction::MaskValues => {
+            if let Some(value) = map.get_mut(key) {
+                mask(value);
+            }
+        }
+        ValueRedaction::Omit => {
+            map.remove(key);
+        }
+    }
+}
+
+/// Replaces every value but objects and arrays with a placeholder naming
+/// its type.
+pub(crate) fn mask(value: &mut Value) {
+    let placeholder = match value {
+        Value::Object(map) => {
+            map.values_mut().for_each(mask);
+            return;
+        }
+        Value::Array(values) => {
+            values.iter_mut().for_each(mask);
+            return;
+        }
+        Value::Null => "<null>",
+        Value::Bool(_) => "<bool>",
+        Value::Number(_) => "<number>",
+        Value::String(_) => "<string>",
+    };
+    *value = Value::String(placeholder.into());
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn mask_values() {
+        let mut value = json!({
+            "a": null,
+            "b": true,
+            "c": 1.5,
+            "d": "secret",
+            "e": [1, { "f": "secret" }],
+        });
+        mask(&mut value);
+        assert_eq!(
+            value,
+            json!({
+                "a": "<null>",
+                "b": "<bool>",
+                "c": "<number>",
+                "d": "<string>",
+                "e": ["<number>", { "f": "<string>" }],
+            })
+        );
+    }
+}
@@ -0,0 +1,454 @@
+//! Building blocks for an HTTP endpoint that relays push notifications.
+//!
+//! [`PushRequestDto`] is the JSON body such an endpoint accepts, and
+//! [`error_response`] turns any [`Error`] into a status code and JSON body to
+//! respond with. Neither depends on a web framework.
+//!
+//! ```rust
+//! use apple_apns::relay::{error_response, PushRequestDto};
+//! use apple_apns::Request;
+//!
+//! let dto: PushRequestDto = serde_json::from_str(r#"{
+//!     "device_token": "a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae",
+//!     "topic": "com.example.myapp",
+//!     "alert": { "title": "Hello", "body": "World" }
+//! }"#).unwrap();
+//!
+//! match Request::try_from(dto) {
+//!     Ok(request) => { /* client.post(request).await */ }
+//!     Err(err) => {
+//!         let (status, body) = error_response(&err);
+//!         /* respond with status and body */
+//!     }
+//! }
+//! ```
+
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::header::{Priority, PushType};
+use crate::payload::{Alert, Badge, InterruptionLevel, Sound};
+use crate::request::{Request, ValidationError};
+use crate::result::Error;
+
+/// JSON body of a push relay request, mirroring [`Request<Value>`].
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PushRequestDto {
+    /// See [`Request::device_token`].
+    pub device_token: String,
+
+    /// See [`Request::push_type`].
+    pub push_type: PushType,
+
+    /// See [`Request::id`].
+    pub id: Option<Uuid>,
+
+    /// See [`Request::expiration`]. An RFC 3339 timestamp.
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+
+    /// See [`Request::priority`].
+    pub priority: Option<Priority>,
+
+    /// See [`Request::topic`].
+    pub topic: Option<String>,
+
+    /// See [`Request::collapse_id`].
+    pub collapse_id: Option<String>,
+
+    /// See [`Request::alert`].
+    pub alert: Option<AlertDto>,
+
+    /// See [`Request::badge`].
+    pub badge: Option<u32>,
+
+    /// The name of the sound to play, see [`Request::sound`]. The sound is
+    /// critical if the interruption level is critical.
+    pub sound: Option<String>,
+
+    /// The volume of a critical sound, see [`Sound::volume`].
+    pub sound_volume: Option<f64>,
+
+    /// See [`Request::thread_id`].
+    pub thread_id: Option<String>,
+
+    /// See [`Request::category`].
+    pub category: Option<String>,
+
+    /// See [`Request::target_content_id`].
+    pub target_content_id: Option<String>,
+
+    /// See [`Request::content_available`].
+    pub content_available: bool,
+
+    /// See [`Request::mutable_content`].
+    pub mutable_content: bool,
+
+    /// See [`Request::interruption_level`].
+    pub interruption_level: Option<InterruptionLevel>,
+
+    /// See [`Request::relevance_score`].
+    pub relevance_score: Option<f64>,
+
+    /// See [`Request::user_info`].
+    pub user_info: Option<Map<String, Value>>,
+}
+
+/// Alert of a [`PushRequestDto`], see [`Alert`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertDto {
+    pub title: Option<String>,
+    pub subtitle: Option<String>,
+    pub body: Option<String>,
+}
+
+impl TryFrom<PushRequestDto> for Request<Value> {
+    type Error = Error;
+
+    /// Converts the DTO and checks it with [`Request::validate`]. Use
+    /// [`field_path`] or [`error_response`] to map the errors to the DTO's
+    /// fields.
+    fn try_from(dto: PushRequestDto) -> Result<Self, Error> {
+        let critical = dto.interruption_level == Some(InterruptionLevel::Critical);
+
+        let request = Request {
+            device_token: dto.device_token.into(),
+            push_type: dto.push_type,
+            id: dto.id,
+            expiration: dto.expires_at.into(),
+            priority: dto.priority,
+            topic: dto.topic,
+            collapse_id: dto.collapse_id,
+            alert: dto.alert.map(|alert| Alert {
+                title: alert.title,
+                subtitle: alert.subtitle,
+                body: alert.body,
+                ..Default::default()
+            }),
+            badge: dto.badge.map(Badge::from),
+            sound: dto.sound.map(|name| Sound {
+                critical,
+                name,
+                volume: dto.sound_volume.unwrap_or(1.),
+                extra: None,
+            }),
+            thread_id: dto.thread_id,
+            category: dto.category,
+            target_content_id: dto.target_content_id,
+            content_available: dto.content_available,
+            mutable_content: dto.mutable_content,
+            interruption_level: dto.interruption_level,
+            relevance_score: dto.relevance_score,
+            user_info: dto.user_info.map(Value::Object),
+            ..Default::default()
+        };
+
+        request.validate()?;
+        Ok(request)
+    }
+}
+
+/// Returns the [`PushRequestDto`] field that a validation error is about, or
+/// `None` if it is about the request as a whole.
+pub fn field_path(err: &ValidationError) -> Option<&'static str> {
+    match err {
+        ValidationError::DeviceToken => Some("device_token"),
+        ValidationError::CollapseIdTooLong { .. } => Some("collapse_id"),
+        ValidationError::RelevanceScore(_) => Some("relevance_score"),
+        ValidationError::CriticalSound => Some("interruption_level"),
+        ValidationError::CriticalSoundVolume(_) => Some("sound_volume"),
+        ValidationError::MissingAlert { .. } => Some("interruption_level"),
+        ValidationError::FieldTooLong { field, .. } => Some(field),
+        ValidationError::FieldCharacters { field } => Some(field),
+        ValidationError::PayloadSize { field, .. } => *field,
+        ValidationError::Priority { .. } => Some("priority"),
+        ValidationError::Key { key, .. } => Some(key),
+        ValidationError::MissingKey { .. } => Some("push_type"),
+        ValidationError::Topic => Some("topic"),
+        ValidationError::Authentication { .. } => Some("push_type"),
+        ValidationError::Expiration { .. } => Some("expires_at"),
+    }
+}
+
+/// Returns the status code and JSON body to respond with for an error.
+///
+/// Errors in the request are reported as `400 Bad Request` with a `fields`
+/// array of `{"field": ..., "message": ...}` objects. APNs error reasons keep
+/// their status code, except that problems with the relay's own credentials
+/// or configuration are reported as `502 Bad Gateway`. A `TooManyRequests`
+/// error has a `throttle_scope` of `device`, `provider` or `unknown`, see
+/// [`ThrottleInfo`](crate::ThrottleInfo).
+///
+/// ```json
+/// {
+///   "error": "InvalidRequest",
+///   "message": "invalid request: collapse id must be at most 64 bytes, not 65",
+///   "fields": [{ "field": "collapse_id", "message": "collapse id must be at most 64 bytes, not 65" }]
+/// }
+/// ```
+pub fn error_response(err: &Error) -> (StatusCode, Value) {
+    let (status, error, fields) = match err {
+        Error::WithApnsId { apns_id, source } => {
+            let (status, mut body) = error_response(source);
+            body["apns_id"] = json!(apns_id);
+            return (status, body);
+        }
+        Error::Throttled { throttle, source } => {
+            let (status, mut body) = error_response(source);
+            body["throttle_scope"] = json!(throttle.scope.to_string());
+            return (status, body);
+        }
+        Error::Validation(errors) => {
+            let fields = errors
+                .iter()
+                .map(|err| json!({ "field": field_path(err), "message": err.to_string() }))
+                .collect();
+            (StatusCode::BAD_REQUEST, "InvalidRequest", fields)
+        }
+        Error::InvalidDeviceToken => (
+            StatusCode::BAD_REQUEST,
+            "InvalidRequest",
+            vec![json!({ "field": "device_token", "message": err.to_string() })],
+        ),
+        Error::CriticalSound
+        | Error::InvalidHeader { .. }
+        | Error::InvalidRequest(_)
+        | Error::NilApnsId => (StatusCode::BAD_REQUEST, "InvalidRequest", vec![]),
+        Error::PayloadTooLarge { .. } => (StatusCode::PAYLOAD_TOO_LARGE, "PayloadTooLarge", vec![]),
+        Error::DeadlineExceeded => (StatusCode::GATEWAY_TIMEOUT, "DeadlineExceeded", vec![]),
+        Error::Apns(reason) => {
+            let status = match StatusCode::from(reason.clone()) {
+                StatusCode::FORBIDDEN
+                | StatusCode::NOT_FOUND
+                | StatusCode::METHOD_NOT_ALLOWED
+                | StatusCode::INTERNAL_SERVER_ERROR => StatusCode::BAD_GATEWAY,
+                status => status,
+            };
+            (status, reason.as_str(), vec![])
+        }
+        Error::Reqwest(_) | Error::ReqwestMiddleware(_) | Error::UnexpectedResponse { .. } => {
+            (StatusCode::BAD_GATEWAY, "Unavailable", vec![])
+        }
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, "Internal", vec![]),
+    };
+
+    let mut body = json!({
+        "error": error,
+        "message": err.to_string(),
+    });
+    if !fields.is_empty() {
+        body["fields"] = Value::Array(fields);
+    }
+    (status, body)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::header::Expiration;
+    use crate::reason::{Reason, ThrottleInfo, ThrottleScope};
+
+    use super::*;
+
+    const DEVICE_TOKEN: &str = "a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae";
+
+    fn dto() -> PushRequestDto {
+        PushRequestDto {
+            device_token: DEVICE_TOKEN.into(),
+            topic: Some("com.example.myapp".into()),
+            alert: Some(AlertDto {
+                title: Some("Hello".into()),
+                body: Some("World".into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn error_fields(dto: PushRequestDto) -> (StatusCode, Value) {
+        let err = Request::try_from(dto).unwrap_err();
+        let (status, body) = error_response(&err);
+        (status, body["fields"].clone())
+    }
+
+    #[test]
+    fn dto_de() {
+        let dto: PushRequestDto = serde_json::from_value(json!({
+            "device_token": DEVICE_TOKEN,
+            "push_type": "alert",
+            "expires_at": "2023-11-14T22:13:20Z",
+            "priority": 5,
+            "topic": "com.example.myapp",
+            "alert": { "title": "Hello", "body": "World" },
+            "sound": "default",
+            "user_info": { "acme": 1 },
+        }))
+        .unwrap();
+
+        let request = Request::try_from(dto).unwrap();
+        assert_eq!(request.device_token.as_str(), DEVICE_TOKEN);
+        assert_eq!(
+            request.expiration,
+            Expiration::At(OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap())
+        );
+        assert_eq!(request.priority, Some(Priority::ConsiderPower));
+        assert_eq!(request.alert.unwrap().title.as_deref(), Some("Hello"));
+        assert_eq!(request.sound.unwrap().name, "default");
+        assert_eq!(request.user_info, Some(json!({ "acme": 1 })));
+    }
+
+    #[test]
+    fn dto_de_unknown_field() {
+        let result = serde_json::from_value::<PushRequestDto>(json!({
+            "device_token": DEVICE_TOKEN,
+            "titel": "Hello",
+        }));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn dto_validation_fields() {
+        let (status, fields) = error_fields(PushRequestDto {
+            device_token: "not-a-token".into(),
+            collapse_id: Some("x".repeat(65)),
+            relevance_score: Some(2.),
+            ..dto()
+        });
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            fields,
+            json!([
+                {
+                    "field": "device_token",
+                    "message": "device token must be at least 64 hexadecimal characters",
+                },
+                {
+                    "field": "collapse_id",
+                    "message": "collapse id must be at most 64 bytes, not 65",
+                },
+                {
+                    "field": "relevance_score",
+                    "message": "relevance score must be between 0 and 1, not 2",
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn dto_validation_background() {
+        let (_, fields) = error_fields(PushRequestDto {
+            push_type: PushType::Background,
+            badge: Some(1),
+            ..dto()
+        });
+        let fields: Vec<_> = fields
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| field["field"].as_str().unwrap())
+            .collect();
+        assert_eq!(fields, ["alert", "badge"]);
+    }
+
+    #[test]
+    fn dto_validation_critical_sound() {
+        let (_, fields) = error_fields(PushRequestDto {
+            sound: Some("default".into()),
+            sound_volume: Some(-1.),
+            interruption_level: Some(InterruptionLevel::Critical),
+            ..dto()
+        });
+        assert_eq!(fields[0]["field"], "sound_volume");
+
+        let (_, fields) = error_fields(PushRequestDto {
+            interruption_level: Some(InterruptionLevel::Critical),
+            ..dto()
+        });
+        assert_eq!(fields[0]["field"], "interruption_level");
+    }
+
+    #[test]
+    fn dto_validation_payload_size() {
+        let mut user_info = Map::new();
+        user_info.insert("acme".into(), "x".repeat(5000).into());
+
+        let (_, fields) = error_fields(PushRequestDto {
+            user_info: Some(user_info),
+            ..dto()
+        });
+        assert_eq!(fields[0]["field"], "user_info");
+    }
+
+    #[test]
+    fn error_response_apns() {
+        for (reason, status) in [
+            (Reason::BadDeviceToken, StatusCode::BAD_REQUEST),
+            (Reason::Unregistered { timestamp: None }, StatusCode::GONE),
+            (Reason::TooManyRequests, StatusCode::TOO_MANY_REQUESTS),
+            (Reason::InvalidProviderToken, StatusCode::BAD_GATEWAY),
+            (Reason::BadPath, StatusCode::BAD_GATEWAY),
+            (Reason::InternalServerError, StatusCode::BAD_GATEWAY),
+            (Reason::ServiceUnavailable, StatusCode::SERVICE_UNAVAILABLE),
+        ] {
+            let error = reason.as_str();
+            let err = Error::Apns(reason);
+            assert_eq!(
+                error_response(&err),
+                (
+                    status,
+                    json!({ "error": error, "message": err.to_string() })
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn error_response_with_apns_id() {
+        let apns_id = Uuid::new_v4();
+        let err = Error::WithApnsId {
+            apns_id,
+            source: Box::new(Error::Apns(Reason::BadDeviceToken)),
+        };
+        let (status, body) = error_response(&err);
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "BadDeviceToken");
+        assert_eq!(body["apns_id"], apns_id.to_string());
+    }
+
+    #[test]
+    fn error_response_throttled() {
+        let err = Error::Throttled {
+            throttle: ThrottleInfo {
+                scope: ThrottleScope::Provider,
+                retry_after: None,
+            },
+            source: Box::new(Error::Apns(Reason::TooManyRequests)),
+        };
+        let (status, body) = error_response(&err);
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(body["error"], "TooManyRequests");
+        assert_eq!(body["throttle_scope"], "provider");
+    }
+
+    #[test]
+    fn error_response_invalid_request() {
+        let (status, body) = error_response(&Error::InvalidDeviceToken);
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["fields"][0]["field"], "device_token");
+
+        let err = Error::PayloadTooLarge {
+            size: 5000,
+            limit: 4096,
+        };
+        assert_eq!(error_response(&err).0, StatusCode::PAYLOAD_TOO_LARGE);
+
+        assert_eq!(
+            error_response(&Error::Unknown).0,
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+}
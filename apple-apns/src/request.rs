@@ -1,23 +1,43 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
 use http::{header, HeaderMap, HeaderValue};
-use serde::Serialize;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_with::skip_serializing_none;
 use time::OffsetDateTime;
+use unicode_segmentation::UnicodeSegmentation;
 use uuid::Uuid;
 
 use crate::header::*;
+use crate::limits::{
+    FieldLimits, LOW_RELEVANCE_SCORE, PRIORITIZE_POWER_MIN_EXPIRATION,
+    WATCHOS_SUBTITLE_RECOMMENDED_MAX, WATCHOS_TITLE_RECOMMENDED_MAX,
+};
 use crate::payload::*;
+use crate::redact::Redaction;
 use crate::result::{Error, Result};
 
 /// Apple Push Notification service request options.
-#[derive(Clone, Debug, Default, PartialEq)]
-pub struct Request<T = ()> {
+///
+/// Requests serialize to JSON with kebab-case keys matching the CLI flags,
+/// e.g. to queue them, with dates in RFC 3339 format.
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct Request<T = UserInfo> {
     /// The hex-encoded device token.
-    pub device_token: String,
+    pub device_token: DeviceToken,
 
     /// (Required for watchOS 6 and later; recommended for macOS, iOS, tvOS, and
     /// iPadOS) The value of this header must accurately reflect the contents of
     /// your notification’s payload. If there’s a mismatch, or if the header is
     /// missing on required systems, APNs may return an error, delay the
     /// delivery of the notification, or drop it altogether.
+    #[serde(default)]
     pub push_type: PushType,
 
     /// A canonical UUID that is the unique ID for the notification. If an error
@@ -43,7 +63,8 @@ pub struct Request<T = ()> {
     /// honor the expiry date without any guarantee. If the value is nonzero,
     /// the notification may be delivered after the mentioned date. If the value
     /// is 0, the notification may be delivered with some delay.
-    pub expiration: Option<OffsetDateTime>,
+    #[serde(default, skip_serializing_if = "Expiration::is_default")]
+    pub expiration: Expiration,
 
     /// The priority of the notification. If you omit this header, APNs sets the
     /// notification priority to 10.
@@ -55,7 +76,10 @@ pub struct Request<T = ()> {
     ///
     /// Specify 1 to prioritize the device’s power considerations over all other
     /// factors for delivery, and prevent awakening the device.
-    pub priority: Priority,
+    ///
+    /// If unset, the request is sent with the push type's
+    /// [default priority](PushType::default_priority).
+    pub priority: Option<Priority>,
 
     /// The topic for the notification. In general, the topic is your app’s
     /// bundle ID/app ID. It can have a suffix based on the type of push
@@ -75,12 +99,20 @@ pub struct Request<T = ()> {
     /// coalesce the requests. The value of this key must not exceed 64 bytes.
     pub collapse_id: Option<String>,
 
+    /// The time by which the request must complete. [`Client::post`](crate::Client::post)
+    /// fails with [`Error::DeadlineExceeded`] without sending the request if
+    /// the deadline has passed, and otherwise times out the HTTP request at
+    /// the deadline. Not serialized.
+    #[serde(skip)]
+    pub deadline: Option<Instant>,
+
     /// The information for displaying an alert.
     pub alert: Option<Alert>,
 
-    /// The number to display in a badge on your app’s icon. Specify `0` to
-    /// remove the current badge, if any.
-    pub badge: Option<u32>,
+    /// The number to display in a badge on your app’s icon. Specify
+    /// [`Badge::Clear`] to remove the current badge, if any, and `None` to
+    /// leave it as is.
+    pub badge: Option<Badge>,
 
     /// The name of a sound file in your app’s main bundle or in the
     /// `Library/Sounds` folder of your app’s container directory or a
@@ -104,6 +136,7 @@ pub struct Request<T = ()> {
     /// specify the value `1` and don’t include the `alert`, `badge`, or `sound`
     /// keys in your payload. See [Pushing Background Updates to Your
     /// App](https://developer.apple.com/documentation/usernotifications/setting_up_a_remote_notification_server/pushing_background_updates_to_your_app).
+    #[serde(default, skip_serializing_if = "is_false")]
     pub content_available: bool,
 
     /// The notification service app extension flag. If the value is `1`, the
@@ -111,6 +144,7 @@ pub struct Request<T = ()> {
     /// extension before delivery. Use your extension to modify the
     /// notification’s content. See [Modifying Content in Newly Delivered
     /// Notifications](https://developer.apple.com/documentation/usernotifications/modifying_content_in_newly_delivered_notifications).
+    #[serde(default, skip_serializing_if = "is_false")]
     pub mutable_content: bool,
 
     /// The identifier of the window brought forward. The value of this key will
@@ -135,87 +169,3771 @@ pub struct Request<T = ()> {
     /// [`relevanceScore`](https://developer.apple.com/documentation/usernotifications/unnotificationcontent/3821031-relevancescore).
     pub relevance_score: Option<f64>,
 
+    /// (Live Activity) The time at which the Live Activity content was
+    /// updated. Required for [`PushType::Liveactivity`].
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(with = "Option<crate::schema::Rfc3339>")
+    )]
+    pub timestamp: Option<OffsetDateTime>,
+
+    /// (Live Activity) Whether the notification starts, updates, or ends a
+    /// Live Activity. Required for [`PushType::Liveactivity`].
+    pub event: Option<Event>,
+
+    /// (Live Activity) The dynamic content of the Live Activity. It must match
+    /// the `ContentState` of the Live Activity’s `ActivityAttributes`.
+    pub content_state: Option<serde_json::Value>,
+
+    /// (Live Activity) The time at which the system considers the Live
+    /// Activity content outdated.
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(with = "Option<crate::schema::Rfc3339>")
+    )]
+    pub stale_date: Option<OffsetDateTime>,
+
+    /// (Live Activity) The time at which the system removes an ended Live
+    /// Activity from the Lock Screen.
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(with = "Option<crate::schema::Rfc3339>")
+    )]
+    pub dismissal_date: Option<OffsetDateTime>,
+
+    /// (Live Activity start) The name of the Live Activity’s
+    /// `ActivityAttributes` type.
+    pub attributes_type: Option<String>,
+
+    /// (Live Activity start) The static content of the Live Activity. It must
+    /// match the Live Activity’s `ActivityAttributes`.
+    pub attributes: Option<serde_json::Value>,
+
+    /// (Live Activity start) The flag that requests a push token to update the
+    /// started Live Activity.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub input_push_token: bool,
+
+    /// (Live Activity broadcast update) A predicate that limits the update to
+    /// the Live Activities that match it.
+    pub filter_criteria: Option<String>,
+
     /// Additional data to send.
     pub user_info: Option<T>,
 }
 
-impl<T> TryFrom<Request<T>> for (HeaderMap<HeaderValue>, Payload<T>)
-where
-    T: Serialize,
-{
-    type Error = Error;
+/// Initial capacity of the request headers: `content-type`, the `apns-*`
+/// headers, and the `authorization`, `accept` and `user-agent` headers added
+/// on the way out, so sending never grows the map.
+const HEADER_CAPACITY: usize = 12;
 
-    fn try_from(this: Request<T>) -> Result<Self> {
-        let mut headers = HeaderMap::new();
+impl<T> Request<T> {
+    /// Returns the priority the request is sent with: the explicit
+    /// [`priority`](Self::priority), or the push type's
+    /// [default priority](PushType::default_priority).
+    pub fn effective_priority(&self) -> Priority {
+        self.priority
+            .unwrap_or_else(|| self.push_type.default_priority())
+    }
+
+    /// Returns the push type that matches the payload:
+    /// [`PushType::Background`] for a silent payload, with
+    /// [`content_available`](Self::content_available) set and no alert, badge
+    /// or sound, and [`PushType::Alert`] otherwise.
+    ///
+    /// See [`ClientBuilder::infer_push_type`](crate::ClientBuilder::infer_push_type).
+    pub fn inferred_push_type(&self) -> PushType {
+        if self.content_available && !self.has_alert_content() {
+            PushType::Background
+        } else {
+            PushType::Alert
+        }
+    }
+
+    /// Creates a VoIP request whose payload is `user_info` alone, without an
+    /// `aps` dictionary, as commonly sent with PushKit. See
+    /// [`Payload::raw`].
+    ///
+    /// The VoIP payload size limit applies and the topic gets the `.voip`
+    /// suffix if it lacks one.
+    pub fn with_raw_user_info(user_info: T) -> Self {
+        Request::builder()
+            .push_type(PushType::Voip)
+            .user_info(user_info)
+            .request
+    }
+
+    /// Clones the request without its [`user_info`](Self::user_info), which
+    /// doesn't need to implement [`Clone`].
+    ///
+    /// Use it to retry a request whose user info is large or can't be
+    /// cloned, setting the user info again before sending the copy.
+    pub fn clone_without_user_info(&self) -> Self {
+        let Request {
+            device_token,
+            push_type,
+            id,
+            expiration,
+            priority,
+            topic,
+            collapse_id,
+            deadline,
+            alert,
+            badge,
+            sound,
+            thread_id,
+            category,
+            content_available,
+            mutable_content,
+            target_content_id,
+            interruption_level,
+            relevance_score,
+            timestamp,
+            event,
+            content_state,
+            stale_date,
+            dismissal_date,
+            attributes_type,
+            attributes,
+            input_push_token,
+            filter_criteria,
+            user_info: _,
+        } = self;
+
+        Request {
+            device_token: device_token.clone(),
+            push_type: *push_type,
+            id: *id,
+            expiration: *expiration,
+            priority: *priority,
+            topic: topic.clone(),
+            collapse_id: collapse_id.clone(),
+            deadline: *deadline,
+            alert: alert.clone(),
+            badge: *badge,
+            sound: sound.clone(),
+            thread_id: thread_id.clone(),
+            category: category.clone(),
+            content_available: *content_available,
+            mutable_content: *mutable_content,
+            target_content_id: target_content_id.clone(),
+            interruption_level: *interruption_level,
+            relevance_score: *relevance_score,
+            timestamp: *timestamp,
+            event: *event,
+            content_state: content_state.clone(),
+            stale_date: *stale_date,
+            dismissal_date: *dismissal_date,
+            attributes_type: attributes_type.clone(),
+            attributes: attributes.clone(),
+            input_push_token: *input_push_token,
+            filter_criteria: filter_criteria.clone(),
+            user_info: None,
+        }
+    }
+
+    /// Sets [`timestamp`](Self::timestamp) to the current time, in whole
+    /// seconds as it is sent to APNs.
+    pub fn timestamp_now(&mut self) {
+        let now = OffsetDateTime::now_utc();
+        self.timestamp = Some(now.replace_nanosecond(0).unwrap_or(now));
+    }
+
+    /// Clamps [`relevance_score`](Self::relevance_score) to `0..=1`. NaN is
+    /// left as is, for validation to reject.
+    ///
+    /// See [`ClientBuilder::clamp_relevance_score`](crate::ClientBuilder::clamp_relevance_score).
+    pub fn clamp_relevance_score(&mut self) {
+        if let Some(relevance_score) = &mut self.relevance_score {
+            if !relevance_score.is_nan() {
+                *relevance_score = relevance_score.clamp(0., 1.);
+            }
+        }
+    }
+
+    /// Makes the request a critical alert: sets
+    /// [`interruption_level`](Self::interruption_level) to
+    /// [`InterruptionLevel::Critical`] and [`sound`](Self::sound) to a critical
+    /// sound with `volume`.
+    pub fn critical(&mut self, sound_name: &str, volume: f64) {
+        self.sound = Some(Sound {
+            critical: true,
+            name: sound_name.into(),
+            volume,
+            extra: None,
+        });
+        self.interruption_level = Some(InterruptionLevel::Critical);
+    }
+
+    /// Makes a request with either a critical interruption level or a
+    /// critical sound a critical alert, instead of rejecting the mismatch
+    /// with [`Error::CriticalSound`].
+    ///
+    /// A critical interruption level upgrades the sound to a critical sound,
+    /// adding the default sound if there is none. A sound that isn't critical
+    /// has no volume, so a volume of `0` becomes full volume. A critical sound
+    /// sets the interruption level to [`InterruptionLevel::Critical`].
+    ///
+    /// See [`ClientBuilder::reconcile_critical_sound`](crate::ClientBuilder::reconcile_critical_sound).
+    pub fn reconcile_critical_sound(&mut self) {
+        let is_critical_sound = matches!(&self.sound, Some(sound) if sound.critical);
+        if is_critical_sound {
+            self.interruption_level = Some(InterruptionLevel::Critical);
+        } else if self.interruption_level == Some(InterruptionLevel::Critical) {
+            let sound = self.sound.get_or_insert_with(Sound::default);
+            sound.critical = true;
+            if sound.volume == 0. {
+                sound.volume = 1.;
+            }
+        }
+    }
+
+    /// Returns `true` if the interruption level contradicts the sound's
+    /// critical flag.
+    ///
+    /// A critical sound without an interruption level is a critical alert,
+    /// as it was before iOS 15 added the interruption level.
+    fn has_critical_sound_mismatch(&self) -> bool {
+        let is_critical_sound = matches!(&self.sound, Some(sound) if sound.critical);
+        match self.interruption_level {
+            None => false,
+            Some(level) => (level == InterruptionLevel::Critical) != is_critical_sound,
+        }
+    }
+
+    /// Returns `true` if the request has an alert that isn't empty.
+    fn has_alert(&self) -> bool {
+        matches!(
+            &self.alert,
+            Some(alert) if *alert != Alert::default() && *alert != Alert::from("")
+        )
+    }
+
+    /// Returns `true` if the request has an alert, badge or sound.
+    fn has_alert_content(&self) -> bool {
+        self.alert.is_some() || self.badge.is_some() || self.sound.is_some()
+    }
+
+    /// Returns the time to expiration of a [`Priority::PrioritizePower`]
+    /// request that expires less than `min` after `now`.
+    pub(crate) fn short_expiration(&self, min: Duration, now: OffsetDateTime) -> Option<Duration> {
+        if self.effective_priority() != Priority::PrioritizePower {
+            return None;
+        }
+        self.expiration
+            .remaining_at(now)
+            .filter(|remaining| *remaining < min)
+    }
+
+    /// Sets [`id`](Self::id) from a hyphenated or simple UUID string, for
+    /// callers that use a different version of the `uuid` crate.
+    ///
+    /// Returns [`Error::InvalidHeader`] if `id` is not a UUID and
+    /// [`Error::NilApnsId`] if it is the nil UUID.
+    pub fn set_id_str(&mut self, id: &str) -> Result<()> {
+        let parsed = Uuid::parse_str(id).map_err(|_| Error::InvalidHeader {
+            name: APNS_ID.as_str(),
+            value: id.into(),
+        })?;
+        if parsed.is_nil() {
+            return Err(Error::NilApnsId);
+        }
+        self.id = Some(parsed);
+        Ok(())
+    }
+
+    /// Returns the request headers as name and value strings, for callers
+    /// that use a different version of the `http` crate.
+    pub fn header_pairs(&self) -> Result<Vec<(String, String)>> {
+        Ok(self
+            .headers()?
+            .iter()
+            .map(|(name, value)| {
+                // Values come from `String`s, so they are valid UTF-8.
+                let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                (name.as_str().to_owned(), value)
+            })
+            .collect())
+    }
+
+    /// Returns the request headers, the first half of converting a request
+    /// into `(HeaderMap, Payload)`.
+    ///
+    /// Returns [`Error::InvalidRequest`] for a collapse ID longer than
+    /// [`COLLAPSE_ID_LIMIT`] and [`Error::InvalidHeaderValue`] for a topic or
+    /// collapse ID that is not a valid header value.
+    pub fn headers(&self) -> Result<HeaderMap<HeaderValue>> {
+        if let Some(collapse_id) = &self.collapse_id {
+            check_collapse_id(collapse_id)?;
+        }
+
+        let mut headers = HeaderMap::with_capacity(HEADER_CAPACITY);
 
         headers.insert(
             header::CONTENT_TYPE,
             HeaderValue::from_static("application/json"),
         );
 
-        let _ = headers.insert(APNS_PUSH_TYPE.clone(), this.push_type.into());
+        let _ = headers.insert(APNS_PUSH_TYPE.clone(), self.push_type.into());
 
-        if let Some(id) = this.id {
-            let id = id.hyphenated().to_string().parse()?;
+        if let Some(id) = self.id {
+            let id = id.hyphenated();
+            let id = HeaderValue::from_str(id.encode_lower(&mut Uuid::encode_buffer()))?;
             let _ = headers.insert(APNS_ID.clone(), id);
         }
 
-        if let Some(expiration) = this.expiration {
-            let expiration = expiration.unix_timestamp().to_string().parse()?;
+        if let Some(expiration) = self.expiration.header_value()? {
             let _ = headers.insert(APNS_EXPIRATION.clone(), expiration);
         }
 
-        if this.priority != Priority::default() {
-            let _ = headers.insert(APNS_PRIORITY.clone(), this.priority.into());
+        // Without an explicit priority, omit the header when APNs' default
+        // of 10 applies.
+        let priority = self.effective_priority();
+        if self.priority.is_some() || priority != Priority::default() {
+            let _ = headers.insert(APNS_PRIORITY.clone(), priority.into());
         }
 
-        if let Some(topic) = this.topic {
-            let topic = topic.parse()?;
+        if let Some(topic) = &self.topic {
+            let mut topic = Cow::Borrowed(topic.as_str());
+            // APNs rejects a bundle ID without the push type suffix.
+            if let Some(suffix) = self.push_type.topic_suffix() {
+                if !topic.ends_with(suffix) {
+                    topic.to_mut().push_str(suffix);
+                }
+            }
+            let topic = HeaderValue::from_str(&topic)?;
             let _ = headers.insert(APNS_TOPIC.clone(), topic);
         }
 
-        if let Some(collapse_id) = this.collapse_id {
+        if let Some(collapse_id) = &self.collapse_id {
             let collapse_id = collapse_id.parse()?;
             let _ = headers.insert(APNS_COLLAPSE_ID.clone(), collapse_id);
         }
 
-        let is_critical = this
-            .interruption_level
-            .as_ref()
-            .map(|il| *il == InterruptionLevel::Critical)
-            .unwrap_or_default();
+        Ok(headers)
+    }
+}
 
-        let is_critical_sound = this
-            .sound
-            .as_ref()
-            .map(|sound| sound.critical)
+impl<T> Request<T>
+where
+    T: Serialize,
+{
+    /// Serializes the request like its [`Serialize`] impl and redacts it for
+    /// logging, see [`Redaction`]. The derived [`Debug`](fmt::Debug) output
+    /// masks the device token, but not the user info.
+    pub fn to_redacted_json(&self, redaction: &Redaction) -> Result<serde_json::Value> {
+        let mut value = serde_json::to_value(self)?;
+        redaction.redact_request(&mut value);
+        Ok(value)
+    }
+
+    /// Returns the request redacted for logging, see [`RedactedRequest`].
+    ///
+    /// ```
+    /// use apple_apns::Request;
+    /// use serde_json::json;
+    ///
+    /// let request = Request::builder()
+    ///     .device_token("a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae")
+    ///     .topic("com.example.myapp")
+    ///     .alert("Hello World!")
+    ///     .user_info(json!({ "email": "jane@example.com" }))
+    ///     .build()?;
+    ///
+    /// let redacted = serde_json::to_value(request.redacted())?;
+    /// assert_eq!(redacted["device-token"], "a863…e5ae");
+    /// assert_eq!(redacted["topic"], "com.example.myapp");
+    /// assert_eq!(redacted["user-info"], json!({ "email": "<string>" }));
+    /// assert!(!request.redacted().to_string().contains("jane@example.com"));
+    /// # Ok::<_, apple_apns::Error>(())
+    /// ```
+    pub fn redacted(&self) -> RedactedRequest<'_, T> {
+        RedactedRequest(self)
+    }
+
+    /// Checks the request against the rules that APNs enforces server-side
+    /// and returns every rule the request breaks.
+    /// [`Client::post`](crate::Client::post) validates every request before
+    /// sending it, see
+    /// [`ClientBuilder::validate_requests`](crate::ClientBuilder::validate_requests).
+    ///
+    /// - The device token is at least 64 hexadecimal characters.
+    /// - The collapse ID is at most 64 bytes.
+    /// - The thread ID, category and target content ID are within the
+    ///   default [`FieldLimits`] and have no control characters.
+    /// - The relevance score is between `0` and `1`.
+    /// - A critical interruption level is paired with a critical sound, and
+    ///   a critical sound has either no interruption level or a critical one
+    ///   and a non-negative volume.
+    /// - A time-sensitive interruption level has an alert that isn't empty,
+    ///   see [`InterruptionLevel::requirements`].
+    /// - The serialized payload is within the push type's size limit.
+    /// - A widgets push does not use [`Priority::Immediate`].
+    /// - A background push uses [`Priority::ConsiderPower`] and does not
+    ///   include an alert, badge or sound. APNs accepts such requests but
+    ///   throttles them.
+    /// - A liveactivity push includes an event and a timestamp.
+    /// - Only a liveactivity push includes filter criteria.
+    ///
+    /// Rules that depend on the client's authentication are checked by
+    /// [`Client::validate`](crate::Client::validate).
+    pub fn validate(&self) -> std::result::Result<(), Vec<ValidationError>> {
+        self.validate_with_limits(&FieldLimits::default())
+    }
+
+    /// Checks the request like [`Request::validate`], with the given limits
+    /// on free-form fields.
+    pub fn validate_with_limits(
+        &self,
+        field_limits: &FieldLimits,
+    ) -> std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+
+        if !self.device_token.is_valid() {
+            errors.push(ValidationError::DeviceToken);
+        }
+
+        if let Some(collapse_id) = &self.collapse_id {
+            if collapse_id.len() > COLLAPSE_ID_LIMIT {
+                errors.push(ValidationError::CollapseIdTooLong {
+                    len: collapse_id.len(),
+                });
+            }
+        }
+
+        for (field, value, max) in [
+            ("thread_id", &self.thread_id, field_limits.thread_id),
+            ("category", &self.category, field_limits.category),
+            (
+                "target_content_id",
+                &self.target_content_id,
+                field_limits.target_content_id,
+            ),
+        ] {
+            if let Some(value) = value {
+                if value.len() > max {
+                    errors.push(ValidationError::FieldTooLong {
+                        field,
+                        len: value.len(),
+                        max,
+                    });
+                }
+                if value.chars().any(char::is_control) {
+                    errors.push(ValidationError::FieldCharacters { field });
+                }
+            }
+        }
+
+        if let Some(relevance_score) = self.relevance_score {
+            if !(0. ..=1.).contains(&relevance_score) {
+                errors.push(ValidationError::RelevanceScore(relevance_score));
+            }
+        }
+
+        if self.has_critical_sound_mismatch() {
+            errors.push(ValidationError::CriticalSound);
+        }
+        if let Some(sound) = &self.sound {
+            if sound.critical && !sound.has_valid_volume() {
+                errors.push(ValidationError::CriticalSoundVolume(sound.volume));
+            }
+        }
+        if let Some(interruption_level) = self.interruption_level {
+            if interruption_level.requirements().alert && !self.has_alert() {
+                errors.push(ValidationError::MissingAlert { interruption_level });
+            }
+        }
+
+        let priority = self.effective_priority();
+        if self.push_type == PushType::Widgets && priority == Priority::Immediate {
+            errors.push(ValidationError::Priority {
+                push_type: self.push_type,
+                priority,
+            });
+        }
+
+        if self.push_type == PushType::Background {
+            if priority != Priority::ConsiderPower {
+                errors.push(ValidationError::Priority {
+                    push_type: self.push_type,
+                    priority,
+                });
+            }
+            for (key, is_set) in [
+                ("alert", self.alert.is_some()),
+                ("badge", self.badge.is_some()),
+                ("sound", self.sound.is_some()),
+            ] {
+                if is_set {
+                    errors.push(ValidationError::Key {
+                        push_type: self.push_type,
+                        key,
+                    });
+                }
+            }
+        }
+
+        if self.filter_criteria.is_some() && self.push_type != PushType::Liveactivity {
+            errors.push(ValidationError::Key {
+                push_type: self.push_type,
+                key: "filter_criteria",
+            });
+        }
+
+        if self.push_type == PushType::Liveactivity {
+            for (key, is_set) in [
+                ("event", self.event.is_some()),
+                ("timestamp", self.timestamp.is_some()),
+            ] {
+                if !is_set {
+                    errors.push(ValidationError::MissingKey {
+                        push_type: self.push_type,
+                        key,
+                    });
+                }
+            }
+        }
+
+        // Skip the size check if the payload can't be serialized, which is
+        // already reported as an invalid critical sound volume.
+        if let Ok(size) = self.payload_size() {
+            let limit = self.push_type.payload_size_limit();
+            if size > limit {
+                errors.push(ValidationError::PayloadSize {
+                    size,
+                    limit,
+                    field: self.oversized_field(limit),
+                });
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Checks the request against Apple's recommendations and returns every
+    /// recommendation the request doesn't follow. Lints are advisory: APNs
+    /// accepts the request and delivers the notification regardless.
+    ///
+    /// - The alert title and subtitle fit the Apple Watch short-look
+    ///   interface, see [`limits`](crate::limits). Lengths are counted in
+    ///   grapheme clusters, so an emoji counts once however many bytes it
+    ///   takes.
+    /// - A sound that isn't critical doesn't set a custom volume, which would
+    ///   be ignored.
+    /// - An alert push has an alert, badge or sound. A silent notification
+    ///   should use [`PushType::Background`], see
+    ///   [`Request::inferred_push_type`].
+    /// - Priority 10 is reserved for notifications that need immediate
+    ///   interaction: a passive notification or one with a relevance score
+    ///   below [`LOW_RELEVANCE_SCORE`] is likely summarized and should use
+    ///   priority 5, and a critical notification should not use priority 5.
+    /// - A priority 1 notification doesn't expire within
+    ///   [`PRIORITIZE_POWER_MIN_EXPIRATION`]. The device isn't woken up for
+    ///   it, so it may expire before it is delivered.
+    pub fn lint(&self) -> Vec<Lint> {
+        self.lint_with(&LintOptions::default(), OffsetDateTime::now_utc())
+    }
+
+    /// Checks the request like [`Request::lint`], with the given options and
+    /// with expiration durations measured from `now`.
+    pub fn lint_with(&self, options: &LintOptions, now: OffsetDateTime) -> Vec<Lint> {
+        let mut lints = Vec::new();
+
+        if self.push_type == PushType::Alert && !self.has_alert_content() {
+            lints.push(Lint::PushType {
+                push_type: self.push_type,
+            });
+        }
+
+        if let Some(alert) = &self.alert {
+            if let Some(title) = &alert.title {
+                let len = title.graphemes(true).count();
+                if len > WATCHOS_TITLE_RECOMMENDED_MAX {
+                    lints.push(Lint::TitleLength {
+                        len,
+                        max: WATCHOS_TITLE_RECOMMENDED_MAX,
+                    });
+                }
+            }
+            if let Some(subtitle) = &alert.subtitle {
+                let len = subtitle.graphemes(true).count();
+                if len > WATCHOS_SUBTITLE_RECOMMENDED_MAX {
+                    lints.push(Lint::SubtitleLength {
+                        len,
+                        max: WATCHOS_SUBTITLE_RECOMMENDED_MAX,
+                    });
+                }
+            }
+        }
+
+        if let Some(sound) = &self.sound {
+            if !sound.critical && sound.volume != 0. && sound.volume != 1. {
+                lints.push(Lint::SoundVolume(sound.volume));
+            }
+        }
+
+        // Apple recommends priority 10 only for notifications that need
+        // immediate interaction.
+        let priority = self.effective_priority();
+        let is_passive = self.interruption_level == Some(InterruptionLevel::Passive);
+        let is_critical = self.interruption_level == Some(InterruptionLevel::Critical);
+        let is_low_relevance = self
+            .relevance_score
+            .map(|score| score < LOW_RELEVANCE_SCORE)
             .unwrap_or_default();
+        if priority == Priority::Immediate && (is_passive || is_low_relevance) {
+            lints.push(Lint::Priority {
+                priority,
+                suggested: Priority::ConsiderPower,
+                cause: if is_passive {
+                    "passive interruption level"
+                } else {
+                    "low relevance score"
+                },
+            });
+        } else if priority == Priority::ConsiderPower && is_critical {
+            lints.push(Lint::Priority {
+                priority,
+                suggested: Priority::Immediate,
+                cause: "critical interruption level",
+            });
+        }
 
-        if is_critical != is_critical_sound {
-            return Err(Error::CriticalSound);
+        if let Some(expires_in) =
+            self.short_expiration(options.prioritize_power_min_expiration, now)
+        {
+            lints.push(Lint::Expiration {
+                expires_in,
+                min: options.prioritize_power_min_expiration,
+            });
+        }
+
+        lints
+    }
+
+    /// Returns the field that takes more than a quarter of the payload size
+    /// limit, if any, to point at the likely cause of an oversized payload.
+    fn oversized_field(&self, limit: usize) -> Option<&'static str> {
+        fn json_len<V: Serialize>(value: &Option<V>) -> usize {
+            value
+                .as_ref()
+                .and_then(|value| serde_json::to_vec(value).ok())
+                .map(|json| json.len())
+                .unwrap_or_default()
+        }
+
+        let str_len = |value: Option<&String>| value.map(String::len).unwrap_or_default();
+        let alert = self.alert.as_ref();
+
+        [
+            ("alert.title", str_len(alert.and_then(|a| a.title.as_ref()))),
+            (
+                "alert.subtitle",
+                str_len(alert.and_then(|a| a.subtitle.as_ref())),
+            ),
+            ("alert.body", str_len(alert.and_then(|a| a.body.as_ref()))),
+            ("thread_id", str_len(self.thread_id.as_ref())),
+            ("category", str_len(self.category.as_ref())),
+            (
+                "target_content_id",
+                str_len(self.target_content_id.as_ref()),
+            ),
+            ("content_state", json_len(&self.content_state)),
+            ("attributes", json_len(&self.attributes)),
+            ("user_info", json_len(&self.user_info)),
+        ]
+        .into_iter()
+        .filter(|(_, len)| *len > limit / 4)
+        .max_by_key(|(_, len)| *len)
+        .map(|(field, _)| field)
+    }
+
+    /// Returns whether the serialized payload is within the size limit of
+    /// the request's push type, see [`PushType::payload_size_limit`].
+    pub fn fits(&self) -> Result<bool> {
+        Ok(self.payload_size()? <= self.push_type.payload_size_limit())
+    }
+
+    /// Returns the size of the serialized payload.
+    fn payload_size(&self) -> serde_json::Result<usize> {
+        #[derive(Serialize)]
+        struct PayloadRef<'a, T> {
+            aps: Aps,
+            #[serde(flatten)]
+            user_info: Option<&'a T>,
+        }
+
+        Ok(serde_json::to_vec(&PayloadRef {
+            aps: self.aps(),
+            user_info: self.user_info.as_ref(),
+        })?
+        .len())
+    }
+
+    /// Returns the `aps` dictionary of the payload, as sent by
+    /// [`Request::into_payload`].
+    fn aps(&self) -> Aps {
+        Aps {
+            alert: self.alert.clone(),
+            badge: self.badge,
+            sound: self.sound.clone(),
+            thread_id: self.thread_id.clone(),
+            category: self.category.clone(),
+            // Background pushes are only delivered with `content-available`.
+            content_available: self.content_available || self.push_type == PushType::Background,
+            mutable_content: self.mutable_content,
+            target_content_id: self.target_content_id.clone(),
+            interruption_level: self.interruption_level,
+            relevance_score: self.relevance_score,
+            timestamp: self.timestamp,
+            event: self.event,
+            content_state: self.content_state.clone(),
+            stale_date: self.stale_date,
+            dismissal_date: self.dismissal_date,
+            attributes_type: self.attributes_type.clone(),
+            attributes: self.attributes.clone(),
+            input_push_token: self.input_push_token,
+            filter_criteria: self.filter_criteria.clone(),
+            extra: Default::default(),
+        }
+    }
+}
+
+/// A rule of [`Request::validate`] or [`Client::validate`](crate::Client::validate)
+/// that a request breaks.
+#[derive(thiserror::Error, Clone, Debug, PartialEq)]
+pub enum ValidationError {
+    #[error("device token must be at least 64 hexadecimal characters")]
+    DeviceToken,
+
+    #[error("collapse id must be at most {COLLAPSE_ID_LIMIT} bytes, not {len}")]
+    CollapseIdTooLong { len: usize },
+
+    #[error("relevance score must be between 0 and 1, not {0}")]
+    RelevanceScore(f64),
+
+    #[error("interruption level does not match sound critical flag")]
+    CriticalSound,
+
+    #[error("critical sound volume must not be negative, not {0}")]
+    CriticalSoundVolume(f64),
+
+    #[error("{interruption_level} interruption level requires an alert")]
+    MissingAlert {
+        interruption_level: InterruptionLevel,
+    },
+
+    #[error("{field} must be at most {max} bytes, not {len}")]
+    FieldTooLong {
+        field: &'static str,
+        len: usize,
+        max: usize,
+    },
+
+    #[error("{field} must not contain control characters")]
+    FieldCharacters { field: &'static str },
+
+    #[error("payload size {size} exceeds {limit}{}", display_oversized_field(.field))]
+    PayloadSize {
+        size: usize,
+        limit: usize,
+        /// The field that takes more than a quarter of the limit, if any.
+        field: Option<&'static str>,
+    },
+
+    #[error("{push_type} push must not use priority {priority} ({priority:?})")]
+    Priority {
+        push_type: PushType,
+        priority: Priority,
+    },
+
+    #[error("{push_type} push must not include {key}")]
+    Key {
+        push_type: PushType,
+        key: &'static str,
+    },
+
+    #[error("{push_type} push requires {key}")]
+    MissingKey {
+        push_type: PushType,
+        key: &'static str,
+    },
+
+    #[error("topic is required with token-based authentication")]
+    Topic,
+
+    #[error("{push_type} push requires token-based authentication")]
+    Authentication { push_type: PushType },
+
+    #[error(
+        "priority 1 notification must not expire in less than {}s, not {}s",
+        .min.as_secs(),
+        .expires_in.as_secs()
+    )]
+    Expiration { expires_in: Duration, min: Duration },
+}
+
+fn display_oversized_field(field: &Option<&'static str>) -> String {
+    field
+        .map(|field| format!(", mostly {field}"))
+        .unwrap_or_default()
+}
+
+/// A recommendation of [`Request::lint`] that a request doesn't follow.
+#[derive(thiserror::Error, Clone, Debug, PartialEq)]
+pub enum Lint {
+    #[error("title is {len} characters, Apple Watch may truncate titles over {max}")]
+    TitleLength { len: usize, max: usize },
+
+    #[error("subtitle is {len} characters, Apple Watch may truncate subtitles over {max}")]
+    SubtitleLength { len: usize, max: usize },
+
+    #[error("sound volume {0} is ignored because the sound is not critical")]
+    SoundVolume(f64),
+
+    #[error("{push_type} push has no alert, badge or sound")]
+    PushType { push_type: PushType },
+
+    #[error("priority {priority} with {cause}, consider priority {suggested} ({suggested:?})")]
+    Priority {
+        priority: Priority,
+        suggested: Priority,
+        cause: &'static str,
+    },
+
+    #[error(
+        "priority 1 notification expires in {}s, consider an expiration of at least {}s or a higher priority",
+        .expires_in.as_secs(),
+        .min.as_secs()
+    )]
+    Expiration { expires_in: Duration, min: Duration },
+
+    #[error("{field} {key:?} takes {placeholders} arguments, not {args}")]
+    LocArgs {
+        field: &'static str,
+        key: String,
+        placeholders: usize,
+        args: usize,
+    },
+
+    #[error("{field} {key:?} is not in the localizable strings")]
+    UnknownLocKey { field: &'static str, key: String },
+}
+
+/// Options of [`Request::lint_with`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LintOptions {
+    /// Minimum time to expiration of a [`Priority::PrioritizePower`]
+    /// notification. Defaults to [`PRIORITIZE_POWER_MIN_EXPIRATION`].
+    pub prioritize_power_min_expiration: Duration,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        Self {
+            prioritize_power_min_expiration: PRIORITIZE_POWER_MIN_EXPIRATION,
         }
+    }
+}
+
+impl Request {
+    /// Creates a [`RequestBuilder`].
+    ///
+    /// ```rust
+    /// use apple_apns::{Alert, Request};
+    ///
+    /// let request = Request::builder()
+    ///     .device_token("a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae")
+    ///     .topic("com.example.myapp")
+    ///     .alert(Alert::builder().title("Hello").body("World"))
+    ///     .badge(1)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn builder() -> RequestBuilder {
+        RequestBuilder::default()
+    }
+
+    /// Inserts a custom key into [`user_info`](Self::user_info), returning
+    /// its previous value.
+    pub fn user_info_entry(
+        &mut self,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Option<serde_json::Value> {
+        self.user_info
+            .get_or_insert_with(UserInfo::new)
+            .insert(key.into(), value.into())
+    }
+}
+
+/// [`Request`] builder.
+///
+/// Setters that affect several fields keep them consistent, e.g.
+/// [`critical_sound`](Self::critical_sound) sets both the critical sound and
+/// the critical interruption level. [`build`](Self::build) checks the request
+/// with [`Request::validate`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RequestBuilder<T = UserInfo> {
+    request: Request<T>,
+}
+
+impl<T> RequestBuilder<T> {
+    /// Sets [`Request::device_token`]. A token that doesn't parse is kept as
+    /// given and reported by [`build`](Self::build).
+    pub fn device_token(mut self, device_token: impl Into<String>) -> Self {
+        self.request.device_token = device_token.into().into();
+        self
+    }
+
+    /// Sets [`Request::push_type`].
+    pub fn push_type(mut self, push_type: PushType) -> Self {
+        self.request.push_type = push_type;
+        self
+    }
+
+    /// Sets [`Request::id`].
+    pub fn id(mut self, id: Uuid) -> Self {
+        self.request.id = Some(id);
+        self
+    }
 
-        let sound = this.sound.map(|mut sound| {
-            sound.critical = is_critical || is_critical_sound;
-            sound
+    /// Sets [`Request::expiration`].
+    pub fn expiration(mut self, expiration: impl Into<Expiration>) -> Self {
+        self.request.expiration = expiration.into();
+        self
+    }
+
+    /// Sets [`Request::priority`].
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.request.priority = Some(priority);
+        self
+    }
+
+    /// Sets [`Request::topic`].
+    pub fn topic(mut self, topic: impl Into<String>) -> Self {
+        self.request.topic = Some(topic.into());
+        self
+    }
+
+    /// Sets [`Request::collapse_id`].
+    pub fn collapse_id(mut self, collapse_id: impl Into<String>) -> Self {
+        self.request.collapse_id = Some(collapse_id.into());
+        self
+    }
+
+    /// Sets [`Request::deadline`].
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.request.deadline = Some(deadline);
+        self
+    }
+
+    /// Sets [`Request::alert`].
+    pub fn alert(mut self, alert: impl Into<Alert>) -> Self {
+        self.request.alert = Some(alert.into());
+        self
+    }
+
+    /// Sets [`Request::badge`]. `0` clears the badge, like
+    /// [`clear_badge`](Self::clear_badge).
+    pub fn badge(mut self, badge: u32) -> Self {
+        self.request.badge = Some(badge.into());
+        self
+    }
+
+    /// Sets [`Request::badge`] to [`Badge::Clear`].
+    pub fn clear_badge(mut self) -> Self {
+        self.request.badge = Some(Badge::Clear);
+        self
+    }
+
+    /// Sets [`Request::badge`] to [`Badge::Increment`].
+    pub fn increment_badge(mut self) -> Self {
+        self.request.badge = Some(Badge::Increment);
+        self
+    }
+
+    /// Sets [`Request::sound`] to a sound that isn't critical.
+    pub fn sound(mut self, sound: impl Into<String>) -> Self {
+        self.request.sound = Some(sound.into().into());
+        self
+    }
+
+    /// Sets [`Request::sound`] to a critical sound and
+    /// [`Request::interruption_level`] to [`InterruptionLevel::Critical`].
+    pub fn critical_sound(mut self, name: impl Into<String>, volume: f64) -> Self {
+        self.request.sound = Some(Sound {
+            critical: true,
+            name: name.into(),
+            volume,
+            extra: None,
         });
+        self.request.interruption_level = Some(InterruptionLevel::Critical);
+        self
+    }
 
-        let payload = Payload {
-            aps: Aps {
-                alert: this.alert.map(Into::into),
-                badge: this.badge,
+    /// Sets [`Request::thread_id`].
+    pub fn thread_id(mut self, thread_id: impl Into<String>) -> Self {
+        self.request.thread_id = Some(thread_id.into());
+        self
+    }
+
+    /// Sets [`Request::category`].
+    pub fn category(mut self, category: impl Into<String>) -> Self {
+        self.request.category = Some(category.into());
+        self
+    }
+
+    /// Sets [`Request::content_available`].
+    pub fn content_available(mut self) -> Self {
+        self.request.content_available = true;
+        self
+    }
+
+    /// Makes the request a background update: sets the push type to
+    /// [`PushType::Background`], sets
+    /// [`content_available`](Request::content_available) and clears the
+    /// alert, badge and sound.
+    pub fn background(mut self) -> Self {
+        self.request.push_type = PushType::Background;
+        self.request.content_available = true;
+        self.request.alert = None;
+        self.request.badge = None;
+        self.request.sound = None;
+        self
+    }
+
+    /// Sets [`Request::mutable_content`].
+    pub fn mutable_content(mut self) -> Self {
+        self.request.mutable_content = true;
+        self
+    }
+
+    /// Sets [`Request::target_content_id`].
+    pub fn target_content_id(mut self, target_content_id: impl Into<String>) -> Self {
+        self.request.target_content_id = Some(target_content_id.into());
+        self
+    }
+
+    /// Sets [`Request::interruption_level`]. Use
+    /// [`critical_sound`](Self::critical_sound) for critical alerts.
+    pub fn interruption_level(mut self, interruption_level: InterruptionLevel) -> Self {
+        self.request.interruption_level = Some(interruption_level);
+        self
+    }
+
+    /// Sets [`Request::relevance_score`].
+    pub fn relevance_score(mut self, relevance_score: f64) -> Self {
+        self.request.relevance_score = Some(relevance_score);
+        self
+    }
+
+    /// Sets [`Request::push_type`] to [`PushType::Liveactivity`],
+    /// [`Request::event`] and [`Request::timestamp`].
+    pub fn live_activity(mut self, event: Event, timestamp: OffsetDateTime) -> Self {
+        self.request.push_type = PushType::Liveactivity;
+        self.request.event = Some(event);
+        self.request.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Sets [`Request::content_state`].
+    pub fn content_state(mut self, content_state: serde_json::Value) -> Self {
+        self.request.content_state = Some(content_state);
+        self
+    }
+
+    /// Sets [`Request::stale_date`].
+    pub fn stale_date(mut self, stale_date: OffsetDateTime) -> Self {
+        self.request.stale_date = Some(stale_date);
+        self
+    }
+
+    /// Sets [`Request::dismissal_date`].
+    pub fn dismissal_date(mut self, dismissal_date: OffsetDateTime) -> Self {
+        self.request.dismissal_date = Some(dismissal_date);
+        self
+    }
+
+    /// Sets [`Request::attributes_type`] and [`Request::attributes`].
+    pub fn attributes(
+        mut self,
+        attributes_type: impl Into<String>,
+        attributes: serde_json::Value,
+    ) -> Self {
+        self.request.attributes_type = Some(attributes_type.into());
+        self.request.attributes = Some(attributes);
+        self
+    }
+
+    /// Sets [`Request::input_push_token`].
+    pub fn input_push_token(mut self) -> Self {
+        self.request.input_push_token = true;
+        self
+    }
+
+    /// Sets [`Request::filter_criteria`].
+    pub fn filter_criteria(mut self, filter_criteria: impl Into<String>) -> Self {
+        self.request.filter_criteria = Some(filter_criteria.into());
+        self
+    }
+
+    /// Sets [`Request::user_info`], changing its type.
+    pub fn user_info<U>(self, user_info: U) -> RequestBuilder<U> {
+        let Request {
+            device_token,
+            push_type,
+            id,
+            expiration,
+            priority,
+            topic,
+            collapse_id,
+            deadline,
+            alert,
+            badge,
+            sound,
+            thread_id,
+            category,
+            content_available,
+            mutable_content,
+            target_content_id,
+            interruption_level,
+            relevance_score,
+            timestamp,
+            event,
+            content_state,
+            stale_date,
+            dismissal_date,
+            attributes_type,
+            attributes,
+            input_push_token,
+            filter_criteria,
+            user_info: _,
+        } = self.request;
+
+        RequestBuilder {
+            request: Request {
+                device_token,
+                push_type,
+                id,
+                expiration,
+                priority,
+                topic,
+                collapse_id,
+                deadline,
+                alert,
+                badge,
                 sound,
-                thread_id: this.thread_id,
-                category: this.category,
-                content_available: this.content_available,
-                mutable_content: this.mutable_content,
-                target_content_id: this.target_content_id,
-                interruption_level: this.interruption_level,
-                relevance_score: this.relevance_score,
+                thread_id,
+                category,
+                content_available,
+                mutable_content,
+                target_content_id,
+                interruption_level,
+                relevance_score,
+                timestamp,
+                event,
+                content_state,
+                stale_date,
+                dismissal_date,
+                attributes_type,
+                attributes,
+                input_push_token,
+                filter_criteria,
+                user_info: Some(user_info),
             },
-            user_info: this.user_info,
-        };
+        }
+    }
+}
 
-        Ok((headers, payload))
+impl<T> RequestBuilder<T>
+where
+    T: Serialize,
+{
+    /// Builds the request, returning [`Error::Validation`] if it breaks any
+    /// rule of [`Request::validate`].
+    pub fn build(self) -> Result<Request<T>> {
+        self.request.validate()?;
+        Ok(self.request)
+    }
+}
+
+/// [`Request`] with arbitrary JSON user info.
+///
+/// The user info is a `BTreeMap`, so its keys serialize in sorted order. Use
+/// [`Payload::serialize_sorted`] to also sort the keys of nested objects.
+pub type DynamicRequest = Request<BTreeMap<String, serde_json::Value>>;
+
+/// A [`Request`] redacted for logging, from [`Request::redacted`].
+///
+/// Serializes and displays as the JSON of the request with the device token
+/// redacted to its first and last four characters, and the values of the
+/// user info, the Live Activity content state and attributes replaced with
+/// placeholders naming their types, keeping the keys. The headers and the
+/// `aps` fields are kept. Use [`Request::to_redacted_json`] to choose what
+/// is redacted.
+pub struct RedactedRequest<'a, T = UserInfo>(&'a Request<T>);
+
+impl<T> RedactedRequest<'_, T>
+where
+    T: Serialize,
+{
+    fn to_json(&self) -> Result<serde_json::Value> {
+        let mut value = self.0.to_redacted_json(&Redaction::default())?;
+        if let Some(attributes) = value.get_mut("attributes") {
+            crate::redact::mask(attributes);
+        }
+        Ok(value)
+    }
+}
+
+impl<T> Serialize for RedactedRequest<'_, T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.to_json()
+            .map_err(serde::ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl<T> fmt::Display for RedactedRequest<'_, T>
+where
+    T: Serialize,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_json().map_err(|_| fmt::Error)?;
+        write!(f, "{value}")
+    }
+}
+
+impl<T> fmt::Debug for RedactedRequest<'_, T>
+where
+    T: Serialize,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// A hex-encoded device token.
+///
+/// Parsing with [`DeviceToken::parse`], [`FromStr`] or [`TryFrom<&str>`]
+/// strips surrounding whitespace and the angle brackets and group spaces of
+/// tokens copied from Xcode logs like `<a863a50a 904a4bb7 …>`, lowercases the
+/// token, and rejects tokens that are not hex. [`From<String>`] keeps a token that doesn't parse as given, so
+/// that [`Request::validate`] reports it.
+///
+/// The [`Debug`](fmt::Debug) output redacts all but the first and last four
+/// characters.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct DeviceToken(String);
+
+impl DeviceToken {
+    /// Parses a device token.
+    ///
+    /// Returns [`Error::InvalidDeviceToken`] if the token is empty, contains
+    /// whitespace other than the single spaces between the groups of an Xcode
+    /// token, or is not hex.
+    pub fn parse(device_token: &str) -> Result<Self> {
+        let device_token = normalize_device_token(device_token)?;
+        if !device_token.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(Error::InvalidDeviceToken);
+        }
+
+        Ok(Self(device_token.into_owned()))
+    }
+
+    /// Returns the token, lowercase if it was parsed.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns whether the token is at least 64 hex characters.
+    pub(crate) fn is_valid(&self) -> bool {
+        self.0.len() >= 64 && self.0.bytes().all(|b| b.is_ascii_hexdigit())
+    }
+}
+
+impl From<String> for DeviceToken {
+    fn from(device_token: String) -> Self {
+        Self::parse(&device_token).unwrap_or(Self(device_token))
+    }
+}
+
+impl From<DeviceToken> for String {
+    fn from(device_token: DeviceToken) -> Self {
+        device_token.0
+    }
+}
+
+impl TryFrom<&str> for DeviceToken {
+    type Error = Error;
+
+    fn try_from(device_token: &str) -> Result<Self> {
+        Self::parse(device_token)
+    }
+}
+
+impl FromStr for DeviceToken {
+    type Err = Error;
+
+    fn from_str(device_token: &str) -> Result<Self> {
+        Self::parse(device_token)
+    }
+}
+
+impl AsRef<str> for DeviceToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for DeviceToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl fmt::Debug for DeviceToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DeviceToken({})", redact_device_token(&self.0))
+    }
+}
+
+impl Serialize for DeviceToken {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for DeviceToken {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let device_token = String::deserialize(deserializer)?;
+        Self::parse(&device_token).map_err(de::Error::custom)
+    }
+}
+
+/// An `apns-collapse-id` of at most [`COLLAPSE_ID_LIMIT`] bytes.
+///
+/// Use it to check the limit where a request is built rather than when it is
+/// sent:
+///
+/// ```
+/// use apple_apns::{CollapseId, Request};
+///
+/// let request = Request::<()> {
+///     collapse_id: Some(CollapseId::new("game-score")?.into()),
+///     ..Default::default()
+/// };
+/// # Ok::<(), apple_apns::Error>(())
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CollapseId(String);
+
+impl CollapseId {
+    /// Creates a collapse ID.
+    ///
+    /// Returns [`Error::InvalidRequest`] if it is longer than
+    /// [`COLLAPSE_ID_LIMIT`] bytes.
+    pub fn new(collapse_id: impl Into<String>) -> Result<Self> {
+        let collapse_id = collapse_id.into();
+        check_collapse_id(&collapse_id)?;
+        Ok(Self(collapse_id))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<CollapseId> for String {
+    fn from(collapse_id: CollapseId) -> Self {
+        collapse_id.0
+    }
+}
+
+impl TryFrom<String> for CollapseId {
+    type Error = Error;
+
+    fn try_from(collapse_id: String) -> Result<Self> {
+        Self::new(collapse_id)
+    }
+}
+
+impl TryFrom<&str> for CollapseId {
+    type Error = Error;
+
+    fn try_from(collapse_id: &str) -> Result<Self> {
+        Self::new(collapse_id)
+    }
+}
+
+impl AsRef<str> for CollapseId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for CollapseId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+fn check_collapse_id(collapse_id: &str) -> Result<()> {
+    if collapse_id.len() > COLLAPSE_ID_LIMIT {
+        return Err(Error::InvalidRequest(
+            ValidationError::CollapseIdTooLong {
+                len: collapse_id.len(),
+            }
+            .to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Redacts all but the first and last four characters of a device token.
+pub(crate) fn redact_device_token(device_token: &str) -> String {
+    if device_token.len() <= 8 {
+        return "…".into();
+    }
+    match (
+        device_token.get(..4),
+        device_token.get(device_token.len() - 4..),
+    ) {
+        (Some(first), Some(last)) => format!("{first}…{last}"),
+        _ => "…".into(),
+    }
+}
+
+/// Trims surrounding whitespace from a device token, unwraps an Xcode token
+/// like `<a863a50a 904a4bb7 …>` and lowercases it.
+///
+/// Returns [`Error::InvalidDeviceToken`] if the token is empty or contains
+/// whitespace, other than single spaces between the groups of eight
+/// characters of an Xcode token.
+pub(crate) fn normalize_device_token(device_token: &str) -> Result<Cow<'_, str>> {
+    let device_token = device_token.trim();
+    let device_token = match device_token
+        .strip_prefix('<')
+        .and_then(|device_token| device_token.strip_suffix('>'))
+    {
+        Some(groups) if groups.contains(' ') => {
+            let groups = groups.split(' ').collect::<Vec<_>>();
+            let (last, init) = groups.split_last().ok_or(Error::InvalidDeviceToken)?;
+            if last.is_empty() || last.len() > 8 || init.iter().any(|group| group.len() != 8) {
+                return Err(Error::InvalidDeviceToken);
+            }
+            Cow::Owned(groups.concat())
+        }
+        Some(device_token) => Cow::Borrowed(device_token),
+        None => Cow::Borrowed(device_token),
+    };
+    if device_token.is_empty() || device_token.contains(char::is_whitespace) {
+        return Err(Error::InvalidDeviceToken);
+    }
+    if device_token.bytes().any(|b| b.is_ascii_uppercase()) {
+        Ok(Cow::Owned(device_token.to_ascii_lowercase()))
+    } else {
+        Ok(device_token)
+    }
+}
+
+impl<T> TryFrom<Request<T>> for (HeaderMap<HeaderValue>, Payload<T>)
+where
+    T: Serialize,
+{
+    type Error = Error;
+
+    fn try_from(this: Request<T>) -> Result<Self> {
+        let headers = this.headers()?;
+        let payload = this.into_payload()?;
+
+        Ok((headers, payload))
+    }
+}
+
+impl<T> Request<T>
+where
+    T: Serialize,
+{
+    /// Converts the request into its payload, the second half of converting
+    /// a request into `(HeaderMap, Payload)`, e.g. to store the payload or
+    /// hand it to another delivery system.
+    ///
+    /// Returns [`Error::CriticalSound`] if the interruption level and sound
+    /// disagree on a critical alert, and [`Error::InvalidRequest`] for a Live
+    /// Activity push without event and timestamp, a relevance score that is
+    /// not finite, or a negative critical sound volume.
+    pub fn into_payload(self) -> Result<Payload<T>> {
+        if self.push_type == PushType::Liveactivity
+            && (self.event.is_none() || self.timestamp.is_none())
+        {
+            return Err(Error::InvalidRequest(
+                "liveactivity push requires event and timestamp".into(),
+            ));
+        }
+
+        if self.has_critical_sound_mismatch() {
+            return Err(Error::CriticalSound);
+        }
+
+        // NaN and infinity would be sent as `null`.
+        if let Some(relevance_score) = self.relevance_score {
+            if !relevance_score.is_finite() {
+                return Err(Error::InvalidRequest(
+                    ValidationError::RelevanceScore(relevance_score).to_string(),
+                ));
+            }
+        }
+
+        let is_valid_critical_volume = self
+            .sound
+            .as_ref()
+            .map(|sound| !sound.critical || sound.has_valid_volume())
+            .unwrap_or(true);
+
+        if !is_valid_critical_volume {
+            return Err(Error::InvalidRequest(
+                "critical sound volume must not be negative".into(),
+            ));
+        }
+
+        Ok(Payload {
+            aps: self.aps(),
+            user_info: self.user_info,
+        })
+    }
+
+    /// Parses the headers and payload of a provider request back into a
+    /// request, the inverse of converting a request into `(HeaderMap,
+    /// Payload)`.
+    ///
+    /// Missing headers leave the corresponding fields unset, except for a
+    /// missing `apns-push-type`, which means [`PushType::Alert`]. Returns
+    /// [`Error::InvalidHeader`] for a header value that doesn't parse and
+    /// [`Error::NilApnsId`] for a nil `apns-id`.
+    pub fn from_parts(
+        device_token: String,
+        headers: &HeaderMap<HeaderValue>,
+        payload: Payload<T>,
+    ) -> Result<Self> {
+        let push_type =
+            parse_header(headers, &APNS_PUSH_TYPE, |value| value.parse().ok())?.unwrap_or_default();
+
+        let id = parse_header(headers, &APNS_ID, |value| Uuid::parse_str(value).ok())?;
+        if id.map(|id| id.is_nil()).unwrap_or_default() {
+            return Err(Error::NilApnsId);
+        }
+
+        let expiration = parse_header(headers, &APNS_EXPIRATION, |value| {
+            match value.parse::<i64>().ok()? {
+                0 => Some(Expiration::Immediate),
+                timestamp => OffsetDateTime::from_unix_timestamp(timestamp)
+                    .ok()
+                    .map(Expiration::At),
+            }
+        })?
+        .unwrap_or_default();
+
+        let priority = parse_header(headers, &APNS_PRIORITY, |value| value.parse().ok())?;
+        let topic = parse_header(headers, &APNS_TOPIC, |value| Some(value.to_string()))?;
+        let collapse_id =
+            parse_header(headers, &APNS_COLLAPSE_ID, |value| Some(value.to_string()))?;
+
+        let Payload { aps, user_info } = payload;
+
+        Ok(Self {
+            device_token: device_token.into(),
+            push_type,
+            id,
+            expiration,
+            priority,
+            topic,
+            collapse_id,
+            deadline: None,
+            alert: aps.alert,
+            badge: aps.badge,
+            sound: aps.sound,
+            thread_id: aps.thread_id,
+            category: aps.category,
+            // Background pushes always get `content-available`, so it is only
+            // set explicitly for other push types.
+            content_available: aps.content_available && push_type != PushType::Background,
+            mutable_content: aps.mutable_content,
+            target_content_id: aps.target_content_id,
+            interruption_level: aps.interruption_level,
+            relevance_score: aps.relevance_score,
+            timestamp: aps.timestamp,
+            event: aps.event,
+            content_state: aps.content_state,
+            stale_date: aps.stale_date,
+            dismissal_date: aps.dismissal_date,
+            attributes_type: aps.attributes_type,
+            attributes: aps.attributes,
+            input_push_token: aps.input_push_token,
+            filter_criteria: aps.filter_criteria,
+            user_info,
+        })
+    }
+}
+
+/// Parses the value of header `name`, if present.
+///
+/// Returns [`Error::InvalidHeader`] if the value is not visible ASCII or
+/// `parse` returns `None`.
+fn parse_header<U>(
+    headers: &HeaderMap<HeaderValue>,
+    name: &'static http::HeaderName,
+    parse: impl FnOnce(&str) -> Option<U>,
+) -> Result<Option<U>> {
+    let value = match headers.get(name) {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    value
+        .to_str()
+        .ok()
+        .and_then(parse)
+        .map(Some)
+        .ok_or_else(|| Error::InvalidHeader {
+            name: name.as_str(),
+            value: String::from_utf8_lossy(value.as_bytes()).into_owned(),
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DEVICE_TOKEN: &str = "a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae";
+
+    fn valid_request() -> Request {
+        Request {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            alert: Some("Hello World!".into()),
+            ..Default::default()
+        }
+    }
+
+    fn validation_errors(request: &Request) -> Vec<ValidationError> {
+        request.validate().err().unwrap_or_default()
+    }
+
+    #[test]
+    fn device_token_parse() {
+        let chunks = DEVICE_TOKEN
+            .as_bytes()
+            .chunks(8)
+            .map(|chunk| std::str::from_utf8(chunk).unwrap())
+            .collect::<Vec<_>>();
+
+        for device_token in [
+            DEVICE_TOKEN.to_string(),
+            DEVICE_TOKEN.to_uppercase(),
+            format!("  {DEVICE_TOKEN}\r\n"),
+            // Xcode logs `Data` tokens as `<…>` in groups of eight.
+            format!("<{}>", chunks.join(" ")),
+            format!("<{}>", chunks.join(" ").to_uppercase()),
+            format!("\t<{}>\n", chunks.concat()),
+        ] {
+            let parsed = DeviceToken::parse(&device_token).unwrap();
+            assert_eq!(parsed.as_str(), DEVICE_TOKEN, "{device_token:?}");
+            assert_eq!(device_token.parse::<DeviceToken>().unwrap(), parsed);
+            assert_eq!(
+                DeviceToken::try_from(device_token.as_str()).unwrap(),
+                parsed
+            );
+            assert_eq!(DeviceToken::from(device_token.clone()), parsed);
+        }
+    }
+
+    #[test]
+    fn device_token_parse_rejected() {
+        for device_token in [
+            "",
+            " ",
+            "<>",
+            "< >",
+            "invalid",
+            "0x1234",
+            "a863-a50a",
+            // Only Xcode tokens are grouped, by single spaces every eight
+            // characters.
+            "a863a50a 904a4bb7",
+            "<a863a50a  904a4bb7>",
+            "<a863a50a\n904a4bb7>",
+            "<a863 a50a904a4bb7>",
+            "<a863a50a 904a4bb7 >",
+        ] {
+            assert!(
+                matches!(
+                    DeviceToken::parse(device_token),
+                    Err(Error::InvalidDeviceToken)
+                ),
+                "{device_token:?}"
+            );
+
+            // Kept as given, for `Request::validate` to report.
+            let device_token = DeviceToken::from(device_token.to_string());
+            assert!(!device_token.is_valid());
+        }
+    }
+
+    #[test]
+    fn device_token_fmt() {
+        let device_token: DeviceToken = DEVICE_TOKEN.parse().unwrap();
+        assert_eq!(device_token.to_string(), DEVICE_TOKEN);
+        assert_eq!(format!("{device_token:?}"), "DeviceToken(a863…e5ae)");
+
+        let request = Request::<()> {
+            device_token,
+            ..Default::default()
+        };
+        assert!(!format!("{request:?}").contains(DEVICE_TOKEN));
+    }
+
+    #[test]
+    fn device_token_serde() {
+        let device_token: DeviceToken =
+            serde_json::from_str(&format!("\"<{}>\"", DEVICE_TOKEN.to_uppercase())).unwrap();
+        assert_eq!(device_token.as_str(), DEVICE_TOKEN);
+        assert_eq!(
+            serde_json::to_string(&device_token).unwrap(),
+            format!("\"{DEVICE_TOKEN}\"")
+        );
+
+        assert!(serde_json::from_str::<DeviceToken>("\"invalid\"").is_err());
+    }
+
+    #[test]
+    fn validate_valid() {
+        assert_eq!(valid_request().validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_device_token() {
+        for device_token in [
+            DEVICE_TOKEN.to_string(),
+            DEVICE_TOKEN.to_uppercase(),
+            format!(" {DEVICE_TOKEN}\n"),
+            format!("{DEVICE_TOKEN}{DEVICE_TOKEN}"),
+        ] {
+            let request = Request {
+                device_token: device_token.into(),
+                ..valid_request()
+            };
+            assert_eq!(request.validate(), Ok(()), "{request:?}");
+        }
+
+        for device_token in [
+            String::new(),
+            DEVICE_TOKEN[..63].to_string(),
+            DEVICE_TOKEN.replace('a', "g"),
+            format!("{}-{}", &DEVICE_TOKEN[..32], &DEVICE_TOKEN[32..]),
+            format!("{} {}", &DEVICE_TOKEN[..32], &DEVICE_TOKEN[32..]),
+        ] {
+            let request = Request {
+                device_token: device_token.into(),
+                ..valid_request()
+            };
+            assert_eq!(
+                validation_errors(&request),
+                [ValidationError::DeviceToken],
+                "{request:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn validate_collapse_id() {
+        let request = Request {
+            collapse_id: Some("x".repeat(COLLAPSE_ID_LIMIT)),
+            ..valid_request()
+        };
+        assert_eq!(request.validate(), Ok(()));
+
+        // 33 two-byte characters are 66 bytes.
+        let request = Request {
+            collapse_id: Some("é".repeat(33)),
+            ..valid_request()
+        };
+        assert_eq!(
+            validation_errors(&request),
+            [ValidationError::CollapseIdTooLong { len: 66 }]
+        );
+    }
+
+    #[test]
+    fn collapse_id_limit() {
+        // 64 bytes are accepted, 65 are not, whatever the number of chars.
+        let accepted = ["x".repeat(64), "é".repeat(32), "😀".repeat(16)];
+        let rejected = [
+            "x".repeat(65),
+            format!("{}x", "é".repeat(32)),
+            format!("{}x", "😀".repeat(16)),
+        ];
+
+        for collapse_id in accepted {
+            assert_eq!(collapse_id.len(), 64);
+            assert_eq!(
+                CollapseId::new(collapse_id.clone()).unwrap().as_str(),
+                collapse_id
+            );
+
+            let request = Request {
+                collapse_id: Some(collapse_id.clone()),
+                ..valid_request()
+            };
+            assert_eq!(request.validate(), Ok(()));
+            let (headers, _): (_, Payload) = request.try_into().unwrap();
+            assert_eq!(headers[&APNS_COLLAPSE_ID], collapse_id.as_str());
+        }
+
+        for collapse_id in rejected {
+            assert!(collapse_id.len() > 64);
+            assert!(matches!(
+                CollapseId::try_from(collapse_id.as_str()),
+                Err(Error::InvalidRequest(_))
+            ));
+
+            let request = Request {
+                collapse_id: Some(collapse_id.clone()),
+                ..valid_request()
+            };
+            assert_eq!(
+                validation_errors(&request),
+                [ValidationError::CollapseIdTooLong {
+                    len: collapse_id.len()
+                }]
+            );
+            let result: Result<(_, Payload)> = request.try_into();
+            assert!(matches!(result, Err(Error::InvalidRequest(_))));
+        }
+    }
+
+    #[test]
+    fn validate_relevance_score() {
+        for relevance_score in [0., 0.5, 1.] {
+            let request = Request {
+                relevance_score: Some(relevance_score),
+                ..valid_request()
+            };
+            assert_eq!(request.validate(), Ok(()));
+        }
+
+        for relevance_score in [-0.1, 1.1] {
+            let request = Request {
+                relevance_score: Some(relevance_score),
+                ..valid_request()
+            };
+            assert_eq!(
+                validation_errors(&request),
+                [ValidationError::RelevanceScore(relevance_score)]
+            );
+        }
+
+        let request = Request {
+            relevance_score: Some(f64::NAN),
+            ..valid_request()
+        };
+        assert!(matches!(
+            validation_errors(&request)[..],
+            [ValidationError::RelevanceScore(_)]
+        ));
+    }
+
+    #[test]
+    fn timestamp() {
+        let mut request = valid_request();
+        let before = OffsetDateTime::now_utc().unix_timestamp();
+        request.timestamp_now();
+        let timestamp = request.timestamp.unwrap();
+        assert!((before..=before + 1).contains(&timestamp.unix_timestamp()));
+        assert_eq!(timestamp.nanosecond(), 0);
+
+        // Any push type may include a timestamp.
+        request.timestamp = Some(OffsetDateTime::from_unix_timestamp(1685952000).unwrap());
+        assert_eq!(request.validate(), Ok(()));
+        let (_, payload): (_, Payload) = request.try_into().unwrap();
+        assert_eq!(
+            serde_json::to_value(payload).unwrap(),
+            serde_json::json!({ "aps": { "alert": "Hello World!", "timestamp": 1685952000 } })
+        );
+    }
+
+    #[test]
+    fn clamp_relevance_score() {
+        for (relevance_score, clamped) in [
+            (0., 0.),
+            (0.5, 0.5),
+            (1., 1.),
+            (-0.1, 0.),
+            (1.1, 1.),
+            (f64::INFINITY, 1.),
+            (f64::NEG_INFINITY, 0.),
+        ] {
+            let mut request = Request {
+                relevance_score: Some(relevance_score),
+                ..valid_request()
+            };
+            request.clamp_relevance_score();
+            assert_eq!(request.relevance_score, Some(clamped), "{relevance_score}");
+            assert_eq!(request.validate(), Ok(()));
+        }
+
+        let mut request = Request {
+            relevance_score: Some(f64::NAN),
+            ..valid_request()
+        };
+        request.clamp_relevance_score();
+        assert!(request.relevance_score.unwrap().is_nan());
+    }
+
+    #[test]
+    fn critical() {
+        let mut request = valid_request();
+        request.critical("siren.aiff", 0.5);
+        assert_eq!(
+            request.interruption_level,
+            Some(InterruptionLevel::Critical)
+        );
+        assert_eq!(
+            request.sound,
+            Some(Sound {
+                critical: true,
+                name: "siren.aiff".into(),
+                volume: 0.5,
+                extra: None,
+            })
+        );
+        assert_eq!(request.validate(), Ok(()));
+    }
+
+    #[test]
+    fn reconcile_critical_sound() {
+        // Strict: the mismatch is an error.
+        let request = Request {
+            interruption_level: Some(InterruptionLevel::Critical),
+            sound: Some("siren.aiff".into()),
+            ..valid_request()
+        };
+        let result: Result<(HeaderMap, Payload)> = request.clone().try_into();
+        assert!(matches!(result, Err(Error::CriticalSound)));
+
+        // Lenient: the critical interruption level upgrades the sound.
+        let mut request = request;
+        request.reconcile_critical_sound();
+        assert_eq!(
+            request.sound,
+            Some(Sound {
+                critical: true,
+                name: "siren.aiff".into(),
+                volume: 1.,
+                extra: None,
+            })
+        );
+        assert_eq!(request.validate(), Ok(()));
+
+        let mut request = Request {
+            interruption_level: Some(InterruptionLevel::Critical),
+            ..valid_request()
+        };
+        request.reconcile_critical_sound();
+        assert_eq!(
+            request.sound,
+            Some(Sound {
+                critical: true,
+                ..Default::default()
+            })
+        );
+
+        // A critical sound upgrades the interruption level.
+        let mut request = Request {
+            interruption_level: Some(InterruptionLevel::Active),
+            sound: Some(Sound {
+                critical: true,
+                name: "siren.aiff".into(),
+                volume: 0.5,
+                extra: None,
+            }),
+            ..valid_request()
+        };
+        request.reconcile_critical_sound();
+        assert_eq!(
+            request.interruption_level,
+            Some(InterruptionLevel::Critical)
+        );
+        assert_eq!(request.sound.as_ref().unwrap().volume, 0.5);
+
+        // Requests that aren't critical are left as is.
+        let mut request = Request {
+            sound: Some("ping.aiff".into()),
+            ..valid_request()
+        };
+        request.reconcile_critical_sound();
+        assert_eq!(request.interruption_level, None);
+        assert!(!request.sound.as_ref().unwrap().critical);
+    }
+
+    #[test]
+    fn relevance_score_not_finite() {
+        for relevance_score in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let request = Request {
+                relevance_score: Some(relevance_score),
+                ..valid_request()
+            };
+            let result: Result<(HeaderMap, Payload)> = request.try_into();
+            assert!(
+                matches!(result, Err(Error::InvalidRequest(_))),
+                "{relevance_score}"
+            );
+        }
+
+        // Out of range but finite scores are only rejected by validation.
+        let request = Request {
+            relevance_score: Some(1.5),
+            ..valid_request()
+        };
+        let (_, payload): (HeaderMap, Payload) = request.try_into().unwrap();
+        assert_eq!(payload.aps.relevance_score, Some(1.5));
+    }
+
+    #[test]
+    fn validate_critical_sound() {
+        let critical_sound = Sound {
+            critical: true,
+            name: "default".into(),
+            volume: 1.,
+            extra: None,
+        };
+
+        let request = Request {
+            interruption_level: Some(InterruptionLevel::Critical),
+            sound: Some(critical_sound.clone()),
+            ..valid_request()
+        };
+        assert_eq!(request.validate(), Ok(()));
+
+        let request = Request {
+            interruption_level: Some(InterruptionLevel::Critical),
+            sound: Some("default".into()),
+            ..valid_request()
+        };
+        assert_eq!(
+            validation_errors(&request),
+            [ValidationError::CriticalSound]
+        );
+
+        // A critical sound without an interruption level is a critical alert.
+        let request = Request {
+            sound: Some(critical_sound.clone()),
+            ..valid_request()
+        };
+        assert_eq!(request.validate(), Ok(()));
+
+        let request = Request {
+            interruption_level: Some(InterruptionLevel::Critical),
+            sound: Some(Sound {
+                volume: -1.,
+                ..critical_sound
+            }),
+            ..valid_request()
+        };
+        assert_eq!(
+            validation_errors(&request),
+            [ValidationError::CriticalSoundVolume(-1.)]
+        );
+    }
+
+    #[test]
+    fn request_traits() {
+        fn assert_clone_partial_eq<T: Clone + PartialEq>() {}
+
+        assert_clone_partial_eq::<Request>();
+        assert_clone_partial_eq::<Request<serde_json::Value>>();
+        assert_clone_partial_eq::<Payload>();
+        assert_clone_partial_eq::<Payload<serde_json::Value>>();
+    }
+
+    #[test]
+    fn clone_without_user_info() {
+        // Neither `Clone` nor `PartialEq`.
+        #[derive(Serialize)]
+        struct Large {
+            data: Vec<u8>,
+        }
+
+        let request = Request::builder()
+            .device_token(DEVICE_TOKEN)
+            .alert("Hello World!")
+            .user_info(Large {
+                data: vec![0; 4096],
+            })
+            .request;
+        let copy = request.clone_without_user_info();
+        assert!(copy.user_info.is_none());
+        assert_eq!(copy.alert, request.alert);
+        assert_eq!(copy.device_token, request.device_token);
+
+        let payload = Payload {
+            aps: Aps {
+                badge: Some(1.into()),
+                ..Default::default()
+            },
+            user_info: Some(Large {
+                data: vec![0; 4096],
+            }),
+        };
+        let copy = payload.clone_without_user_info();
+        assert!(copy.user_info.is_none());
+        assert_eq!(copy.aps, payload.aps);
+    }
+
+    #[test]
+    fn to_redacted_json() {
+        let request = Request {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            alert: Some("Hello World!".into()),
+            push_type: PushType::Liveactivity,
+            content_state: Some(serde_json::json!({ "player": "jane@example.com" })),
+            user_info: Some(serde_json::json!({ "email": "jane@example.com", "ids": [1, 2] })),
+            ..Default::default()
+        };
+
+        let redacted = request.to_redacted_json(&Redaction::default()).unwrap();
+        let output = redacted.to_string();
+        assert!(!output.contains(DEVICE_TOKEN), "{output}");
+        assert!(!output.contains("jane@example.com"), "{output}");
+        assert_eq!(redacted["device-token"], "a863…e5ae");
+        assert_eq!(redacted["alert"], "Hello World!");
+        assert_eq!(
+            redacted["user-info"],
+            serde_json::json!({ "email": "<string>", "ids": ["<number>", "<number>"] })
+        );
+        assert_eq!(
+            redacted["content-state"],
+            serde_json::json!({ "player": "<string>" })
+        );
+
+        let redacted = request
+            .to_redacted_json(&Redaction {
+                mask_device_token: false,
+                user_info: crate::ValueRedaction::Omit,
+                content_state: crate::ValueRedaction::Keep,
+            })
+            .unwrap();
+        assert_eq!(redacted["device-token"], DEVICE_TOKEN);
+        assert!(redacted.get("user-info").is_none());
+        assert_eq!(redacted["content-state"], request.content_state.unwrap());
+    }
+
+    #[test]
+    fn redacted_snapshot() {
+        // Every field is set, so that a new field fails to compile here until
+        // its redaction is pinned below.
+        let timestamp = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        let request = Request {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            push_type: PushType::Liveactivity,
+            id: Some(Uuid::parse_str("4d947500-498e-4524-8aa8-7220c4e65d75").unwrap()),
+            expiration: Expiration::At(timestamp),
+            priority: Some(Priority::Immediate),
+            topic: Some("com.example.myapp".into()),
+            collapse_id: Some("collapse".into()),
+            deadline: Some(Instant::now()),
+            alert: Some(Alert {
+                title: Some("Game Request".into()),
+                body: Some("Bob wants to play poker".into()),
+                ..Default::default()
+            }),
+            badge: Some(Badge::Count(1)),
+            sound: Some(Sound {
+                critical: true,
+                name: "alarm.aiff".into(),
+                volume: 0.5,
+                extra: None,
+            }),
+            thread_id: Some("thread".into()),
+            category: Some("GAME_INVITATION".into()),
+            content_available: true,
+            mutable_content: true,
+            target_content_id: Some("game".into()),
+            interruption_level: Some(InterruptionLevel::TimeSensitive),
+            relevance_score: Some(0.5),
+            timestamp: Some(timestamp),
+            event: Some(Event::Update),
+            content_state: Some(serde_json::json!({ "player": "jane@example.com", "score": 1 })),
+            stale_date: Some(timestamp),
+            dismissal_date: Some(timestamp),
+            attributes_type: Some("GameAttributes".into()),
+            attributes: Some(serde_json::json!({ "opponent": "jane@example.com" })),
+            input_push_token: true,
+            filter_criteria: Some("poker".into()),
+            user_info: Some(serde_json::json!({ "email": "jane@example.com", "ids": [1, 2] })),
+        };
+
+        let json = serde_json::json!({
+            "device-token": "a863…e5ae",
+            "push-type": "liveactivity",
+            "id": "4d947500-498e-4524-8aa8-7220c4e65d75",
+            "expiration": "2023-11-14T22:13:20Z",
+            "priority": 10,
+            "topic": "com.example.myapp",
+            "collapse-id": "collapse",
+            "alert": {
+                "title": "Game Request",
+                "body": "Bob wants to play poker",
+            },
+            "badge": 1,
+            "sound": {
+                "critical": 1,
+                "name": "alarm.aiff",
+                "volume": 0.5,
+            },
+            "thread-id": "thread",
+            "category": "GAME_INVITATION",
+            "content-available": true,
+            "mutable-content": true,
+            "target-content-id": "game",
+            "interruption-level": "time-sensitive",
+            "relevance-score": 0.5,
+            "timestamp": "2023-11-14T22:13:20Z",
+            "event": "update",
+            "content-state": { "player": "<string>", "score": "<number>" },
+            "stale-date": "2023-11-14T22:13:20Z",
+            "dismissal-date": "2023-11-14T22:13:20Z",
+            "attributes-type": "GameAttributes",
+            "attributes": { "opponent": "<string>" },
+            "input-push-token": true,
+            "filter-criteria": "poker",
+            "user-info": { "email": "<string>", "ids": ["<number>", "<number>"] },
+        });
+
+        let redacted = request.redacted();
+        assert_eq!(serde_json::to_value(&redacted).unwrap(), json);
+        assert_eq!(redacted.to_string(), json.to_string());
+        assert_eq!(format!("{redacted:?}"), json.to_string());
+    }
+
+    #[test]
+    fn critical_sound_matrix() {
+        use InterruptionLevel::*;
+
+        let cases = [
+            (None, false, true),
+            (None, true, true),
+            (Some(Passive), false, true),
+            (Some(Passive), true, false),
+            (Some(Active), false, true),
+            (Some(Active), true, false),
+            (Some(TimeSensitive), false, true),
+            (Some(TimeSensitive), true, false),
+            (Some(Critical), false, false),
+            (Some(Critical), true, true),
+        ];
+        for (interruption_level, critical, ok) in cases {
+            let request = Request {
+                interruption_level,
+                sound: Some(Sound {
+                    critical,
+                    name: "default".into(),
+                    volume: 1.,
+                    extra: None,
+                }),
+                ..valid_request()
+            };
+            let case = format!("{interruption_level:?}, critical: {critical}");
+
+            let result: Result<(HeaderMap, Payload)> = request.clone().try_into();
+            if ok {
+                assert_eq!(request.validate(), Ok(()), "{case}");
+                let (_, payload) = result.unwrap();
+                let sound = payload.aps.sound.unwrap();
+                assert_eq!(sound.critical, critical, "{case}");
+                assert_eq!(payload.aps.interruption_level, interruption_level, "{case}");
+            } else {
+                assert_eq!(
+                    validation_errors(&request),
+                    [ValidationError::CriticalSound],
+                    "{case}"
+                );
+                assert!(matches!(result, Err(Error::CriticalSound)), "{case}");
+                assert!(
+                    matches!(request.into_payload(), Err(Error::CriticalSound)),
+                    "{case}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn headers_and_into_payload() {
+        let request = Request {
+            push_type: PushType::Background,
+            id: Some(Uuid::new_v4()),
+            priority: Some(Priority::ConsiderPower),
+            topic: Some("com.example.myapp".into()),
+            collapse_id: Some("game".into()),
+            badge: Some(Badge::Count(3)),
+            user_info: serde_json::json!({ "acme": 1 }).as_object().cloned(),
+            ..valid_request()
+        };
+
+        let headers = request.headers().unwrap();
+        let payload = request.clone().into_payload().unwrap();
+        let combined: (HeaderMap, Payload) = request.try_into().unwrap();
+        assert_eq!((headers, payload), combined);
+
+        // Each half only fails for its own part of the request.
+        let invalid_headers = Request {
+            collapse_id: Some("a".repeat(COLLAPSE_ID_LIMIT + 1)),
+            ..valid_request()
+        };
+        assert!(invalid_headers.headers().is_err());
+        assert!(invalid_headers.into_payload().is_ok());
+
+        let invalid_payload = Request {
+            interruption_level: Some(InterruptionLevel::Critical),
+            ..valid_request()
+        };
+        assert!(invalid_payload.headers().is_ok());
+        assert!(matches!(
+            invalid_payload.into_payload(),
+            Err(Error::CriticalSound)
+        ));
+    }
+
+    #[test]
+    fn validate_time_sensitive_alert() {
+        let request = Request {
+            interruption_level: Some(InterruptionLevel::TimeSensitive),
+            ..valid_request()
+        };
+        assert_eq!(request.validate(), Ok(()));
+
+        for alert in [None, Some(Alert::default()), Some("".into())] {
+            let request = Request {
+                alert,
+                badge: Some(Badge::Count(1)),
+                interruption_level: Some(InterruptionLevel::TimeSensitive),
+                ..valid_request()
+            };
+            let errors = validation_errors(&request);
+            assert_eq!(
+                errors,
+                [ValidationError::MissingAlert {
+                    interruption_level: InterruptionLevel::TimeSensitive
+                }]
+            );
+            assert_eq!(
+                errors[0].to_string(),
+                "time-sensitive interruption level requires an alert"
+            );
+        }
+
+        // Other levels don't need an alert.
+        for interruption_level in [InterruptionLevel::Passive, InterruptionLevel::Active] {
+            let request = Request {
+                alert: None,
+                badge: Some(Badge::Count(1)),
+                interruption_level: Some(interruption_level),
+                ..valid_request()
+            };
+            assert_eq!(request.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn validate_field_limits() {
+        let with_field = |field: &str, value: String| {
+            let mut request = valid_request();
+            match field {
+                "thread_id" => request.thread_id = Some(value),
+                "category" => request.category = Some(value),
+                "target_content_id" => request.target_content_id = Some(value),
+                _ => unreachable!(),
+            }
+            request
+        };
+
+        for field in ["thread_id", "category", "target_content_id"] {
+            let request = with_field(field, "x".repeat(256));
+            assert_eq!(request.validate(), Ok(()), "{field}");
+
+            let request = with_field(field, "x".repeat(257));
+            assert_eq!(
+                validation_errors(&request),
+                [ValidationError::FieldTooLong {
+                    field,
+                    len: 257,
+                    max: 256,
+                }],
+                "{field}"
+            );
+
+            let request = with_field(field, "a\nb".into());
+            assert_eq!(
+                validation_errors(&request),
+                [ValidationError::FieldCharacters { field }],
+                "{field}"
+            );
+        }
+    }
+
+    #[test]
+    fn validate_field_limits_custom() {
+        let field_limits = FieldLimits {
+            category: 8,
+            ..Default::default()
+        };
+
+        let request = Request {
+            category: Some("MESSAGE".into()),
+            ..valid_request()
+        };
+        assert_eq!(request.validate_with_limits(&field_limits), Ok(()));
+
+        let request = Request {
+            category: Some("MESSAGE_CATEGORY".into()),
+            ..valid_request()
+        };
+        assert_eq!(
+            request.validate_with_limits(&field_limits),
+            Err(vec![ValidationError::FieldTooLong {
+                field: "category",
+                len: 16,
+                max: 8,
+            }])
+        );
+        assert_eq!(request.validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_payload_size_field() {
+        let user_info = |len| {
+            Some(BTreeMap::from([(
+                "data".to_string(),
+                serde_json::Value::String("x".repeat(len)),
+            )]))
+        };
+        let valid_request = || DynamicRequest {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            ..Default::default()
+        };
+
+        let request = Request {
+            user_info: user_info(PAYLOAD_SIZE_LIMIT),
+            ..valid_request()
+        };
+        let errors = request.validate().unwrap_err();
+        assert!(matches!(
+            errors[..],
+            [ValidationError::PayloadSize {
+                field: Some("user_info"),
+                ..
+            }]
+        ));
+        assert!(errors[0].to_string().ends_with(", mostly user_info"));
+
+        // The largest field is named.
+        let request = Request {
+            category: Some("x".repeat(1500)),
+            user_info: user_info(3000),
+            ..valid_request()
+        };
+        let field_limits = FieldLimits {
+            category: usize::MAX,
+            ..Default::default()
+        };
+        assert!(matches!(
+            request.validate_with_limits(&field_limits).unwrap_err()[..],
+            [ValidationError::PayloadSize {
+                field: Some("user_info"),
+                ..
+            }]
+        ));
+
+        // No field takes more than a quarter of the limit.
+        let request = Request {
+            alert: Some(Alert {
+                title: Some("x".repeat(1020)),
+                subtitle: Some("x".repeat(1020)),
+                body: Some("x".repeat(1020)),
+                ..Default::default()
+            }),
+            user_info: user_info(1000),
+            ..valid_request()
+        };
+        let errors = request.validate().unwrap_err();
+        assert!(matches!(
+            errors[..],
+            [ValidationError::PayloadSize { field: None, .. }]
+        ));
+        assert!(errors[0]
+            .to_string()
+            .ends_with(&format!("exceeds {PAYLOAD_SIZE_LIMIT}")));
+    }
+
+    #[test]
+    fn validate_payload_size() {
+        let request = Request {
+            alert: Some(
+                "x".repeat(PAYLOAD_SIZE_LIMIT - r#"{"aps":{"alert":""}}"#.len())
+                    .into(),
+            ),
+            ..valid_request()
+        };
+        assert_eq!(request.validate(), Ok(()));
+
+        let request = Request {
+            alert: Some("x".repeat(PAYLOAD_SIZE_LIMIT).into()),
+            ..valid_request()
+        };
+        assert_eq!(
+            validation_errors(&request),
+            [ValidationError::PayloadSize {
+                size: PAYLOAD_SIZE_LIMIT + r#"{"aps":{"alert":""}}"#.len(),
+                limit: PAYLOAD_SIZE_LIMIT,
+                field: Some("alert.body"),
+            }]
+        );
+
+        let request = Request {
+            push_type: PushType::Voip,
+            alert: Some("x".repeat(PAYLOAD_SIZE_LIMIT).into()),
+            ..valid_request()
+        };
+        assert_eq!(request.validate(), Ok(()));
+
+        let request = Request {
+            user_info: Some(BTreeMap::from([(
+                "data".to_string(),
+                serde_json::Value::String("x".repeat(PAYLOAD_SIZE_LIMIT)),
+            )])),
+            ..Request::default()
+        };
+        assert!(request
+            .validate()
+            .unwrap_err()
+            .iter()
+            .any(|err| matches!(err, ValidationError::PayloadSize { .. })));
+    }
+
+    #[test]
+    fn fits() {
+        let body_limit = PAYLOAD_SIZE_LIMIT - r#"{"aps":{"alert":""}}"#.len();
+        let request = Request {
+            alert: Some("x".repeat(body_limit).into()),
+            ..valid_request()
+        };
+        assert!(request.fits().unwrap());
+
+        let request = Request {
+            alert: Some("x".repeat(body_limit + 1).into()),
+            ..valid_request()
+        };
+        assert!(!request.fits().unwrap());
+
+        let request = Request {
+            push_type: PushType::Voip,
+            ..request
+        };
+        assert!(request.fits().unwrap());
+    }
+
+    #[test]
+    fn payload_size_matches_payload() {
+        let request = Request {
+            push_type: PushType::Background,
+            alert: None,
+            thread_id: Some("thread".into()),
+            user_info: Some(serde_json::Map::from_iter([("acme".into(), "data".into())])),
+            ..valid_request()
+        };
+        let payload = serde_json::to_vec(&request.clone().into_payload().unwrap()).unwrap();
+        assert_eq!(request.payload_size().unwrap(), payload.len());
+    }
+
+    #[test]
+    fn validate_widgets_priority() {
+        let request = Request {
+            push_type: PushType::Widgets,
+            priority: Some(Priority::Immediate),
+            ..valid_request()
+        };
+        assert_eq!(
+            validation_errors(&request),
+            [ValidationError::Priority {
+                push_type: PushType::Widgets,
+                priority: Priority::Immediate,
+            }]
+        );
+
+        for priority in [None, Some(Priority::ConsiderPower)] {
+            let request = Request {
+                push_type: PushType::Widgets,
+                priority,
+                ..valid_request()
+            };
+            assert_eq!(request.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn validate_background() {
+        let priorities = [
+            None,
+            Some(Priority::Immediate),
+            Some(Priority::ConsiderPower),
+            Some(Priority::PrioritizePower),
+        ];
+
+        for priority in priorities {
+            for mask in 0..8 {
+                let request = Request {
+                    push_type: PushType::Background,
+                    priority,
+                    alert: (mask & 1 != 0).then(|| "Hello".into()),
+                    badge: (mask & 2 != 0).then_some(Badge::Count(1)),
+                    sound: (mask & 4 != 0).then(|| "default".into()),
+                    ..valid_request()
+                };
+
+                let mut expected = Vec::new();
+                if let Some(priority) = priority.filter(|p| *p != Priority::ConsiderPower) {
+                    expected.push(ValidationError::Priority {
+                        push_type: PushType::Background,
+                        priority,
+                    });
+                }
+                for (bit, key) in [(1, "alert"), (2, "badge"), (4, "sound")] {
+                    if mask & bit != 0 {
+                        expected.push(ValidationError::Key {
+                            push_type: PushType::Background,
+                            key,
+                        });
+                    }
+                }
+
+                assert_eq!(
+                    validation_errors(&request),
+                    expected,
+                    "{priority:?} {mask:#b}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn validate_liveactivity() {
+        let request = Request {
+            push_type: PushType::Liveactivity,
+            ..valid_request()
+        };
+        assert_eq!(
+            validation_errors(&request),
+            [
+                ValidationError::MissingKey {
+                    push_type: PushType::Liveactivity,
+                    key: "event",
+                },
+                ValidationError::MissingKey {
+                    push_type: PushType::Liveactivity,
+                    key: "timestamp",
+                },
+            ]
+        );
+
+        let request = Request {
+            push_type: PushType::Liveactivity,
+            event: Some(Event::Update),
+            timestamp: Some(OffsetDateTime::UNIX_EPOCH),
+            ..valid_request()
+        };
+        assert_eq!(request.validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_filter_criteria() {
+        let request = Request {
+            push_type: PushType::Liveactivity,
+            event: Some(Event::Update),
+            timestamp: Some(OffsetDateTime::UNIX_EPOCH),
+            filter_criteria: Some("team == 'Blue'".into()),
+            ..valid_request()
+        };
+        assert_eq!(request.validate(), Ok(()));
+
+        for push_type in [PushType::Alert, PushType::Background, PushType::Widgets] {
+            let request = Request {
+                push_type,
+                alert: None,
+                filter_criteria: Some("team == 'Blue'".into()),
+                ..valid_request()
+            };
+            assert_eq!(
+                validation_errors(&request),
+                [ValidationError::Key {
+                    push_type,
+                    key: "filter_criteria",
+                }],
+                "{push_type}"
+            );
+        }
+    }
+
+    #[test]
+    fn validate_accumulates_errors() {
+        let request = Request {
+            device_token: String::from("invalid").into(),
+            collapse_id: Some("x".repeat(COLLAPSE_ID_LIMIT + 1)),
+            relevance_score: Some(2.),
+            interruption_level: Some(InterruptionLevel::Critical),
+            ..valid_request()
+        };
+        let errors = validation_errors(&request);
+        assert_eq!(
+            errors,
+            [
+                ValidationError::DeviceToken,
+                ValidationError::CollapseIdTooLong {
+                    len: COLLAPSE_ID_LIMIT + 1
+                },
+                ValidationError::RelevanceScore(2.),
+                ValidationError::CriticalSound,
+            ]
+        );
+        assert_eq!(
+            Error::from(errors).to_string(),
+            "invalid request: device token must be at least 64 hexadecimal characters; \
+             collapse id must be at most 64 bytes, not 65; \
+             relevance score must be between 0 and 1, not 2; \
+             interruption level does not match sound critical flag"
+        );
+    }
+
+    #[test]
+    fn device_token_normalized() {
+        for device_token in [" A863A50A904A4BB7\n", "<A863A50A 904A4BB7>"] {
+            assert_eq!(
+                normalize_device_token(device_token).unwrap(),
+                "a863a50a904a4bb7"
+            );
+        }
+    }
+
+    #[test]
+    fn device_token_rejected() {
+        for device_token in [
+            "",
+            "  ",
+            "a863a50a 904a4bb7",
+            "a863a50a\t904a4bb7",
+            "<a863a50a\t904a4bb7>",
+        ] {
+            assert!(matches!(
+                normalize_device_token(device_token),
+                Err(Error::InvalidDeviceToken)
+            ));
+        }
+    }
+
+    #[test]
+    fn liveactivity_requires_event_and_timestamp() {
+        let request = Request::<()> {
+            push_type: PushType::Liveactivity,
+            topic: Some("com.example.myapp".into()),
+            event: Some(Event::Update),
+            ..Default::default()
+        };
+
+        let result: Result<(HeaderMap, Payload<()>)> = request.try_into();
+        assert!(matches!(result, Err(Error::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn liveactivity_topic_suffix() {
+        for topic in [
+            "com.example.myapp",
+            "com.example.myapp.push-type.liveactivity",
+        ] {
+            let request = Request::<()> {
+                push_type: PushType::Liveactivity,
+                topic: Some(topic.into()),
+                event: Some(Event::Update),
+                timestamp: Some(OffsetDateTime::from_unix_timestamp(1168364460).unwrap()),
+                ..Default::default()
+            };
+
+            let (headers, _): (HeaderMap, Payload<()>) = request.try_into().unwrap();
+            assert_eq!(
+                headers[&APNS_TOPIC],
+                "com.example.myapp.push-type.liveactivity"
+            );
+        }
+    }
+
+    #[test]
+    fn critical_sound_negative_volume() {
+        let request = Request::<()> {
+            interruption_level: Some(InterruptionLevel::Critical),
+            sound: Some(Sound {
+                critical: true,
+                volume: -3.,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let result: Result<(HeaderMap, Payload<()>)> = request.try_into();
+        assert!(matches!(result, Err(Error::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn background_content_available() {
+        for content_available in [false, true] {
+            let request = Request::<()> {
+                push_type: PushType::Background,
+                priority: Some(Priority::ConsiderPower),
+                content_available,
+                ..Default::default()
+            };
+            assert_eq!(request.validate(), Err(vec![ValidationError::DeviceToken]));
+            let (_, payload): (_, Payload<()>) = request.try_into().unwrap();
+            assert!(payload.aps.content_available);
+        }
+
+        let request = Request::<()> {
+            push_type: PushType::Alert,
+            ..Default::default()
+        };
+        let (_, payload): (_, Payload<()>) = request.try_into().unwrap();
+        assert!(!payload.aps.content_available);
+    }
+
+    #[test]
+    fn lint_title_length() {
+        let lint_alert = |title: String, subtitle: String| {
+            Request {
+                alert: Some(Alert {
+                    title: Some(title),
+                    subtitle: Some(subtitle),
+                    ..Default::default()
+                }),
+                ..valid_request()
+            }
+            .lint()
+        };
+
+        // ASCII, CJK and emoji made of several code points.
+        for grapheme in ["a", "通", "👩‍👩‍👧‍👦", "🇺🇸"] {
+            let max = grapheme.repeat(WATCHOS_TITLE_RECOMMENDED_MAX);
+            assert_eq!(lint_alert(max.clone(), max), vec![]);
+
+            let over = grapheme.repeat(WATCHOS_TITLE_RECOMMENDED_MAX + 1);
+            assert_eq!(
+                lint_alert(over.clone(), over),
+                vec![
+                    Lint::TitleLength {
+                        len: WATCHOS_TITLE_RECOMMENDED_MAX + 1,
+                        max: WATCHOS_TITLE_RECOMMENDED_MAX,
+                    },
+                    Lint::SubtitleLength {
+                        len: WATCHOS_SUBTITLE_RECOMMENDED_MAX + 1,
+                        max: WATCHOS_SUBTITLE_RECOMMENDED_MAX,
+                    },
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn lint_is_advisory() {
+        let request = Request {
+            alert: Some(Alert {
+                title: Some("a".repeat(WATCHOS_TITLE_RECOMMENDED_MAX + 1)),
+                ..Default::default()
+            }),
+            ..valid_request()
+        };
+        assert_eq!(request.lint().len(), 1);
+        assert_eq!(request.validate(), Ok(()));
+    }
+
+    #[test]
+    fn lint_priority() {
+        let immediate = Some(Priority::Immediate);
+        let consider_power = Some(Priority::ConsiderPower);
+        let passive = Some(InterruptionLevel::Passive);
+        let critical = Some(InterruptionLevel::Critical);
+        let lint = |priority, suggested, cause| {
+            vec![Lint::Priority {
+                priority,
+                suggested,
+                cause,
+            }]
+        };
+        let summarized = |cause| lint(Priority::Immediate, Priority::ConsiderPower, cause);
+
+        for (priority, interruption_level, relevance_score, lints) in [
+            (None, None, None, vec![]),
+            (
+                None,
+                passive,
+                None,
+                summarized("passive interruption level"),
+            ),
+            (
+                immediate,
+                passive,
+                None,
+                summarized("passive interruption level"),
+            ),
+            (consider_power, passive, None, vec![]),
+            (
+                immediate,
+                None,
+                Some(0.1),
+                summarized("low relevance score"),
+            ),
+            (immediate, None, Some(LOW_RELEVANCE_SCORE), vec![]),
+            (consider_power, None, Some(0.1), vec![]),
+            (
+                immediate,
+                passive,
+                Some(0.1),
+                summarized("passive interruption level"),
+            ),
+            (immediate, critical, None, vec![]),
+            (
+                consider_power,
+                critical,
+                None,
+                lint(
+                    Priority::ConsiderPower,
+                    Priority::Immediate,
+                    "critical interruption level",
+                ),
+            ),
+            (Some(Priority::PrioritizePower), critical, None, vec![]),
+        ] {
+            let request = Request {
+                priority,
+                interruption_level,
+                relevance_score,
+                ..valid_request()
+            };
+            assert_eq!(
+                request.lint(),
+                lints,
+                "{priority:?} {interruption_level:?} {relevance_score:?}"
+            );
+        }
+
+        assert_eq!(
+            summarized("passive interruption level")[0].to_string(),
+            "priority 10 with passive interruption level, consider priority 5 (ConsiderPower)"
+        );
+    }
+
+    #[test]
+    fn lint_expiration() {
+        let now = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        let minute = Duration::from_secs(60);
+        let min = PRIORITIZE_POWER_MIN_EXPIRATION;
+        let prioritize_power = Some(Priority::PrioritizePower);
+
+        for (priority, expiration, lints) in [
+            (prioritize_power, Expiration::Default, vec![]),
+            (
+                prioritize_power,
+                Expiration::Immediate,
+                vec![Lint::Expiration {
+                    expires_in: Duration::ZERO,
+                    min,
+                }],
+            ),
+            (
+                prioritize_power,
+                Expiration::In(minute),
+                vec![Lint::Expiration {
+                    expires_in: minute,
+                    min,
+                }],
+            ),
+            (
+                prioritize_power,
+                Expiration::At(now + minute),
+                vec![Lint::Expiration {
+                    expires_in: minute,
+                    min,
+                }],
+            ),
+            (prioritize_power, Expiration::In(min), vec![]),
+            (prioritize_power, Expiration::At(now + min), vec![]),
+            (
+                Some(Priority::ConsiderPower),
+                Expiration::In(minute),
+                vec![],
+            ),
+            (None, Expiration::In(minute), vec![]),
+        ] {
+            let request = Request {
+                priority,
+                expiration,
+                ..valid_request()
+            };
+            assert_eq!(
+                request.lint_with(&LintOptions::default(), now),
+                lints,
+                "{priority:?} {expiration:?}"
+            );
+        }
+
+        let request = Request {
+            priority: prioritize_power,
+            expiration: Expiration::In(minute),
+            ..valid_request()
+        };
+        let options = LintOptions {
+            prioritize_power_min_expiration: minute,
+        };
+        assert_eq!(request.lint_with(&options, now), vec![]);
+        assert_eq!(
+            request.lint()[0].to_string(),
+            "priority 1 notification expires in 60s, consider an expiration of at least 3600s or a higher priority"
+        );
+    }
+
+    #[test]
+    fn lint_sound_volume() {
+        for (critical, volume, lints) in [
+            (false, 0., vec![]),
+            (false, 1., vec![]),
+            (false, 0.5, vec![Lint::SoundVolume(0.5)]),
+            (true, 0.5, vec![]),
+        ] {
+            let request = Request {
+                sound: Some(Sound {
+                    critical,
+                    volume,
+                    ..Default::default()
+                }),
+                ..valid_request()
+            };
+            assert_eq!(request.lint(), lints);
+        }
+    }
+
+    #[test]
+    fn inferred_push_type() {
+        for (content_available, alert, badge, sound, push_type) in [
+            (false, false, false, false, PushType::Alert),
+            (false, true, false, false, PushType::Alert),
+            (true, false, false, false, PushType::Background),
+            (true, true, false, false, PushType::Alert),
+            (true, false, true, false, PushType::Alert),
+            (true, false, false, true, PushType::Alert),
+        ] {
+            let request = Request::<()> {
+                content_available,
+                alert: alert.then(|| "Hello World!".into()),
+                badge: badge.then_some(Badge::Count(1)),
+                sound: sound.then(Sound::default),
+                ..Default::default()
+            };
+            assert_eq!(request.inferred_push_type(), push_type);
+        }
+    }
+
+    #[test]
+    fn lint_push_type() {
+        let request = Request {
+            content_available: true,
+            ..valid_request()
+        };
+        assert_eq!(request.lint(), vec![]);
+
+        let request = Request {
+            alert: None,
+            content_available: true,
+            ..valid_request()
+        };
+        assert_eq!(
+            request.lint(),
+            vec![Lint::PushType {
+                push_type: PushType::Alert
+            }]
+        );
+
+        let request = Request {
+            push_type: PushType::Background,
+            priority: Some(Priority::ConsiderPower),
+            alert: None,
+            content_available: true,
+            ..valid_request()
+        };
+        assert_eq!(request.lint(), vec![]);
+    }
+
+    #[test]
+    fn builder_default() {
+        assert_eq!(Request::builder().request, Request::default());
+        assert_eq!(
+            Request::builder().build().unwrap_err().to_string(),
+            "invalid request: device token must be at least 64 hexadecimal characters"
+        );
+    }
+
+    #[test]
+    fn builder_headers() {
+        let id = Uuid::new_v4();
+        let expiration = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        let request = Request::builder()
+            .device_token(DEVICE_TOKEN.to_uppercase())
+            .push_type(PushType::Voip)
+            .id(id)
+            .expiration(expiration)
+            .priority(Priority::ConsiderPower)
+            .topic("com.example.myapp.voip")
+            .collapse_id("collapse")
+            .build()
+            .unwrap();
+        assert_eq!(
+            request,
+            Request {
+                device_token: DEVICE_TOKEN.parse().unwrap(),
+                push_type: PushType::Voip,
+                id: Some(id),
+                expiration: Expiration::At(expiration),
+                priority: Some(Priority::ConsiderPower),
+                topic: Some("com.example.myapp.voip".into()),
+                collapse_id: Some("collapse".into()),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn builder_aps() {
+        let request = Request::builder()
+            .device_token(DEVICE_TOKEN)
+            .alert("Hello World!")
+            .badge(1)
+            .sound("chime.aiff")
+            .thread_id("thread")
+            .category("category")
+            .content_available()
+            .mutable_content()
+            .target_content_id("window")
+            .interruption_level(InterruptionLevel::TimeSensitive)
+            .relevance_score(0.5)
+            .build()
+            .unwrap();
+        assert_eq!(
+            request,
+            Request {
+                device_token: DEVICE_TOKEN.parse().unwrap(),
+                alert: Some("Hello World!".into()),
+                badge: Some(Badge::Count(1)),
+                sound: Some("chime.aiff".into()),
+                thread_id: Some("thread".into()),
+                category: Some("category".into()),
+                content_available: true,
+                mutable_content: true,
+                target_content_id: Some("window".into()),
+                interruption_level: Some(InterruptionLevel::TimeSensitive),
+                relevance_score: Some(0.5),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn builder_alert_builder() {
+        let request = Request::builder()
+            .device_token(DEVICE_TOKEN)
+            .alert(Alert::builder().title("Hello").body("World"))
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.alert,
+            Some(Alert {
+                title: Some("Hello".into()),
+                body: Some("World".into()),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn builder_clear_badge() {
+        for builder in [
+            Request::builder().clear_badge(),
+            Request::builder().badge(0),
+        ] {
+            let request = builder.device_token(DEVICE_TOKEN).build().unwrap();
+            assert_eq!(request.badge, Some(Badge::Clear));
+        }
+    }
+
+    #[test]
+    fn builder_critical_sound() {
+        let request = Request::builder()
+            .device_token(DEVICE_TOKEN)
+            .alert("Hello World!")
+            .interruption_level(InterruptionLevel::Active)
+            .critical_sound("alarm.aiff", 0.5)
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.sound,
+            Some(Sound {
+                critical: true,
+                name: "alarm.aiff".into(),
+                volume: 0.5,
+                extra: None,
+            })
+        );
+        assert_eq!(
+            request.interruption_level,
+            Some(InterruptionLevel::Critical)
+        );
+
+        let err = Request::builder()
+            .device_token(DEVICE_TOKEN)
+            .critical_sound("alarm.aiff", -1.)
+            .build()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Validation(errors) if errors == [ValidationError::CriticalSoundVolume(-1.)]
+        ));
+    }
+
+    #[test]
+    fn builder_background() {
+        let request = Request::builder()
+            .device_token(DEVICE_TOKEN)
+            .alert("Hello World!")
+            .badge(1)
+            .sound("default")
+            .background()
+            .build()
+            .unwrap();
+        assert_eq!(
+            request,
+            Request {
+                device_token: DEVICE_TOKEN.parse().unwrap(),
+                push_type: PushType::Background,
+                content_available: true,
+                ..Default::default()
+            }
+        );
+
+        // Explicit settings after background() are still validated.
+        let err = Request::builder()
+            .device_token(DEVICE_TOKEN)
+            .background()
+            .badge(1)
+            .build()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Validation(errors) if errors == [ValidationError::Key {
+                push_type: PushType::Background,
+                key: "badge",
+            }]
+        ));
+    }
+
+    #[test]
+    fn builder_live_activity() {
+        let timestamp = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        let request = Request::builder()
+            .device_token(DEVICE_TOKEN)
+            .live_activity(Event::Start, timestamp)
+            .content_state(serde_json::json!({ "score": 1 }))
+            .stale_date(timestamp)
+            .dismissal_date(timestamp)
+            .attributes("GameAttributes", serde_json::json!({ "team": "Blue" }))
+            .input_push_token()
+            .build()
+            .unwrap();
+        assert_eq!(
+            request,
+            Request {
+                device_token: DEVICE_TOKEN.parse().unwrap(),
+                push_type: PushType::Liveactivity,
+                event: Some(Event::Start),
+                timestamp: Some(timestamp),
+                content_state: Some(serde_json::json!({ "score": 1 })),
+                stale_date: Some(timestamp),
+                dismissal_date: Some(timestamp),
+                attributes_type: Some("GameAttributes".into()),
+                attributes: Some(serde_json::json!({ "team": "Blue" })),
+                input_push_token: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn builder_user_info() {
+        let request = Request::builder()
+            .device_token(DEVICE_TOKEN)
+            .topic("com.example.myapp")
+            .alert("Hello World!")
+            .user_info(serde_json::json!({ "acme": 1 }))
+            .build()
+            .unwrap();
+        assert_eq!(
+            request,
+            Request {
+                device_token: DEVICE_TOKEN.parse().unwrap(),
+                topic: Some("com.example.myapp".into()),
+                alert: Some("Hello World!".into()),
+                user_info: Some(serde_json::json!({ "acme": 1 })),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn user_info_entry() {
+        let mut request = valid_request();
+        assert_eq!(request.user_info_entry("acme1", "bar"), None);
+        assert_eq!(request.user_info_entry("acme2", 42), None);
+        assert_eq!(
+            request.user_info_entry("acme2", 43),
+            Some(serde_json::json!(42))
+        );
+        let (_, payload): (HeaderMap, Payload) = request.try_into().unwrap();
+        let untyped = serde_json::to_value(payload).unwrap();
+        assert_eq!(
+            untyped,
+            serde_json::json!({
+                "aps": { "alert": "Hello World!" },
+                "acme1": "bar",
+                "acme2": 43,
+            })
+        );
+
+        #[derive(Serialize)]
+        struct Acme {
+            acme1: &'static str,
+            acme2: i64,
+        }
+        let request = Request::builder()
+            .device_token(DEVICE_TOKEN)
+            .alert("Hello World!")
+            .user_info(Acme {
+                acme1: "bar",
+                acme2: 43,
+            })
+            .request;
+        let (_, payload): (HeaderMap, Payload<Acme>) = request.try_into().unwrap();
+        assert_eq!(serde_json::to_value(payload).unwrap(), untyped);
+
+        // Empty user info is omitted.
+        let request = Request {
+            user_info: Some(UserInfo::new()),
+            ..valid_request()
+        };
+        let (_, payload): (HeaderMap, Payload) = request.try_into().unwrap();
+        assert_eq!(
+            serde_json::to_vec(&payload).unwrap(),
+            br#"{"aps":{"alert":"Hello World!"}}"#
+        );
+    }
+
+    #[test]
+    fn request_serde() {
+        let json = serde_json::json!({
+            "device-token": DEVICE_TOKEN,
+            "push-type": "alert",
+            "id": "4d947500-498e-4524-8aa8-7220c4e65d75",
+            "expiration": "2023-11-14T22:13:20Z",
+            "priority": 5,
+            "topic": "com.example.myapp",
+            "collapse-id": "collapse",
+            "alert": {
+                "title": "Game Request",
+                "body": "Bob wants to play poker",
+            },
+            "badge": 1,
+            "sound": {
+                "critical": 1,
+                "name": "alarm.aiff",
+                "volume": 0.5,
+            },
+            "thread-id": "thread",
+            "mutable-content": true,
+            "interruption-level": "critical",
+            "relevance-score": 0.5,
+            "stale-date": "2023-11-14T22:13:20Z",
+            "user-info": {
+                "acme": [1, 2, 3],
+            },
+        });
+
+        let request: DynamicRequest = serde_json::from_str(&json.to_string()).unwrap();
+        let timestamp = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        assert_eq!(
+            request,
+            Request {
+                device_token: DEVICE_TOKEN.parse().unwrap(),
+                push_type: PushType::Alert,
+                id: Some(Uuid::parse_str("4d947500-498e-4524-8aa8-7220c4e65d75").unwrap()),
+                expiration: Expiration::At(timestamp),
+                priority: Some(Priority::ConsiderPower),
+                topic: Some("com.example.myapp".into()),
+                collapse_id: Some("collapse".into()),
+                alert: Some(Alert {
+                    title: Some("Game Request".into()),
+                    body: Some("Bob wants to play poker".into()),
+                    ..Default::default()
+                }),
+                badge: Some(Badge::Count(1)),
+                sound: Some(Sound {
+                    critical: true,
+                    name: "alarm.aiff".into(),
+                    volume: 0.5,
+                    extra: None,
+                }),
+                thread_id: Some("thread".into()),
+                mutable_content: true,
+                interruption_level: Some(InterruptionLevel::Critical),
+                relevance_score: Some(0.5),
+                stale_date: Some(timestamp),
+                user_info: Some(BTreeMap::from([(
+                    "acme".to_string(),
+                    serde_json::json!([1, 2, 3]),
+                )])),
+                ..Default::default()
+            }
+        );
+
+        assert_eq!(serde_json::to_value(&request).unwrap(), json);
+
+        let roundtrip: DynamicRequest =
+            serde_json::from_slice(&serde_json::to_vec(&request).unwrap()).unwrap();
+        assert_eq!(roundtrip, request);
+    }
+
+    #[test]
+    fn request_serde_minimal() {
+        let request = Request {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&request).unwrap();
+        assert_eq!(
+            json,
+            format!(r#"{{"device-token":"{DEVICE_TOKEN}","push-type":"alert"}}"#)
+        );
+        assert_eq!(serde_json::from_str::<Request>(&json).unwrap(), request);
+
+        let request: Request = serde_json::from_str(&format!(
+            r#"{{"device-token":"{DEVICE_TOKEN}","expiration":"never-store"}}"#
+        ))
+        .unwrap();
+        assert_eq!(request.expiration, Expiration::Immediate);
+    }
+
+    #[test]
+    fn priority_header() {
+        let priority_header = |push_type, priority| {
+            let request = Request::<()> {
+                push_type,
+                priority,
+                event: Some(Event::Update),
+                timestamp: Some(OffsetDateTime::UNIX_EPOCH),
+                ..Default::default()
+            };
+            let (headers, _): (HeaderMap, Payload<()>) = request.try_into().unwrap();
+            headers
+                .get(&APNS_PRIORITY)
+                .map(|value| value.to_str().unwrap().to_owned())
+        };
+
+        for (push_type, expected) in [
+            (PushType::Alert, None),
+            (PushType::Background, Some("5")),
+            (PushType::Location, None),
+            (PushType::Voip, None),
+            (PushType::Complication, None),
+            (PushType::Fileprovider, Some("5")),
+            (PushType::Mdm, None),
+            (PushType::Liveactivity, None),
+            (PushType::Pushtotalk, None),
+            (PushType::Widgets, Some("5")),
+        ] {
+            assert_eq!(
+                priority_header(push_type, None).as_deref(),
+                expected,
+                "{push_type}"
+            );
+
+            for (priority, expected) in [
+                (Priority::Immediate, "10"),
+                (Priority::ConsiderPower, "5"),
+                (Priority::PrioritizePower, "1"),
+            ] {
+                assert_eq!(
+                    priority_header(push_type, Some(priority)).as_deref(),
+                    Some(expected),
+                    "{push_type} {priority:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn expiration_header() {
+        let expiration_header = |expiration| {
+            let request = Request::<()> {
+                expiration,
+                ..Default::default()
+            };
+            let (headers, _): (HeaderMap, Payload<()>) = request.try_into().unwrap();
+            headers.get(&APNS_EXPIRATION).cloned()
+        };
+
+        assert_eq!(expiration_header(Expiration::Default), None);
+        assert_eq!(expiration_header(Expiration::Immediate).unwrap(), "0");
+        assert_eq!(
+            expiration_header(Expiration::At(
+                OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap()
+            ))
+            .unwrap(),
+            "1700000000"
+        );
+
+        let before = OffsetDateTime::now_utc().unix_timestamp() + 60;
+        let header = expiration_header(Expiration::In(std::time::Duration::from_secs(60)));
+        let after = OffsetDateTime::now_utc().unix_timestamp() + 60;
+        let expiration: i64 = header.unwrap().to_str().unwrap().parse().unwrap();
+        assert!((before..=after).contains(&expiration));
+
+        let request = Request::<()> {
+            expiration: Expiration::In(std::time::Duration::MAX),
+            ..Default::default()
+        };
+        let result: Result<(HeaderMap, Payload<()>)> = request.try_into();
+        assert!(matches!(result, Err(Error::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn raw_user_info() {
+        let request = Request {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            topic: Some("com.example.myapp".into()),
+            ..Request::with_raw_user_info(serde_json::json!({ "caller": "Alice" }))
+        };
+        assert_eq!(request.validate(), Ok(()));
+
+        let (headers, payload): (HeaderMap, Payload<_>) = request.try_into().unwrap();
+        assert_eq!(headers[&APNS_PUSH_TYPE], "voip");
+        assert_eq!(headers[&APNS_TOPIC], "com.example.myapp.voip");
+        assert_eq!(
+            serde_json::to_value(&payload).unwrap(),
+            serde_json::json!({ "caller": "Alice" })
+        );
+    }
+
+    #[test]
+    fn raw_user_info_size_limit() {
+        let request = Request {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            ..Request::with_raw_user_info(BTreeMap::from([(
+                "data",
+                "a".repeat(VOIP_PAYLOAD_SIZE_LIMIT),
+            )]))
+        };
+        assert!(matches!(
+            &request.validate().unwrap_err()[..],
+            [ValidationError::PayloadSize {
+                limit: VOIP_PAYLOAD_SIZE_LIMIT,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn location_topic_suffix() {
+        for topic in ["com.example.myapp", "com.example.myapp.location-query"] {
+            let request = Request::<()> {
+                push_type: PushType::Location,
+                topic: Some(topic.into()),
+                ..Default::default()
+            };
+
+            let (headers, _): (HeaderMap, Payload<()>) = request.try_into().unwrap();
+            assert_eq!(headers[&APNS_TOPIC], "com.example.myapp.location-query");
+        }
+    }
+
+    #[test]
+    fn topic_suffix() {
+        for (push_type, topic) in [
+            (PushType::Alert, "com.example.myapp"),
+            (PushType::Complication, "com.example.myapp.complication"),
+            (
+                PushType::Fileprovider,
+                "com.example.myapp.pushkit.fileprovider",
+            ),
+            (PushType::Pushtotalk, "com.example.myapp.voip-ptt"),
+            (PushType::Widgets, "com.example.myapp.push-type.widgets"),
+        ] {
+            let request = Request::<()> {
+                push_type,
+                topic: Some("com.example.myapp".into()),
+                ..Default::default()
+            };
+            assert_eq!(request.headers().unwrap()[&APNS_TOPIC], topic);
+        }
+    }
+
+    #[test]
+    fn from_parts_round_trip() {
+        let push_types = [
+            PushType::Alert,
+            PushType::Background,
+            PushType::Location,
+            PushType::Voip,
+            PushType::Complication,
+            PushType::Fileprovider,
+            PushType::Mdm,
+            PushType::Liveactivity,
+            PushType::Pushtotalk,
+            PushType::Widgets,
+        ];
+        let priorities = [
+            None,
+            Some(Priority::Immediate),
+            Some(Priority::ConsiderPower),
+            Some(Priority::PrioritizePower),
+        ];
+        let expirations = [
+            Expiration::Default,
+            Expiration::Immediate,
+            Expiration::At(OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap()),
+        ];
+
+        for push_type in push_types {
+            for priority in priorities {
+                for expiration in expirations {
+                    let request = Request {
+                        device_token: DEVICE_TOKEN.parse().unwrap(),
+                        push_type,
+                        id: Some(Uuid::new_v4()),
+                        expiration,
+                        priority,
+                        topic: Some(format!(
+                            "com.example.myapp{}",
+                            push_type.topic_suffix().unwrap_or_default()
+                        )),
+                        collapse_id: Some("collapse".into()),
+                        alert: Some("Hello World!".into()),
+                        event: Some(Event::Update),
+                        timestamp: Some(OffsetDateTime::UNIX_EPOCH),
+                        user_info: Some(BTreeMap::from([("key".to_string(), 1)])),
+                        ..Default::default()
+                    };
+
+                    let (headers, payload): (HeaderMap, Payload<_>) =
+                        request.clone().try_into().unwrap();
+                    let parsed =
+                        Request::from_parts(DEVICE_TOKEN.into(), &headers, payload.clone())
+                            .unwrap();
+
+                    // The priority header is omitted or made explicit when it
+                    // matches the push type's default.
+                    assert_eq!(
+                        parsed.effective_priority(),
+                        request.effective_priority(),
+                        "{push_type} {priority:?}"
+                    );
+                    assert_eq!(
+                        Request {
+                            priority: request.priority,
+                            ..parsed.clone()
+                        },
+                        request,
+                        "{push_type} {priority:?} {expiration:?}"
+                    );
+
+                    let parts: (HeaderMap, Payload<_>) = parsed.try_into().unwrap();
+                    assert_eq!(parts, (headers, payload), "{push_type} {priority:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn from_parts_missing_headers() {
+        let request =
+            Request::<()>::from_parts(DEVICE_TOKEN.into(), &HeaderMap::new(), Payload::default())
+                .unwrap();
+        assert_eq!(
+            request,
+            Request {
+                device_token: DEVICE_TOKEN.parse().unwrap(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn from_parts_invalid_headers() {
+        for (name, value) in [
+            (&APNS_PUSH_TYPE, "notification"),
+            (&APNS_ID, "not-a-uuid"),
+            (&APNS_EXPIRATION, "tomorrow"),
+            (&APNS_PRIORITY, "7"),
+            (&APNS_TOPIC, "com.example.myapp\u{e9}"),
+        ] {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                name.clone(),
+                HeaderValue::from_bytes(value.as_bytes()).unwrap(),
+            );
+            let err = Request::<()>::from_parts(DEVICE_TOKEN.into(), &headers, Payload::default())
+                .unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("invalid {name} header value {value:?}")
+            );
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            APNS_ID.clone(),
+            HeaderValue::from_static("00000000-0000-0000-0000-000000000000"),
+        );
+        assert!(matches!(
+            Request::<()>::from_parts(DEVICE_TOKEN.into(), &headers, Payload::default()),
+            Err(Error::NilApnsId)
+        ));
     }
 }
@@ -1,4 +1,10 @@
-use crate::reason::Reason;
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::endpoint::{Endpoint, EnvironmentHint};
+use crate::reason::{Reason, ThrottleInfo};
+use crate::request::ValidationError;
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -8,20 +14,96 @@ pub enum Error {
     #[error(transparent)]
     Apns(#[from] Reason),
 
+    #[error("building the client failed: {0}")]
+    Build(#[source] Arc<Error>),
+
     #[error("interruption level does not match sound critical flag")]
     CriticalSound,
 
+    #[error("deadline exceeded")]
+    DeadlineExceeded,
+
+    #[error("endpoint {} is not in the expected {expected} environment", .endpoint.as_url())]
+    EnvironmentMismatch {
+        endpoint: Endpoint,
+        expected: EnvironmentHint,
+    },
+
+    #[error("endpoint {} uses http without TLS, set allow_insecure_http to use it", .0.as_url())]
+    InsecureEndpoint(Endpoint),
+
+    #[error("invalid device token")]
+    InvalidDeviceToken,
+
+    #[error("invalid {name} header value {value:?}")]
+    InvalidHeader { name: &'static str, value: String },
+
     #[error(transparent)]
     InvalidHeaderValue(#[from] http::header::InvalidHeaderValue),
 
+    #[error("HTTP/2 max frame size {0} is not between 16384 and 16777215")]
+    InvalidHttp2MaxFrameSize(u32),
+
+    #[cfg(feature = "jwt")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
+    #[error("JWT iat backdate {0:?} exceeds 5 minutes")]
+    InvalidIatBackdate(std::time::Duration),
+
+    #[cfg(feature = "jwt")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
+    #[error("key file name {} does not match AuthKey_<key id>.p8", .0.display())]
+    InvalidKeyFileName(std::path::PathBuf),
+
+    #[cfg(feature = "jwt")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
+    #[error("JWT refresh period {0:?} is not between 20 and 60 minutes")]
+    InvalidRefreshPeriod(std::time::Duration),
+
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[cfg(feature = "jwt")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
+    #[error(
+        "invalid signing key{}: {kind}",
+        .path.as_ref().map(|path| format!(" {}", path.display())).unwrap_or_default()
+    )]
+    InvalidSigningKey {
+        path: Option<std::path::PathBuf>,
+        kind: crate::token::SigningKeyErrorKind,
+    },
+
+    #[error(transparent)]
+    InvalidUri(#[from] http::uri::InvalidUri),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
     #[cfg(feature = "jwt")]
     #[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
     #[error(transparent)]
     Jwt(#[from] jsonwebtoken::errors::Error),
 
+    #[error("invalid Localizable.strings at line {line}: {reason}")]
+    LocalizableStrings { line: usize, reason: &'static str },
+
+    #[error("request mutator failed: {0}")]
+    Mutator(#[source] Box<Error>),
+
+    #[error("no authentication configured, set authentication or allow_unauthenticated")]
+    NoAuthentication,
+
+    #[error("apns-id must not be the nil UUID")]
+    NilApnsId,
+
     #[error("payload too large: {size} exceeds {limit}")]
     PayloadTooLarge { size: usize, limit: usize },
 
+    #[cfg(feature = "metrics-prometheus")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "metrics-prometheus")))]
+    #[error(transparent)]
+    Prometheus(#[from] prometheus::Error),
+
     #[error(transparent)]
     Reqwest(#[from] reqwest::Error),
 
@@ -36,9 +118,98 @@ pub enum Error {
     #[error(transparent)]
     SystemTime(#[from] std::time::SystemTimeError),
 
+    #[error("{source} ({} throttling)", .throttle.scope)]
+    Throttled {
+        throttle: ThrottleInfo,
+        #[source]
+        source: Box<Error>,
+    },
+
+    #[error("unexpected {status} response: {}", display_body(.body.as_deref()))]
+    UnexpectedResponse {
+        status: http::StatusCode,
+        body: Option<Vec<u8>>,
+    },
+
+    #[error(
+        "unsupported request schema version {0}, expected 1 to {}",
+        crate::persist::SCHEMA_VERSION
+    )]
+    UnsupportedSchemaVersion(u32),
+
     #[error(transparent)]
     Url(#[from] url::ParseError),
 
+    #[error("invalid request: {}", display_validation_errors(.0))]
+    Validation(Vec<ValidationError>),
+
     #[error("unknown")]
     Unknown,
+
+    #[error("{source} (apns-id {apns_id})")]
+    WithApnsId {
+        apns_id: Uuid,
+        #[source]
+        source: Box<Error>,
+    },
+}
+
+impl From<Vec<ValidationError>> for Error {
+    fn from(errors: Vec<ValidationError>) -> Self {
+        Self::Validation(errors)
+    }
+}
+
+fn display_body(body: Option<&[u8]>) -> String {
+    match body {
+        Some([]) => "empty body".into(),
+        Some(body) => format!("{:?}", String::from_utf8_lossy(body)),
+        None => "body could not be read".into(),
+    }
+}
+
+fn display_validation_errors(errors: &[ValidationError]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+impl Error {
+    /// Returns the `apns-id` of the failed request, if known.
+    pub fn apns_id(&self) -> Option<Uuid> {
+        match self {
+            Self::WithApnsId { apns_id, .. } => Some(*apns_id),
+            _ => None,
+        }
+    }
+
+    /// Returns the APNs error reason, if any.
+    pub fn reason(&self) -> Option<&Reason> {
+        match self {
+            Self::Apns(reason) => Some(reason),
+            Self::Throttled { source, .. } | Self::WithApnsId { source, .. } => source.reason(),
+            _ => None,
+        }
+    }
+
+    /// Returns the context of a [`Reason::TooManyRequests`] error.
+    pub fn throttle(&self) -> Option<&ThrottleInfo> {
+        match self {
+            Self::Throttled { throttle, .. } => Some(throttle),
+            Self::WithApnsId { source, .. } => source.throttle(),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the request was not sent or timed out because its
+    /// [`deadline`](crate::Request::deadline) passed.
+    pub fn is_deadline_exceeded(&self) -> bool {
+        match self {
+            Self::DeadlineExceeded => true,
+            Self::WithApnsId { source, .. } => source.is_deadline_exceeded(),
+            _ => false,
+        }
+    }
 }
@@ -0,0 +1,188 @@
+//! JSON schemas for the types with custom serialization.
+//!
+//! The other types derive [`JsonSchema`] and point fields with custom serde
+//! representations at the helper types here.
+
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde_json::{json, Value};
+
+use crate::header::{Expiration, Priority};
+use crate::payload::{Alert, Badge, Sound};
+use crate::request::DeviceToken;
+
+fn schema(value: Value) -> Schema {
+    serde_json::from_value(value).expect("valid schema")
+}
+
+/// A flag sent as `0` or `1`, like `content-available`.
+pub(crate) struct BoolInt;
+
+impl JsonSchema for BoolInt {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        "BoolInt".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        schema(json!({ "type": "integer", "enum": [0, 1] }))
+    }
+}
+
+/// A date sent as UNIX epoch seconds.
+pub(crate) struct UnixTimestamp;
+
+impl JsonSchema for UnixTimestamp {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        "UnixTimestamp".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        schema(json!({ "type": "integer" }))
+    }
+}
+
+/// A date in RFC 3339 format.
+pub(crate) struct Rfc3339;
+
+impl JsonSchema for Rfc3339 {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        "Rfc3339".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        schema(json!({ "type": "string", "format": "date-time" }))
+    }
+}
+
+/// A string for an alert with only a body, or a dictionary.
+impl JsonSchema for Alert {
+    fn schema_name() -> String {
+        "Alert".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        let string = json!({ "type": "string" });
+        let strings = json!({ "type": "array", "items": { "type": "string" } });
+        schema(json!({
+            "anyOf": [
+                string,
+                {
+                    "type": "object",
+                    "properties": {
+                        "title": string,
+                        "title-loc-key": string,
+                        "title-loc-args": strings,
+                        "subtitle": string,
+                        "subtitle-loc-key": string,
+                        "subtitle-loc-args": strings,
+                        "body": string,
+                        "loc-key": string,
+                        "loc-args": strings,
+                        "launch-image": string,
+                    },
+                },
+            ],
+        }))
+    }
+}
+
+/// A number, `0` for [`Badge::Clear`], or `"increment"` for
+/// [`Badge::Increment`], which the APNs payload doesn't allow.
+impl JsonSchema for Badge {
+    fn schema_name() -> String {
+        "Badge".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        schema(json!({
+            "anyOf": [
+                { "type": "integer", "minimum": 0, "maximum": u32::MAX },
+                { "const": "increment" },
+            ],
+        }))
+    }
+}
+
+/// A sound name, or a dictionary for a critical sound or a sound with extra
+/// keys.
+impl JsonSchema for Sound {
+    fn schema_name() -> String {
+        "Sound".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        schema(json!({
+            "anyOf": [
+                { "type": "string" },
+                {
+                    "type": "object",
+                    "properties": {
+                        "critical": { "type": "integer" },
+                        "name": { "type": "string" },
+                        "volume": { "type": "number", "minimum": 0 },
+                    },
+                    "required": ["critical", "name", "volume"],
+                },
+            ],
+        }))
+    }
+}
+
+/// A string of hex digits.
+impl JsonSchema for DeviceToken {
+    fn schema_name() -> String {
+        "DeviceToken".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        schema(json!({ "type": "string", "pattern": "^[0-9a-fA-F]+$" }))
+    }
+}
+
+/// See the [`Serialize`](serde::Serialize) impl of [`Expiration`].
+impl JsonSchema for Expiration {
+    fn schema_name() -> String {
+        "Expiration".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        schema(json!({
+            "anyOf": [
+                { "type": "null" },
+                { "const": 0 },
+                { "const": "never-store" },
+                { "type": "string", "format": "date-time" },
+                {
+                    "type": "object",
+                    "properties": { "in": { "type": "number", "minimum": 0 } },
+                    "required": ["in"],
+                    "additionalProperties": false,
+                },
+            ],
+        }))
+    }
+}
+
+/// The `apns-priority` number.
+impl JsonSchema for Priority {
+    fn schema_name() -> String {
+        "Priority".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        schema(json!({ "type": "integer", "enum": [10, 5, 1] }))
+    }
+}
@@ -0,0 +1,395 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use serde::Serialize;
+use time::OffsetDateTime;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::header::PushType;
+
+/// Snapshot of [`Client`](crate::Client) statistics.
+///
+/// Only the async client keeps statistics, the
+/// [`blocking`](crate::blocking) client has none.
+///
+/// The serialized field names are a stable interface for admin endpoints.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ClientStats {
+    /// Request body bytes currently in flight.
+    pub inflight_bytes: usize,
+
+    /// Highest number of request body bytes that were in flight at once.
+    pub peak_inflight_bytes: usize,
+
+    /// Number of requests sent again because APNs refused their stream or
+    /// sent a GOAWAY before processing them.
+    pub goaway_retries: usize,
+
+    /// Number of requests with a topic that is not in
+    /// [`ClientBuilder::expected_topics`](crate::ClientBuilder::expected_topics).
+    pub unexpected_topics: usize,
+}
+
+impl ClientStats {
+    /// Combines the statistics of two clients, e.g. to report on all clients
+    /// in a registry. Counters are added up, so the combined
+    /// `peak_inflight_bytes` is an upper bound of the combined peak.
+    pub fn merge(&self, other: &Self) -> Self {
+        Self {
+            inflight_bytes: self.inflight_bytes + other.inflight_bytes,
+            peak_inflight_bytes: self.peak_inflight_bytes + other.peak_inflight_bytes,
+            goaway_retries: self.goaway_retries + other.goaway_retries,
+            unexpected_topics: self.unexpected_topics + other.unexpected_topics,
+        }
+    }
+}
+
+impl fmt::Display for ClientStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes in flight (peak {}), {} GOAWAY retries, {} unexpected topics",
+            self.inflight_bytes,
+            self.peak_inflight_bytes,
+            self.goaway_retries,
+            self.unexpected_topics
+        )
+    }
+}
+
+/// A topic and push type combination recently used by a
+/// [`Client`](crate::Client), see
+/// [`Client::recent_targets`](crate::Client::recent_targets).
+///
+/// The serialized field names are a stable interface for admin endpoints.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RecentTarget {
+    pub topic: Option<String>,
+    pub push_type: PushType,
+
+    /// Number of requests sent with this combination while it was tracked.
+    pub count: u64,
+
+    /// Time of the most recent request with this combination.
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_used: OffsetDateTime,
+}
+
+impl fmt::Display for RecentTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}: {} requests, last at {}",
+            self.topic.as_deref().unwrap_or("(no topic)"),
+            self.push_type,
+            self.count,
+            self.last_used
+        )
+    }
+}
+
+/// Tracks the most recently used topic and push type combinations and counts
+/// requests with unexpected topics.
+#[derive(Debug)]
+pub(crate) struct RecentTargets {
+    capacity: usize,
+    expected_topics: Option<HashSet<String>>,
+
+    /// Ordered from least to most recently used.
+    targets: Mutex<Vec<RecentTarget>>,
+    unexpected_topics: AtomicUsize,
+}
+
+impl RecentTargets {
+    pub fn new(capacity: usize, expected_topics: Option<&[String]>) -> Self {
+        Self {
+            capacity,
+            expected_topics: expected_topics.map(|topics| topics.iter().cloned().collect()),
+            targets: Mutex::new(Vec::with_capacity(capacity)),
+            unexpected_topics: Default::default(),
+        }
+    }
+
+    pub fn record(&self, topic: Option<&str>, push_type: PushType) {
+        self.record_at(topic, push_type, OffsetDateTime::now_utc());
+    }
+
+    fn record_at(&self, topic: Option<&str>, push_type: PushType, now: OffsetDateTime) {
+        if let (Some(expected_topics), Some(topic)) = (&self.expected_topics, topic) {
+            if !expected_topics.contains(topic) {
+                self.unexpected_topics.fetch_add(1, Ordering::Relaxed);
+
+                #[cfg(feature = "tracing")]
+                tracing::warn!(topic, push_type = %push_type, "unexpected apns-topic");
+            }
+        }
+
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut targets = self.targets.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let target = match targets
+            .iter()
+            .position(|target| target.topic.as_deref() == topic && target.push_type == push_type)
+        {
+            Some(index) => {
+                let mut target = targets.remove(index);
+                target.count += 1;
+                target.last_used = now;
+                target
+            }
+            None => {
+                if targets.len() >= self.capacity {
+                    targets.remove(0);
+                }
+                RecentTarget {
+                    topic: topic.map(Into::into),
+                    push_type,
+                    count: 1,
+                    last_used: now,
+                }
+            }
+        };
+        targets.push(target);
+    }
+
+    /// Returns the tracked combinations, most recently used first.
+    pub fn get(&self) -> Vec<RecentTarget> {
+        let targets = self.targets.lock().unwrap_or_else(PoisonError::into_inner);
+        targets.iter().rev().cloned().collect()
+    }
+
+    pub fn unexpected_topics(&self) -> usize {
+        self.unexpected_topics.load(Ordering::Relaxed)
+    }
+}
+
+/// Tracks request body bytes in flight and limits them to an optional cap.
+#[derive(Debug)]
+pub(crate) struct InflightBytes {
+    limit: Option<(usize, Arc<Semaphore>)>,
+    current: Arc<AtomicUsize>,
+    peak: AtomicUsize,
+}
+
+impl InflightBytes {
+    pub fn new(max_inflight_bytes: Option<usize>) -> Self {
+        Self {
+            limit: max_inflight_bytes.map(|max| (max, Arc::new(Semaphore::new(max)))),
+            current: Default::default(),
+            peak: Default::default(),
+        }
+    }
+
+    /// Waits until `bytes` more body bytes fit under the cap and reserves them
+    /// until the returned guard is dropped.
+    pub async fn acquire(&self, bytes: usize) -> InflightGuard {
+        let permit = if let Some((max, semaphore)) = &self.limit {
+            // A body larger than the cap is admitted once nothing else is in
+            // flight.
+            let permits = bytes.min(*max).min(u32::MAX as usize) as u32;
+
+            // Crash OK: the semaphore is never closed.
+            Some(semaphore.clone().acquire_many_owned(permits).await.unwrap())
+        } else {
+            None
+        };
+
+        let current = self.current.fetch_add(bytes, Ordering::SeqCst) + bytes;
+        self.peak.fetch_max(current, Ordering::SeqCst);
+
+        InflightGuard {
+            bytes,
+            current: self.current.clone(),
+            _permit: permit,
+        }
+    }
+
+    pub fn current(&self) -> usize {
+        self.current.load(Ordering::SeqCst)
+    }
+
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::SeqCst)
+    }
+}
+
+/// Releases reserved body bytes on drop.
+#[derive(Debug)]
+pub(crate) struct InflightGuard {
+    bytes: usize,
+    current: Arc<AtomicUsize>,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for InflightGuard {
+    fn drop(&mut self) {
+        self.current.fetch_sub(self.bytes, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn client_stats_ser() {
+        let stats = ClientStats {
+            inflight_bytes: 1,
+            peak_inflight_bytes: 2,
+            goaway_retries: 3,
+            unexpected_topics: 4,
+        };
+        assert_eq!(
+            serde_json::to_value(stats).unwrap(),
+            json!({
+                "inflight_bytes": 1,
+                "peak_inflight_bytes": 2,
+                "goaway_retries": 3,
+                "unexpected_topics": 4,
+            })
+        );
+        assert_eq!(
+            stats.to_string(),
+            "1 bytes in flight (peak 2), 3 GOAWAY retries, 4 unexpected topics"
+        );
+    }
+
+    #[test]
+    fn client_stats_merge() {
+        let stats = ClientStats {
+            inflight_bytes: 1,
+            peak_inflight_bytes: 2,
+            goaway_retries: 3,
+            unexpected_topics: 4,
+        };
+        assert_eq!(
+            stats.merge(&stats),
+            ClientStats {
+                inflight_bytes: 2,
+                peak_inflight_bytes: 4,
+                goaway_retries: 6,
+                unexpected_topics: 8,
+            }
+        );
+        assert_eq!(stats.merge(&ClientStats::default()), stats);
+    }
+
+    #[test]
+    fn recent_target_ser() {
+        let target = RecentTarget {
+            topic: Some("com.example.myapp".into()),
+            push_type: PushType::Alert,
+            count: 2,
+            last_used: OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap(),
+        };
+        assert_eq!(
+            serde_json::to_value(&target).unwrap(),
+            json!({
+                "topic": "com.example.myapp",
+                "push_type": "alert",
+                "count": 2,
+                "last_used": "2023-11-14T22:13:20Z",
+            })
+        );
+        assert_eq!(
+            target.to_string(),
+            "com.example.myapp alert: 2 requests, last at 2023-11-14 22:13:20.0 +00:00:00"
+        );
+    }
+
+    fn summary(targets: &[RecentTarget]) -> Vec<(Option<&str>, PushType, u64)> {
+        targets
+            .iter()
+            .map(|target| (target.topic.as_deref(), target.push_type, target.count))
+            .collect()
+    }
+
+    #[test]
+    fn recent_targets_counts() {
+        let recent_targets = RecentTargets::new(4, None);
+        let now = OffsetDateTime::UNIX_EPOCH;
+
+        recent_targets.record_at(Some("a"), PushType::Alert, now);
+        recent_targets.record_at(Some("a"), PushType::Background, now);
+        recent_targets.record_at(None, PushType::Alert, now);
+        recent_targets.record_at(Some("a"), PushType::Alert, now + Duration::SECOND);
+
+        assert_eq!(
+            summary(&recent_targets.get()),
+            [
+                (Some("a"), PushType::Alert, 2),
+                (None, PushType::Alert, 1),
+                (Some("a"), PushType::Background, 1),
+            ]
+        );
+        assert_eq!(recent_targets.get()[0].last_used, now + Duration::SECOND);
+        assert_eq!(recent_targets.unexpected_topics(), 0);
+    }
+
+    #[test]
+    fn recent_targets_eviction() {
+        let recent_targets = RecentTargets::new(2, None);
+
+        recent_targets.record(Some("a"), PushType::Alert);
+        recent_targets.record(Some("b"), PushType::Alert);
+        recent_targets.record(Some("a"), PushType::Alert);
+        recent_targets.record(Some("c"), PushType::Alert);
+
+        // "b" is the least recently used.
+        assert_eq!(
+            summary(&recent_targets.get()),
+            [
+                (Some("c"), PushType::Alert, 1),
+                (Some("a"), PushType::Alert, 2),
+            ]
+        );
+
+        recent_targets.record(Some("b"), PushType::Alert);
+        assert_eq!(
+            summary(&recent_targets.get()),
+            [
+                (Some("b"), PushType::Alert, 1),
+                (Some("c"), PushType::Alert, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn recent_targets_disabled() {
+        let recent_targets = RecentTargets::new(0, Some(&["a".into()]));
+
+        recent_targets.record(Some("a"), PushType::Alert);
+        recent_targets.record(Some("b"), PushType::Alert);
+
+        assert!(recent_targets.get().is_empty());
+        assert_eq!(recent_targets.unexpected_topics(), 1);
+    }
+
+    #[test]
+    fn recent_targets_expected_topics() {
+        let recent_targets = RecentTargets::new(4, Some(&["a".into(), "a.voip".into()]));
+
+        recent_targets.record(Some("a"), PushType::Alert);
+        recent_targets.record(Some("a.voip"), PushType::Voip);
+        assert_eq!(recent_targets.unexpected_topics(), 0);
+
+        recent_targets.record(Some("b"), PushType::Alert);
+        recent_targets.record(Some("a.complication"), PushType::Complication);
+        assert_eq!(recent_targets.unexpected_topics(), 2);
+
+        // Requests without a topic use the certificate's topic.
+        recent_targets.record(None, PushType::Alert);
+        assert_eq!(recent_targets.unexpected_topics(), 2);
+
+        // Unexpected topics are still tracked.
+        assert_eq!(recent_targets.get().len(), 4);
+    }
+}
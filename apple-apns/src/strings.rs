@@ -0,0 +1,309 @@
+//! `Localizable.strings` files, to check localized alerts against the
+//! strings of an app, see [`Alert::validate_against_strings`].
+//!
+//! [`Alert::validate_against_strings`]: crate::Alert::validate_against_strings
+
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::result::{Error, Result};
+
+/// Localized strings by key.
+pub type LocalizableStrings = HashMap<String, String>;
+
+/// Parses the contents of a `Localizable.strings` file with entries like
+/// `"key" = "value";`.
+///
+/// Keys and values are quoted strings with the escapes `\"`, `\\`, `\n`,
+/// `\r`, `\t` and `\U` followed by four hex digits. Keys may also be
+/// unquoted words. Comments are skipped. A repeated key keeps its last
+/// value, like on the device.
+///
+/// Returns [`Error::LocalizableStrings`] with the line of the first syntax
+/// error.
+///
+/// ```
+/// use apple_apns::strings;
+///
+/// let strings = strings::parse(r#"
+///     /* Shown when a friend invites the user */
+///     "GAME_PLAY_REQUEST_FORMAT" = "%@ and %@ have invited you to play Monopoly";
+/// "#)?;
+/// assert_eq!(
+///     strings["GAME_PLAY_REQUEST_FORMAT"],
+///     "%@ and %@ have invited you to play Monopoly"
+/// );
+/// # Ok::<_, apple_apns::Error>(())
+/// ```
+pub fn parse(content: &str) -> Result<LocalizableStrings> {
+    let mut parser = Parser {
+        chars: content
+            .strip_prefix('\u{feff}')
+            .unwrap_or(content)
+            .chars()
+            .peekable(),
+        line: 1,
+    };
+    let mut strings = LocalizableStrings::new();
+
+    while parser.skip_whitespace()? {
+        let key = parser.key()?;
+        parser.skip_whitespace()?;
+        parser.expect('=', "expected `=`")?;
+        parser.skip_whitespace()?;
+        let value = parser.quoted()?;
+        parser.skip_whitespace()?;
+        parser.expect(';', "expected `;`")?;
+        strings.insert(key, value);
+    }
+
+    Ok(strings)
+}
+
+/// Returns the number of arguments a format string takes, counting
+/// `printf`-style conversions like `%@`, `%d` and `%1$@` but not `%%`.
+///
+/// With positional conversions like `%2$@ %1$@`, the count is the highest
+/// position.
+///
+/// ```
+/// use apple_apns::strings::placeholder_count;
+///
+/// assert_eq!(placeholder_count("%@ and %@ have invited you"), 2);
+/// assert_eq!(placeholder_count("%2$@ was invited by %1$@"), 2);
+/// assert_eq!(placeholder_count("100%% done"), 0);
+/// ```
+pub fn placeholder_count(format: &str) -> usize {
+    let mut chars = format.chars().peekable();
+    let mut sequential = 0;
+    let mut positional = 0;
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            continue;
+        }
+        if chars.next_if_eq(&'%').is_some() {
+            continue;
+        }
+
+        let mut digits = String::new();
+        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+            digits.push(digit);
+        }
+        let position = match chars.next_if_eq(&'$') {
+            Some(_) => digits.parse::<usize>().ok(),
+            None => None,
+        };
+
+        // Flags, width, precision and length modifiers.
+        while chars
+            .next_if(|c| c.is_ascii_digit() || "-+ #0.*hlqLzjt'".contains(*c))
+            .is_some()
+        {}
+
+        match chars.next() {
+            Some(c) if "@dDiuUxXoOfFeEgGaAcCsSp".contains(c) => match position {
+                Some(position) => positional = positional.max(position),
+                None => sequential += 1,
+            },
+            _ => {}
+        }
+    }
+
+    sequential.max(positional)
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, reason: &'static str) -> Error {
+        Error::LocalizableStrings {
+            line: self.line,
+            reason,
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    /// Skips whitespace and comments, returning `false` at the end of the
+    /// input.
+    fn skip_whitespace(&mut self) -> Result<bool> {
+        loop {
+            match self.chars.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.next();
+                }
+                Some('/') => {
+                    self.next();
+                    match self.next() {
+                        Some('/') => while !matches!(self.next(), Some('\n') | None) {},
+                        Some('*') => loop {
+                            match self.next() {
+                                Some('*') if self.chars.next_if_eq(&'/').is_some() => break,
+                                Some(_) => {}
+                                None => return Err(self.error("unterminated comment")),
+                            }
+                        },
+                        _ => return Err(self.error("expected a comment")),
+                    }
+                }
+                Some(_) => return Ok(true),
+                None => return Ok(false),
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: char, reason: &'static str) -> Result<()> {
+        match self.chars.next_if_eq(&expected) {
+            Some(_) => Ok(()),
+            None => Err(self.error(reason)),
+        }
+    }
+
+    fn key(&mut self) -> Result<String> {
+        if self.chars.peek() == Some(&'"') {
+            return self.quoted();
+        }
+
+        let mut key = String::new();
+        while let Some(c) = self
+            .chars
+            .next_if(|c| c.is_alphanumeric() || "_.-".contains(*c))
+        {
+            key.push(c);
+        }
+        if key.is_empty() {
+            return Err(self.error("expected a key"));
+        }
+        Ok(key)
+    }
+
+    fn quoted(&mut self) -> Result<String> {
+        if self.chars.next_if_eq(&'"').is_none() {
+            return Err(self.error("expected a quoted string"));
+        }
+
+        let mut string = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => {
+                    let c = match self.next() {
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('U') | Some('u') => self.unicode_escape()?,
+                        Some(c) => c,
+                        None => return Err(self.error("unterminated string")),
+                    };
+                    string.push(c);
+                }
+                Some(c) => string.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self) -> Result<char> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .next()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.error("expected four hex digits after `\\U`"))?;
+            code = code * 16 + digit;
+        }
+        char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_strings() {
+        let strings = parse(concat!(
+            "\u{feff}/* Alerts */\n",
+            "\"GAME_PLAY_REQUEST_FORMAT\" = \"%@ and %@ have invited you to play %@\";\n",
+            "// Unquoted key\n",
+            "TITLE = \"Say \\\"hi\\\"\\n\\U00e9\";\n",
+            "\"PERCENT\"=\"100%% of %d\" ; /* trailing */\n",
+            "\"TITLE\" = \"Last wins\";\n",
+        ))
+        .unwrap();
+
+        assert_eq!(strings.len(), 3);
+        assert_eq!(
+            strings["GAME_PLAY_REQUEST_FORMAT"],
+            "%@ and %@ have invited you to play %@"
+        );
+        assert_eq!(strings["TITLE"], "Last wins");
+        assert_eq!(strings["PERCENT"], "100%% of %d");
+
+        let strings = parse("TITLE = \"Say \\\"hi\\\"\\n\\U00e9\";").unwrap();
+        assert_eq!(strings["TITLE"], "Say \"hi\"\né");
+
+        assert!(parse("").unwrap().is_empty());
+        assert!(parse("  /* only a comment */\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_strings_invalid() {
+        let cases = [
+            ("\"KEY\" \"value\";", 1, "expected `=`"),
+            ("\"KEY\" = \"value\"", 1, "expected `;`"),
+            ("\n\"KEY\" = value;", 2, "expected a quoted string"),
+            ("\"KEY\" = \"value;\n", 2, "unterminated string"),
+            ("/* open", 1, "unterminated comment"),
+            ("= \"value\";", 1, "expected a key"),
+            (
+                "\"KEY\" = \"\\U12\";",
+                1,
+                "expected four hex digits after `\\U`",
+            ),
+        ];
+        for (content, line, reason) in cases {
+            match parse(content) {
+                Err(Error::LocalizableStrings {
+                    line: err_line,
+                    reason: err_reason,
+                }) => assert_eq!((err_line, err_reason), (line, reason), "{content}"),
+                result => panic!("{content}: {result:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn placeholder_counts() {
+        let cases = [
+            ("", 0),
+            ("Hello", 0),
+            ("%@", 1),
+            ("%@ and %@ have invited you to play %@", 3),
+            ("%d%%", 1),
+            ("100%% done", 0),
+            ("%%@", 0),
+            ("%1$@ %2$@", 2),
+            ("%2$@ was invited by %1$@", 2),
+            ("%1$@ and %1$@ again", 1),
+            ("%3$@", 3),
+            ("%.2f km, %5d steps, %ld points, %-10s", 4),
+            ("%lld", 1),
+            ("trailing %", 0),
+        ];
+        for (format, count) in cases {
+            assert_eq!(placeholder_count(format), count, "{format}");
+        }
+    }
+}
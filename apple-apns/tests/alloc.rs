@@ -0,0 +1,186 @@
+//! Counts the heap allocations of a push to keep the per-push overhead of
+//! [`Client`] over a bare reqwest request in check.
+//!
+//! Only allocations on the test thread are counted. The mock server runs on
+//! its own thread, while the tests run on a current-thread runtime, so the
+//! counts include the connection tasks that reqwest spawns.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::future::Future;
+
+use apple_apns::*;
+use http::{header, HeaderValue, Method};
+use tokio::test;
+use url::Url;
+use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+const USER_AGENT: &str = "test/1.0.0";
+const DEVICE_TOKEN: &str = "a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae";
+const APS_ID: &str = "4d947500-498e-4524-8aa8-7220c4e65d75";
+const TOPIC: &str = "com.example.myapp";
+
+/// Pushes sent before counting, to open the connection and fill caches.
+const WARMUP: usize = 3;
+
+/// Pushes counted, of which the fewest allocations are kept.
+const PUSHES: usize = 10;
+
+/// The most allocations a push may take over sending an equivalent request
+/// with reqwest: encoding the payload, headers and device URL, the request
+/// mutator and metrics hooks, and reading the `apns-id` of the response.
+const MAX_CLIENT_OVERHEAD: usize = 32;
+
+struct CountingAllocator;
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count() {
+    let _ = COUNTING.try_with(|counting| {
+        if counting.get() {
+            ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        }
+    });
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Returns the output of `future` and the allocations made on this thread
+/// while it ran.
+async fn allocations<F: Future>(future: F) -> (F::Output, usize) {
+    ALLOCATIONS.with(|allocations| allocations.set(0));
+    COUNTING.with(|counting| counting.set(true));
+    let output = future.await;
+    COUNTING.with(|counting| counting.set(false));
+    (output, ALLOCATIONS.with(Cell::get))
+}
+
+async fn start_mock_server() -> MockServer {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .mount(&mock_server)
+        .await;
+    mock_server
+}
+
+fn create_apns_client_builder(mock_server_uri: &str) -> ClientBuilder<'static> {
+    ClientBuilder {
+        endpoint: Endpoint::Custom(format!("{mock_server_uri}/3/device/").parse().unwrap()),
+        user_agent: USER_AGENT,
+        allow_insecure_http: true,
+        allow_unauthenticated: true,
+        ..Default::default()
+    }
+}
+
+fn request() -> Request {
+    Request {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        id: Some(APS_ID.parse().unwrap()),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    }
+}
+
+/// Returns the fewest allocations of a push with `client`.
+async fn client_allocations(client: &Client) -> usize {
+    for _ in 0..WARMUP {
+        client.post(request()).await.unwrap();
+    }
+
+    let mut fewest = usize::MAX;
+    for request in vec![request(); PUSHES] {
+        let (result, count) = allocations(client.post(request)).await;
+        result.unwrap();
+        fewest = fewest.min(count);
+    }
+    fewest
+}
+
+/// Returns the fewest allocations of sending the request of a push with
+/// reqwest.
+async fn reqwest_allocations(mock_server_uri: &str) -> usize {
+    let client = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .build()
+        .unwrap();
+    let url: Url = format!("{mock_server_uri}/3/device/{DEVICE_TOKEN}")
+        .parse()
+        .unwrap();
+    let (headers, payload): (_, payload::Payload) = request().try_into().unwrap();
+    let body = serde_json::to_vec(&payload).unwrap();
+
+    let mut req = reqwest::Request::new(Method::POST, url);
+    *req.headers_mut() = headers;
+    req.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    *req.body_mut() = Some(body.into());
+
+    for _ in 0..WARMUP {
+        client.execute(req.try_clone().unwrap()).await.unwrap();
+    }
+
+    let mut fewest = usize::MAX;
+    let requests = (0..PUSHES)
+        .map(|_| req.try_clone().unwrap())
+        .collect::<Vec<_>>();
+    for req in requests {
+        let (result, count) = allocations(client.execute(req)).await;
+        result.unwrap();
+        fewest = fewest.min(count);
+    }
+    fewest
+}
+
+#[test]
+async fn post_allocations() {
+    let mock_server = start_mock_server().await;
+
+    let baseline = reqwest_allocations(&mock_server.uri()).await;
+
+    let client = create_apns_client_builder(&mock_server.uri())
+        .build()
+        .unwrap();
+    let plain = client_allocations(&client).await;
+
+    let client = create_apns_client_builder(&mock_server.uri())
+        .build_with_middleware(Ok)
+        .unwrap();
+    let middleware = client_allocations(&client).await;
+
+    assert!(
+        plain <= baseline + MAX_CLIENT_OVERHEAD,
+        "a push allocates {plain} times, {} over reqwest's {baseline}",
+        plain.saturating_sub(baseline)
+    );
+
+    // Without middleware, pushes skip the middleware stack.
+    assert!(
+        plain < middleware,
+        "a push allocates {plain} times without middleware and {middleware} times with"
+    );
+}
@@ -0,0 +1,176 @@
+#![cfg(feature = "blocking")]
+
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use apple_apns::audit::AuditOutcome;
+use apple_apns::*;
+use serde_json::json;
+use wiremock::{
+    matchers::{body_json, header, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+const USER_AGENT: &str = "test/1.0.0";
+const DEVICE_TOKEN: &str = "a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae";
+const APS_ID: &str = "4d947500-498e-4524-8aa8-7220c4e65d75";
+const TOPIC: &str = "com.example.myapp";
+
+/// Starts a mock server on a background thread and returns its URI. The
+/// server runs until `stop` is dropped.
+fn start_mock_server(stop: mpsc::Receiver<()>) -> String {
+    let (uri_tx, uri_rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let mock_server = MockServer::start().await;
+
+            Mock::given(method("POST"))
+                .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+                .and(header("user-agent", USER_AGENT))
+                .and(header("apns-push-type", "alert"))
+                .and(header("apns-topic", TOPIC))
+                .and(body_json(json!({
+                    "aps": {
+                        "alert": "Hello World!",
+                    },
+                })))
+                .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+                .mount(&mock_server)
+                .await;
+
+            Mock::given(method("POST"))
+                .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                    "reason": "BadDeviceToken",
+                })))
+                .mount(&mock_server)
+                .await;
+
+            uri_tx.send(mock_server.uri()).unwrap();
+
+            // Keep the mock server alive until the test is done.
+            tokio::task::spawn_blocking(move || stop.recv().ok())
+                .await
+                .unwrap();
+        });
+    });
+
+    uri_rx.recv().unwrap()
+}
+
+#[test]
+fn blocking_client() {
+    let (stop_tx, stop_rx) = mpsc::channel();
+    let mock_server_uri = start_mock_server(stop_rx);
+
+    let records = Arc::new(Mutex::new(Vec::new()));
+    let client = ClientBuilder {
+        endpoint: Endpoint::Custom(format!("{mock_server_uri}/3/device/").parse().unwrap()),
+        allow_insecure_http: true,
+        allow_unauthenticated: true,
+        user_agent: USER_AGENT,
+        audit_sink: Some(Arc::new({
+            let records = records.clone();
+            move |record| records.lock().unwrap().push(record)
+        })),
+        ..Default::default()
+    }
+    .build_blocking()
+    .unwrap();
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+
+    let apns_id = client.post(request).unwrap();
+    assert_eq!(APS_ID, apns_id.hyphenated().to_string());
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.replace('a', "b").into(),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+
+    let err = client.post(request).unwrap_err();
+    assert!(matches!(err, Error::Apns(Reason::BadDeviceToken)));
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        alert: Some("x".repeat(header::PAYLOAD_SIZE_LIMIT).into()),
+        ..Default::default()
+    };
+
+    let err = client.post(request).unwrap_err();
+    assert!(matches!(
+        &err,
+        Error::Validation(errors)
+            if matches!(errors[..], [ValidationError::PayloadSize { .. }])
+    ));
+
+    let outcomes = records
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|record| (record.outcome, record.reason))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        outcomes,
+        [
+            (AuditOutcome::Accepted, None),
+            (AuditOutcome::Rejected, Some("BadDeviceToken")),
+            (AuditOutcome::Invalid, None),
+        ]
+    );
+
+    drop(stop_tx);
+}
+
+#[test]
+fn blocking_client_infer_push_type() {
+    let (stop_tx, stop_rx) = mpsc::channel();
+    let mock_server_uri = start_mock_server(stop_rx);
+
+    let records = Arc::new(Mutex::new(Vec::new()));
+    let client = ClientBuilder {
+        endpoint: Endpoint::Custom(format!("{mock_server_uri}/3/device/").parse().unwrap()),
+        allow_insecure_http: true,
+        allow_unauthenticated: true,
+        user_agent: USER_AGENT,
+        infer_push_type: true,
+        audit_sink: Some(Arc::new({
+            let records = records.clone();
+            move |record| records.lock().unwrap().push(record)
+        })),
+        ..Default::default()
+    }
+    .build_blocking()
+    .unwrap();
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        topic: Some(TOPIC.into()),
+        content_available: true,
+        ..Default::default()
+    };
+    let http_request = client.build_http_request(request.clone()).unwrap();
+    assert_eq!(http_request.headers()["apns-push-type"], "background");
+
+    // The mock server only accepts alerts.
+    let err = client.post(request).unwrap_err();
+    assert!(matches!(err, Error::Apns(Reason::BadDeviceToken)));
+
+    let records = records.lock().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].push_type, PushType::Background);
+
+    drop(stop_tx);
+}
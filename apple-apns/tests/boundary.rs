@@ -0,0 +1,94 @@
+//! Usage without depending on `http`, `time`, `url` or `uuid` directly, as in
+//! crates that use different versions of them.
+
+use apple_apns::{time::OffsetDateTime, url::Url, uuid::Uuid, *};
+use wiremock::{
+    matchers::{header, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+const DEVICE_TOKEN: &str = "a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae";
+const APS_ID: &str = "4d947500-498e-4524-8aa8-7220c4e65d75";
+
+#[test]
+fn request_id_from_str() {
+    let mut request = Request::<()>::default();
+    request.set_id_str(APS_ID).unwrap();
+    assert_eq!(request.id, Some(Uuid::parse_str(APS_ID).unwrap()));
+
+    assert!(matches!(
+        request.set_id_str("not-a-uuid"),
+        Err(Error::InvalidHeader {
+            name: "apns-id",
+            ..
+        })
+    ));
+    assert!(matches!(
+        request.set_id_str(&Uuid::nil().to_string()),
+        Err(Error::NilApnsId)
+    ));
+    assert_eq!(request.id, Some(Uuid::parse_str(APS_ID).unwrap()));
+}
+
+#[test]
+fn request_header_pairs() {
+    let mut request = Request::builder()
+        .device_token(DEVICE_TOKEN)
+        .topic("com.example.myapp")
+        .expiration(OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap())
+        .alert("Hello World!")
+        .build()
+        .unwrap();
+    request.set_id_str(APS_ID).unwrap();
+
+    let mut pairs = request.header_pairs().unwrap();
+    pairs.sort();
+    assert_eq!(
+        pairs,
+        [
+            ("apns-expiration", "1700000000"),
+            ("apns-id", APS_ID),
+            ("apns-push-type", "alert"),
+            ("apns-topic", "com.example.myapp"),
+            ("content-type", "application/json"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+    );
+}
+
+#[tokio::test]
+async fn client_reexported_types() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .and(header("apns-id", APS_ID))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let endpoint: Url = format!("{}/3/device/", mock_server.uri()).parse().unwrap();
+    let client = ClientBuilder {
+        endpoint: Endpoint::Custom(endpoint),
+        allow_insecure_http: true,
+        allow_unauthenticated: true,
+        ..Default::default()
+    }
+    .build()
+    .unwrap();
+
+    let mut request = Request::builder()
+        .device_token(DEVICE_TOKEN)
+        .alert("Hello World!")
+        .build()
+        .unwrap();
+    request.set_id_str(APS_ID).unwrap();
+
+    let http_request: http::Request<Vec<u8>> =
+        client.build_http_request(request.clone()).await.unwrap();
+    assert_eq!(http_request.headers()["apns-id"], APS_ID);
+
+    let apns_id: Uuid = client.post(request).await.unwrap();
+    assert_eq!(apns_id.to_string(), APS_ID);
+}
@@ -0,0 +1,226 @@
+use apple_apns::*;
+use serde_json::json;
+use tokio::test;
+use wiremock::{
+    matchers::{body_json, header, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+const BUNDLE_ID: &str = "com.example.myapp";
+const CHANNEL_ID: &str = "dHN0LXNyY2gtY2hubA==";
+const APNS_ID: &str = "4d947500-498e-4524-8aa8-7220c4e65d75";
+
+fn create_apns_client(mock_server_uri: &str) -> Client {
+    ClientBuilder {
+        endpoint: Endpoint::Custom(format!("{mock_server_uri}/3/device/").parse().unwrap()),
+        allow_insecure_http: true,
+        allow_unauthenticated: true,
+        ..Default::default()
+    }
+    .build()
+    .unwrap()
+}
+
+#[test]
+async fn create_channel() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/1/apps/{BUNDLE_ID}/channels")))
+        .and(header("content-type", "application/json"))
+        .and(body_json(json!({
+            "message-storage-policy": 1,
+            "push-type": "LiveActivity",
+        })))
+        .respond_with(ResponseTemplate::new(201).insert_header("apns-channel-id", CHANNEL_ID))
+        .mount(&mock_server)
+        .await;
+
+    let client = create_apns_client(&mock_server.uri());
+
+    let channel_id = client
+        .create_channel(
+            BUNDLE_ID,
+            &ChannelConfig {
+                message_storage_policy: MessageStoragePolicy::MostRecentMessageStored,
+                push_type: ChannelPushType::LiveActivity,
+            },
+        )
+        .await;
+
+    drop(mock_server);
+
+    assert_eq!(channel_id.unwrap(), CHANNEL_ID);
+}
+
+#[test]
+async fn read_channel() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path(format!("/1/apps/{BUNDLE_ID}/channels")))
+        .and(header("apns-channel-id", CHANNEL_ID))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "message-storage-policy": 0,
+            "push-type": "LiveActivity",
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let client = create_apns_client(&mock_server.uri());
+
+    let config = client.read_channel(BUNDLE_ID, CHANNEL_ID).await;
+
+    drop(mock_server);
+
+    assert_eq!(
+        config.unwrap(),
+        ChannelConfig {
+            message_storage_policy: MessageStoragePolicy::NoMessageStored,
+            push_type: ChannelPushType::LiveActivity,
+        }
+    );
+}
+
+#[test]
+async fn read_all_channels() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path(format!("/1/apps/{BUNDLE_ID}/all-channels")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "channels": [CHANNEL_ID],
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let client = create_apns_client(&mock_server.uri());
+
+    let channels = client.read_all_channels(BUNDLE_ID).await;
+
+    drop(mock_server);
+
+    assert_eq!(channels.unwrap(), [CHANNEL_ID]);
+}
+
+#[test]
+async fn delete_channel() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("DELETE"))
+        .and(path(format!("/1/apps/{BUNDLE_ID}/channels")))
+        .and(header("apns-channel-id", CHANNEL_ID))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_apns_client(&mock_server.uri());
+
+    client.delete_channel(BUNDLE_ID, CHANNEL_ID).await.unwrap();
+
+    mock_server.verify().await;
+}
+
+#[test]
+async fn channel_errors() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path(format!("/1/apps/{BUNDLE_ID}/channels")))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+            "reason": "ChannelNotRegistered",
+        })))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/1/apps/{BUNDLE_ID}/channels")))
+        .respond_with(ResponseTemplate::new(403).set_body_json(json!({
+            "reason": "CannotCreateChannelConfig",
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let client = create_apns_client(&mock_server.uri());
+
+    let read_err = client
+        .read_channel(BUNDLE_ID, CHANNEL_ID)
+        .await
+        .unwrap_err();
+    let create_err = client
+        .create_channel(BUNDLE_ID, &ChannelConfig::default())
+        .await
+        .unwrap_err();
+
+    drop(mock_server);
+
+    assert!(matches!(
+        read_err,
+        Error::Apns(Reason::ChannelNotRegistered)
+    ));
+    assert!(matches!(
+        create_err,
+        Error::Apns(Reason::CannotCreateChannelConfig)
+    ));
+}
+
+#[test]
+async fn post_broadcast() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/4/broadcasts/apps/{BUNDLE_ID}")))
+        .and(header("apns-channel-id", CHANNEL_ID))
+        .and(header("apns-push-type", "liveactivity"))
+        .and(body_json(json!({
+            "aps": {
+                "timestamp": 1168364460,
+                "event": "update",
+                "content-state": {
+                    "currentHealthLevel": 0.941,
+                },
+            },
+        })))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APNS_ID))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(header("apns-topic", BUNDLE_ID))
+        .respond_with(ResponseTemplate::new(400))
+        .mount(&mock_server)
+        .await;
+
+    let client = create_apns_client(&mock_server.uri());
+
+    let request = Request::<()> {
+        push_type: PushType::Liveactivity,
+        topic: Some(BUNDLE_ID.into()),
+        timestamp: Some(time::OffsetDateTime::from_unix_timestamp(1168364460).unwrap()),
+        event: Some(Event::Update),
+        content_state: Some(json!({ "currentHealthLevel": 0.941 })),
+        ..Default::default()
+    };
+
+    let apns_id = client
+        .post_broadcast(BUNDLE_ID, CHANNEL_ID, request.clone())
+        .await;
+    let alert_err = client
+        .post_broadcast(
+            BUNDLE_ID,
+            CHANNEL_ID,
+            Request::<()> {
+                push_type: PushType::Alert,
+                ..request
+            },
+        )
+        .await
+        .unwrap_err();
+
+    mock_server.verify().await;
+
+    assert_eq!(apns_id.unwrap().to_string(), APNS_ID);
+    assert!(matches!(alert_err, Error::InvalidRequest(_)));
+}
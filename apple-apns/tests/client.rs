@@ -1,4 +1,9 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use apple_apns::uuid::Uuid;
 use apple_apns::*;
+use http::StatusCode;
 use serde_json::json;
 use tokio::test;
 use wiremock::{
@@ -12,13 +17,53 @@ const APS_ID: &str = "4d947500-498e-4524-8aa8-7220c4e65d75";
 const TOPIC: &str = "com.example.myapp";
 
 fn create_apns_client(mock_server_uri: &str) -> Client {
+    create_apns_client_builder(mock_server_uri).build().unwrap()
+}
+
+fn create_apns_client_builder(mock_server_uri: &str) -> ClientBuilder<'static> {
     ClientBuilder {
         endpoint: Endpoint::Custom(format!("{mock_server_uri}/3/device/").parse().unwrap()),
         user_agent: USER_AGENT,
+        allow_insecure_http: true,
+        allow_unauthenticated: true,
         ..Default::default()
     }
-    .build()
-    .unwrap()
+}
+
+fn content_length(request: &wiremock::Request) -> usize {
+    request.headers[&"content-length".into()]
+        .as_str()
+        .parse()
+        .unwrap()
+}
+
+#[derive(Default)]
+struct CountingMetrics {
+    payloads: Mutex<Vec<usize>>,
+    requests: Mutex<Vec<PushType>>,
+    responses: Mutex<Vec<(StatusCode, Option<Reason>)>>,
+    errors: Mutex<Vec<String>>,
+}
+
+impl Metrics for CountingMetrics {
+    fn on_payload(&self, _push_type: PushType, payload_size: usize) {
+        self.payloads.lock().unwrap().push(payload_size);
+    }
+
+    fn on_request(&self, push_type: PushType) {
+        self.requests.lock().unwrap().push(push_type);
+    }
+
+    fn on_response(&self, status: StatusCode, reason: Option<&Reason>, _latency: Duration) {
+        self.responses
+            .lock()
+            .unwrap()
+            .push((status, reason.cloned()));
+    }
+
+    fn on_error(&self, error: &Error, _latency: Duration) {
+        self.errors.lock().unwrap().push(error.to_string());
+    }
 }
 
 #[test]
@@ -47,7 +92,7 @@ async fn client() {
     let client = create_apns_client(&mock_server.uri());
 
     let request = Request::<()> {
-        device_token: DEVICE_TOKEN.into(),
+        device_token: DEVICE_TOKEN.parse().unwrap(),
         id: Some(APS_ID.parse().unwrap()),
         topic: Some(TOPIC.into()),
         alert: Some(Alert {
@@ -64,3 +109,1969 @@ async fn client() {
 
     assert_eq!(APS_ID, aps_id.unwrap().hyphenated().to_string());
 }
+
+#[test]
+async fn client_allow_insecure_http() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let err = ClientBuilder {
+        allow_insecure_http: false,
+        ..create_apns_client_builder(&mock_server.uri())
+    }
+    .build()
+    .err()
+    .unwrap();
+    assert!(matches!(err, Error::InsecureEndpoint(_)), "{err:?}");
+
+    let client = create_apns_client(&mock_server.uri());
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+    let apns_id = client.post(request).await.unwrap();
+    assert_eq!(apns_id, APS_ID.parse::<Uuid>().unwrap());
+}
+
+#[test]
+async fn client_post_with_response() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .mount(&mock_server)
+        .await;
+
+    let client = create_apns_client(&mock_server.uri());
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+
+    let res = client.post_with_response(request).await.unwrap();
+    let address = *mock_server.address();
+    let received = mock_server.received_requests().await.unwrap();
+
+    drop(mock_server);
+
+    assert_eq!(res.apns_id.hyphenated().to_string(), APS_ID);
+    // The tests use the `http1` feature to talk to wiremock.
+    assert_eq!(res.version, http::Version::HTTP_11);
+    assert_eq!(res.remote_addr, Some(address));
+    assert_eq!(res.payload_size, content_length(&received[0]));
+    assert_eq!(res.payload_size, received[0].body.len());
+}
+
+#[test]
+async fn client_metrics() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .up_to_n_times(1)
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .respond_with(ResponseTemplate::new(410).set_body_json(json!({
+            "reason": "Unregistered",
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let metrics = Arc::new(CountingMetrics::default());
+    let client = ClientBuilder {
+        metrics: metrics.clone(),
+        ..create_apns_client_builder(&mock_server.uri())
+    }
+    .build()
+    .unwrap();
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+
+    client.post(request.clone()).await.unwrap();
+    let err = client.post(request).await.unwrap_err();
+    let received = mock_server.received_requests().await.unwrap();
+
+    drop(mock_server);
+
+    assert!(matches!(err, Error::Apns(Reason::Unregistered { .. })));
+    assert_eq!(
+        *metrics.payloads.lock().unwrap(),
+        received.iter().map(content_length).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        *metrics.requests.lock().unwrap(),
+        [PushType::Alert, PushType::Alert]
+    );
+    assert_eq!(
+        *metrics.responses.lock().unwrap(),
+        [
+            (StatusCode::OK, None),
+            (
+                StatusCode::GONE,
+                Some(Reason::Unregistered { timestamp: None })
+            )
+        ]
+    );
+    assert!(metrics.errors.lock().unwrap().is_empty());
+}
+
+#[test]
+async fn client_metrics_transport_error() {
+    // Nothing listens on the address of a closed listener.
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let metrics = Arc::new(CountingMetrics::default());
+    let client = ClientBuilder {
+        metrics: metrics.clone(),
+        ..create_apns_client_builder(&format!("http://{addr}"))
+    }
+    .build()
+    .unwrap();
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+    let err = client.post(request).await.unwrap_err();
+
+    assert_eq!(*metrics.requests.lock().unwrap(), [PushType::Alert]);
+    assert!(metrics.responses.lock().unwrap().is_empty());
+    assert_eq!(*metrics.errors.lock().unwrap(), [err.to_string()]);
+}
+
+#[test]
+async fn client_max_inflight_bytes() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("apns-id", APS_ID)
+                .set_delay(Duration::from_millis(100)),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        topic: Some(TOPIC.into()),
+        alert: Some("x".repeat(3900).into()),
+        ..Default::default()
+    };
+    let body_size = serde_json::to_vec(&json!({ "aps": { "alert": "x".repeat(3900) } }))
+        .unwrap()
+        .len();
+    let max_inflight_bytes = 2 * body_size + body_size / 2;
+
+    let client = Arc::new(
+        ClientBuilder {
+            max_inflight_bytes: Some(max_inflight_bytes),
+            ..create_apns_client_builder(&mock_server.uri())
+        }
+        .build()
+        .unwrap(),
+    );
+
+    let tasks = (0..6)
+        .map(|_| {
+            let client = client.clone();
+            let request = request.clone();
+            tokio::spawn(async move { client.post(request).await })
+        })
+        .collect::<Vec<_>>();
+    for task in tasks {
+        task.await.unwrap().unwrap();
+    }
+
+    drop(mock_server);
+
+    let stats = client.stats();
+    assert_eq!(stats.inflight_bytes, 0);
+    assert_eq!(stats.peak_inflight_bytes, 2 * body_size);
+}
+
+#[test]
+async fn client_error_body_with_bom() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .respond_with(ResponseTemplate::new(400).set_body_raw(
+            &b"\xEF\xBB\xBF{\"reason\":\"BadDeviceToken\",\"extra\":1}\n"[..],
+            "application/json",
+        ))
+        .mount(&mock_server)
+        .await;
+
+    let client = create_apns_client(&mock_server.uri());
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+
+    let err = client.post(request).await.unwrap_err();
+
+    drop(mock_server);
+
+    assert!(matches!(err, Error::Apns(Reason::BadDeviceToken)));
+}
+
+#[test]
+async fn client_too_many_requests() {
+    let cases = [
+        (ResponseTemplate::new(429), ThrottleScope::Device, None),
+        (
+            ResponseTemplate::new(429).insert_header("retry-after", "120"),
+            ThrottleScope::Provider,
+            Some(Duration::from_secs(120)),
+        ),
+    ];
+
+    for (template, scope, retry_after) in cases {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+            .respond_with(template.set_body_json(json!({ "reason": "TooManyRequests" })))
+            .mount(&mock_server)
+            .await;
+
+        let client = create_apns_client(&mock_server.uri());
+
+        let request = Request::<()> {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            topic: Some(TOPIC.into()),
+            alert: Some("Hello World!".into()),
+            ..Default::default()
+        };
+
+        let err = client.post(request).await.unwrap_err();
+
+        assert_eq!(err.reason(), Some(&Reason::TooManyRequests));
+        assert_eq!(err.throttle(), Some(&ThrottleInfo { scope, retry_after }));
+        assert!(err.to_string().ends_with(&format!("({scope} throttling)")));
+    }
+}
+
+#[test]
+async fn client_error_body() {
+    let cases: [(u16, &[u8]); 3] = [
+        (
+            410,
+            br#"{"reason":"Unregistered","timestamp":1685952000000}"#,
+        ),
+        (400, b"<html>Bad Request</html>"),
+        (500, b""),
+    ];
+
+    for (status, body) in cases {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+            .respond_with(ResponseTemplate::new(status).set_body_raw(body, "application/json"))
+            .mount(&mock_server)
+            .await;
+
+        let metrics = Arc::new(CountingMetrics::default());
+        let client = ClientBuilder {
+            metrics: metrics.clone(),
+            ..create_apns_client_builder(&mock_server.uri())
+        }
+        .build()
+        .unwrap();
+
+        let request = Request::<()> {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            topic: Some(TOPIC.into()),
+            alert: Some("Hello World!".into()),
+            ..Default::default()
+        };
+        let err = client.post(request).await.unwrap_err();
+        let responses = metrics.responses.lock().unwrap().clone();
+        let status = StatusCode::from_u16(status).unwrap();
+
+        match status {
+            StatusCode::GONE => {
+                assert!(
+                    matches!(err, Error::Apns(Reason::Unregistered { .. })),
+                    "{err:?}"
+                );
+                assert_eq!(responses, [(status, err.reason().cloned())]);
+            }
+            _ => {
+                assert!(
+                    matches!(
+                        &err,
+                        Error::UnexpectedResponse { status: s, body: Some(b) }
+                            if *s == status && b == body
+                    ),
+                    "{err:?}"
+                );
+                assert_eq!(responses, [(status, None)]);
+            }
+        }
+    }
+
+    let err = Error::UnexpectedResponse {
+        status: StatusCode::BAD_REQUEST,
+        body: Some(b"<html>Bad Request</html>".to_vec()),
+    };
+    assert_eq!(
+        err.to_string(),
+        r#"unexpected 400 Bad Request response: "<html>Bad Request</html>""#
+    );
+    let err = Error::UnexpectedResponse {
+        status: StatusCode::INTERNAL_SERVER_ERROR,
+        body: Some(vec![]),
+    };
+    assert_eq!(
+        err.to_string(),
+        "unexpected 500 Internal Server Error response: empty body"
+    );
+}
+
+#[cfg(feature = "jwt")]
+#[test]
+async fn client_build_http_request() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .mount(&mock_server)
+        .await;
+
+    let client = ClientBuilder {
+        authentication: Some(Authentication::Token {
+            key_id: "ABC123DEF4".into(),
+            key_pem: include_bytes!("fixtures/AuthKey_ABC123DEF4.p8")[..].into(),
+            team_id: "DEF123GHIJ".into(),
+        }),
+        ..create_apns_client_builder(&mock_server.uri())
+    }
+    .build()
+    .unwrap();
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        id: Some(APS_ID.parse().unwrap()),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+
+    let http_request = client.build_http_request(request.clone()).await.unwrap();
+    client.post(request).await.unwrap();
+
+    let received = mock_server.received_requests().await.unwrap();
+
+    drop(mock_server);
+
+    assert_eq!(received.len(), 1);
+    let received = &received[0];
+
+    assert_eq!(http_request.method(), "POST");
+    assert_eq!(http_request.uri().path(), received.url.path());
+    assert_eq!(http_request.body(), &received.body);
+
+    let authorization = &http_request.headers()["authorization"];
+    assert!(authorization.is_sensitive());
+    assert!(authorization.to_str().unwrap().starts_with("Bearer "));
+
+    for (name, value) in http_request.headers() {
+        let (_, received_values) = received
+            .headers
+            .iter()
+            .find(|(received_name, _)| received_name.as_str() == name.as_str())
+            .unwrap();
+        assert_eq!(value.to_str().unwrap(), received_values.as_str());
+    }
+}
+
+#[cfg(feature = "jwt")]
+#[test]
+async fn client_provider_token_retry() {
+    let cases = [
+        ("ExpiredProviderToken", 200),
+        ("InvalidProviderToken", 200),
+        // A failed retry returns the original error.
+        ("ExpiredProviderToken", 500),
+    ];
+
+    for (reason, retry_status) in cases {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+            .respond_with(ResponseTemplate::new(403).set_body_json(json!({ "reason": reason })))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+            .respond_with(ResponseTemplate::new(retry_status).insert_header("apns-id", APS_ID))
+            .mount(&mock_server)
+            .await;
+
+        // The mutator signs the provider token of every attempt.
+        let signed = Arc::new(Mutex::new(0));
+        let mutator_signed = signed.clone();
+        let metrics = Arc::new(CountingMetrics::default());
+        let client = ClientBuilder {
+            metrics: metrics.clone(),
+            request_mutator: Some(Arc::new(
+                move |headers: &mut http::HeaderMap, body: &[u8]| -> Result<()> {
+                    let authorization = headers["authorization"].as_bytes();
+                    let signature = signature(authorization, body).parse()?;
+                    headers.insert("x-signature", signature);
+                    *mutator_signed.lock().unwrap() += 1;
+                    Ok(())
+                },
+            )),
+            authentication: Some(Authentication::Token {
+                key_id: "ABC123DEF4".into(),
+                key_pem: include_bytes!("fixtures/AuthKey_ABC123DEF4.p8")[..].into(),
+                team_id: "DEF123GHIJ".into(),
+            }),
+            ..create_apns_client_builder(&mock_server.uri())
+        }
+        .build()
+        .unwrap();
+
+        let request = Request::<()> {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            topic: Some(TOPIC.into()),
+            alert: Some("Hello World!".into()),
+            ..Default::default()
+        };
+        let result = client.post(request).await;
+        let received = mock_server.received_requests().await.unwrap();
+
+        drop(mock_server);
+
+        match retry_status {
+            200 => assert_eq!(result.unwrap().hyphenated().to_string(), APS_ID),
+            _ => assert_eq!(result.unwrap_err().reason().unwrap().as_str(), reason),
+        }
+        assert_eq!(received.len(), 2, "{reason}");
+        assert_eq!(received[0].body, received[1].body);
+        assert_eq!(*signed.lock().unwrap(), 2);
+        for request in &received {
+            assert_eq!(
+                request.headers[&"x-signature".into()].as_str(),
+                signature(
+                    request.headers[&"authorization".into()].as_str().as_bytes(),
+                    &request.body
+                )
+            );
+        }
+        assert_eq!(metrics.payloads.lock().unwrap().len(), 1);
+        assert_eq!(metrics.requests.lock().unwrap().len(), 2);
+    }
+}
+
+#[cfg(feature = "jwt")]
+#[test]
+async fn client_proactive_token_refresh() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .and(wiremock::matchers::header_exists("authorization"))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let builder = || ClientBuilder {
+        authentication: Some(Authentication::Token {
+            key_id: "ABC123DEF4".into(),
+            key_pem: include_bytes!("fixtures/AuthKey_ABC123DEF4.p8")[..].into(),
+            team_id: "DEF123GHIJ".into(),
+        }),
+        proactive_token_refresh: true,
+        ..create_apns_client_builder(&mock_server.uri())
+    };
+
+    for client in [builder().build().unwrap(), builder().build_lazy().unwrap()] {
+        let request = Request::<()> {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            topic: Some(TOPIC.into()),
+            alert: Some("Hello World!".into()),
+            ..Default::default()
+        };
+        client.post(request).await.unwrap();
+    }
+
+    mock_server.verify().await;
+}
+
+#[test]
+async fn client_normalizes_device_token() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_apns_client(&mock_server.uri());
+
+    let request = Request::<()> {
+        // Copied from Xcode, `<…>` in groups of eight.
+        device_token: format!(
+            " <{}>\n",
+            DEVICE_TOKEN
+                .to_uppercase()
+                .as_bytes()
+                .chunks(8)
+                .map(|chunk| std::str::from_utf8(chunk).unwrap())
+                .collect::<Vec<_>>()
+                .join(" ")
+        )
+        .into(),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+
+    let aps_id = client.post(request).await.unwrap();
+
+    mock_server.verify().await;
+
+    assert_eq!(APS_ID, aps_id.hyphenated().to_string());
+}
+
+#[test]
+async fn client_rejects_device_token_with_whitespace() {
+    let client = create_apns_client("http://localhost");
+
+    let device_token = format!("{} {}", &DEVICE_TOKEN[..32], &DEVICE_TOKEN[32..]);
+    let request = Request::<()> {
+        device_token: device_token.clone().into(),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+
+    let err = client.post(request).await.unwrap_err();
+
+    assert!(matches!(err, Error::InvalidDeviceToken));
+    assert!(matches!(
+        device_url(&Endpoint::Production, &device_token),
+        Err(Error::InvalidDeviceToken)
+    ));
+}
+
+#[test]
+async fn client_rejects_invalid_device_token() {
+    let client = create_apns_client("http://localhost");
+
+    let request = Request::<()> {
+        device_token: format!("{}-{}", &DEVICE_TOKEN[..32], &DEVICE_TOKEN[32..]).into(),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+
+    let err = client.post(request).await.unwrap_err();
+
+    assert!(matches!(
+        &err,
+        Error::Validation(errors) if errors == &[ValidationError::DeviceToken]
+    ));
+}
+
+#[test]
+async fn client_nil_apns_id() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .mount(&mock_server)
+        .await;
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        id: Some(uuid::Uuid::nil()),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+
+    let client = create_apns_client(&mock_server.uri());
+    let err = client.post(request.clone()).await.unwrap_err();
+    assert!(matches!(err, Error::NilApnsId));
+
+    // Lenient clients replace the nil ID with a generated one.
+    let client = ClientBuilder {
+        generate_apns_id: true,
+        validate_requests: false,
+        ..create_apns_client_builder(&mock_server.uri())
+    }
+    .build()
+    .unwrap();
+    let apns_id = client.post(request).await.unwrap();
+    assert!(!apns_id.is_nil());
+
+    let requests = mock_server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0].headers[&"apns-id".into()].as_str(),
+        apns_id.hyphenated().to_string()
+    );
+}
+
+#[test]
+async fn client_generate_apns_id() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .respond_with(ResponseTemplate::new(200))
+        .up_to_n_times(1)
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "reason": "BadTopic",
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let client = ClientBuilder {
+        generate_apns_id: true,
+        ..create_apns_client_builder(&mock_server.uri())
+    }
+    .build()
+    .unwrap();
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+
+    let success_id = client.post(request.clone()).await.unwrap();
+    let err = client.post(request).await.unwrap_err();
+
+    let received = mock_server.received_requests().await.unwrap();
+
+    drop(mock_server);
+
+    let sent_ids = received
+        .iter()
+        .map(|request| {
+            request.headers[&"apns-id".into()]
+                .as_str()
+                .parse::<uuid::Uuid>()
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    assert!(!success_id.is_nil());
+    assert_eq!(sent_ids[0], success_id);
+    assert_eq!(err.apns_id(), Some(sent_ids[1]));
+    assert_ne!(sent_ids[0], sent_ids[1]);
+    assert!(matches!(err.reason(), Some(Reason::BadTopic)));
+}
+
+#[test]
+async fn client_does_not_retry_dropped_connection() {
+    use tokio::io::AsyncReadExt;
+    use tokio::net::{TcpListener, TcpStream};
+
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+
+    // Closes the first connection after reading the request without
+    // responding, as if it failed after APNs processed the request, then
+    // proxies later connections to the mock server.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let proxy_uri = format!("http://{}", listener.local_addr().unwrap());
+    let mock_server_address = *mock_server.address();
+    tokio::spawn(async move {
+        let (mut first, _) = listener.accept().await.unwrap();
+        let _ = first.read(&mut [0; 4096]).await;
+        drop(first);
+
+        while let Ok((mut inbound, _)) = listener.accept().await {
+            let mut outbound = TcpStream::connect(mock_server_address).await.unwrap();
+            tokio::spawn(async move {
+                let _ = tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await;
+            });
+        }
+    });
+
+    let client = create_apns_client(&proxy_uri);
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+
+    let err = client.post(request).await.unwrap_err();
+
+    mock_server.verify().await;
+
+    assert!(err.reason().is_none(), "{err:?}");
+    assert_eq!(client.stats().goaway_retries, 0);
+}
+
+#[test]
+async fn client_liveactivity() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .and(header("apns-push-type", "liveactivity"))
+        .and(header(
+            "apns-topic",
+            "com.example.myapp.push-type.liveactivity",
+        ))
+        .and(body_json(json!({
+            "aps": {
+                "timestamp": 1168364460,
+                "event": "update",
+                "content-state": {
+                    "currentHealthLevel": 0.941,
+                    "eventDescription": "Power Panda found a sword!",
+                },
+            },
+        })))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_apns_client(&mock_server.uri());
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        push_type: PushType::Liveactivity,
+        topic: Some(TOPIC.into()),
+        timestamp: Some(time::OffsetDateTime::from_unix_timestamp(1168364460).unwrap()),
+        event: Some(Event::Update),
+        content_state: Some(json!({
+            "currentHealthLevel": 0.941,
+            "eventDescription": "Power Panda found a sword!",
+        })),
+        ..Default::default()
+    };
+
+    client.post(request).await.unwrap();
+
+    mock_server.verify().await;
+}
+
+#[test]
+async fn client_pushtotalk() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .and(header("apns-push-type", "pushtotalk"))
+        .and(header("apns-topic", "com.example.myapp.voip-ptt"))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_apns_client(&mock_server.uri());
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        push_type: PushType::Pushtotalk,
+        // The suffix is appended to a bare bundle ID.
+        topic: Some(TOPIC.into()),
+        user_info: Some(()),
+        ..Default::default()
+    };
+
+    client.post(request).await.unwrap();
+
+    mock_server.verify().await;
+}
+
+#[test]
+async fn client_post_mdm() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .and(header("apns-push-type", "mdm"))
+        .and(header("apns-topic", "com.apple.mgmt.External.example"))
+        .and(header("apns-expiration", "0"))
+        .and(body_json(json!({ "mdm": "push-magic" })))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let records = Arc::new(Mutex::new(Vec::new()));
+    let client = ClientBuilder {
+        audit_sink: Some(Arc::new({
+            let records = records.clone();
+            move |record| records.lock().unwrap().push(record)
+        })),
+        ..create_apns_client_builder(&mock_server.uri())
+    }
+    .build()
+    .unwrap();
+
+    let request = MdmRequest {
+        expiration: Expiration::Immediate,
+        topic: Some("com.apple.mgmt.External.example".into()),
+        ..MdmRequest::new(DEVICE_TOKEN, "push-magic")
+    };
+
+    let apns_id = client.post_mdm(request).await.unwrap();
+    assert_eq!(apns_id.to_string(), APS_ID);
+
+    let requests = mock_server.received_requests().await.unwrap();
+    assert_eq!(requests[0].body, br#"{"mdm":"push-magic"}"#);
+
+    mock_server.verify().await;
+
+    let records = records.lock().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].push_type, PushType::Mdm);
+    assert_eq!(
+        records[0].topic.as_deref(),
+        Some("com.apple.mgmt.External.example")
+    );
+    assert_eq!(records[0].payload_size, Some(requests[0].body.len()));
+    assert_eq!(records[0].apns_id, Some(apns_id));
+    assert_eq!(records[0].outcome, audit::AuditOutcome::Accepted);
+}
+
+#[test]
+async fn client_post_mdm_requires_push_magic() {
+    let client = create_apns_client("http://localhost");
+
+    let err = client
+        .post_mdm(MdmRequest::new(DEVICE_TOKEN, ""))
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        &err,
+        Error::Validation(errors) if errors == &[ValidationError::MissingKey {
+            push_type: PushType::Mdm,
+            key: "mdm",
+        }]
+    ));
+}
+
+#[test]
+async fn client_voip_raw_user_info() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .and(header("apns-push-type", "voip"))
+        .and(header("apns-topic", "com.example.myapp.voip"))
+        .and(body_json(json!({ "caller": "Alice", "call-id": 1 })))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_apns_client(&mock_server.uri());
+
+    let request = Request {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        topic: Some(TOPIC.into()),
+        ..Request::with_raw_user_info(json!({ "caller": "Alice", "call-id": 1 }))
+    };
+
+    client.post(request).await.unwrap();
+
+    let requests = mock_server.received_requests().await.unwrap();
+    assert!(!requests[0]
+        .headers
+        .contains_key(&"apns-priority".parse().unwrap()));
+
+    mock_server.verify().await;
+}
+
+#[test]
+async fn client_widgets_priority() {
+    let client = create_apns_client("http://localhost");
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        push_type: PushType::Widgets,
+        priority: Some(Priority::Immediate),
+        topic: Some(format!("{TOPIC}.push-type.widgets")),
+        ..Default::default()
+    };
+
+    let err = client.post(request).await.unwrap_err();
+
+    assert!(matches!(
+        &err,
+        Error::Validation(errors) if errors == &[ValidationError::Priority {
+            push_type: PushType::Widgets,
+            priority: Priority::Immediate,
+        }]
+    ));
+}
+
+#[cfg(feature = "jwt")]
+#[test]
+async fn client_token_authentication_requires_topic() {
+    let client = ClientBuilder {
+        authentication: Some(Authentication::Token {
+            key_id: "ABC123DEF4".into(),
+            key_pem: include_bytes!("fixtures/AuthKey_ABC123DEF4.p8")[..].into(),
+            team_id: "DEF123GHIJ".into(),
+        }),
+        ..create_apns_client_builder("http://localhost")
+    }
+    .build()
+    .unwrap();
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+
+    assert_eq!(client.validate(&request), Err(vec![ValidationError::Topic]));
+
+    let err = client.post(request).await.unwrap_err();
+    assert!(matches!(
+        &err,
+        Error::Validation(errors) if errors == &[ValidationError::Topic]
+    ));
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+    assert_eq!(client.validate(&request), Ok(()));
+}
+
+#[test]
+async fn client_recent_targets() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .expect(3)
+        .mount(&mock_server)
+        .await;
+
+    let client = ClientBuilder {
+        expected_topics: Some(vec![TOPIC.into()]),
+        ..create_apns_client_builder(&mock_server.uri())
+    }
+    .build()
+    .unwrap();
+
+    for topic in [TOPIC, "com.example.stale", TOPIC] {
+        let request = Request::<()> {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            topic: Some(topic.into()),
+            alert: Some("Hello World!".into()),
+            ..Default::default()
+        };
+        client.post(request).await.unwrap();
+    }
+
+    mock_server.verify().await;
+
+    let recent_targets = client.recent_targets();
+    assert_eq!(
+        recent_targets
+            .iter()
+            .map(|target| (target.topic.as_deref(), target.push_type, target.count))
+            .collect::<Vec<_>>(),
+        [
+            (Some(TOPIC), PushType::Alert, 2),
+            (Some("com.example.stale"), PushType::Alert, 1),
+        ]
+    );
+    assert_eq!(client.stats().unexpected_topics, 1);
+}
+
+#[test]
+async fn client_post_batch() {
+    use futures_util::StreamExt;
+
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "reason": "BadDeviceToken",
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let client = create_apns_client(&mock_server.uri());
+
+    let request = Request::<()> {
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+    let bad_device_token = "b".repeat(64);
+
+    let results = client
+        .post_batch(
+            request,
+            vec![DEVICE_TOKEN.to_string(), bad_device_token.clone()],
+            BatchOptions {
+                ordered: true,
+                ..Default::default()
+            },
+        )
+        .collect::<Vec<_>>()
+        .await;
+    let received = mock_server.received_requests().await.unwrap();
+
+    drop(mock_server);
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].device_token, DEVICE_TOKEN);
+    assert_eq!(
+        results[0].result.as_ref().unwrap().hyphenated().to_string(),
+        APS_ID
+    );
+    assert_eq!(results[0].version, Some(http::Version::HTTP_11));
+    assert!(results[0].remote_addr.is_some());
+    assert_eq!(results[0].payload_size, Some(content_length(&received[0])));
+    assert_eq!(results[1].device_token, bad_device_token);
+    assert!(matches!(
+        results[1].result,
+        Err(Error::Apns(Reason::BadDeviceToken))
+    ));
+    assert_eq!(results[1].remote_addr, None);
+}
+
+#[test]
+async fn client_deadline() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("apns-id", APS_ID)
+                .set_delay(Duration::from_secs(5)),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let metrics = Arc::new(CountingMetrics::default());
+    let client = ClientBuilder {
+        metrics: metrics.clone(),
+        ..create_apns_client_builder(&mock_server.uri())
+    }
+    .build()
+    .unwrap();
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+
+    // A passed deadline fails without sending the request.
+    let err = client
+        .post(Request {
+            deadline: Some(Instant::now()),
+            ..request.clone()
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::DeadlineExceeded), "{err:?}");
+
+    // A slow response is cut off at the deadline.
+    let start = Instant::now();
+    let err = client
+        .post(Request {
+            deadline: Some(start + Duration::from_millis(200)),
+            ..request
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::DeadlineExceeded), "{err:?}");
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(200), "{elapsed:?}");
+    assert!(elapsed < Duration::from_secs(5), "{elapsed:?}");
+
+    assert_eq!(*metrics.requests.lock().unwrap(), [PushType::Alert]);
+    assert!(metrics.responses.lock().unwrap().is_empty());
+    assert_eq!(
+        *metrics.errors.lock().unwrap(),
+        [Error::DeadlineExceeded.to_string()]
+    );
+}
+
+#[test]
+async fn client_post_batch_deadline() {
+    use futures_util::StreamExt;
+
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("apns-id", APS_ID)
+                .set_delay(Duration::from_secs(5)),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_apns_client(&mock_server.uri());
+
+    let request = Request::<()> {
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        deadline: Some(Instant::now() + Duration::from_millis(200)),
+        ..Default::default()
+    };
+
+    // The first request times out at the deadline and the others are
+    // skipped without sending.
+    let results = client
+        .post_batch(
+            request,
+            ["a", "b", "c"].map(|c| c.repeat(64)),
+            BatchOptions {
+                concurrency: 1,
+                ordered: true,
+                ..Default::default()
+            },
+        )
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut report = BatchReport::default();
+    for result in &results {
+        assert!(matches!(result.result, Err(Error::DeadlineExceeded)));
+        report.record(result);
+    }
+    assert_eq!(
+        report.to_string(),
+        "3 sent, 0 ok, 3 failed (3 DeadlineExceeded)"
+    );
+}
+
+#[test]
+async fn client_post_batch_throttled() {
+    use futures_util::StreamExt;
+
+    let device_tokens = ["a", "b", "c"].map(|c| c.repeat(64));
+    let cases = [
+        // Throttling of one device token doesn't slow down the batch.
+        (ResponseTemplate::new(429), Duration::ZERO),
+        // Provider throttling pauses the batch for the Retry-After.
+        (
+            ResponseTemplate::new(429).insert_header("retry-after", "1"),
+            Duration::from_secs(1),
+        ),
+    ];
+
+    for (template, min_elapsed) in cases {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path(format!("/3/device/{}", device_tokens[0])))
+            .respond_with(template.set_body_json(json!({ "reason": "TooManyRequests" })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let client = create_apns_client(&mock_server.uri());
+
+        let request = Request::<()> {
+            topic: Some(TOPIC.into()),
+            alert: Some("Hello World!".into()),
+            ..Default::default()
+        };
+
+        let start = Instant::now();
+        let results = client
+            .post_batch(
+                request,
+                device_tokens.clone(),
+                BatchOptions {
+                    concurrency: 1,
+                    ordered: true,
+                    throttle_backoff: Duration::from_secs(60),
+                },
+            )
+            .collect::<Vec<_>>()
+            .await;
+        let elapsed = start.elapsed();
+
+        let mut report = BatchReport::default();
+        for result in &results {
+            report.record(result);
+        }
+        assert_eq!(
+            report.to_string(),
+            "3 sent, 2 ok, 1 failed (1 TooManyRequests)"
+        );
+        assert!(elapsed >= min_elapsed, "{elapsed:?}");
+        assert!(
+            elapsed < min_elapsed + Duration::from_secs(10),
+            "{elapsed:?}"
+        );
+    }
+}
+
+/// Signs the topic and body of a request like an egress proxy would.
+fn signature(topic: &[u8], body: &[u8]) -> String {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    (b"secret", topic, body).hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+#[test]
+async fn client_request_mutator() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = ClientBuilder {
+        request_mutator: Some(Arc::new(
+            |headers: &mut http::HeaderMap, body: &[u8]| -> Result<()> {
+                let topic = headers["apns-topic"].as_bytes();
+                let signature = signature(topic, body).parse()?;
+                headers.insert("x-signature", signature);
+                Ok(())
+            },
+        )),
+        ..create_apns_client_builder(&mock_server.uri())
+    }
+    .build()
+    .unwrap();
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+    client.post(request).await.unwrap();
+
+    let requests = mock_server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0].headers[&"x-signature".into()].as_str(),
+        signature(TOPIC.as_bytes(), &requests[0].body)
+    );
+}
+
+#[test]
+async fn client_request_mutator_errors() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+
+    let mutators: [Arc<dyn RequestMutator + Send + Sync>; 4] = [
+        Arc::new(|_: &mut http::HeaderMap, _: &[u8]| Err(Error::Unknown)),
+        Arc::new(|headers: &mut http::HeaderMap, _: &[u8]| -> Result<()> {
+            headers.remove("apns-topic");
+            Ok(())
+        }),
+        Arc::new(|headers: &mut http::HeaderMap, _: &[u8]| -> Result<()> {
+            headers.insert(
+                "apns-push-type",
+                http::HeaderValue::from_static("background"),
+            );
+            Ok(())
+        }),
+        Arc::new(|headers: &mut http::HeaderMap, _: &[u8]| -> Result<()> {
+            headers.insert("apns-priority", http::HeaderValue::from_static("5"));
+            Ok(())
+        }),
+    ];
+    for mutator in mutators {
+        let client = ClientBuilder {
+            request_mutator: Some(mutator),
+            ..create_apns_client_builder(&mock_server.uri())
+        }
+        .build()
+        .unwrap();
+
+        let err = client.post(request.clone()).await.unwrap_err();
+        assert!(matches!(err, Error::Mutator(_)), "{err:?}");
+    }
+}
+
+#[cfg(feature = "rustls")]
+fn create_certificate_apns_client(mock_server_uri: &str, validate_requests: bool) -> Client {
+    ClientBuilder {
+        authentication: Some(Authentication::Certificate {
+            client_pem: include_bytes!("fixtures/client.pem"),
+        }),
+        validate_requests,
+        ..create_apns_client_builder(mock_server_uri)
+    }
+    .build()
+    .unwrap()
+}
+
+#[cfg(feature = "rustls")]
+#[test]
+async fn client_location_requires_token_authentication() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_certificate_apns_client(&mock_server.uri(), true);
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        push_type: PushType::Location,
+        topic: Some(TOPIC.into()),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        client.validate(&request),
+        Err(vec![ValidationError::Authentication {
+            push_type: PushType::Location,
+        }])
+    );
+
+    let err = client.post(request).await.unwrap_err();
+
+    mock_server.verify().await;
+
+    assert!(matches!(err, Error::Validation(_)));
+}
+
+#[cfg(feature = "rustls")]
+#[test]
+async fn client_validate_requests_disabled() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .and(header("apns-push-type", "location"))
+        .and(header("apns-topic", "com.example.myapp.location-query"))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = create_certificate_apns_client(&mock_server.uri(), false);
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        push_type: PushType::Location,
+        topic: Some(TOPIC.into()),
+        ..Default::default()
+    };
+
+    client.post(request).await.unwrap();
+
+    mock_server.verify().await;
+}
+
+#[test]
+async fn client_lint_localizable_strings() {
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        alert: Some(Alert::localized(
+            "GAME_PLAY_REQUEST_FORMAT",
+            ["Jenna", "Frank"],
+        )),
+        ..Default::default()
+    };
+
+    let client = create_apns_client(&MockServer::start().await.uri());
+    assert_eq!(client.lint(&request), vec![]);
+
+    let strings = strings::parse(
+        r#""GAME_PLAY_REQUEST_FORMAT" = "%1$@ and %2$@ have invited you to play %3$@";"#,
+    )
+    .unwrap();
+    let client = ClientBuilder {
+        localizable_strings: Some(Arc::new(strings)),
+        ..create_apns_client_builder(&MockServer::start().await.uri())
+    }
+    .build()
+    .unwrap();
+    assert_eq!(
+        client.lint(&request),
+        vec![Lint::LocArgs {
+            field: "loc-key",
+            key: "GAME_PLAY_REQUEST_FORMAT".into(),
+            placeholders: 3,
+            args: 2,
+        }]
+    );
+}
+
+#[cfg(feature = "jwt")]
+#[test]
+async fn client_token_from_p8_path() {
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/AuthKey_ABC123DEF4.p8"
+    );
+    let authentication = Authentication::token_from_p8_path(path, "DEF123GHIJ").unwrap();
+    match &authentication {
+        Authentication::Token {
+            key_id,
+            key_pem,
+            team_id,
+        } => {
+            assert_eq!(key_id, "ABC123DEF4");
+            assert_eq!(
+                &key_pem[..],
+                &include_bytes!("fixtures/AuthKey_ABC123DEF4.p8")[..]
+            );
+            assert_eq!(team_id, "DEF123GHIJ");
+        }
+        #[allow(unreachable_patterns)]
+        _ => unreachable!(),
+    }
+
+    ClientBuilder {
+        authentication: Some(authentication),
+        ..create_apns_client_builder("http://localhost")
+    }
+    .build()
+    .unwrap();
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/key.p8");
+    let err = Authentication::token_from_p8_path(path, "DEF123GHIJ").unwrap_err();
+    assert!(matches!(err, Error::InvalidKeyFileName(_)));
+}
+
+#[cfg(feature = "jwt")]
+#[test]
+async fn client_token_from_p8_file() {
+    use apple_apns::token::SigningKeyErrorKind;
+
+    let fixture = |name: &str| format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+
+    // Windows line endings are normalized.
+    let authentication =
+        Authentication::token_from_p8_file("ABC123DEF4", fixture("keys/crlf.p8"), "DEF123GHIJ")
+            .unwrap();
+    match &authentication {
+        Authentication::Token { key_pem, .. } => assert_eq!(
+            &key_pem[..],
+            &include_bytes!("fixtures/AuthKey_ABC123DEF4.p8")[..]
+        ),
+        #[allow(unreachable_patterns)]
+        _ => unreachable!(),
+    }
+    ClientBuilder {
+        authentication: Some(authentication),
+        ..create_apns_client_builder("http://localhost")
+    }
+    .build()
+    .unwrap();
+
+    let cases = [
+        ("keys/AuthKey_ABC123DEF4.der", SigningKeyErrorKind::NotPem),
+        ("Localizable.strings", SigningKeyErrorKind::NotPem),
+        (
+            "client.pem",
+            SigningKeyErrorKind::NotPrivateKey("CERTIFICATE".into()),
+        ),
+        ("keys/rsa.p8", SigningKeyErrorKind::Rsa),
+        ("keys/rsa-traditional.pem", SigningKeyErrorKind::Rsa),
+        ("keys/p384.p8", SigningKeyErrorKind::WrongCurve),
+    ];
+    for (name, expected) in cases {
+        let path = fixture(name);
+        let err =
+            Authentication::token_from_p8_file("ABC123DEF4", &path, "DEF123GHIJ").unwrap_err();
+        match &err {
+            Error::InvalidSigningKey {
+                path: err_path,
+                kind,
+            } => {
+                assert_eq!(
+                    err_path.as_deref().and_then(|path| path.to_str()),
+                    Some(path.as_str())
+                );
+                assert_eq!(kind, &expected, "{name}");
+            }
+            err => panic!("{name}: {err}"),
+        }
+        assert!(err.to_string().contains(&path), "{err}");
+    }
+}
+
+#[cfg(feature = "jwt")]
+#[test]
+async fn client_builder_debug_omits_keys() {
+    let key_pem = include_str!("fixtures/AuthKey_ABC123DEF4.p8");
+    let builder = ClientBuilder {
+        authentication: Some(
+            Authentication::token_from_p8_path(
+                concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/tests/fixtures/AuthKey_ABC123DEF4.p8"
+                ),
+                "DEF123GHIJ",
+            )
+            .unwrap(),
+        ),
+        ..create_apns_client_builder("http://localhost")
+    };
+
+    let debug = format!("{builder:?}");
+    assert!(debug.contains(r#"Token { key_id: "ABC123DEF4", team_id: "DEF123GHIJ", .. }"#));
+    for line in key_pem.lines().filter(|line| !line.starts_with("-----")) {
+        assert!(!debug.contains(line), "{debug}");
+    }
+    // Nor as bytes.
+    let bytes = format!("{:?}", &key_pem.as_bytes()[..8]);
+    assert!(!debug.contains(bytes.trim_end_matches(']')), "{debug}");
+
+    #[cfg(feature = "rustls")]
+    {
+        let client_pem = include_bytes!("fixtures/client.pem");
+        let authentication = Authentication::Certificate {
+            client_pem: &client_pem[..],
+        };
+        assert_eq!(format!("{authentication:?}"), "Certificate { .. }");
+    }
+}
+
+#[cfg(feature = "jwt")]
+#[test]
+async fn client_token_der() {
+    use apple_apns::token::SigningKeyErrorKind;
+
+    let fixture = |name: &str| format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+
+    // Both encodings of the same key sign JWTs with the same header.
+    let mut jwt_headers = Vec::new();
+    for authentication in [
+        Authentication::token_from_p8_file(
+            "ABC123DEF4",
+            fixture("AuthKey_ABC123DEF4.p8"),
+            "DEF123GHIJ",
+        )
+        .unwrap(),
+        Authentication::token_from_der_file(
+            "ABC123DEF4",
+            fixture("keys/AuthKey_ABC123DEF4.der"),
+            "DEF123GHIJ",
+        )
+        .unwrap(),
+        Authentication::TokenDer {
+            key_id: "ABC123DEF4".into(),
+            key_der: include_bytes!("fixtures/keys/AuthKey_ABC123DEF4.der")[..].into(),
+            team_id: "DEF123GHIJ".into(),
+        },
+    ] {
+        let client = ClientBuilder {
+            authentication: Some(authentication),
+            ..create_apns_client_builder("http://localhost")
+        }
+        .build()
+        .unwrap();
+        let http_request = client.build_http_request(request.clone()).await.unwrap();
+        let authorization = http_request.headers()["authorization"].to_str().unwrap();
+        let jwt = authorization.strip_prefix("Bearer ").unwrap();
+        jwt_headers.push(jwt.split('.').next().unwrap().to_string());
+    }
+    assert_eq!(jwt_headers[1], jwt_headers[0]);
+    assert_eq!(jwt_headers[2], jwt_headers[0]);
+
+    let cases = [
+        ("AuthKey_ABC123DEF4.p8", SigningKeyErrorKind::NotDer),
+        ("keys/rsa.der", SigningKeyErrorKind::Rsa),
+        ("keys/p384.der", SigningKeyErrorKind::WrongCurve),
+    ];
+    for (name, expected) in cases {
+        let path = fixture(name);
+        let err =
+            Authentication::token_from_der_file("ABC123DEF4", &path, "DEF123GHIJ").unwrap_err();
+        match &err {
+            Error::InvalidSigningKey { kind, .. } => assert_eq!(kind, &expected, "{name}"),
+            err => panic!("{name}: {err}"),
+        }
+        assert!(err.to_string().contains(&path), "{err}");
+    }
+}
+
+/// Counts the calls of a [`MemoryBadgeCounter`].
+#[derive(Default)]
+struct CountingBadgeCounter {
+    counter: MemoryBadgeCounter,
+    calls: std::sync::atomic::AtomicUsize,
+}
+
+impl BadgeCounter for CountingBadgeCounter {
+    fn next<'a>(&'a self, device_token: &'a str) -> futures_util::future::BoxFuture<'a, u32> {
+        self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        self.counter.next(device_token)
+    }
+}
+
+#[test]
+async fn client_badge_increment() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .mount(&mock_server)
+        .await;
+
+    let counter = Arc::new(CountingBadgeCounter::default());
+    let client = ClientBuilder {
+        badge_counter: Some(counter.clone()),
+        ..create_apns_client_builder(&mock_server.uri())
+    }
+    .build()
+    .unwrap();
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        alert: Some("Hello World!".into()),
+        badge: Some(Badge::Increment),
+        ..Default::default()
+    };
+
+    // Each send resolves the badge exactly once.
+    client.post(request.clone()).await.unwrap();
+    assert_eq!(counter.calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+    // Concurrent sends to the same device get distinct numbers.
+    let results =
+        futures_util::future::join_all((0..8).map(|_| client.post(request.clone()))).await;
+    assert!(results.iter().all(Result::is_ok));
+    assert_eq!(counter.calls.load(std::sync::atomic::Ordering::SeqCst), 9);
+
+    // Requests that fail to encode don't use the counter.
+    let err = client
+        .post(Request::<()> {
+            alert: Some("x".repeat(header::PAYLOAD_SIZE_LIMIT).into()),
+            ..request.clone()
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::PayloadTooLarge { .. }), "{err}");
+    let err = client
+        .post(Request::<()> {
+            relevance_score: Some(2.0),
+            ..request.clone()
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Validation(_)), "{err}");
+    assert_eq!(counter.calls.load(std::sync::atomic::Ordering::SeqCst), 9);
+
+    // Requests with a number don't use the counter.
+    client
+        .post(Request::<()> {
+            badge: Some(Badge::Count(5)),
+            ..request.clone()
+        })
+        .await
+        .unwrap();
+    assert_eq!(counter.calls.load(std::sync::atomic::Ordering::SeqCst), 9);
+
+    let received = mock_server.received_requests().await.unwrap();
+    let mut badges = received
+        .iter()
+        .map(|received| {
+            let body: serde_json::Value = serde_json::from_slice(&received.body).unwrap();
+            body["aps"]["badge"].as_u64().unwrap()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(badges[0], 1);
+    assert_eq!(badges.pop(), Some(5));
+    badges.sort_unstable();
+    assert_eq!(badges, (1..=9).collect::<Vec<_>>());
+
+    // Without a counter, the request is rejected before it is sent.
+    let client = create_apns_client_builder(&mock_server.uri())
+        .build()
+        .unwrap();
+    let err = client.post(request).await.unwrap_err();
+    assert!(matches!(err, Error::InvalidRequest(_)), "{err}");
+    assert!(err.to_string().contains("badge counter"), "{err}");
+    assert_eq!(mock_server.received_requests().await.unwrap().len(), 10);
+}
+
+#[test]
+async fn client_pin_addresses() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .mount(&mock_server)
+        .await;
+
+    // The `.invalid` host never resolves, so only the pinned address works.
+    let port = mock_server.address().port();
+    let uri = format!("http://apns.invalid:{port}");
+    let client = ClientBuilder {
+        pin_addresses: vec![*mock_server.address()],
+        ..create_apns_client_builder(&uri)
+    }
+    .build()
+    .unwrap();
+
+    assert_eq!(
+        client.resolve_endpoints().await.unwrap(),
+        vec![*mock_server.address()]
+    );
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+    let apns_id = client.post(request.clone()).await.unwrap();
+    assert_eq!(apns_id, APS_ID.parse::<Uuid>().unwrap());
+
+    let unpinned = create_apns_client(&uri);
+    assert!(unpinned.post(request).await.is_err());
+}
+
+#[test]
+async fn client_resolve_endpoints() {
+    let client = create_apns_client("http://127.0.0.1:2197");
+    assert_eq!(
+        client.resolve_endpoints().await.unwrap(),
+        vec!["127.0.0.1:2197".parse().unwrap()]
+    );
+
+    let client = create_apns_client("http://[::1]");
+    assert_eq!(
+        client.resolve_endpoints().await.unwrap(),
+        vec!["[::1]:80".parse().unwrap()]
+    );
+}
+
+#[test]
+async fn client_audit_sink() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(header("apns-topic", "com.example.accepted"))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(header("apns-topic", "com.example.rejected"))
+        .respond_with(
+            ResponseTemplate::new(400)
+                .insert_header("apns-id", APS_ID)
+                .set_body_json(json!({ "reason": "BadDeviceToken" })),
+        )
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(header("apns-topic", "com.example.failed"))
+        .respond_with(ResponseTemplate::new(502))
+        .mount(&mock_server)
+        .await;
+
+    let records = Arc::new(Mutex::new(Vec::new()));
+    let client = ClientBuilder {
+        audit_sink: Some(Arc::new({
+            let records = records.clone();
+            move |record| records.lock().unwrap().push(record)
+        })),
+        ..create_apns_client_builder(&mock_server.uri())
+    }
+    .build()
+    .unwrap();
+
+    let request = Request::builder()
+        .device_token(DEVICE_TOKEN)
+        .alert(Alert::builder().title("Top secret").body("Launch codes"))
+        .interruption_level(InterruptionLevel::TimeSensitive)
+        .user_info(json!({ "acme": "classified" }))
+        .build()
+        .unwrap();
+    let requests = [
+        Request {
+            topic: Some("com.example.accepted".into()),
+            ..request.clone()
+        },
+        Request {
+            topic: Some("com.example.rejected".into()),
+            ..request.clone()
+        },
+        Request {
+            topic: Some("com.example.failed".into()),
+            ..request.clone()
+        },
+        Request {
+            topic: Some("com.example.invalid".into()),
+            alert: Some(Alert {
+                body: Some("Top secret ".repeat(500)),
+                ..Default::default()
+            }),
+            ..request.clone()
+        },
+        Request {
+            topic: Some("com.example.deadline".into()),
+            deadline: Some(Instant::now()),
+            ..request
+        },
+    ];
+    for request in requests {
+        let _ = client.post(request).await;
+    }
+
+    let received = mock_server.received_requests().await.unwrap();
+    let records = records.lock().unwrap();
+    let outcomes = records
+        .iter()
+        .map(|record| {
+            (
+                record.topic.as_deref().unwrap(),
+                record.outcome,
+                record.reason,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        outcomes,
+        [
+            ("com.example.accepted", audit::AuditOutcome::Accepted, None),
+            (
+                "com.example.rejected",
+                audit::AuditOutcome::Rejected,
+                Some("BadDeviceToken")
+            ),
+            ("com.example.failed", audit::AuditOutcome::Failed, None),
+            ("com.example.invalid", audit::AuditOutcome::Invalid, None),
+            (
+                "com.example.deadline",
+                audit::AuditOutcome::DeadlineExceeded,
+                None
+            ),
+        ]
+    );
+
+    let accepted = &records[0];
+    assert_eq!(accepted.device_token, "a863…e5ae");
+    assert_eq!(accepted.push_type, PushType::Alert);
+    assert_eq!(
+        accepted.interruption_level,
+        Some(InterruptionLevel::TimeSensitive)
+    );
+    assert_eq!(accepted.apns_id, Some(APS_ID.parse().unwrap()));
+    assert_eq!(accepted.payload_size, Some(received[0].body.len()));
+    assert!(accepted.sent_at <= accepted.completed_at);
+    assert_eq!(records[4].payload_size, None);
+
+    for record in records.iter() {
+        let json = serde_json::to_string(record).unwrap();
+        for content in [
+            "Top secret",
+            "Launch codes",
+            "acme",
+            "classified",
+            DEVICE_TOKEN,
+        ] {
+            assert!(!json.contains(content), "{content} in {json}");
+        }
+    }
+}
@@ -0,0 +1,117 @@
+#![cfg(all(feature = "config", feature = "jwt"))]
+
+use apple_apns::config::{AuthenticationConfig, PemSource};
+use apple_apns::*;
+use tokio::test;
+use wiremock::{
+    matchers::{header, header_exists, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+const DEVICE_TOKEN: &str = "a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae";
+const TOPIC: &str = "com.example.myapp";
+
+fn assert_fixture_config(config: &ClientConfig) {
+    assert_eq!(config.endpoint, Endpoint::Development);
+    assert_eq!(config.user_agent.as_deref(), Some("test/1.0.0"));
+    assert!(config.generate_apns_id);
+    assert!(config.validate_requests);
+    assert_eq!(config.recent_targets, 16);
+    assert_eq!(
+        config.expected_topics.as_deref(),
+        Some(&["com.example.myapp".to_string()][..])
+    );
+    assert!(matches!(
+        &config.authentication,
+        Some(AuthenticationConfig::Token {
+            key_id,
+            key_pem: PemSource::File(_),
+            team_id,
+        }) if key_id == "ABC123DEF4" && team_id == "DEF123GHIJ"
+    ));
+}
+
+#[test]
+async fn config_yaml() {
+    let config: ClientConfig = serde_yaml::from_str(include_str!("fixtures/config.yaml")).unwrap();
+    assert_fixture_config(&config);
+}
+
+#[test]
+async fn config_toml() {
+    let config: ClientConfig = toml::from_str(include_str!("fixtures/config.toml")).unwrap();
+    assert_fixture_config(&config);
+}
+
+#[test]
+async fn config_inline_pem() {
+    let config: ClientConfig = serde_yaml::from_str(&format!(
+        "authentication:\n  type: token\n  key_id: ABC123DEF4\n  key_pem:\n    inline: |\n{}  team_id: DEF123GHIJ\n",
+        include_str!("fixtures/AuthKey_ABC123DEF4.p8")
+            .lines()
+            .map(|line| format!("      {line}\n"))
+            .collect::<String>()
+    ))
+    .unwrap();
+
+    config.into_builder().unwrap().build().unwrap();
+}
+
+#[test]
+async fn config_missing_pem_file() {
+    let config: ClientConfig = serde_yaml::from_str(
+        "authentication:\n  type: token\n  key_id: ABC123DEF4\n  key_pem:\n    file: tests/fixtures/missing.p8\n  team_id: DEF123GHIJ\n",
+    )
+    .unwrap();
+
+    assert!(matches!(config.into_builder(), Err(Error::Io(_))));
+}
+
+#[test]
+async fn config_localizable_strings() {
+    let config: ClientConfig =
+        serde_yaml::from_str("localizable_strings: tests/fixtures/Localizable.strings\n").unwrap();
+    let builder = config.into_builder().unwrap();
+    let strings = builder.localizable_strings.as_deref().unwrap();
+    assert_eq!(
+        strings["GAME_PLAY_REQUEST_FORMAT"],
+        "%@ and %@ have invited you to play %@"
+    );
+
+    let config: ClientConfig =
+        serde_yaml::from_str("localizable_strings: tests/fixtures/missing.strings\n").unwrap();
+    assert!(matches!(config.into_builder(), Err(Error::Io(_))));
+}
+
+#[test]
+async fn config_client() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .and(header("user-agent", "test/1.0.0"))
+        .and(header_exists("authorization"))
+        .and(header_exists("apns-id"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let mut config: ClientConfig =
+        serde_yaml::from_str(include_str!("fixtures/config.yaml")).unwrap();
+    config.endpoint = format!("{}/3/device/", mock_server.uri()).parse().unwrap();
+    config.allow_insecure_http = true;
+
+    let client = config.into_builder().unwrap().build().unwrap();
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+
+    client.post(request).await.unwrap();
+
+    mock_server.verify().await;
+}
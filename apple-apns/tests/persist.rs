@@ -0,0 +1,155 @@
+use apple_apns::persist::{VersionedRequest, SCHEMA_VERSION};
+use apple_apns::time::OffsetDateTime;
+use apple_apns::*;
+use serde_json::{json, Value};
+
+const DEVICE_TOKEN: &str = "a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae";
+
+fn v1_fixtures() -> Vec<VersionedRequest> {
+    include_str!("fixtures/requests-v1.jsonl")
+        .lines()
+        .map(|line| VersionedRequest::from_persist_bytes(line.as_bytes()).unwrap())
+        .collect()
+}
+
+#[test]
+fn persist_roundtrip() {
+    let requests = [
+        Request::builder()
+            .device_token(DEVICE_TOKEN)
+            .topic("com.example.myapp")
+            .alert(
+                Alert::builder()
+                    .title("Game Request")
+                    .body("Bob wants to play"),
+            )
+            .expiration(OffsetDateTime::from_unix_timestamp(1685952000).unwrap())
+            .user_info(json!({ "game-id": "poker-42" }))
+            .build()
+            .unwrap(),
+        Request {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            push_type: PushType::Background,
+            expiration: Expiration::Immediate,
+            content_available: true,
+            ..Default::default()
+        },
+        Request {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            expiration: Expiration::In(std::time::Duration::from_secs(3600)),
+            ..Default::default()
+        },
+        Request {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            alert: Some("Hello World!".into()),
+            badge: Some(Badge::Increment),
+            ..Default::default()
+        },
+    ];
+
+    for request in requests {
+        let bytes = VersionedRequest::from(request.clone())
+            .to_persist_bytes()
+            .unwrap();
+        let value: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(value["schema-version"], SCHEMA_VERSION);
+
+        let versioned = VersionedRequest::from_persist_bytes(&bytes).unwrap();
+        assert_eq!(versioned.schema_version, SCHEMA_VERSION);
+        assert_eq!(versioned.request, request);
+    }
+}
+
+#[test]
+fn persist_badge_increment() {
+    let request = Request::<Value> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        alert: Some("Hello World!".into()),
+        badge: Some(Badge::Increment),
+        ..Default::default()
+    };
+
+    let bytes = VersionedRequest::from(request.clone())
+        .to_persist_bytes()
+        .unwrap();
+    let value: Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(value["request"]["badge"], "increment");
+
+    let redacted = request.to_redacted_json(&Redaction::default()).unwrap();
+    assert_eq!(redacted["badge"], "increment");
+    assert_eq!(
+        serde_json::to_value(request.redacted()).unwrap()["badge"],
+        "increment"
+    );
+}
+
+#[test]
+fn persist_migrate_v1() {
+    let requests = v1_fixtures();
+    assert!(requests
+        .iter()
+        .all(|versioned| versioned.schema_version == 1));
+
+    let requests = requests
+        .into_iter()
+        .map(|versioned| versioned.request)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        requests,
+        [
+            Request {
+                device_token: DEVICE_TOKEN.parse().unwrap(),
+                push_type: PushType::Alert,
+                expiration: Expiration::At(
+                    OffsetDateTime::from_unix_timestamp(1685952000).unwrap()
+                ),
+                priority: Some(Priority::Immediate),
+                topic: Some("com.example.myapp".into()),
+                alert: Some(Alert {
+                    title: Some("Game Request".into()),
+                    body: Some("Bob wants to play poker".into()),
+                    ..Default::default()
+                }),
+                badge: Some(Badge::Count(1)),
+                user_info: Some(json!({ "game-id": "poker-42" })),
+                ..Default::default()
+            },
+            Request {
+                device_token: DEVICE_TOKEN.parse().unwrap(),
+                push_type: PushType::Background,
+                expiration: Expiration::Immediate,
+                priority: Some(Priority::ConsiderPower),
+                content_available: true,
+                ..Default::default()
+            },
+            Request {
+                device_token: DEVICE_TOKEN.parse().unwrap(),
+                alert: Some("Hello World!".into()),
+                ..Default::default()
+            },
+        ]
+    );
+
+    // Migrated requests are written back with the current version.
+    for request in requests {
+        let bytes = VersionedRequest::from(request.clone())
+            .to_persist_bytes()
+            .unwrap();
+        let versioned = VersionedRequest::from_persist_bytes(&bytes).unwrap();
+        assert_eq!(versioned.schema_version, SCHEMA_VERSION);
+        assert_eq!(versioned.request, request);
+    }
+}
+
+#[test]
+fn persist_future_version() {
+    let bytes = serde_json::to_vec(&json!({
+        "schema-version": SCHEMA_VERSION + 1,
+        "request": { "device-token": DEVICE_TOKEN, "new-field": true },
+    }))
+    .unwrap();
+    let err = VersionedRequest::from_persist_bytes(&bytes).unwrap_err();
+    assert!(
+        matches!(err, Error::UnsupportedSchemaVersion(version) if version == SCHEMA_VERSION + 1)
+    );
+}
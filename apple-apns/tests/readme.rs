@@ -0,0 +1,51 @@
+use apple_apns::*;
+use serde_json::json;
+use tokio::test;
+use wiremock::{
+    matchers::{body_json, header, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+const DEVICE_TOKEN: &str = "a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae";
+const APS_ID: &str = "4d947500-498e-4524-8aa8-7220c4e65d75";
+
+/// The README usage example, sent to a mock server.
+#[test]
+async fn readme_usage() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .and(header("apns-push-type", "alert"))
+        .and(header("apns-topic", "com.example.myapp"))
+        .and(body_json(json!({
+            "aps": {
+                "alert": { "title": "Hello", "body": "World" },
+                "badge": 1,
+            },
+        })))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let client = ClientBuilder {
+        endpoint: Endpoint::Custom(format!("{}/3/device/", mock_server.uri()).parse().unwrap()),
+        allow_insecure_http: true,
+        allow_unauthenticated: true,
+        ..Default::default()
+    }
+    .build()
+    .unwrap();
+
+    let request = Request::builder()
+        .device_token(DEVICE_TOKEN)
+        .topic("com.example.myapp")
+        .alert(Alert::builder().title("Hello").body("World"))
+        .badge(1)
+        .build()
+        .unwrap();
+
+    let apns_id = client.post(request).await.unwrap();
+    assert_eq!(apns_id.to_string(), APS_ID);
+}
@@ -0,0 +1,160 @@
+#![cfg(feature = "schemars")]
+
+use std::time::Duration;
+
+use apple_apns::http::HeaderMap;
+use apple_apns::payload::Payload;
+use apple_apns::time::OffsetDateTime;
+use apple_apns::*;
+use jsonschema::JSONSchema;
+use schemars::{schema_for, JsonSchema};
+use serde_json::{json, Value};
+
+const DEVICE_TOKEN: &str = "a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae";
+
+fn compile<T: JsonSchema>() -> JSONSchema {
+    let schema = serde_json::to_value(schema_for!(T)).unwrap();
+    JSONSchema::compile(&schema).unwrap()
+}
+
+fn assert_valid(schema: &JSONSchema, instance: &Value) {
+    if let Err(errors) = schema.validate(instance) {
+        let errors = errors.map(|err| err.to_string()).collect::<Vec<_>>();
+        panic!("{instance} does not match the schema: {errors:?}");
+    }
+}
+
+fn payload(request: Request<Value>) -> Value {
+    let (_, payload): (HeaderMap, Payload<Value>) = request.try_into().unwrap();
+    serde_json::to_value(payload).unwrap()
+}
+
+fn timestamp() -> OffsetDateTime {
+    OffsetDateTime::from_unix_timestamp(1685952000).unwrap()
+}
+
+#[test]
+fn payload_schema() {
+    let schema = compile::<Payload>();
+
+    let payloads = [
+        payload(presets::simple_alert(
+            DEVICE_TOKEN,
+            "Game Request",
+            "Bob wants to play",
+        )),
+        payload(presets::localized_alert(
+            DEVICE_TOKEN,
+            "GAME_PLAY_REQUEST_FORMAT",
+            ["Jenna", "Frank"],
+        )),
+        payload(presets::critical_alert(
+            DEVICE_TOKEN,
+            "Severe Weather Alert",
+            "Seek shelter now",
+            "default",
+            1.,
+        )),
+        payload(presets::background_update(
+            DEVICE_TOKEN,
+            json!({ "acme1": "bar", "acme2": 42 }),
+        )),
+        payload(presets::live_activity_update(
+            DEVICE_TOKEN,
+            timestamp(),
+            json!({ "currentHealthLevel": 0.941 }),
+        )),
+        payload(Request {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            alert: Some("Hello World!".into()),
+            badge: Some(Badge::Clear),
+            sound: Some("ping.aiff".into()),
+            mutable_content: true,
+            interruption_level: Some(InterruptionLevel::TimeSensitive),
+            relevance_score: Some(0.5),
+            ..Default::default()
+        }),
+    ];
+    for payload in &payloads {
+        assert_valid(&schema, payload);
+    }
+
+    // Device-captured payloads with keys this crate doesn't know.
+    assert_valid(
+        &schema,
+        &json!({
+            "aps": {
+                "alert": { "title": "Hi", "future-key": 1 },
+                "sound": { "critical": 0, "name": "ping.aiff", "volume": 1, "future": 1 },
+                "future-key": true,
+            },
+            "unknown": [1, 2],
+        }),
+    );
+
+    let invalid = [
+        json!({ "aps": { "content-available": true } }),
+        json!({ "aps": { "badge": -1 } }),
+        json!({ "aps": { "sound": { "name": "default" } } }),
+        json!({ "aps": { "interruption-level": "loud" } }),
+        json!({ "aps": { "timestamp": "2023-06-05T08:00:00Z" } }),
+        json!({ "aps": { "alert": 1 } }),
+    ];
+    for payload in &invalid {
+        assert!(!schema.is_valid(payload), "{payload} matches the schema");
+    }
+}
+
+#[test]
+fn request_schema() {
+    let schema = compile::<Request<Value>>();
+
+    let requests = [
+        Request {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            alert: Some("Hello World!".into()),
+            ..Default::default()
+        },
+        Request {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            push_type: PushType::Liveactivity,
+            id: Some(apple_apns::uuid::Uuid::new_v4()),
+            expiration: Expiration::At(timestamp()),
+            priority: Some(Priority::ConsiderPower),
+            topic: Some("com.example.myapp".into()),
+            timestamp: Some(timestamp()),
+            event: Some(Event::End),
+            dismissal_date: Some(timestamp()),
+            content_state: Some(json!({ "score": 1 })),
+            ..Default::default()
+        },
+        Request {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            expiration: Expiration::In(Duration::from_secs(3600)),
+            content_available: true,
+            user_info: Some(json!({ "acme": 1 })),
+            ..Default::default()
+        },
+        Request {
+            device_token: DEVICE_TOKEN.parse().unwrap(),
+            expiration: Expiration::Immediate,
+            sound: Some(Sound::default_critical(0.5)),
+            interruption_level: Some(InterruptionLevel::Critical),
+            ..Default::default()
+        },
+    ];
+    for request in requests {
+        assert_valid(&schema, &serde_json::to_value(request).unwrap());
+    }
+
+    let invalid = [
+        json!({}),
+        json!({ "device-token": "not hex" }),
+        json!({ "device-token": DEVICE_TOKEN, "priority": 7 }),
+        json!({ "device-token": DEVICE_TOKEN, "push-type": "unknown" }),
+        json!({ "device-token": DEVICE_TOKEN, "expiration": { "at": 1 } }),
+    ];
+    for request in &invalid {
+        assert!(!schema.is_valid(request), "{request} matches the schema");
+    }
+}
@@ -0,0 +1,173 @@
+#![cfg(feature = "tracing")]
+
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use apple_apns::*;
+use serde_json::json;
+use tokio::test;
+use tracing_subscriber::fmt::{format::FmtSpan, MakeWriter};
+use wiremock::{
+    matchers::{header, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+const DEVICE_TOKEN: &str = "a863a50a904a4bb79380aae1e6c80b4dad25fcf8552848599d979b020aece5ae";
+const APS_ID: &str = "4d947500-498e-4524-8aa8-7220c4e65d75";
+const TOPIC: &str = "com.example.myapp";
+
+#[derive(Clone, Default)]
+struct TestWriter(Arc<Mutex<Vec<u8>>>);
+
+impl TestWriter {
+    fn output(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl io::Write for TestWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for TestWriter {
+    type Writer = Self;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+fn create_apns_client(mock_server_uri: &str) -> Client {
+    ClientBuilder {
+        endpoint: Endpoint::Custom(format!("{mock_server_uri}/3/device/").parse().unwrap()),
+        allow_insecure_http: true,
+        allow_unauthenticated: true,
+        ..Default::default()
+    }
+    .build()
+    .unwrap()
+}
+
+#[test]
+async fn post_span() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .up_to_n_times(1)
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "reason": "BadTopic",
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let writer = TestWriter::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(writer.clone())
+        .with_span_events(FmtSpan::CLOSE)
+        .with_ansi(false)
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let client = create_apns_client(&mock_server.uri());
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        topic: Some(TOPIC.into()),
+        alert: Some("Hello World!".into()),
+        ..Default::default()
+    };
+
+    client.post(request.clone()).await.unwrap();
+    client.post(request).await.unwrap_err();
+
+    let mock_server_address = *mock_server.address();
+    drop(mock_server);
+
+    let output = writer.output();
+    let mut lines = output.lines().filter(|line| line.contains("apns.post"));
+
+    let success = lines.next().unwrap();
+    assert!(success.contains("device_token=a863…e5ae"), "{success}");
+    assert!(success.contains("apns_push_type=alert"), "{success}");
+    assert!(
+        success.contains(&format!("apns_topic=\"{TOPIC}\"")),
+        "{success}"
+    );
+    assert!(
+        success.contains(&format!("apns_id=\"{APS_ID}\"")),
+        "{success}"
+    );
+    assert!(success.contains("status=200"), "{success}");
+    assert!(success.contains("http_version=HTTP/1.1"), "{success}");
+    assert!(
+        success.contains(&format!("remote_addr={mock_server_address}")),
+        "{success}"
+    );
+
+    let failure = lines.next().unwrap();
+    assert!(failure.contains("status=400"), "{failure}");
+    assert!(failure.contains("reason=\"BadTopic\""), "{failure}");
+
+    assert!(!output.contains(DEVICE_TOKEN));
+}
+
+#[test]
+async fn post_span_inferred_push_type() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/3/device/{DEVICE_TOKEN}")))
+        .and(header("apns-push-type", "background"))
+        .respond_with(ResponseTemplate::new(200).insert_header("apns-id", APS_ID))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let writer = TestWriter::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(writer.clone())
+        .with_span_events(FmtSpan::CLOSE)
+        .with_ansi(false)
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let client = ClientBuilder {
+        endpoint: Endpoint::Custom(format!("{}/3/device/", mock_server.uri()).parse().unwrap()),
+        allow_insecure_http: true,
+        allow_unauthenticated: true,
+        infer_push_type: true,
+        ..Default::default()
+    }
+    .build()
+    .unwrap();
+
+    let request = Request::<()> {
+        device_token: DEVICE_TOKEN.parse().unwrap(),
+        topic: Some(TOPIC.into()),
+        content_available: true,
+        ..Default::default()
+    };
+    client.post(request).await.unwrap();
+
+    mock_server.verify().await;
+
+    let output = writer.output();
+    let span = output
+        .lines()
+        .find(|line| line.contains("apns.post"))
+        .unwrap();
+    assert!(span.contains("apns_push_type=background"), "{span}");
+}